@@ -0,0 +1,78 @@
+use std::time::{Duration, Instant};
+use writer::large_file::{LargeFileView, RopeBuffer};
+
+/// Size of the generated file
+const FILE_SIZE: usize = 100 * 1024 * 1024;
+/// Keystrokes typed in each place
+const KEYSTROKES: usize = 2_000;
+/// Lines laid out per frame, about a screenful
+const SHOWN_LINES: usize = 60;
+/// Time a frame may take at 60 frames per second
+const FRAME_BUDGET: Duration = Duration::from_millis(16);
+
+/// What a frame does after a keystroke: find the cursor and fetch the
+/// lines around it for drawing
+fn frame(view: &LargeFileView) -> usize {
+    let (line, _) = view.cursor_position();
+    let first = line.saturating_sub(SHOWN_LINES / 2);
+    (first..first + SHOWN_LINES).map(|line| view.buffer.shown_line(line).len()).sum()
+}
+
+/// Type, delete and move in one place, returning the slowest and average
+/// keystroke with its frame
+fn type_at(view: &mut LargeFileView, line: usize) -> (Duration, Duration) {
+    view.set_cursor(line, 10);
+    let mut slowest = Duration::ZERO;
+    let mut total = Duration::ZERO;
+    for index in 0..KEYSTROKES {
+        let start = Instant::now();
+        match index % 10 {
+            9 => view.backspace(),
+            7 => view.move_vertically(1),
+            _ => view.insert("x"),
+        }
+        std::hint::black_box(frame(view));
+        let elapsed = start.elapsed();
+        slowest = slowest.max(elapsed);
+        total += elapsed;
+    }
+    (slowest, total / KEYSTROKES as u32)
+}
+
+fn main() {
+    let line = "The quick brown fox jumps over the lazy dog while typing in a large file.\n";
+    let text = line.repeat(FILE_SIZE.div_ceil(line.len()));
+
+    let start = Instant::now();
+    let buffer = RopeBuffer::from_text(&text);
+    println!(
+        "loaded {} MB, {} lines in {:?}",
+        buffer.len_bytes() / (1024 * 1024),
+        buffer.len_lines(),
+        start.elapsed()
+    );
+    drop(text);
+
+    let mut view = LargeFileView::new(buffer);
+    let lines = view.buffer.len_lines();
+    let mut within_budget = true;
+    for (place, line) in [("start", 0), ("middle", lines / 2), ("end", lines - 2)] {
+        let (slowest, average) = type_at(&mut view, line);
+        println!("typing at the {:<6}: average {:>10.2?}, slowest {:>10.2?}", place, average, slowest);
+        within_budget &= slowest < FRAME_BUDGET;
+    }
+
+    let start = Instant::now();
+    let mut undone = 0;
+    while view.buffer.can_undo() {
+        view.undo();
+        undone += 1;
+    }
+    println!("undid {} changes in {:?}", undone, start.elapsed());
+    assert!(!view.buffer.is_dirty(), "undoing everything gives back the loaded text");
+
+    if !within_budget {
+        eprintln!("a keystroke took longer than the {:?} frame budget", FRAME_BUDGET);
+        std::process::exit(1);
+    }
+}
@@ -1,19 +1,56 @@
 use eframe::egui;
 use crate::actions::ConfirmationAction;
 use crate::file_ops;
-use crate::ui::{menu, status_bar, dialogs};
+use crate::ui::{menu, status_bar, dialogs, editor, outline, output, lsp as lsp_ui, terminal as terminal_ui, git as git_ui, compare as compare_ui, conflicts as conflicts_ui, history as history_ui, drop as drop_ui, large_file as large_file_ui};
+use crate::output::OutputLocation;
+use crate::outline::OutlineState;
+use crate::output::OutputLog;
+use crate::format::{self, FormatJob};
+use crate::filter::{self, ShellAction, ShellCommandKind, ShellJob};
+use crate::run::{self as runner, RunJob, RunOutput};
+use crate::terminal::{Terminal, TerminalState};
+use crate::diff;
+use crate::git::GitState;
+use crate::compare::{CompareSide, CompareSource, CompareView, Side};
+use crate::conflicts::{self, ConflictState, Resolution};
+use crate::history::{self as file_history, HistoryState, HistoryStore};
+use crate::recent::RecentFiles;
+use crate::file_ops::Dropped;
+use crate::instance::{InstanceServer, OpenRequest};
+use crate::large_file::LargeFileView;
+use crate::lsp::{self, LspClient, LspEvent, LspState, SyncedDocument};
+use crate::folding::{FoldEdit, FoldState};
+use crate::treesitter::TreeHighlighter;
+use crate::wrap::WrapMode;
+use crate::settings::{self, Appearance, Settings};
+use crate::theme::ThemeRegistry;
+use crate::keybindings::KeyBindings;
+use crate::fonts;
 use crate::search::SearchState;
 use crate::undo::UndoHistory;
 use crate::syntax;
-use std::time::Instant;
-use egui_code_editor::{CodeEditor, ColorTheme};
+use crate::languages::{self, LanguageRegistry};
+use crate::brackets::{self, BracketState};
+use crate::indent::{self, IndentStyle};
+use std::ops::Range;
+use std::time::{Duration, Instant, SystemTime};
+use egui_code_editor::ColorTheme;
 
 pub struct MyApp {
     pub text: String,
+    /// Bumped on every change to `text`, so what is worked out from the
+    /// text is only redone after it changed
+    pub text_revision: u64,
     pub show_about_window: bool,
     pub filename: Option<String>,
     pub file_path: Option<std::path::PathBuf>,
     pub is_dirty: bool,
+    /// Revision of `text` last found to match the saved text, so it is
+    /// only compared again after a change
+    pub clean_revision: u64,
+    /// Copy of `text` from before the edits of a frame, with the revision
+    /// it was taken at; it is only copied again after the text changed
+    pub text_before_edits: (u64, String),
     pub last_saved_text: String,
     pub show_quit_dialog: bool,
     pub show_new_dialog: bool,
@@ -24,23 +61,91 @@ pub struct MyApp {
     pub undo_history: UndoHistory,
     pub last_text_change: Option<Instant>,
     pub pending_undo_text: Option<String>,
-    // Code editor configuration
-    pub show_line_numbers: bool,
-    pub syntax_highlighting: bool,
     pub show_goto_line_dialog: bool,
     pub goto_line_input: String,
+    pub show_goto_symbol_dialog: bool,
+    pub goto_symbol_input: String,
+    /// Index of the highlighted entry in the Go to Symbol list
+    pub goto_symbol_selected: usize,
+    /// Symbols of the current document for the outline and Go to Symbol
+    pub outline: OutlineState,
+    /// Language servers and their diagnostics, completions and hovers
+    pub lsp: LspState,
+    /// Output panel contents, such as formatter errors
+    pub output: OutputLog,
+    /// Program started with Run File, streaming into the output panel
+    pub run_job: Option<RunJob>,
+    /// Shell in the terminal panel
+    pub terminal: TerminalState,
+    /// Repository of the current file and its uncommitted changes
+    pub git: GitState,
+    /// Diff of two texts shown in the compare window
+    pub compare: Option<CompareView>,
+    /// Merge conflicts left in the buffer
+    pub conflicts: ConflictState,
+    /// Saved versions of the current file
+    pub history: HistoryState,
+    /// Text the editor selection referred to before a formatter replaced
+    /// the buffer; the selection is moved onto the new text next frame
+    pub cursor_remap: Option<String>,
+    pub show_shell_dialog: bool,
+    pub shell_command_kind: ShellCommandKind,
+    /// Command typed into the shell command dialog, kept to run it again
+    pub shell_command_input: String,
+    /// Shell command running in the background
+    pub shell_job: Option<ShellJob>,
+    /// Formatter running in the background
+    pub format_job: Option<FormatJob>,
+    /// Wakes the app up when work running in the background finished
+    waker: egui::Context,
     pub scroll_to_line: Option<usize>,
     pub suppress_undo_save: bool,
+    /// Indentation of the current file
+    pub indent_style: IndentStyle,
+    /// Language of the current file, by registry name
+    pub language: &'static str,
+    /// The language was picked by the user rather than detected
+    pub language_override: bool,
+    pub editor_id: Option<egui::Id>,
+    pub folds: FoldState,
+    /// Buffer classified for highlighting the bracket pair at the cursor
+    pub brackets: BracketState,
+    /// Tree-sitter highlighting for the current language, when compiled in
+    pub tree_highlighter: Option<TreeHighlighter>,
+    // User preferences
+    pub settings: Settings,
+    pub keybindings: KeyBindings,
+    pub themes: ThemeRegistry,
+    /// Modification time of the settings file when it was last read or written
+    pub settings_modified: Option<SystemTime>,
+    pub last_settings_check: Instant,
+    /// Settings being edited in the Preferences window
+    pub preferences: Option<dialogs::PreferencesState>,
+    pub recent: RecentFiles,
+    /// Recent file to open once the user decided about unsaved changes
+    pub pending_open: Option<std::path::PathBuf>,
+    /// File given on the command line, waiting to be opened so its line
+    /// can be shown and its read-only flag applied
+    pub pending_request: Option<OpenRequest>,
+    /// Edits to the document are rejected and Save is turned off
+    pub read_only: bool,
+    /// Receives files from later `writer` invocations
+    pub instance: Option<InstanceServer>,
+    /// The file when it is open in large-file mode, where `text` stays empty
+    pub large_file: Option<LargeFileView>,
 }
 
 impl Default for MyApp {
     fn default() -> Self {
         Self {
             text: String::new(),
+            text_revision: 0,
             show_about_window: false,
             filename: None,
             file_path: None,
             is_dirty: false,
+            clean_revision: 0,
+            text_before_edits: (0, String::new()),
             last_saved_text: String::new(),
             show_quit_dialog: false,
             show_new_dialog: false,
@@ -51,26 +156,465 @@ impl Default for MyApp {
             undo_history: UndoHistory::default(),
             last_text_change: None,
             pending_undo_text: None,
-            // User preferences: both off by default
-            show_line_numbers: false,
-            syntax_highlighting: false,
             show_goto_line_dialog: false,
             goto_line_input: String::new(),
+            show_goto_symbol_dialog: false,
+            goto_symbol_input: String::new(),
+            goto_symbol_selected: 0,
+            outline: OutlineState::default(),
+            lsp: LspState::default(),
+            output: OutputLog::default(),
+            run_job: None,
+            terminal: TerminalState::default(),
+            git: GitState::default(),
+            compare: None,
+            conflicts: ConflictState::default(),
+            history: HistoryState::default(),
+            cursor_remap: None,
+            show_shell_dialog: false,
+            shell_command_kind: ShellCommandKind::Filter,
+            shell_command_input: String::new(),
+            shell_job: None,
+            format_job: None,
+            waker: egui::Context::default(),
             scroll_to_line: None,
             suppress_undo_save: false,
+            indent_style: IndentStyle::default(),
+            language: "Plain Text",
+            language_override: false,
+            editor_id: None,
+            folds: FoldState::default(),
+            brackets: BracketState::default(),
+            tree_highlighter: None,
+            settings: Settings::default(),
+            keybindings: KeyBindings::default(),
+            themes: ThemeRegistry::default(),
+            settings_modified: None,
+            last_settings_check: Instant::now(),
+            preferences: None,
+            recent: RecentFiles::default(),
+            pending_open: None,
+            pending_request: None,
+            read_only: false,
+            instance: None,
+            large_file: None,
         }
     }
 }
 
 impl MyApp {
+    /// Create the app with the user's settings, themes and fonts applied
+    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
+        let mut app = Self { waker: cc.egui_ctx.clone(), ..Self::default() };
+        app.reload_themes();
+        app.reload_languages();
+        if let Err(e) = app.load_settings(&cc.egui_ctx) {
+            app.show_error(e);
+            app.apply_fonts(&cc.egui_ctx);
+            app.apply_appearance(&cc.egui_ctx);
+        }
+        app.load_recent();
+        // Zoom shortcuts are handled by the app so the zoom can be saved
+        cc.egui_ctx.options_mut(|o| o.zoom_with_keyboard = false);
+        app
+    }
+    
+    /// Create the app, open files given on the command line and listen on
+    /// `socket` for files sent by later invocations
+    pub fn with_files(cc: &eframe::CreationContext<'_>, files: Vec<OpenRequest>, socket: Option<&std::path::Path>) -> Self {
+        let mut app = Self::new(cc);
+        if let Some(socket) = socket {
+            let ctx = cc.egui_ctx.clone();
+            match InstanceServer::start(socket, move || ctx.request_repaint()) {
+                Ok(server) => app.instance = Some(server),
+                Err(e) => app.show_error(format!("Failed to listen for files on {}: {}", socket.display(), e)),
+            }
+        }
+        app.open_requests(files);
+        app
+    }
+    
+    /// Read the settings file and apply it
+    ///
+    /// Invalid settings are reported and the current ones are kept.
+    pub fn load_settings(&mut self, ctx: &egui::Context) -> Result<(), String> {
+        let Some(path) = Settings::path() else {
+            return Ok(());
+        };
+        self.settings_modified = modified_time(&path);
+        let settings = Settings::load_from(&path)?;
+        let appearance = &settings.appearance;
+        for name in [&appearance.dark_theme, &appearance.light_theme] {
+            if self.themes.find(name).is_none() {
+                return Err(format!("Invalid settings in {}: unknown theme \"{}\"", path.display(), name));
+            }
+        }
+        self.apply_settings(ctx, settings);
+        Ok(())
+    }
+    
+    /// Switch to new settings, updating fonts, theme and shortcuts
+    pub fn apply_settings(&mut self, ctx: &egui::Context, settings: Settings) {
+        let old = std::mem::replace(&mut self.settings, settings);
+        if let Ok(keybindings) = KeyBindings::new(&self.settings.keybindings) {
+            self.keybindings = keybindings;
+        }
+        let appearance = &self.settings.appearance;
+        if (&old.appearance.monospace_font, &old.appearance.proportional_font)
+            != (&appearance.monospace_font, &appearance.proportional_font)
+        {
+            self.apply_fonts(ctx);
+        }
+        self.apply_appearance(ctx);
+        if old.editor.indent_style() != self.settings.editor.indent_style() {
+            self.detect_indent_style();
+        }
+        if old.language_servers != self.settings.language_servers {
+            self.lsp.stop_servers();
+        }
+    }
+    
+    /// Reload the settings if the file was changed by another program
+    pub fn check_settings_file(&mut self, ctx: &egui::Context) {
+        if self.last_settings_check.elapsed() < Duration::from_secs(1) {
+            return;
+        }
+        self.last_settings_check = Instant::now();
+        let Some(path) = Settings::path() else {
+            return;
+        };
+        if modified_time(&path) != self.settings_modified
+            && let Err(e) = self.load_settings(ctx)
+        {
+            self.show_error(e);
+        }
+    }
+    
+    /// Write the settings file, reporting failures
+    pub fn save_settings(&mut self) {
+        let Some(path) = Settings::path() else {
+            return;
+        };
+        match self.settings.save_to(&path) {
+            Ok(()) => self.settings_modified = modified_time(&path),
+            Err(e) => self.show_error(format!("Failed to save settings: {}", e)),
+        }
+    }
+    
+    /// Open the Preferences window with a copy of the current settings
+    pub fn open_preferences(&mut self) {
+        self.preferences = Some(dialogs::PreferencesState::new(&self.settings));
+    }
+    
+    /// Load the built-in themes and those in the `themes` config directory
+    pub fn reload_themes(&mut self) {
+        let dir = settings::config_dir().map(|dir| dir.join("themes"));
+        self.themes = ThemeRegistry::load(dir.as_deref());
+        if !self.themes.errors.is_empty() {
+            self.show_error(format!("Failed to load themes:\n{}", self.themes.errors.join("\n")));
+        }
+    }
+    
+    /// Load the built-in languages and the syntax definitions in the
+    /// `syntaxes` config directory
+    pub fn reload_languages(&mut self) {
+        let dir = settings::config_dir().map(|dir| dir.join("syntaxes"));
+        let registry = LanguageRegistry::load(dir.as_deref());
+        let errors = registry.errors.clone();
+        if self.language_override && registry.by_name(self.language).is_none() {
+            self.language_override = false;
+        }
+        languages::install(registry);
+        self.detect_language();
+        if !errors.is_empty() {
+            self.show_error(format!("Failed to load syntax definitions:\n{}", errors.join("\n")));
+        }
+    }
+    
+    /// Install the fonts chosen in the settings
+    pub fn apply_fonts(&mut self, ctx: &egui::Context) {
+        let appearance = &self.settings.appearance;
+        let errors = fonts::apply_fonts(
+            ctx,
+            appearance.monospace_font.as_deref(),
+            appearance.proportional_font.as_deref(),
+        );
+        if !errors.is_empty() {
+            self.show_error(errors.join("\n"));
+        }
+    }
+    
+    /// Apply light/dark mode and zoom from the settings
+    pub fn apply_appearance(&self, ctx: &egui::Context) {
+        let preference = match self.settings.appearance.appearance {
+            Appearance::System => egui::ThemePreference::System,
+            Appearance::Light => egui::ThemePreference::Light,
+            Appearance::Dark => egui::ThemePreference::Dark,
+        };
+        ctx.set_theme(preference);
+        ctx.set_zoom_factor(self.settings.appearance.zoom);
+    }
+    
+    /// Editor theme for the current light or dark mode
+    pub fn editor_theme(&self, ctx: &egui::Context) -> ColorTheme {
+        let appearance = &self.settings.appearance;
+        match ctx.theme() {
+            egui::Theme::Dark => self.themes.find(&appearance.dark_theme).unwrap_or(ColorTheme::GITHUB_DARK),
+            egui::Theme::Light => self.themes.find(&appearance.light_theme).unwrap_or(ColorTheme::GITHUB_LIGHT),
+        }
+    }
+    
+    /// Use an editor theme for its kind (dark or light), switching the
+    /// interface to match if it shows the other kind
+    pub fn set_editor_theme(&mut self, ctx: &egui::Context, theme: ColorTheme) {
+        let appearance = &mut self.settings.appearance;
+        if theme.dark {
+            appearance.dark_theme = theme.name.to_string();
+        } else {
+            appearance.light_theme = theme.name.to_string();
+        }
+        if theme.dark != (ctx.theme() == egui::Theme::Dark) {
+            appearance.appearance = if theme.dark { Appearance::Dark } else { Appearance::Light };
+        }
+        self.apply_appearance(ctx);
+        self.save_settings();
+    }
+    
+    /// Follow the system, or force light or dark mode
+    pub fn set_appearance(&mut self, ctx: &egui::Context, appearance: Appearance) {
+        self.settings.appearance.appearance = appearance;
+        self.apply_appearance(ctx);
+        self.save_settings();
+    }
+    
+    /// Zoom in (positive steps) or out; zero steps resets the zoom
+    pub fn zoom(&mut self, ctx: &egui::Context, steps: i32) {
+        if steps == 0 {
+            self.settings.appearance.zoom = 1.0;
+        } else {
+            self.settings.zoom_by(steps);
+        }
+        self.apply_appearance(ctx);
+        self.save_settings();
+    }
+    
+    /// Let the user pick a font file for the editor or the interface;
+    /// `None` restores the built-in fonts
+    pub fn choose_font(&mut self, ctx: &egui::Context, family: Option<egui::FontFamily>) {
+        let appearance = &mut self.settings.appearance;
+        match family {
+            None => {
+                appearance.monospace_font = None;
+                appearance.proportional_font = None;
+            }
+            Some(family) => {
+                let Some(path) = rfd::FileDialog::new()
+                    .add_filter("Fonts", &["ttf", "otf", "ttc"])
+                    .pick_file()
+                else {
+                    return;
+                };
+                if let Err(e) = fonts::read_font(&path) {
+                    self.show_error(e);
+                    return;
+                }
+                if family == egui::FontFamily::Monospace {
+                    appearance.monospace_font = Some(path);
+                } else {
+                    appearance.proportional_font = Some(path);
+                }
+            }
+        }
+        self.apply_fonts(ctx);
+        self.save_settings();
+    }
+
+    /// Read the recent files list, dropping files that no longer exist
+    fn load_recent(&mut self) {
+        let Some(path) = RecentFiles::path() else {
+            return;
+        };
+        match RecentFiles::load_from(&path) {
+            Ok(recent) => self.recent = recent,
+            Err(e) => self.show_error(e),
+        }
+        if self.recent.prune_missing() {
+            self.save_recent();
+        }
+    }
+    
+    /// Write the recent files list
+    fn save_recent(&mut self) {
+        let Some(path) = RecentFiles::path() else {
+            return;
+        };
+        if let Err(e) = self.recent.save_to(&path) {
+            self.show_error(format!("Failed to save the recent files list: {}", e));
+        }
+    }
+    
+    /// Put a file at the top of the recent files list
+    fn remember_recent(&mut self, path: &std::path::Path) {
+        self.recent.add(path);
+        self.save_recent();
+    }
+    
+    /// Pin a recent file, or unpin it if it is pinned
+    pub fn toggle_pin_recent(&mut self, path: &std::path::Path) {
+        self.recent.toggle_pin(path);
+        self.save_recent();
+    }
+    
+    /// Forget the recent files that are not pinned
+    pub fn clear_recent(&mut self) {
+        self.recent.clear();
+        self.save_recent();
+    }
+    
+    /// Open a file from the recent files list, asking about unsaved
+    /// changes first
+    pub fn open_recent(&mut self, path: std::path::PathBuf) {
+        if path.is_file() {
+            self.request_open(path);
+        } else {
+            self.recent.remove(&path);
+            self.save_recent();
+            self.show_error(format!("{} no longer exists", path.display()));
+        }
+    }
+    
+    /// Open files asked for on the command line: the first in the editor
+    /// and the others in Open Recent, telling the user which ones those are
+    pub fn open_requests(&mut self, files: Vec<OpenRequest>) {
+        let mut files = files.into_iter();
+        let Some(first) = files.next() else {
+            return;
+        };
+        let others: Vec<_> = files.collect();
+        for other in others.iter().rev() {
+            self.recent.add(&other.path);
+        }
+        let path = first.path.clone();
+        self.pending_request = Some(first);
+        self.request_open(path);
+        if others.is_empty() {
+            return;
+        }
+        self.save_recent();
+        let names: Vec<String> = others.iter().map(|other| other.path.display().to_string()).collect();
+        let message = format!(
+            "Only one file can be open at a time. These were added to Open Recent instead:\n{}",
+            names.join("\n")
+        );
+        // Keep any error from opening the first file
+        if self.show_error_dialog {
+            self.error_message = format!("{}\n\n{}", self.error_message, message);
+        } else {
+            self.show_error(message);
+        }
+    }
+    
+    /// Come to the front and open the files sent by later invocations
+    fn poll_instance(&mut self, ctx: &egui::Context) {
+        if let Some(server) = &self.instance {
+            for files in server.poll() {
+                ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(false));
+                ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+                self.open_requests(files);
+            }
+        }
+        if let Some(request) = self.pending_request.take() {
+            if self.file_path.as_ref() == Some(&request.path) {
+                self.read_only |= request.read_only;
+                if let Some(line) = request.line {
+                    self.reveal_line(line.saturating_sub(1));
+                }
+            } else if self.show_open_dialog {
+                // Still waiting on the unsaved changes dialog
+                self.pending_request = Some(request);
+            }
+        }
+    }
+    
+    /// Open a file, asking about unsaved changes first
+    pub fn request_open(&mut self, path: std::path::PathBuf) {
+        if self.is_dirty {
+            self.pending_open = Some(path);
+            self.show_open_dialog = true;
+        } else if let Err(e) = self.open_file(path) {
+            self.show_error(format!("Failed to open file: {}", e));
+        }
+    }
+    
+    /// Insert dropped text at a char index, or at the cursor when it was
+    /// not dropped onto the editor, and select it
+    fn insert_dropped_text(&mut self, ctx: &egui::Context, dropped: &str, at: Option<usize>) {
+        if self.read_only {
+            self.show_read_only_error();
+            return;
+        }
+        if let Some(view) = &mut self.large_file {
+            let index = view.selection().end;
+            view.select(index..index);
+            view.insert(dropped);
+            view.select(index..view.cursor);
+            return;
+        }
+        let index = at
+            .or_else(|| self.editor_selection(ctx).map(|selection| selection.end))
+            .unwrap_or_else(|| self.text.chars().count());
+        let mut text = self.text.clone();
+        text.insert_str(indent::byte_index(&text, index), dropped);
+        self.replace_text(text);
+        self.set_editor_selection(ctx, index..index + dropped.chars().count());
+    }
+    
+    /// Open the most recently closed file again
+    pub fn reopen_closed_file(&mut self) {
+        if let Some(path) = self.recent.pop_closed(self.file_path.as_deref()) {
+            self.open_recent(path);
+        }
+    }
+    
+    /// Remember the current file as closed when another replaces it
+    fn close_current_file(&mut self, next: Option<&std::path::Path>) {
+        if let Some(path) = self.file_path.clone()
+            && Some(path.as_path()) != next
+        {
+            self.recent.push_closed(&path);
+        }
+    }
+    
     /// Open a file and load its contents into the editor
     pub fn open_file(&mut self, path: std::path::PathBuf) -> Result<(), std::io::Error> {
-        let (contents, filename, path) = file_ops::open_file(path)?;
+        // Files above the threshold are kept in a rope rather than the text
+        let (contents, filename, path, large_file) = if self.settings.files.is_large(std::fs::metadata(&path)?.len()) {
+            let (buffer, filename, path) = file_ops::open_large_file(path)?;
+            (String::new(), filename, path, Some(LargeFileView::new(buffer)))
+        } else {
+            let (contents, filename, path) = file_ops::open_file(path)?;
+            (contents, filename, path, None)
+        };
+        self.close_current_file(Some(&path));
+        self.remember_recent(&path);
         self.text = contents.clone();
+        self.text_changed();
         self.last_saved_text = contents;
         self.filename = Some(filename);
+        self.read_only = file_ops::is_read_only(&path);
         self.file_path = Some(path);
         self.is_dirty = false;
+        self.language_override = false;
+        self.detect_language();
+        self.detect_indent_style();
+        self.folds.clear();
+        self.git.open(if large_file.is_some() { None } else { self.file_path.as_deref() });
+        self.large_file = large_file;
+        self.conflicts = ConflictState::default();
+        self.conflicts.refresh(&self.text, self.text_revision);
+        if self.history.show_panel {
+            self.reload_history();
+        }
         // Clear undo history when opening a new file
         self.undo_history.clear();
         self.last_text_change = None;
@@ -79,143 +623,1316 @@ impl MyApp {
         Ok(())
     }
     
-    /// Save the current text to the existing file path
-    pub fn save_file(&mut self) -> Result<(), std::io::Error> {
-        if let Some(path) = &self.file_path {
-            file_ops::save_file(path, &self.text)?;
-            self.last_saved_text = self.text.clone();
-            self.is_dirty = false;
-            Ok(())
-        } else {
-            // No file path exists, need to use save_as
-            Err(std::io::Error::new(
-                std::io::ErrorKind::NotFound,
-                "No file path set"
-            ))
+    /// Save the current text to the existing file path
+    pub fn save_file(&mut self) -> Result<(), std::io::Error> {
+        if self.read_only {
+            return Err(std::io::Error::new(std::io::ErrorKind::PermissionDenied, "the file is read-only"));
+        }
+        if let Some(path) = self.file_path.clone() {
+            if let Some(view) = &mut self.large_file {
+                // Written as is, without formatting or a copy in the history
+                view.buffer.save(&path)?;
+                self.is_dirty = false;
+                return Ok(());
+            }
+            self.write_file(&path)?;
+            self.format_after_save();
+            Ok(())
+        } else {
+            // No file path exists, need to use save_as
+            Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "No file path set"
+            ))
+        }
+    }
+    
+    /// Write the buffer, prepared for saving, to its file
+    fn write_file(&mut self, path: &std::path::PathBuf) -> Result<(), std::io::Error> {
+        let prepared = self.settings.files.prepare_for_save(&self.text);
+        if prepared != self.text {
+            self.replace_text(prepared);
+        }
+        file_ops::save_file(path, &self.text)?;
+        self.last_saved_text = self.text.clone();
+        self.is_dirty = false;
+        self.record_history(path, &self.last_saved_text.clone());
+        self.detect_language();
+        self.notify_saved();
+        Ok(())
+    }
+    
+    /// Save the current text to a new file path
+    pub fn save_file_as(&mut self, path: std::path::PathBuf) -> Result<(), std::io::Error> {
+        if self.read_only && self.file_path.as_ref() == Some(&path) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::PermissionDenied,
+                "the file is read-only, choose another name to save a copy",
+            ));
+        }
+        if let Some(view) = &mut self.large_file {
+            view.buffer.save(&path)?;
+            self.read_only = file_ops::is_read_only(&path);
+            self.close_current_file(Some(&path));
+            self.remember_recent(&path);
+            self.filename = Some(file_ops::file_name(&path));
+            self.file_path = Some(path);
+            self.is_dirty = false;
+            self.detect_language();
+            return Ok(());
+        }
+        // A read-only buffer cannot take the changes, so only the copy gets them
+        let prepared = self.settings.files.prepare_for_save(&self.text);
+        if !self.read_only && prepared != self.text {
+            self.replace_text(prepared.clone());
+        }
+        let (filename, path) = file_ops::save_file_as(path, &prepared)?;
+        self.read_only = file_ops::is_read_only(&path);
+        self.record_history(&path, &prepared);
+        self.is_dirty = self.text != prepared;
+        self.last_saved_text = prepared;
+        self.close_current_file(Some(&path));
+        self.remember_recent(&path);
+        self.filename = Some(filename);
+        self.file_path = Some(path);
+        self.detect_language();
+        self.detect_indent_style();
+        self.git.open(self.file_path.as_deref());
+        self.notify_saved();
+        self.format_after_save();
+        Ok(())
+    }
+    
+    /// Pick the indentation style from the buffer contents, falling back to
+    /// the default for the current file's language
+    pub fn detect_indent_style(&mut self) {
+        let detected = if self.settings.files.detect_indentation {
+            indent::detect_indent(&self.text)
+        } else {
+            None
+        };
+        self.indent_style = detected
+            .or_else(|| indent::default_for_language(self.language))
+            .unwrap_or_else(|| self.settings.editor.indent_style());
+    }
+    
+    /// Detect the language from the file name and contents, unless the
+    /// user picked one for this document
+    pub fn detect_language(&mut self) {
+        if !self.language_override {
+            self.language = languages::registry()
+                .detect_document(self.filename.as_ref(), &self.text)
+                .name;
+        }
+    }
+    
+    /// Use a language for the current document, or go back to detecting it
+    pub fn set_language(&mut self, language: Option<&'static str>) {
+        self.language_override = language.is_some();
+        match language {
+            Some(language) => self.language = language,
+            None => self.detect_language(),
+        }
+        self.detect_indent_style();
+    }
+    
+    /// Replace the whole buffer as a single undoable edit
+    pub fn replace_text(&mut self, new_text: String) {
+        if new_text == self.text {
+            return;
+        }
+        if self.read_only {
+            self.show_read_only_error();
+            return;
+        }
+        self.save_undo_state();
+        self.undo_history.push(std::mem::replace(&mut self.text, new_text));
+        self.text_changed();
+        self.last_text_change = None;
+    }
+    
+    /// Note a change made to `text` in place
+    pub fn text_changed(&mut self) {
+        self.text_revision += 1;
+    }
+    
+    /// Convert the buffer's indentation to another style and use it from now on
+    pub fn convert_indentation(&mut self, target: IndentStyle) {
+        let converted = indent::convert_indentation(&self.text, self.indent_style, target);
+        self.replace_text(converted);
+        self.indent_style = target;
+    }
+    
+    /// Create a new file (clear current content)
+    pub fn new_file(&mut self) {
+        self.close_current_file(None);
+        self.large_file = None;
+        self.read_only = false;
+        self.text.clear();
+        self.text_changed();
+        self.filename = None;
+        self.file_path = None;
+        self.is_dirty = false;
+        self.last_saved_text.clear();
+        self.language_override = false;
+        self.detect_language();
+        self.detect_indent_style();
+        self.folds.clear();
+        self.git.open(None);
+        self.undo_history.clear();
+        self.last_text_change = None;
+        self.pending_undo_text = None;
+        self.suppress_undo_save = true;
+    }
+    
+    /// Show an error message to the user in a dialog
+    pub fn show_error(&mut self, message: String) {
+        self.error_message = message;
+        self.show_error_dialog = true;
+    }
+    
+    /// Handle new file action with proper save confirmation
+    pub fn handle_new_action(&mut self) {
+        if self.is_dirty {
+            self.show_new_dialog = true;
+        } else {
+            self.new_file();
+        }
+    }
+    
+    /// Handle file open action with proper save confirmation
+    pub fn handle_open_action(&mut self) {
+        if self.is_dirty {
+            self.show_open_dialog = true;
+        } else if let Some(path) = rfd::FileDialog::new().pick_file()
+        && let Err(e) = self.open_file(path) {
+            self.show_error(format!("Failed to open file: {}", e));
+        }
+    }
+    
+    /// Tell the user an edit was rejected
+    fn show_read_only_error(&mut self) {
+        let name = self.filename.clone().unwrap_or_else(|| "This document".to_string());
+        self.show_error(format!("{} is read-only. Turn off File > Read-Only to edit it.", name));
+    }
+    
+    /// Allow or reject edits to the document
+    pub fn toggle_read_only(&mut self) {
+        self.read_only = !self.read_only;
+    }
+    
+    /// Handle file save action with save-as fallback
+    pub fn handle_save_action(&mut self) {
+        if self.read_only {
+            self.show_error("The file is read-only. Use Save As to write a copy elsewhere.".to_string());
+            return;
+        }
+        if !self.conflicts.conflicts.is_empty() {
+            self.conflicts.show_save_warning = true;
+            return;
+        }
+        self.save_now();
+    }
+    
+    /// Save without checking for conflict markers, asking for a path if
+    /// the file has none
+    fn save_now(&mut self) {
+        if self.save_file().is_err() {
+            // No file path, prompt for Save As
+            if let Some(path) = rfd::FileDialog::new().save_file()
+                && let Err(e) = self.save_file_as(path) {
+                    self.show_error(format!("Failed to save file: {}", e));
+                }
+        }
+    }
+    
+    /// Handle save-as action
+    fn handle_save_as_action(&mut self) {
+        if let Some(path) = rfd::FileDialog::new().save_file()
+            && let Err(e) = self.save_file_as(path) {
+                self.show_error(format!("Failed to save file: {}", e));
+            }
+    }
+    
+    /// Handle quit action with proper save confirmation
+    pub fn handle_quit_action(&mut self, ctx: &egui::Context) {
+        if self.is_dirty {
+            self.show_quit_dialog = true;
+        } else {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+        }
+    }
+    
+    /// Handle undo action
+    pub fn handle_undo(&mut self) {
+        if let Some(previous_text) = self.undo_history.undo(self.text.clone()) {
+            self.text = previous_text;
+            self.text_changed();
+            // Clear pending undo text since we just performed an undo
+            self.pending_undo_text = None;
+            self.last_text_change = None;
+        }
+    }
+    
+    /// Handle redo action
+    pub fn handle_redo(&mut self) {
+        if let Some(next_text) = self.undo_history.redo(self.text.clone()) {
+            self.text = next_text;
+            self.text_changed();
+            // Clear pending undo text since we just performed a redo
+            self.pending_undo_text = None;
+            self.last_text_change = None;
+        }
+    }
+    
+    /// Save current text to undo history with debouncing
+    pub fn save_undo_state(&mut self) {
+        if self.suppress_undo_save {
+            return;
+        }
+        if let Some(pending) = self.pending_undo_text.take() {
+            // Don't push if it's identical to the last state
+            if let Some(last) = self.undo_history.peek_undo()
+                && last == &pending
+            {
+                return;
+            }
+            self.undo_history.push(pending);
+        }
+    }
+    
+    /// Toggle line numbers visibility
+    pub fn toggle_line_numbers(&mut self) {
+        self.settings.editor.show_line_numbers = !self.settings.editor.show_line_numbers;
+        self.save_settings();
+    }
+    
+    /// Toggle syntax highlighting
+    pub fn toggle_syntax_highlighting(&mut self) {
+        self.settings.editor.syntax_highlighting = !self.settings.editor.syntax_highlighting;
+        self.save_settings();
+    }
+    
+    /// Toggle automatic indentation on Enter
+    pub fn toggle_auto_indent(&mut self) {
+        self.settings.editor.auto_indent = !self.settings.editor.auto_indent;
+        self.save_settings();
+    }
+    
+    /// Toggle automatic insertion of closing brackets and quotes
+    pub fn toggle_auto_close_brackets(&mut self) {
+        self.settings.editor.auto_close_brackets = !self.settings.editor.auto_close_brackets;
+        self.save_settings();
+    }
+    
+    /// Change how long lines are wrapped
+    pub fn set_wrap_mode(&mut self, mode: WrapMode) {
+        self.settings.editor.set_wrap_mode(mode);
+        self.save_settings();
+    }
+    
+    /// Toggle soft wrapping, keeping the wrap column
+    pub fn toggle_word_wrap(&mut self) {
+        self.settings.editor.word_wrap = !self.settings.editor.word_wrap;
+        self.save_settings();
+    }
+    
+    /// Move the cursor to the bracket matching the one next to it
+    pub fn go_to_matching_bracket(&mut self, ctx: &egui::Context) {
+        let Some(selection) = self.editor_selection(ctx) else {
+            return;
+        };
+        let language = self.language;
+        if let Some(target) = brackets::matching_bracket_target(&self.text, selection.start, language) {
+            self.reveal_line(self.text.chars().take(target).filter(|&c| c == '\n').count());
+            self.set_editor_selection(ctx, target..target);
+            if let Some(id) = self.editor_id {
+                ctx.memory_mut(|m| m.request_focus(id));
+            }
+        }
+    }
+    
+    /// Open the Go to Symbol dialog with an empty query
+    pub fn open_goto_symbol(&mut self) {
+        self.show_goto_symbol_dialog = true;
+        self.goto_symbol_input.clear();
+        self.goto_symbol_selected = 0;
+    }
+    
+    /// Move the cursor to the start of a line (0-indexed), such as a symbol's
+    pub fn go_to_line_start(&mut self, ctx: &egui::Context, line: usize) {
+        let start: usize = self.text.split('\n').take(line).map(|l| l.chars().count() + 1).sum();
+        let start = start.min(self.text.chars().count());
+        self.reveal_line(line);
+        self.set_editor_selection(ctx, start..start);
+        if let Some(id) = self.editor_id {
+            ctx.memory_mut(|m| m.request_focus(id));
+        }
+    }
+    
+    /// Cursor position as a char index and as the protocol counts it
+    fn cursor_position(&self, ctx: &egui::Context) -> Option<(usize, lsp::Position)> {
+        let index = self.editor_selection(ctx)?.start;
+        let offset = indent::byte_index(&self.text, index);
+        Some((index, lsp::Position::from_offset(&self.text, offset)))
+    }
+    
+    /// Char range of the identifier around a char index
+    fn word_at(&self, index: usize) -> Range<usize> {
+        let chars: Vec<char> = self.text.chars().collect();
+        let is_word = |c: &char| c.is_alphanumeric() || *c == '_';
+        let start = index - chars[..index.min(chars.len())].iter().rev().take_while(|c| is_word(c)).count();
+        let end = index + chars[index.min(chars.len())..].iter().take_while(|c| is_word(c)).count();
+        start..end
+    }
+    
+    /// Open the current file in its language's server, starting the server
+    /// if needed, and send the edits made since the last call. Large files
+    /// are not sent, as their text is not loaded into the editor
+    pub fn sync_language_server(&mut self, ctx: &egui::Context) {
+        let wanted = self.file_path.as_ref().filter(|_| self.large_file.is_none()).and_then(|path| {
+            let server = self.settings.language_server(self.language)?;
+            Some((lsp::path_to_uri(path), path.clone(), server))
+        });
+        if let Some((uri, ..)) = &wanted
+            && let Some(document) = &mut self.lsp.document
+            && document.uri == *uri
+            && document.language == self.language
+        {
+            if document.revision != self.text_revision
+                && let Some(client) = self.lsp.clients.get_mut(document.language)
+            {
+                client.did_change(&document.uri, &document.text, &self.text);
+                document.text.clone_from(&self.text);
+                document.revision = self.text_revision;
+            }
+            return;
+        }
+
+        // A different file or language: close the old document
+        if let Some(document) = self.lsp.document.take() {
+            if let Some(client) = self.lsp.clients.get_mut(document.language) {
+                client.did_close(&document.uri);
+            }
+            self.lsp.diagnostics.remove(&document.uri);
+            self.lsp.completion = None;
+            self.lsp.hover = None;
+        }
+        let Some((uri, path, server)) = wanted else {
+            return;
+        };
+        let language = self.language;
+        if self.lsp.failed.contains_key(language) {
+            return;
+        }
+        if !self.lsp.clients.contains_key(language) {
+            let waker = ctx.clone();
+            let root = lsp::project_root(&path);
+            match LspClient::start(&server.command, &server.args, &root, move || waker.request_repaint()) {
+                Ok(client) => {
+                    self.lsp.clients.insert(language, client);
+                }
+                Err(e) => {
+                    // Shown in the Problems panel: a server that is not
+                    // installed should not get in the way of editing
+                    self.lsp.failed.insert(
+                        language,
+                        format!(
+                            "The {} language server \"{}\" could not be started: {}. Set its command in the language_servers settings, or to \"\" to turn it off.",
+                            language, server.command, e
+                        ),
+                    );
+                    return;
+                }
+            }
+        }
+        if let Some(client) = self.lsp.clients.get_mut(language) {
+            client.did_open(&uri, language, &self.text);
+            self.lsp.document = Some(SyncedDocument {
+                uri,
+                language,
+                text: self.text.clone(),
+                revision: self.text_revision,
+            });
+        }
+    }
+    
+    /// Tell the language server the file was saved
+    fn notify_saved(&mut self) {
+        if let Some(document) = &self.lsp.document
+            && self.file_path.as_deref().map(lsp::path_to_uri) == Some(document.uri.clone())
+            && let Some((client, uri)) = self.lsp.client()
+        {
+            client.did_save(uri, &self.text);
+        }
+    }
+    
+    /// Where saved versions of files are kept
+    fn history_store(&self) -> Option<HistoryStore> {
+        settings::data_dir().map(|dir| HistoryStore::new(dir.join("history"), &self.settings.files))
+    }
+    
+    /// Keep a copy of a saved file in the local history
+    fn record_history(&mut self, path: &std::path::Path, text: &str) {
+        if !self.settings.files.keep_history {
+            return;
+        }
+        let Some(store) = self.history_store() else {
+            return;
+        };
+        if let Err(e) = store.record(path, text) {
+            self.show_error(format!("Failed to keep a copy in the file history: {}", e));
+        }
+        if self.history.show_panel {
+            self.reload_history();
+        }
+    }
+    
+    /// List the versions of the current file again
+    fn reload_history(&mut self) {
+        let store = self.history_store();
+        self.history.reload(store.as_ref(), self.file_path.as_deref());
+    }
+    
+    /// Show or hide the File History panel
+    pub fn toggle_file_history(&mut self) {
+        self.history.show_panel = !self.history.show_panel;
+        if self.history.show_panel {
+            self.reload_history();
+        }
+    }
+    
+    /// Carry out what was asked for in the File History panel
+    fn handle_history_action(&mut self, action: history_ui::HistoryAction) {
+        let index = match action {
+            history_ui::HistoryAction::Compare(index) | history_ui::HistoryAction::Restore(index) => index,
+        };
+        let Some(version) = self.history.versions.get(index).cloned() else {
+            return;
+        };
+        let text = match file_history::read_version(&version) {
+            Ok(text) => text,
+            Err(e) => {
+                self.show_error(format!("Failed to read the saved version: {}", e));
+                self.reload_history();
+                return;
+            }
+        };
+        match action {
+            history_ui::HistoryAction::Compare(_) => {
+                let title = self.filename.clone().unwrap_or_else(|| "untitled".to_string());
+                let when = file_history::format_time(version.time);
+                self.compare = Some(CompareView::new(
+                    CompareSide::new(CompareSource::History, format!("{} ({} UTC)", title, when), text),
+                    CompareSide::new(CompareSource::Buffer, format!("{} (editor)", title), self.text.clone()),
+                ));
+            }
+            history_ui::HistoryAction::Restore(_) => self.replace_text(text),
+        }
+    }
+    
+    /// Whether the current file has a language server, telling the user why
+    /// not otherwise
+    fn has_language_server(&mut self, ctx: &egui::Context) -> bool {
+        self.sync_language_server(ctx);
+        if self.lsp.client().is_some() {
+            return true;
+        }
+        let message = if self.file_path.is_none() {
+            "Save the file to use a language server".to_string()
+        } else {
+            format!("No language server is running for {}", self.language)
+        };
+        self.show_error(message);
+        false
+    }
+    
+    /// Act on what the language servers sent since the last frame
+    pub fn handle_lsp_events(&mut self, ctx: &egui::Context) {
+        let mut events = Vec::new();
+        for client in self.lsp.clients.values_mut() {
+            events.extend(client.poll());
+        }
+        let failed = &mut self.lsp.failed;
+        self.lsp.clients.retain(|language, client| {
+            if !client.is_running() {
+                failed.insert(language, format!("The {} language server exited", language));
+            }
+            client.is_running()
+        });
+        
+        for event in events {
+            match event {
+                LspEvent::Diagnostics { uri, diagnostics } => {
+                    self.lsp.diagnostics.insert(uri, diagnostics);
+                }
+                LspEvent::Completion(items) => {
+                    self.lsp.completion = None;
+                    if let Some((index, _)) = self.cursor_position(ctx)
+                        && !items.is_empty()
+                    {
+                        self.lsp.completion = Some(lsp::CompletionPopup {
+                            items,
+                            selected: 0,
+                            word_start: self.word_at(index).start,
+                        });
+                    }
+                }
+                LspEvent::Hover(text) => {
+                    self.lsp.hover = None;
+                    if let Some((index, _)) = self.cursor_position(ctx)
+                        && !text.trim().is_empty()
+                    {
+                        self.lsp.hover = Some((text, index));
+                    }
+                }
+                LspEvent::Definition { uri, position } => self.go_to_location(ctx, &uri, position),
+                LspEvent::Edits(changes) => self.apply_lsp_edits(changes),
+                LspEvent::Message(message) => self.show_error(message),
+            }
+        }
+    }
+    
+    /// Ask the language server for completions at the cursor
+    pub fn trigger_completion(&mut self, ctx: &egui::Context) {
+        if self.read_only {
+            self.show_read_only_error();
+            return;
+        }
+        if !self.has_language_server(ctx) {
+            return;
+        }
+        if let Some((_, position)) = self.cursor_position(ctx)
+            && let Some((client, uri)) = self.lsp.client()
+        {
+            client.completion(uri, position);
+        }
+    }
+    
+    /// Indices of the completion items matching the word typed so far, best
+    /// first; `None` when no completion list is open
+    pub fn completion_matches(&self, ctx: &egui::Context) -> Option<Vec<usize>> {
+        let popup = self.lsp.completion.as_ref()?;
+        let cursor = self.editor_selection(ctx)?.start;
+        let typed: String = self
+            .text
+            .chars()
+            .skip(popup.word_start)
+            .take(cursor.checked_sub(popup.word_start)?)
+            .collect();
+        let mut matches: Vec<(i32, usize)> = popup
+            .items
+            .iter()
+            .enumerate()
+            .filter_map(|(i, item)| crate::outline::fuzzy_score(&typed, &item.label).map(|score| (score, i)))
+            .collect();
+        matches.sort_by_key(|&(score, i)| (std::cmp::Reverse(score), i));
+        Some(matches.into_iter().map(|(_, i)| i).collect())
+    }
+    
+    /// Replace the word before the cursor with a match from the completion list
+    pub fn accept_completion(&mut self, ctx: &egui::Context, selected: usize) {
+        if self.read_only {
+            self.lsp.completion = None;
+            return;
+        }
+        let matches = self.completion_matches(ctx).unwrap_or_default();
+        let (Some(popup), Some(selection)) = (self.lsp.completion.take(), self.editor_selection(ctx)) else {
+            return;
+        };
+        let Some(item) = matches.get(selected).and_then(|&i| popup.items.get(i)) else {
+            return;
+        };
+        let start = indent::byte_index(&self.text, popup.word_start);
+        let end = indent::byte_index(&self.text, selection.end);
+        // Through replace_text so it is undone in a step of its own, even
+        // when accepted by a click after this frame's edits were recorded
+        let mut text = self.text.clone();
+        text.replace_range(start..end, &item.insert_text);
+        self.replace_text(text);
+        let cursor = popup.word_start + item.insert_text.chars().count();
+        self.set_editor_selection(ctx, cursor..cursor);
+    }
+    
+    /// Close the completion list once the cursor leaves the word being
+    /// completed, and the hover once the cursor moves
+    fn update_lsp_popups(&mut self, ctx: &egui::Context) {
+        let selection = self.editor_selection(ctx);
+        if let Some(popup) = &self.lsp.completion {
+            let inside = selection.as_ref().is_some_and(|s| {
+                s.is_empty()
+                    && s.start >= popup.word_start
+                    && self
+                        .text
+                        .chars()
+                        .skip(popup.word_start)
+                        .take(s.start - popup.word_start)
+                        .all(|c| c.is_alphanumeric() || c == '_')
+            });
+            if !inside {
+                self.lsp.completion = None;
+            }
+        }
+        if let Some((_, index)) = &self.lsp.hover
+            && (selection.map(|s| s.start) != Some(*index) || ctx.input(|i| i.key_pressed(egui::Key::Escape)))
+        {
+            self.lsp.hover = None;
+        }
+    }
+    
+    /// Ask the language server to describe the symbol at the cursor
+    pub fn show_hover(&mut self, ctx: &egui::Context) {
+        if !self.has_language_server(ctx) {
+            return;
+        }
+        if let Some((_, position)) = self.cursor_position(ctx)
+            && let Some((client, uri)) = self.lsp.client()
+        {
+            client.hover(uri, position);
+        }
+    }
+    
+    /// Ask the language server where the symbol at the cursor is defined
+    pub fn go_to_definition(&mut self, ctx: &egui::Context) {
+        if !self.has_language_server(ctx) {
+            return;
+        }
+        if let Some((_, position)) = self.cursor_position(ctx)
+            && let Some((client, uri)) = self.lsp.client()
+        {
+            client.definition(uri, position);
+        }
+    }
+    
+    /// Move the cursor to a position in the current file
+    pub fn go_to_position(&mut self, ctx: &egui::Context, position: lsp::Position) {
+        let index = indent::char_index(&self.text, position.to_offset(&self.text));
+        self.reveal_line(position.line);
+        self.set_editor_selection(ctx, index..index);
+        if let Some(id) = self.editor_id {
+            ctx.memory_mut(|m| m.request_focus(id));
+        }
+    }
+    
+    /// Move the cursor to a position in a file, opening it if it is not the
+    /// current one
+    fn go_to_location(&mut self, ctx: &egui::Context, uri: &str, position: lsp::Position) {
+        let Some(path) = lsp::uri_to_path(uri) else {
+            self.show_error(format!("Cannot open {}", uri));
+            return;
+        };
+        if self.switch_to_file(&path) {
+            self.go_to_position(ctx, position);
+        }
+    }
+    
+    /// Make a file the current one, opening it unless it already is;
+    /// false when it could not be opened
+    fn switch_to_file(&mut self, path: &std::path::Path) -> bool {
+        let canonical = |p: &std::path::Path| p.canonicalize().unwrap_or_else(|_| p.to_path_buf());
+        if self.file_path.as_deref().is_some_and(|current| canonical(current) == canonical(path)) {
+            return true;
+        }
+        if self.is_dirty {
+            self.show_error(format!("Save your changes before opening {}", path.display()));
+            return false;
+        }
+        if let Err(e) = self.open_file(path.to_path_buf()) {
+            self.show_error(format!("Failed to open file: {}", e));
+            return false;
+        }
+        true
+    }
+    
+    /// Open the Rename Symbol dialog for the identifier at the cursor
+    pub fn open_rename(&mut self, ctx: &egui::Context) {
+        if self.read_only {
+            self.show_read_only_error();
+            return;
+        }
+        if !self.has_language_server(ctx) {
+            return;
+        }
+        let Some((index, _)) = self.cursor_position(ctx) else {
+            return;
+        };
+        let word = self.word_at(index);
+        self.lsp.rename_input = self.text.chars().skip(word.start).take(word.len()).collect();
+        self.lsp.show_rename_dialog = true;
+    }
+    
+    /// Ask the language server to rename the symbol at the cursor
+    pub fn rename_symbol(&mut self, ctx: &egui::Context, new_name: &str) {
+        if !self.has_language_server(ctx) {
+            return;
+        }
+        if let Some((_, position)) = self.cursor_position(ctx)
+            && let Some((client, uri)) = self.lsp.client()
+        {
+            client.rename(uri, position, new_name);
+        }
+    }
+    
+    /// Format the whole file with the language's formatter, or with its
+    /// language server when no formatter is configured
+    pub fn format_document(&mut self, ctx: &egui::Context) {
+        if self.read_only {
+            self.show_read_only_error();
+            return;
+        }
+        if let Some(formatter) = self.settings.formatter(self.language) {
+            self.run_formatter(&formatter, false);
+            return;
+        }
+        self.sync_language_server(ctx);
+        let style = self.indent_style;
+        match self.lsp.client() {
+            Some((client, uri)) => client.formatting(uri, style.width(), style != IndentStyle::Tabs),
+            None => self.show_error(format!("No formatter is configured for {}", self.language)),
+        }
+    }
+    
+    /// Pipe the buffer through a formatter in the background, saving the
+    /// file again afterwards when `save` is set
+    ///
+    /// A formatter still running for an earlier request is stopped.
+    pub fn run_formatter(&mut self, formatter: &settings::CommandSettings, save: bool) {
+        let save = save || self.format_job.as_ref().is_some_and(|job| job.save);
+        let waker = self.waker.clone();
+        self.format_job = Some(FormatJob::start(
+            formatter,
+            self.text.clone(),
+            self.file_path.clone(),
+            self.text_revision,
+            save,
+            move || waker.request_repaint(),
+        ));
+    }
+    
+    /// Replace the buffer with the formatter's output as one undoable edit,
+    /// if it did not change meanwhile; failures are shown in the output
+    /// panel
+    fn poll_format_job(&mut self) {
+        let Some(result) = self.format_job.as_ref().and_then(FormatJob::poll) else {
+            return;
+        };
+        let Some(job) = self.format_job.take() else {
+            return;
+        };
+        let formatted = match result {
+            Ok(formatted) => formatted,
+            Err(e) => {
+                let text = if e.stderr.is_empty() { e.message } else { format!("{}\n\n{}", e.message, e.stderr) };
+                self.output.show(&job.command, &text);
+                return;
+            }
+        };
+        if job.revision != self.text_revision {
+            // A save already wrote the text as it was typed
+            if !job.save {
+                self.show_error("The text changed while the formatter ran, so its output was not applied.".to_string());
+            }
+            return;
+        }
+        if formatted == self.text {
+            return;
+        }
+        self.cursor_remap = Some(self.text.clone());
+        self.replace_text(formatted);
+        if job.save
+            && let Some(path) = self.file_path.clone()
+            && let Err(e) = self.write_file(&path)
+        {
+            self.show_error(format!("Failed to save file: {}", e));
+        }
+    }
+    
+    /// Format the saved file in the background if format on save is turned
+    /// on, saving it again once formatted
+    ///
+    /// The file is written first, so a failing or slow formatter neither
+    /// stops nor holds up the save.
+    fn format_after_save(&mut self) {
+        if self.settings.files.format_on_save
+            && !self.read_only
+            && let Some(formatter) = self.settings.formatter(self.language)
+        {
+            self.run_formatter(&formatter, true);
+        }
+    }
+    
+    /// Ask for a shell command to run
+    pub fn open_shell_dialog(&mut self, kind: ShellCommandKind) {
+        if kind == ShellCommandKind::RunOnFile && self.file_path.is_none() {
+            self.show_error("Save the file before running a command on it".to_string());
+            return;
+        }
+        self.shell_command_kind = kind;
+        self.show_shell_dialog = true;
+    }
+    
+    /// Start a shell command in the background
+    pub fn start_shell_command(&mut self, ctx: &egui::Context, kind: ShellCommandKind, command: &str) {
+        if self.shell_job.is_some() {
+            self.show_error("Another command is still running".to_string());
+            return;
+        }
+        if self.read_only && kind != ShellCommandKind::RunOnFile {
+            self.show_read_only_error();
+            return;
+        }
+        let selection = self.editor_selection(ctx).unwrap_or(0..0);
+        let (command, input, action) = match kind {
+            ShellCommandKind::Filter => {
+                let range = if selection.is_empty() { 0..self.text.chars().count() } else { selection };
+                let input = self.text.chars().skip(range.start).take(range.len()).collect();
+                (command.to_string(), Some(input), ShellAction::Replace(range))
+            }
+            ShellCommandKind::Insert => (command.to_string(), None, ShellAction::Insert(selection.start)),
+            ShellCommandKind::RunOnFile => {
+                if self.is_dirty && let Err(e) = self.save_file() {
+                    self.show_error(format!("Failed to save file: {}", e));
+                    return;
+                }
+                let Some(path) = &self.file_path else {
+                    return;
+                };
+                (filter::command_on_file(command, path), None, ShellAction::Show)
+            }
+        };
+        let dir = self.file_path.as_deref().and_then(std::path::Path::parent);
+        let waker = ctx.clone();
+        self.shell_job = Some(ShellJob::start(&command, input, dir, action, self.text.clone(), move || {
+            waker.request_repaint()
+        }));
+    }
+    
+    /// Apply the output of the shell command once it finished
+    fn poll_shell_job(&mut self, ctx: &egui::Context) {
+        let Some(result) = self.shell_job.as_ref().and_then(ShellJob::poll) else {
+            return;
+        };
+        let Some(job) = self.shell_job.take() else {
+            return;
+        };
+        let output = match result {
+            Ok(output) => output,
+            Err(message) => {
+                self.output.show(&job.command, &message);
+                return;
+            }
+        };
+        if job.action == ShellAction::Show || !output.status.success() {
+            let mut text = output.stdout;
+            text.push_str(&output.stderr);
+            if !text.is_empty() && !text.ends_with('\n') {
+                text.push('\n');
+            }
+            text.push_str(&format!("\n[{}]", output.status));
+            self.output.show(&job.command, &text);
+            return;
+        }
+        if self.text != job.original {
+            self.output.show(&job.command, &output.stdout);
+            self.show_error("The text changed while the command ran, so its output was not applied. It is shown in the output panel.".to_string());
+            return;
+        }
+        if !output.stderr.is_empty() {
+            self.output.show(&job.command, &output.stderr);
+        }
+        if let Some((text, range)) = filter::apply_output(&self.text, &job.action, &output.stdout) {
+            self.replace_text(text);
+            self.set_editor_selection(ctx, range);
+        }
+    }
+    
+    /// Save the file and run it, streaming its output into the output
+    /// panel
+    pub fn run_file(&mut self, ctx: &egui::Context) {
+        if self.run_job.is_some() {
+            self.show_error("The program is still running; stop it first".to_string());
+            return;
+        }
+        let Some(path) = self.file_path.clone() else {
+            self.show_error("Save the file before running it".to_string());
+            return;
+        };
+        if self.is_dirty && let Err(e) = self.save_file() {
+            self.show_error(format!("Failed to save file: {}", e));
+            return;
+        }
+        let configured = self.settings.runners.get(self.language);
+        let Some(command) = runner::run_command(configured, &self.text, self.language, &path) else {
+            self.show_error(format!("No runner is configured for {}", self.language));
+            return;
+        };
+        let dir = path.parent().map(std::path::Path::to_path_buf);
+        let waker = ctx.clone();
+        match RunJob::start(&command, dir.as_deref(), move || waker.request_repaint()) {
+            Ok(job) => {
+                self.output.show(&job.command, "");
+                self.output.directory = dir;
+                self.run_job = Some(job);
+            }
+            Err(message) => self.output.show(&command.command, &message),
+        }
+    }
+    
+    /// Kill the program started with Run File
+    pub fn stop_run(&mut self) {
+        if let Some(job) = &mut self.run_job {
+            job.kill();
         }
     }
     
-    /// Save the current text to a new file path
-    pub fn save_file_as(&mut self, path: std::path::PathBuf) -> Result<(), std::io::Error> {
-        let (filename, path) = file_ops::save_file_as(path, &self.text)?;
-        self.last_saved_text = self.text.clone();
-        self.filename = Some(filename);
-        self.file_path = Some(path);
-        self.is_dirty = false;
-        Ok(())
+    /// Move the running program's output into the output panel
+    fn poll_run_job(&mut self, ctx: &egui::Context) {
+        let Some(job) = &mut self.run_job else {
+            return;
+        };
+        let (chunks, status) = job.poll();
+        for chunk in chunks {
+            match chunk {
+                RunOutput::Stdout(text) => self.output.append(&text, false),
+                RunOutput::Stderr(text) => self.output.append(&text, true),
+            }
+        }
+        let Some(status) = status else {
+            // Notices a program that closed its output but still runs
+            ctx.request_repaint_after(Duration::from_millis(250));
+            return;
+        };
+        let elapsed = job.started.elapsed();
+        self.run_job = None;
+        if !self.output.text().is_empty() && !self.output.text().ends_with('\n') {
+            self.output.append("\n", false);
+        }
+        self.output.append(&format!("\x1b[2m[{} after {:.1}s]\x1b[0m\n", status, elapsed.as_secs_f32()), false);
     }
     
-    /// Create a new file (clear current content)
-    pub fn new_file(&mut self) {
-        self.text.clear();
-        self.filename = None;
-        self.file_path = None;
-        self.is_dirty = false;
-        self.last_saved_text.clear();
-        self.undo_history.clear();
-        self.last_text_change = None;
-        self.pending_undo_text = None;
-        self.suppress_undo_save = true;
+    /// Open a location printed in the output, relative to the directory
+    /// the program ran in
+    fn open_output_location(&mut self, ctx: &egui::Context, location: &OutputLocation) {
+        let mut path = std::path::PathBuf::from(&location.path);
+        if path.is_relative() && let Some(dir) = &self.output.directory {
+            path = dir.join(path);
+        }
+        if !path.is_file() {
+            self.show_error(format!("{} does not exist", path.display()));
+            return;
+        }
+        if self.switch_to_file(&path) {
+            let position = lsp::Position {
+                line: location.line.saturating_sub(1),
+                character: location.column.unwrap_or(1).saturating_sub(1),
+            };
+            self.go_to_position(ctx, position);
+        }
     }
     
-    /// Show an error message to the user in a dialog
-    pub fn show_error(&mut self, message: String) {
-        self.error_message = message;
-        self.show_error_dialog = true;
+    /// Show or hide the terminal panel, focusing it when shown
+    pub fn toggle_terminal(&mut self, ctx: &egui::Context) {
+        self.terminal.show_panel = !self.terminal.show_panel;
+        self.terminal.focus_requested = self.terminal.show_panel;
+        if !self.terminal.show_panel
+            && let Some(id) = self.editor_id
+        {
+            ctx.memory_mut(|m| m.request_focus(id));
+        }
     }
     
-    /// Handle new file action with proper save confirmation
-    pub fn handle_new_action(&mut self) {
-        if self.is_dirty {
-            self.show_new_dialog = true;
-        } else {
-            self.new_file();
+    /// Start a shell for the terminal panel if none is running, in the
+    /// current file's project root or folder
+    fn ensure_terminal(&mut self, ctx: &egui::Context) {
+        if self.terminal.session.is_some() {
+            return;
+        }
+        let settings = &self.settings.terminal;
+        let dir = match &self.file_path {
+            Some(path) if settings.start_in_project_root => lsp::project_root(path),
+            Some(path) => path.parent().map(std::path::Path::to_path_buf).unwrap_or_default(),
+            None => std::env::current_dir().unwrap_or_default(),
+        };
+        let waker = ctx.clone();
+        match Terminal::start(&settings.shell, &dir, (24, 80), settings.scrollback, move || waker.request_repaint()) {
+            Ok(session) => self.terminal.session = Some(session),
+            Err(message) => {
+                self.terminal.show_panel = false;
+                self.show_error(message);
+            }
         }
     }
     
-    /// Handle file open action with proper save confirmation
-    pub fn handle_open_action(&mut self) {
-        if self.is_dirty {
-            self.show_open_dialog = true;
-        } else if let Some(path) = rfd::FileDialog::new().pick_file()
-        && let Err(e) = self.open_file(path) {
-            self.show_error(format!("Failed to open file: {}", e));
+    /// Move the editor selection onto reformatted text
+    fn remap_cursor(&mut self, ctx: &egui::Context) {
+        let Some(old) = self.cursor_remap.take() else {
+            return;
+        };
+        if let Some(selection) = self.editor_selection(ctx) {
+            let start = format::map_cursor(&old, &self.text, selection.start);
+            let end = format::map_cursor(&old, &self.text, selection.end);
+            self.set_editor_selection(ctx, start..end);
         }
     }
     
-    /// Handle file save action with save-as fallback
-    pub fn handle_save_action(&mut self) {
-        if self.save_file().is_err() {
-            // No file path, prompt for Save As
-            if let Some(path) = rfd::FileDialog::new().save_file()
-                && let Err(e) = self.save_file_as(path) {
-                    self.show_error(format!("Failed to save file: {}", e));
-                }
+    /// Apply edits from a language server: to the buffer as one undoable
+    /// edit for the current file, and on disk for other files
+    fn apply_lsp_edits(&mut self, changes: Vec<(String, Vec<lsp::TextEdit>)>) {
+        let current = self.lsp.document.as_ref().map(|d| d.uri.clone());
+        let mut errors = Vec::new();
+        for (uri, edits) in changes {
+            if edits.is_empty() {
+                continue;
+            }
+            if Some(&uri) == current.as_ref() {
+                let edited = lsp::apply_edits(&self.text, &edits);
+                self.cursor_remap = Some(self.text.clone());
+                self.replace_text(edited);
+                continue;
+            }
+            let Some(path) = lsp::uri_to_path(&uri) else {
+                errors.push(format!("{}: not a file", uri));
+                continue;
+            };
+            let result = std::fs::read_to_string(&path)
+                .and_then(|text| std::fs::write(&path, lsp::apply_edits(&text, &edits)));
+            if let Err(e) = result {
+                errors.push(format!("{}: {}", path.display(), e));
+            }
+        }
+        if !errors.is_empty() {
+            self.show_error(format!("Failed to apply edits:\n{}", errors.join("\n")));
         }
     }
     
-    /// Handle save-as action
-    fn handle_save_as_action(&mut self) {
-        if let Some(path) = rfd::FileDialog::new().save_file()
-            && let Err(e) = self.save_file_as(path) {
-                self.show_error(format!("Failed to save file: {}", e));
-            }
+    /// Recompute fold regions and the displayed text after the buffer changed
+    pub fn refresh_folds(&mut self) {
+        self.folds.refresh(&self.text, self.text_revision, self.language);
     }
     
-    /// Handle quit action with proper save confirmation
-    pub fn handle_quit_action(&mut self, ctx: &egui::Context) {
-        if self.is_dirty {
-            self.show_quit_dialog = true;
-        } else {
-            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+    /// The editor's current selection as char indices into `text`
+    pub fn editor_selection(&self, ctx: &egui::Context) -> Option<Range<usize>> {
+        let state = egui::TextEdit::load_state(ctx, self.editor_id?)?;
+        let range = state.cursor.char_range()?.as_sorted_char_range();
+        let view = &self.folds.view;
+        Some(view.to_original(range.start)..view.to_original(range.end))
+    }
+    
+    /// Select a range of char indices into `text` in the editor
+    pub fn set_editor_selection(&mut self, ctx: &egui::Context, selection: Range<usize>) {
+        let Some(id) = self.editor_id else {
+            return;
+        };
+        self.refresh_folds();
+        let view = &self.folds.view;
+        let mut state = egui::TextEdit::load_state(ctx, id).unwrap_or_default();
+        state.cursor.set_char_range(Some(egui::text::CCursorRange::two(
+            egui::text::CCursor::new(view.to_display(selection.start)),
+            egui::text::CCursor::new(view.to_display(selection.end)),
+        )));
+        state.store(ctx, id);
+    }
+    
+    /// Unfold any fold hiding a line (0-indexed) and scroll it into view
+    pub fn reveal_line(&mut self, line: usize) {
+        if let Some(view) = &mut self.large_file {
+            view.set_cursor(line, 0);
+            return;
         }
+        self.refresh_folds();
+        self.folds.reveal_line(line, &self.text);
+        self.scroll_to_line = Some(line);
     }
     
-    /// Handle undo action
-    pub fn handle_undo(&mut self) {
-        if let Some(previous_text) = self.undo_history.undo(self.text.clone()) {
-            self.text = previous_text;
-            // Clear pending undo text since we just performed an undo
-            self.pending_undo_text = None;
-            self.last_text_change = None;
+    /// Change folds while keeping the editor selection on the same text
+    pub fn update_folds(&mut self, ctx: &egui::Context, change: impl FnOnce(&mut FoldState, &str)) {
+        self.refresh_folds();
+        let selection = self.editor_selection(ctx);
+        change(&mut self.folds, &self.text);
+        if let Some(selection) = selection {
+            self.set_editor_selection(ctx, selection);
         }
     }
     
-    /// Handle redo action
-    pub fn handle_redo(&mut self) {
-        if let Some(next_text) = self.undo_history.redo(self.text.clone()) {
-            self.text = next_text;
-            // Clear pending undo text since we just performed a redo
-            self.pending_undo_text = None;
-            self.last_text_change = None;
+    /// Line (0-indexed) the editor cursor is on
+    fn cursor_line(&self, ctx: &egui::Context) -> usize {
+        self.editor_selection(ctx)
+            .map(|s| self.text.chars().take(s.start).filter(|&c| c == '\n').count())
+            .unwrap_or(0)
+    }
+    
+    /// Replace a conflict with one or both of its sides
+    pub fn resolve_conflict(&mut self, index: usize, resolution: Resolution) {
+        if let Some(conflict) = self.conflicts.conflicts.get(index) {
+            let resolved = conflicts::resolve(&self.text, conflict, resolution);
+            self.replace_text(resolved);
         }
     }
     
-    /// Save current text to undo history with debouncing
-    pub fn save_undo_state(&mut self) {
-        if self.suppress_undo_save {
+    /// Replace the merge view's conflict with the hand-merged result
+    fn use_merge_result(&mut self, index: usize) {
+        if let Some(conflict) = self.conflicts.conflicts.get(index) {
+            let merged = conflicts::replace_conflict(&self.text, conflict, &self.conflicts.result);
+            self.replace_text(merged);
+        }
+    }
+    
+    /// Open the merge view on the first conflict, or the given one
+    pub fn open_merge_view(&mut self, index: Option<usize>) {
+        self.conflicts.refresh(&self.text, self.text_revision);
+        if self.conflicts.conflicts.is_empty() {
+            self.show_error("There are no merge conflicts in this file".to_string());
             return;
         }
-        if let Some(pending) = self.pending_undo_text.take() {
-            // Don't push if it's identical to the last state
-            if let Some(last) = self.undo_history.peek_undo() {
-                if last == &pending {
+        self.conflicts.current = index.unwrap_or(0).min(self.conflicts.conflicts.len() - 1);
+        self.conflicts.show_merge_view = true;
+    }
+    
+    /// Compare the buffer with the file as last saved
+    pub fn compare_with_saved(&mut self) {
+        let title = self.filename.clone().unwrap_or_else(|| "untitled".to_string());
+        self.compare = Some(CompareView::new(
+            CompareSide::new(CompareSource::Saved, format!("{} (saved)", title), self.last_saved_text.clone()),
+            CompareSide::new(CompareSource::Buffer, format!("{} (editor)", title), self.text.clone()),
+        ));
+    }
+    
+    /// Compare two picked files, or one picked file with the buffer
+    pub fn compare_with_file(&mut self) {
+        let Some(mut paths) = rfd::FileDialog::new().set_title("Compare With File").pick_files() else {
+            return;
+        };
+        let right = match paths.len() {
+            1 => {
+                let title = self.filename.clone().unwrap_or_else(|| "untitled".to_string());
+                Ok(CompareSide::new(CompareSource::Buffer, format!("{} (editor)", title), self.text.clone()))
+            }
+            2 => CompareSide::from_file(paths.pop().unwrap_or_default()),
+            _ => Err("Pick one file to compare with the editor, or two files to compare with each other".to_string()),
+        };
+        match right.and_then(|right| Ok((CompareSide::from_file(paths.remove(0))?, right))) {
+            Ok((left, right)) => self.compare = Some(CompareView::new(left, right)),
+            Err(e) => self.show_error(e),
+        }
+    }
+    
+    /// Keep the compared buffer and saved file in step with the editor, and
+    /// carry out what was asked for in the compare window
+    fn update_compare(&mut self, ctx: &egui::Context) {
+        let Some(view) = &mut self.compare else {
+            return;
+        };
+        for side in [Side::Left, Side::Right] {
+            match view.side(side).source {
+                CompareSource::Buffer if view.side(side).revision != Some(self.text_revision) => {
+                    view.side_mut(side).revision = Some(self.text_revision);
+                    if self.text != view.side(side).text {
+                        view.set_text(side, self.text.clone());
+                    }
+                }
+                CompareSource::Saved if !view.side(side).modified && self.last_saved_text != view.side(side).text => {
+                    view.set_text(side, self.last_saved_text.clone());
+                }
+                _ => {}
+            }
+        }
+        match compare_ui::render_compare_window(ctx, view) {
+            Some(compare_ui::CompareAction::Close) => self.compare = None,
+            Some(compare_ui::CompareAction::Copy(index, side)) => {
+                let Some(text) = view.copied_hunk(index, side) else {
                     return;
+                };
+                if view.side(side).source == CompareSource::Buffer {
+                    self.replace_text(text);
+                } else {
+                    view.side_mut(side).modified = true;
+                    view.set_text(side, text);
                 }
             }
-            self.undo_history.push(pending);
+            Some(compare_ui::CompareAction::Save(side)) => {
+                let compared = view.side(side);
+                if self.read_only && compared.source == CompareSource::Saved {
+                    self.show_error("The file is read-only.".to_string());
+                    return;
+                }
+                let path = match &compared.source {
+                    CompareSource::File(path) => Some(path.clone()),
+                    _ => self.file_path.clone(),
+                };
+                let Some(path) = path else {
+                    return;
+                };
+                let text = compared.text.clone();
+                if let Err(e) = file_ops::save_file(&path, &text) {
+                    self.show_error(format!("Failed to save {}: {}", path.display(), e));
+                    return;
+                }
+                self.record_history(&path, &text);
+                let Some(view) = &mut self.compare else {
+                    return;
+                };
+                view.side_mut(side).modified = false;
+                if view.side(side).source == CompareSource::Saved {
+                    self.is_dirty = self.text != text;
+                    self.last_saved_text = text;
+                }
+            }
+            None => {}
         }
     }
     
-    /// Toggle line numbers visibility
-    pub fn toggle_line_numbers(&mut self) {
-        self.show_line_numbers = !self.show_line_numbers;
+    /// Move the cursor to the next change against the committed file, or
+    /// the previous one when `forward` is false
+    pub fn go_to_change(&mut self, ctx: &egui::Context, forward: bool) {
+        if let Some(view) = &mut self.compare {
+            view.step(forward);
+            return;
+        }
+        let line = self.cursor_line(ctx);
+        let hunks = &self.git.hunks;
+        let found = if forward { diff::next_hunk(hunks, line) } else { diff::previous_hunk(hunks, line) };
+        let Some(index) = found else {
+            return;
+        };
+        let line = hunks[index].new.start.min(self.text.matches('\n').count());
+        self.go_to_position(ctx, lsp::Position { line, character: 0 });
     }
     
-    /// Toggle syntax highlighting
-    pub fn toggle_syntax_highlighting(&mut self) {
-        self.syntax_highlighting = !self.syntax_highlighting;
+    /// Put the change at the cursor back the way it is in the committed file
+    pub fn revert_change_at_cursor(&mut self, ctx: &egui::Context) {
+        let line = self.cursor_line(ctx);
+        if let Some(index) = diff::hunk_at(&self.git.hunks, line) {
+            self.revert_change(index);
+        }
+    }
+    
+    /// Put one change back the way it is in the committed file, as an
+    /// undoable edit
+    pub fn revert_change(&mut self, index: usize) {
+        // Hunks still being worked out for the latest edit may be off
+        if !self.git.is_current(self.text_revision) {
+            return;
+        }
+        let (Some(base), Some(hunk)) = (&self.git.base, self.git.hunks.get(index)) else {
+            return;
+        };
+        let reverted = diff::revert_hunk(base, &self.text, hunk);
+        self.git.shown_hunk = None;
+        self.replace_text(reverted);
+    }
+    
+    /// Fold the innermost region around the cursor
+    pub fn fold_at_cursor(&mut self, ctx: &egui::Context) {
+        let line = self.cursor_line(ctx);
+        self.update_folds(ctx, |folds, text| folds.fold_at(line, text));
+    }
+    
+    /// Unfold the regions around the cursor
+    pub fn unfold_at_cursor(&mut self, ctx: &egui::Context) {
+        let line = self.cursor_line(ctx);
+        self.update_folds(ctx, |folds, text| folds.unfold_at(line, text));
     }
+    
     /// Handle result from quit dialog
     fn handle_quit_dialog_result(&mut self, action: ConfirmationAction, ctx: &egui::Context) {
         match action {
@@ -253,31 +1970,39 @@ impl MyApp {
                     if let Some(path) = rfd::FileDialog::new().save_file() {
                         if let Err(e) = self.save_file_as(path) {
                             self.show_error(format!("Failed to save file: {}", e));
-                        } else if let Some(path) = rfd::FileDialog::new().pick_file()
-                        && let Err(e) = self.open_file(path) {
-                            self.show_error(format!("Failed to open file: {}", e));
+                        } else {
+                            self.open_pending_or_picked();
                         }
                     }
-                } else if let Some(path) = rfd::FileDialog::new().pick_file()
-                && let Err(e) = self.open_file(path) {
-                    self.show_error(format!("Failed to open file: {}", e));
+                } else {
+                    self.open_pending_or_picked();
                 }
+                self.pending_open = None;
                 self.show_open_dialog = false;
             }
             ConfirmationAction::DontSave => {
-                if let Some(path) = rfd::FileDialog::new().pick_file()
-                    && let Err(e) = self.open_file(path) {
-                        self.show_error(format!("Failed to open file: {}", e));
-                    }
+                self.open_pending_or_picked();
                 self.show_open_dialog = false;
             }
             ConfirmationAction::Cancel => {
+                self.pending_open = None;
                 self.show_open_dialog = false;
             }
             ConfirmationAction::None => {}
         }
     }
 
+    /// Open the recent file waiting on the open dialog, or else one the
+    /// user picks
+    fn open_pending_or_picked(&mut self) {
+        let path = self.pending_open.take().or_else(|| rfd::FileDialog::new().pick_file());
+        if let Some(path) = path
+            && let Err(e) = self.open_file(path)
+        {
+            self.show_error(format!("Failed to open file: {}", e));
+        }
+    }
+
     /// Handle result from new dialog
     fn handle_new_dialog_result(&mut self, action: ConfirmationAction) {
         match action {
@@ -318,20 +2043,71 @@ impl eframe::App for MyApp {
             }
             // If not dirty, allow the close to proceed naturally
         
+        // Pick up changes made to the settings file outside the app
+        self.check_settings_file(ctx);
+        self.poll_instance(ctx);
+        ctx.request_repaint_after(Duration::from_secs(1));
+        
         // Keyboard shortcuts
         crate::input::handle_shortcuts(ctx, self);
         
+        // Files dropped onto the window are opened, text is inserted where
+        // it was dropped once the editor is laid out
+        let hovered = ctx.input(|i| i.raw.hovered_files.clone());
+        drop_ui::render_drop_overlay(ctx, &hovered);
+        let mut dropped_text = None;
+        let mut dropped_files = Vec::new();
+        for item in ctx.input(|i| file_ops::dropped_items(&i.raw.dropped_files)) {
+            match item {
+                Dropped::File(path) if dropped_text.is_none() => {
+                    dropped_files.push(OpenRequest { path, line: None, read_only: false });
+                }
+                Dropped::Text(text) if dropped_files.is_empty() => {
+                    dropped_text.get_or_insert_with(String::new).push_str(&text);
+                }
+                _ => {}
+            }
+        }
+        // Files past the first are added to Open Recent, with a note saying so
+        self.open_requests(dropped_files);
+        
         // Menubar at the top
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
             egui::MenuBar::new().ui(ui, |ui| {
-                let action = menu::render_menu(
-                    ui,
-                    &mut self.show_about_window,
-                    self.undo_history.can_undo(),
-                    self.undo_history.can_redo(),
-                    self.show_line_numbers,
-                    self.syntax_highlighting,
-                );
+                let state = menu::MenuState {
+                    can_undo: !self.read_only
+                        && self.large_file.as_ref().map_or(self.undo_history.can_undo(), |view| view.buffer.can_undo()),
+                    can_redo: !self.read_only
+                        && self.large_file.as_ref().map_or(self.undo_history.can_redo(), |view| view.buffer.can_redo()),
+                    read_only: self.read_only,
+                    show_line_numbers: self.settings.editor.show_line_numbers,
+                    syntax_highlighting: self.settings.editor.syntax_highlighting,
+                    auto_indent: self.settings.editor.auto_indent,
+                    auto_close_brackets: self.settings.editor.auto_close_brackets,
+                    indent_style: self.indent_style,
+                    wrap_mode: self.settings.editor.wrap_mode(),
+                    show_outline: self.outline.show_panel,
+                    show_history: self.history.show_panel,
+                    recent: &self.recent,
+                    show_problems: self.lsp.show_problems,
+                    show_output: self.output.show_panel,
+                    running: self.run_job.is_some(),
+                    show_terminal: self.terminal.show_panel,
+                    has_changes: !self.git.hunks.is_empty() || self.compare.is_some(),
+                    has_conflicts: !self.conflicts.conflicts.is_empty(),
+                    keybindings: &self.keybindings,
+                    appearance: self.settings.appearance.appearance,
+                    themes: self.themes.themes(),
+                    editor_theme: self.editor_theme(ctx).name,
+                };
+                let mut action = menu::render_menu(ui, &mut self.show_about_window, &state);
+                if self.large_file.is_some() && !action.works_on_large_files() {
+                    self.show_error(
+                        "This is not available in large-file mode. Raise the large-file size in Preferences to use it on this file."
+                            .to_string(),
+                    );
+                    action = menu::MenuAction::None;
+                }
                 
                 match action {
                     menu::MenuAction::New => self.handle_new_action(),
@@ -339,7 +2115,10 @@ impl eframe::App for MyApp {
                     menu::MenuAction::Save => self.handle_save_action(),
                     menu::MenuAction::SaveAs => self.handle_save_as_action(),
                     menu::MenuAction::Quit => self.handle_quit_action(ctx),
+                    menu::MenuAction::Preferences => self.open_preferences(),
                     menu::MenuAction::Find => self.search.show_bar = !self.search.show_bar,
+                    menu::MenuAction::Undo if let Some(view) = &mut self.large_file => view.undo(),
+                    menu::MenuAction::Redo if let Some(view) = &mut self.large_file => view.redo(),
                     menu::MenuAction::Undo => {
                         self.save_undo_state();
                         self.handle_undo();
@@ -358,6 +2137,67 @@ impl eframe::App for MyApp {
                         self.show_goto_line_dialog = true;
                         self.goto_line_input.clear();
                     }
+                    menu::MenuAction::ToggleAutoIndent => {
+                        self.toggle_auto_indent();
+                    }
+                    menu::MenuAction::ToggleAutoCloseBrackets => {
+                        self.toggle_auto_close_brackets();
+                    }
+                    menu::MenuAction::GoToMatchingBracket => {
+                        self.go_to_matching_bracket(ctx);
+                    }
+                    menu::MenuAction::GoToSymbol => self.open_goto_symbol(),
+                    menu::MenuAction::ToggleOutline => self.outline.show_panel = !self.outline.show_panel,
+                    menu::MenuAction::ToggleFileHistory => self.toggle_file_history(),
+                    menu::MenuAction::ToggleReadOnly => self.toggle_read_only(),
+                    menu::MenuAction::OpenRecent(path) => self.open_recent(path),
+                    menu::MenuAction::TogglePinRecent(path) => self.toggle_pin_recent(&path),
+                    menu::MenuAction::ClearRecent => self.clear_recent(),
+                    menu::MenuAction::ReopenClosedFile => self.reopen_closed_file(),
+                    menu::MenuAction::TriggerCompletion => self.trigger_completion(ctx),
+                    menu::MenuAction::ShowHover => self.show_hover(ctx),
+                    menu::MenuAction::GoToDefinition => self.go_to_definition(ctx),
+                    menu::MenuAction::RenameSymbol => self.open_rename(ctx),
+                    menu::MenuAction::FormatDocument => self.format_document(ctx),
+                    menu::MenuAction::ToggleProblems => self.lsp.show_problems = !self.lsp.show_problems,
+                    menu::MenuAction::ToggleOutput => self.output.show_panel = !self.output.show_panel,
+                    menu::MenuAction::ToggleTerminal => self.toggle_terminal(ctx),
+                    menu::MenuAction::RunFile => self.run_file(ctx),
+                    menu::MenuAction::StopRun => self.stop_run(),
+                    menu::MenuAction::NextChange => self.go_to_change(ctx, true),
+                    menu::MenuAction::PreviousChange => self.go_to_change(ctx, false),
+                    menu::MenuAction::RevertChange => self.revert_change_at_cursor(ctx),
+                    menu::MenuAction::CompareWithSaved => self.compare_with_saved(),
+                    menu::MenuAction::CompareWithFile => self.compare_with_file(),
+                    menu::MenuAction::MergeConflicts => self.open_merge_view(None),
+                    menu::MenuAction::ShellCommand(kind) => self.open_shell_dialog(kind),
+                    menu::MenuAction::Fold => self.fold_at_cursor(ctx),
+                    menu::MenuAction::Unfold => self.unfold_at_cursor(ctx),
+                    menu::MenuAction::FoldAll => {
+                        self.update_folds(ctx, |folds, text| folds.fold_all(text));
+                    }
+                    menu::MenuAction::UnfoldAll => {
+                        self.update_folds(ctx, |folds, text| folds.unfold_all(text));
+                    }
+                    menu::MenuAction::SetWrapMode(mode) => self.set_wrap_mode(mode),
+                    menu::MenuAction::SetAppearance(appearance) => self.set_appearance(ctx, appearance),
+                    menu::MenuAction::SetEditorTheme(theme) => self.set_editor_theme(ctx, theme),
+                    menu::MenuAction::ReloadThemes => self.reload_themes(),
+                    menu::MenuAction::ReloadLanguages => self.reload_languages(),
+                    menu::MenuAction::ChooseFont(family) => self.choose_font(ctx, Some(family)),
+                    menu::MenuAction::ResetFonts => self.choose_font(ctx, None),
+                    menu::MenuAction::ZoomIn => self.zoom(ctx, 1),
+                    menu::MenuAction::ZoomOut => self.zoom(ctx, -1),
+                    menu::MenuAction::ResetZoom => self.zoom(ctx, 0),
+                    menu::MenuAction::FoldLevel(level) => {
+                        self.update_folds(ctx, |folds, text| folds.fold_level(level, text));
+                    }
+                    menu::MenuAction::SetIndentStyle(style) => {
+                        self.indent_style = style;
+                    }
+                    menu::MenuAction::ConvertIndentation(style) => {
+                        self.convert_indentation(style);
+                    }
                     menu::MenuAction::None => {}
                 }
             });
@@ -365,66 +2205,281 @@ impl eframe::App for MyApp {
 
         // Central area: code editor filling the remaining space
         // Capture previous text BEFORE any potential modifications by render_bar (replace)
-        let previous_text = self.text.clone();
-        let last_saved_text = self.last_saved_text.clone();
+        if self.text_before_edits.0 != self.text_revision {
+            self.text_before_edits = (self.text_revision, self.text.clone());
+        }
+        let revision = self.text_revision;
         let last_text_change = self.last_text_change;
 
+        // Auto-indent and Tab handling for the editor
+        self.refresh_folds();
+        self.remap_cursor(ctx);
+        crate::input::handle_completion_keys(ctx, self);
+        crate::input::handle_editor_keys(ctx, self);
+        crate::input::handle_large_file_keys(ctx, self);
+
+        self.conflicts.refresh(&self.text, self.text_revision);
+        if !self.conflicts.conflicts.is_empty() && conflicts_ui::render_conflict_bar(ctx, self.conflicts.conflicts.len()) {
+            self.open_merge_view(None);
+        }
+        
         // Find Bar
-        if self.search.show_bar {
+        if self.search.show_bar
+            && let Some(view) = &mut self.large_file
+        {
+            let action = egui::TopBottomPanel::top("find_panel")
+                .show(ctx, |ui| large_file_ui::render_find_bar(ui, &mut self.search, view.not_found))
+                .inner;
+            match action {
+                Some(large_file_ui::FindAction::Next) => {
+                    view.find(&self.search.query, true);
+                }
+                Some(large_file_ui::FindAction::Previous) => {
+                    view.find(&self.search.query, false);
+                }
+                Some(large_file_ui::FindAction::Edited) => view.not_found = false,
+                None => {}
+            }
+        } else if self.search.show_bar {
+            let previous_match = self.search.current_match_index;
             egui::TopBottomPanel::top("find_panel").show(ctx, |ui| {
-                if self.search.render_bar(ui, &mut self.text) {
+                if self.search.render_bar(ui, &mut self.text, self.read_only) {
                     self.is_dirty = true;
+                    self.text_changed();
                 }
             });
+            // Bring the current match into view, unfolding it if needed
+            if self.search.current_match_index != previous_match
+                && let Some(&start) = self.search.current_match_index.and_then(|i| self.search.results.get(i))
+            {
+                let start_char = indent::char_index(&self.text, start);
+                let end_char = start_char + self.search.query.chars().count();
+                self.reveal_line(self.text[..start].matches('\n').count());
+                self.set_editor_selection(ctx, start_char..end_char);
+            }
         }
 
         egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
-            let language = if self.filename.is_some() {
-                syntax::get_language_name(self.filename.as_ref())
-            } else {
-                ""
+            let registry = languages::registry();
+            let state = status_bar::StatusBarState {
+                filename: &self.filename,
+                is_dirty: self.is_dirty,
+                language: self.language,
+                language_override: self.language_override,
+                languages: registry.languages(),
+                indent_style: self.indent_style,
+                branch: self.git.branch.as_deref(),
+                large_file: self.large_file.is_some(),
+                read_only: self.read_only,
             };
-            status_bar::render_status_bar(ui, &self.filename, self.is_dirty, language);
+            let choice = status_bar::render_status_bar(ui, &state);
+            if let Some(choice) = choice {
+                self.set_language(choice);
+            }
         });
         
+        if self.terminal.show_panel {
+            self.ensure_terminal(ctx);
+            match terminal_ui::render_terminal_panel(ctx, &mut self.terminal) {
+                Some(terminal_ui::TerminalPanelAction::Restart) => {
+                    self.terminal.session = None;
+                    self.terminal.focus_requested = true;
+                }
+                Some(terminal_ui::TerminalPanelAction::Kill) => {
+                    self.terminal.session = None;
+                    self.terminal.show_panel = false;
+                }
+                None => {}
+            }
+        } else {
+            self.terminal.focused = false;
+        }
+        self.poll_run_job(ctx);
+        if self.output.show_panel {
+            let running = self.run_job.is_some();
+            match output::render_output_panel(ctx, &mut self.output, running) {
+                Some(output::OutputPanelAction::Stop) => self.stop_run(),
+                Some(output::OutputPanelAction::Open(location)) => self.open_output_location(ctx, &location),
+                None => {}
+            }
+        }
+        if self.lsp.show_problems
+            && let Some(position) = lsp_ui::render_problems_panel(
+                ctx,
+                &self.lsp.document_diagnostics(),
+                self.lsp.failed.get(self.language).map(String::as_str),
+            )
+        {
+            self.go_to_position(ctx, position);
+        }
+        
+        // Symbols are only looked for while something shows them
+        if self.outline.show_panel || self.show_goto_symbol_dialog {
+            self.outline.refresh(&self.text, self.text_revision, self.language);
+        }
+        if self.history.show_panel {
+            if self.history.file != self.file_path {
+                self.reload_history();
+            }
+            if let Some(action) = history_ui::render_history_panel(ctx, &mut self.history) {
+                self.handle_history_action(action);
+            }
+        }
+        if self.outline.show_panel
+            && let Some(line) = outline::render_outline_panel(ctx, &mut self.outline)
+        {
+            self.go_to_line_start(ctx, line);
+        }
+        
+        let waker = ctx.clone();
+        self.git.refresh(&self.text, self.text_revision, move || waker.request_repaint());
+        
+        // Where popups from the language server are shown
+        let mut cursor_rect = None;
+        let mut conflict_lens = None;
+        let mut drop_at = None;
         egui::CentralPanel::default().show(ctx, |ui| {
             // TODO: Reimplement search highlighting for CodeEditor
             // For now, search will work but without visual highlighting
             
-            let row_height = ui.painter().layout_no_wrap(
-                "A".to_string(),
-                egui::FontId::monospace(14.0),
-                egui::Color32::TRANSPARENT
-            ).size().y;
+            let editor_theme = self.editor_theme(ctx);
+            if let Some(view) = &mut self.large_file {
+                if ctx.memory(|m| m.focused().is_none()) {
+                    ctx.memory_mut(|m| m.request_focus(large_file_ui::editor_id()));
+                }
+                let appearance = &self.settings.appearance;
+                large_file_ui::render_large_file(
+                    ui,
+                    view,
+                    &editor_theme,
+                    appearance.font_size,
+                    self.settings.editor.show_line_numbers,
+                );
+                return;
+            }
+            self.refresh_folds();
+            let language = self.language;
+            if !self.settings.editor.syntax_highlighting {
+                self.tree_highlighter = None;
+            } else if self.tree_highlighter.as_ref().map(TreeHighlighter::language) != Some(language) {
+                self.tree_highlighter = TreeHighlighter::new(language);
+            }
+            let scroll_to = self.scroll_to_line.take().map(|line| {
+                let start: usize = self.text.split('\n').take(line).map(|l| l.chars().count() + 1).sum();
+                self.folds.view.to_display(start.min(self.text.chars().count()))
+            });
             
             egui::ScrollArea::vertical()
                 .id_salt("editor_scroll")
                 .show(ui, |ui| {
-                    if let Some(line) = self.scroll_to_line.take() {
-                        ui.scroll_to_rect(
-                            egui::Rect::from_min_size(
-                                egui::pos2(0.0, line as f32 * row_height),
-                                egui::vec2(0.0, row_height)
-                            ),
-                            Some(egui::Align::Center)
-                        );
+                    let editor = editor::EditorView {
+                        id: "main_editor",
+                        theme: editor_theme,
+                        syntax: syntax::get_syntax_for_language(language, self.settings.editor.syntax_highlighting),
+                        tree: self.tree_highlighter.as_mut(),
+                        fontsize: self.settings.appearance.font_size,
+                        rows: 50, // High minimum row count
+                        show_line_numbers: self.settings.editor.show_line_numbers,
+                        folds: &self.folds,
+                        wrap: self.settings.editor.wrap_mode(),
+                        scroll_to,
+                        changes: self.git.base.as_deref().map(|base| (self.git.hunks.as_slice(), base)),
+                        read_only: self.read_only,
+                    };
+                    // With folds the editor works on the displayed text, and
+                    // edits are mapped back onto the buffer
+                    let mut toggled_fold = None;
+                    let output = if self.folds.view.is_identity() {
+                        let output = editor.show(ui, &mut self.text);
+                        if output.text_edit.response.changed() {
+                            self.text_changed();
+                        }
+                        output
+                    } else {
+                        let mut display = self.folds.view.text.clone();
+                        let output = editor.show(ui, &mut display);
+                        if output.text_edit.response.changed() {
+                            match self.folds.view.apply_edit(&self.text, &display) {
+                                FoldEdit::Applied(text) => {
+                                    self.text = text;
+                                    self.text_changed();
+                                }
+                                FoldEdit::Unfold(line) => toggled_fold = Some(line),
+                            }
+                        }
+                        output
+                    };
+                    self.editor_id = Some(output.text_edit.response.id);
+                    if let Some(change) = output.clicked_change {
+                        self.git.shown_hunk = Some(change);
                     }
                     
-                    CodeEditor::default()
-                        .id_source("main_editor")
-                        .with_rows(50)  // High minimum row count
-                        .with_fontsize(14.0)
-                        .with_theme(ColorTheme::GITHUB_DARK)
-                        .with_syntax(syntax::get_syntax_for_file(self.filename.as_ref(), self.syntax_highlighting))
-                        .with_numlines(self.show_line_numbers)
-                        .vscroll(false) // Disable internal scrolling to let ScrollArea handle it
-                        .show(ui, &mut self.text);
+                    if let Some(line) = toggled_fold.or(output.toggled_fold) {
+                        self.update_folds(ctx, |folds, text| folds.toggle(line, text));
+                    }
+                    
+                    // Highlight the bracket pair at the cursor
+                    self.brackets.refresh(&self.text, self.text_revision, language);
+                    let view = &self.folds.view;
+                    if let Some(cursor) = output.text_edit.cursor_range.and_then(|r| r.single())
+                        && let Some((open, close)) = self.brackets.pair_at(view.to_original(cursor.index))
+                    {
+                        let shown = |i: usize| view.is_visible(i).then(|| view.to_display(i));
+                        editor::paint_bracket_pair(ui, &output.text_edit, [shown(open), shown(close)]);
+                    }
+                    
+                    // Underline the problems found by the language server
+                    let diagnostics = self.lsp.document_diagnostics();
+                    let squiggles: Vec<_> = diagnostics
+                        .iter()
+                        .filter_map(|d| {
+                            let start = indent::char_index(&self.text, d.start.to_offset(&self.text));
+                            let end = indent::char_index(&self.text, d.end.to_offset(&self.text));
+                            view.is_visible(start).then(|| {
+                                let range = view.to_display(start)..view.to_display(end.max(start));
+                                (range, lsp_ui::severity_color(d.severity), d.message.as_str())
+                            })
+                        })
+                        .collect();
+                    editor::paint_diagnostics(ui, &output.text_edit, &squiggles);
+                    conflict_lens = conflicts_ui::paint_conflicts(
+                        ui,
+                        &output.text_edit,
+                        &self.text,
+                        &self.folds.view,
+                        &self.conflicts.conflicts,
+                    );
+                    
+                    let text_edit = &output.text_edit;
+                    cursor_rect = text_edit.cursor_range.map(|range| {
+                        text_edit.galley.pos_from_cursor(range.primary).translate(text_edit.galley_pos.to_vec2())
+                    });
+                    if dropped_text.is_some() {
+                        drop_at = ctx
+                            .input(|i| i.pointer.latest_pos())
+                            .filter(|pos| text_edit.response.rect.contains(*pos))
+                            .map(|pos| {
+                                let index = text_edit.galley.cursor_from_pos(pos - text_edit.galley_pos).index;
+                                self.folds.view.to_original(index)
+                            });
+                    }
                 });
         });
         
+        if let Some(text) = dropped_text {
+            self.insert_dropped_text(ctx, &text, drop_at);
+        }
+        
         // Check if text has been modified (after the central panel)
-        if self.text != last_saved_text {
-            self.is_dirty = true;
+        let text_changed = self.text_revision != revision;
+        if let Some(view) = &self.large_file {
+            self.is_dirty = view.buffer.is_dirty();
+        } else if !self.is_dirty && self.clean_revision != self.text_revision {
+            if self.text == self.last_saved_text {
+                self.clean_revision = self.text_revision;
+            } else {
+                self.is_dirty = true;
+            }
         }
         
         // Reset suppress flag after first frame
@@ -432,8 +2487,15 @@ impl eframe::App for MyApp {
             self.suppress_undo_save = false;
         }
         
+        // Unsaved buffers are recognised from what is typed into them
+        if text_changed && self.filename.is_none() {
+            self.detect_language();
+        }
+        
         // Handle text changes for undo history with debouncing
-        if self.text != previous_text {
+        if text_changed {
+            // Copied again at the start of the next frame
+            let previous_text = std::mem::take(&mut self.text_before_edits.1);
             let now = Instant::now();
             
             // If this is a new change or enough time has passed, save the pending state
@@ -462,6 +2524,48 @@ impl eframe::App for MyApp {
             }
         }
         
+        // Keep the language server in step with the buffer
+        self.sync_language_server(ctx);
+        self.handle_lsp_events(ctx);
+        self.update_lsp_popups(ctx);
+        if let Some(rect) = cursor_rect {
+            if let Some(matches) = self.completion_matches(ctx)
+                && let Some(popup) = &self.lsp.completion
+            {
+                let items: Vec<_> = matches.iter().map(|&i| &popup.items[i]).collect();
+                if let Some(index) = lsp_ui::render_completion_popup(ctx, rect.left_bottom(), &items, popup.selected) {
+                    self.accept_completion(ctx, index);
+                }
+            }
+            if let Some((text, _)) = &self.lsp.hover {
+                lsp_ui::render_hover(ctx, rect.left_bottom(), text);
+            }
+        }
+        
+        if let Some((index, position)) = self.git.shown_hunk
+            && let (Some(base), Some(hunk)) = (&self.git.base, self.git.hunks.get(index))
+        {
+            match git_ui::render_change_popup(ctx, position, hunk, &diff::old_text(base, hunk)) {
+                Some(git_ui::ChangePopupAction::Revert) => self.revert_change(index),
+                Some(git_ui::ChangePopupAction::Close) => self.git.shown_hunk = None,
+                None => {}
+            }
+        }
+        
+        self.update_compare(ctx);
+        match conflict_lens {
+            Some((index, conflicts_ui::ConflictLens::Accept(resolution))) => self.resolve_conflict(index, resolution),
+            Some((index, conflicts_ui::ConflictLens::Merge)) => self.open_merge_view(Some(index)),
+            None => {}
+        }
+        if self.conflicts.show_merge_view {
+            match conflicts_ui::render_merge_view(ctx, &mut self.conflicts, &self.text) {
+                Some(conflicts_ui::MergeAction::Resolve(index, resolution)) => self.resolve_conflict(index, resolution),
+                Some(conflicts_ui::MergeAction::UseResult(index)) => self.use_merge_result(index),
+                None => {}
+            }
+        }
+        
         // Render all dialogs
         dialogs::render_about_dialog(ctx, &mut self.show_about_window);
         
@@ -484,11 +2588,71 @@ impl eframe::App for MyApp {
             // Lines are 1-indexed for user, 0-indexed internally
             if target_line > 0 {
                 let line_idx = target_line - 1;
-                self.scroll_to_line = Some(line_idx);
+                self.reveal_line(line_idx);
             }
         }
 
+        // Go to Symbol dialog
+        if let Some(line) = dialogs::render_goto_symbol_dialog(
+            ctx,
+            &mut self.show_goto_symbol_dialog,
+            &mut self.goto_symbol_input,
+            &mut self.goto_symbol_selected,
+            &self.outline.symbols,
+        ) {
+            self.go_to_line_start(ctx, line);
+        }
+
+        // Shell commands
+        self.poll_shell_job(ctx);
+        self.poll_format_job();
+        if let Some(command) = dialogs::render_shell_command_dialog(
+            ctx,
+            &mut self.show_shell_dialog,
+            self.shell_command_kind,
+            &mut self.shell_command_input,
+        ) {
+            self.start_shell_command(ctx, self.shell_command_kind, &command);
+        }
+        if let Some(job) = &self.shell_job
+            && dialogs::render_shell_job_window(ctx, &job.command, job.started.elapsed())
+        {
+            job.cancel();
+        }
+
+        // Rename Symbol dialog
+        if let Some(new_name) = dialogs::render_rename_dialog(ctx, &mut self.lsp.show_rename_dialog, &mut self.lsp.rename_input) {
+            self.rename_symbol(ctx, &new_name);
+        }
+
+        // Preferences window
+        if let Some(preferences) = &mut self.preferences {
+            match dialogs::render_preferences_dialog(ctx, preferences, self.themes.themes()) {
+                dialogs::PreferencesAction::Apply(settings) => {
+                    self.apply_settings(ctx, settings);
+                    self.save_settings();
+                }
+                dialogs::PreferencesAction::ApplyAndClose(settings) => {
+                    self.apply_settings(ctx, settings);
+                    self.save_settings();
+                    self.preferences = None;
+                }
+                dialogs::PreferencesAction::Close => self.preferences = None,
+                dialogs::PreferencesAction::None => {}
+            }
+        }
+
+        // Saving over unresolved conflicts
+        if dialogs::render_conflict_save_dialog(ctx, &mut self.conflicts.show_save_warning, self.conflicts.conflicts.len()) {
+            self.save_now();
+        }
+
         // Error dialog
         dialogs::render_error_dialog(ctx, &mut self.show_error_dialog, &self.error_message);
     }
 }
+
+/// Modification time of a file, if it exists
+fn modified_time(path: &std::path::Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
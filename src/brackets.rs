@@ -0,0 +1,419 @@
+use std::ops::Range;
+use crate::indent::{byte_index, char_index};
+
+/// What a character in the buffer belongs to, as far as bracket pairing
+/// is concerned
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CharKind {
+    Code,
+    String,
+    Comment,
+}
+
+/// Comment and string delimiters used to tell code from strings and comments
+#[derive(Clone, Copy, Debug)]
+pub struct PairRules {
+    pub line_comment: Option<&'static str>,
+    pub block_comment: Option<(&'static str, &'static str)>,
+    pub quotes: &'static [char],
+    /// `'` can start a lifetime (`'a`) rather than a char literal
+    pub lifetimes: bool,
+    /// `"""` and `'''` delimit strings
+    pub triple_quotes: bool,
+    /// Strings may continue past the end of a line
+    pub multiline_strings: bool,
+    /// Words that start a string when right before a quote, such as `f` in
+    /// Python's `f'{x}'`, so that the quote is not taken as an apostrophe
+    pub string_prefixes: &'static [&'static str],
+}
+
+impl PairRules {
+    /// Rules for a language name as returned by `syntax::get_language_name`
+    pub fn for_language(language: &str) -> Self {
+        let registry = crate::languages::registry();
+        let definition = registry.by_name(language);
+        let plain = PairRules {
+            line_comment: definition.and_then(|l| l.line_comment),
+            block_comment: definition.and_then(|l| l.block_comment),
+            quotes: definition.map_or(&['"'], |l| l.quotes),
+            lifetimes: false,
+            triple_quotes: false,
+            multiline_strings: false,
+            string_prefixes: &[],
+        };
+        match language {
+            "Rust" => PairRules {
+                lifetimes: true,
+                multiline_strings: true,
+                string_prefixes: &["b"],
+                ..plain
+            },
+            "Python" => PairRules {
+                triple_quotes: true,
+                string_prefixes: &["r", "u", "b", "f", "br", "rb", "fr", "rf"],
+                ..plain
+            },
+            "TOML" => PairRules {
+                triple_quotes: true,
+                ..plain
+            },
+            "Shell" | "SQL" => PairRules {
+                multiline_strings: true,
+                ..plain
+            },
+            _ => plain,
+        }
+    }
+}
+
+fn starts_with_at(chars: &[char], index: usize, pattern: &str) -> bool {
+    pattern
+        .chars()
+        .enumerate()
+        .all(|(offset, p)| chars.get(index + offset) == Some(&p))
+}
+
+/// A `'` in Rust is a char literal only when followed by an escape or a
+/// single character and a closing quote; otherwise it is a lifetime
+fn is_char_literal(chars: &[char], index: usize) -> bool {
+    match (chars.get(index + 1), chars.get(index + 2)) {
+        (Some('\\'), _) => true,
+        (Some(c), Some('\'')) => *c != '\'',
+        _ => false,
+    }
+}
+
+/// Whether the word right before `index` is one of `prefixes`, ignoring case
+fn follows_string_prefix(chars: &[char], index: usize, prefixes: &[&str]) -> bool {
+    let length = chars[..index].iter().rev().take_while(|c| c.is_alphanumeric() || **c == '_').count();
+    let word: String = chars[index - length..index].iter().collect();
+    length > 0 && prefixes.iter().any(|prefix| prefix.eq_ignore_ascii_case(&word))
+}
+
+/// Classify every character of `chars` as code, string or comment
+fn classify_chars(chars: &[char], rules: &PairRules) -> Vec<CharKind> {
+    let mut kinds = vec![CharKind::Code; chars.len()];
+    let mut i = 0;
+
+    while i < chars.len() {
+        if let Some((open, close)) = rules.block_comment
+            && starts_with_at(chars, i, open)
+        {
+            let mut end = i + open.chars().count();
+            while end < chars.len() && !starts_with_at(chars, end, close) {
+                end += 1;
+            }
+            let end = (end + close.chars().count()).min(chars.len());
+            kinds[i..end].fill(CharKind::Comment);
+            i = end;
+            continue;
+        }
+
+        if let Some(line) = rules.line_comment
+            && starts_with_at(chars, i, line)
+        {
+            let end = chars[i..]
+                .iter()
+                .position(|&c| c == '\n')
+                .map_or(chars.len(), |p| i + p);
+            kinds[i..end].fill(CharKind::Comment);
+            i = end;
+            continue;
+        }
+
+        let c = chars[i];
+        let apostrophe = c == '\''
+            && i > 0
+            && chars[i - 1].is_alphanumeric()
+            && !follows_string_prefix(chars, i, rules.string_prefixes);
+        let lifetime = c == '\'' && rules.lifetimes && !is_char_literal(chars, i);
+        if rules.quotes.contains(&c) && !apostrophe && !lifetime {
+            let delimiter: String = if rules.triple_quotes
+                && chars.get(i + 1) == Some(&c)
+                && chars.get(i + 2) == Some(&c)
+            {
+                [c; 3].iter().collect()
+            } else {
+                c.to_string()
+            };
+            let multiline = rules.multiline_strings || delimiter.len() == 3;
+
+            let mut end = i + delimiter.len();
+            while end < chars.len() {
+                if chars[end] == '\\' {
+                    end += 2;
+                    continue;
+                }
+                if starts_with_at(chars, end, &delimiter) {
+                    end += delimiter.len();
+                    break;
+                }
+                if chars[end] == '\n' && !multiline {
+                    break;
+                }
+                end += 1;
+            }
+            let end = end.min(chars.len());
+            kinds[i..end].fill(CharKind::String);
+            i = end;
+            continue;
+        }
+
+        i += 1;
+    }
+
+    kinds
+}
+
+/// Classify every character of `text` as code, string or comment
+///
+/// The returned vector is indexed by char index.
+pub fn classify(text: &str, rules: &PairRules) -> Vec<CharKind> {
+    let chars: Vec<char> = text.chars().collect();
+    classify_chars(&chars, rules)
+}
+
+/// What a character inserted at `cursor` (char index) would belong to
+pub fn context_at(text: &str, cursor: usize, rules: &PairRules) -> CharKind {
+    // Classify the prefix followed by a neutral placeholder: it takes on
+    // the kind of any string or comment still open at the cursor
+    let mut chars: Vec<char> = text[..byte_index(text, cursor)].chars().collect();
+    chars.push('\u{0}');
+    *classify_chars(&chars, rules).last().unwrap_or(&CharKind::Code)
+}
+
+/// The closing bracket for an opening one
+fn matching_closer(open: char) -> Option<char> {
+    match open {
+        '(' => Some(')'),
+        '[' => Some(']'),
+        '{' => Some('}'),
+        _ => None,
+    }
+}
+
+/// The opening bracket for a closing one
+fn matching_opener(close: char) -> Option<char> {
+    match close {
+        ')' => Some('('),
+        ']' => Some('['),
+        '}' => Some('{'),
+        _ => None,
+    }
+}
+
+/// Find the bracket matching the one at `index`, skipping strings and comments
+fn matching_index(chars: &[char], kinds: &[CharKind], index: usize) -> Option<usize> {
+    if kinds.get(index) != Some(&CharKind::Code) {
+        return None;
+    }
+    let c = chars[index];
+    let mut depth = 0usize;
+
+    if let Some(close) = matching_closer(c) {
+        for i in index + 1..chars.len() {
+            if kinds[i] != CharKind::Code {
+                continue;
+            }
+            if chars[i] == c {
+                depth += 1;
+            } else if chars[i] == close {
+                if depth == 0 {
+                    return Some(i);
+                }
+                depth -= 1;
+            }
+        }
+    } else if let Some(open) = matching_opener(c) {
+        for i in (0..index).rev() {
+            if kinds[i] != CharKind::Code {
+                continue;
+            }
+            if chars[i] == c {
+                depth += 1;
+            } else if chars[i] == open {
+                if depth == 0 {
+                    return Some(i);
+                }
+                depth -= 1;
+            }
+        }
+    }
+    None
+}
+
+/// Find the bracket pair touching the cursor (char index)
+///
+/// The character after the cursor is checked first, then the one before
+/// it. Returns the char indices of the opening and closing bracket.
+pub fn bracket_pair_at(text: &str, cursor: usize, language: &str) -> Option<(usize, usize)> {
+    let mut state = BracketState::default();
+    state.refresh(text, 0, language);
+    state.pair_at(cursor)
+}
+
+/// Classification of the current buffer, kept for highlighting the
+/// bracket pair at the cursor until the buffer changes
+#[derive(Default)]
+pub struct BracketState {
+    chars: Vec<char>,
+    kinds: Vec<CharKind>,
+    /// Revision of the text and the language the chars were classified for
+    classified: Option<(u64, String)>,
+}
+
+impl BracketState {
+    /// Classify the buffer again if its revision or language changed
+    pub fn refresh(&mut self, text: &str, revision: u64, language: &str) {
+        if self.classified.as_ref().is_some_and(|(r, l)| *r == revision && l == language) {
+            return;
+        }
+        self.classified = Some((revision, language.to_string()));
+        self.chars = text.chars().collect();
+        self.kinds = classify_chars(&self.chars, &PairRules::for_language(language));
+    }
+
+    /// The bracket pair touching the cursor, as `bracket_pair_at` finds it
+    pub fn pair_at(&self, cursor: usize) -> Option<(usize, usize)> {
+        [Some(cursor), cursor.checked_sub(1)]
+            .into_iter()
+            .flatten()
+            .filter(|&i| i < self.chars.len())
+            .find_map(|i| matching_index(&self.chars, &self.kinds, i).map(|m| (i.min(m), i.max(m))))
+    }
+}
+
+/// Where "Go to Matching Bracket" should move the cursor (char index)
+///
+/// Jumps to the partner of a bracket touching the cursor, or to the
+/// opening bracket of the innermost pair enclosing it.
+pub fn matching_bracket_target(text: &str, cursor: usize, language: &str) -> Option<usize> {
+    let chars: Vec<char> = text.chars().collect();
+    let kinds = classify_chars(&chars, &PairRules::for_language(language));
+
+    let adjacent = [Some(cursor), cursor.checked_sub(1)]
+        .into_iter()
+        .flatten()
+        .filter(|&i| i < chars.len())
+        .find_map(|i| matching_index(&chars, &kinds, i));
+    if adjacent.is_some() {
+        return adjacent;
+    }
+
+    // Not on a bracket: find the innermost unclosed opener before the cursor
+    let mut depth = 0usize;
+    for i in (0..cursor.min(chars.len())).rev() {
+        if kinds[i] != CharKind::Code {
+            continue;
+        }
+        // Pairs closed before the cursor are skipped over
+        if matching_opener(chars[i]).is_some() {
+            depth += 1;
+        } else if matching_closer(chars[i]).is_some() {
+            if depth == 0 {
+                return Some(i);
+            }
+            depth -= 1;
+        }
+    }
+    None
+}
+
+/// Characters after which an automatically inserted closing character is
+/// welcome
+fn allows_pair_before(next: Option<char>) -> bool {
+    match next {
+        None => true,
+        Some(c) => c.is_whitespace() || matches!(c, ')' | ']' | '}' | ',' | ';' | ':'),
+    }
+}
+
+/// Type `typed` over `selection` with bracket assistance
+///
+/// - a non-empty selection typed over with an opening bracket or quote is
+///   wrapped in the pair instead of being replaced;
+/// - typing a closing bracket or quote right before the same character
+///   moves over it instead of inserting a duplicate;
+/// - an opening bracket or quote typed in code is followed by its closing
+///   character, unless it is a Rust lifetime or an apostrophe.
+///
+/// Returns the new selection (char indices), or `None` if the character
+/// should be inserted normally.
+pub fn type_char(
+    text: &mut String,
+    selection: Range<usize>,
+    typed: char,
+    language: &str,
+) -> Option<Range<usize>> {
+    let rules = PairRules::for_language(language);
+    let is_quote = rules.quotes.contains(&typed);
+    let closing = matching_closer(typed).or(if is_quote { Some(typed) } else { None });
+
+    let start = byte_index(text, selection.start);
+    let end = byte_index(text, selection.end);
+
+    if start != end {
+        let close = closing?;
+        text.insert(end, close);
+        text.insert(start, typed);
+        return Some(selection.start + 1..selection.end + 1);
+    }
+
+    let cursor = selection.start;
+    let next = text[start..].chars().next();
+    let previous = text[..start].chars().next_back();
+    let context = context_at(text, cursor, &rules);
+
+    // Smart overtype of a closing character that is already there
+    if next == Some(typed) {
+        let overtype = if is_quote {
+            context == CharKind::String
+        } else {
+            matching_opener(typed).is_some() && context == CharKind::Code
+        };
+        if overtype {
+            return Some(cursor + 1..cursor + 1);
+        }
+    }
+
+    let close = closing?;
+    if context != CharKind::Code || !allows_pair_before(next) {
+        return None;
+    }
+    if is_quote {
+        let after_word = previous.is_some_and(|c| c.is_alphanumeric() || c == '_');
+        let lifetime = typed == '\''
+            && rules.lifetimes
+            && previous.is_some_and(|c| matches!(c, '&' | '<' | ','));
+        if after_word || lifetime || previous == Some(typed) {
+            return None;
+        }
+    }
+
+    let mut pair = String::with_capacity(2);
+    pair.push(typed);
+    pair.push(close);
+    text.insert_str(start, &pair);
+    Some(cursor + 1..cursor + 1)
+}
+
+/// Backspace with the cursor between an empty pair such as `(|)` or `"|"`
+/// removes both characters
+///
+/// Returns the new cursor position (char index) if a pair was deleted.
+pub fn delete_pair(text: &mut String, cursor: usize, language: &str) -> Option<usize> {
+    let rules = PairRules::for_language(language);
+    let at = byte_index(text, cursor);
+    let previous = text[..at].chars().next_back()?;
+    let next = text[at..].chars().next()?;
+
+    let pairs = matching_closer(previous) == Some(next)
+        || (rules.quotes.contains(&previous) && previous == next);
+    if !pairs || context_at(text, cursor - 1, &rules) != CharKind::Code {
+        return None;
+    }
+
+    let before = at - previous.len_utf8();
+    text.replace_range(before..at + next.len_utf8(), "");
+    Some(char_index(text, before))
+}
@@ -0,0 +1,165 @@
+use std::path::PathBuf;
+use crate::diff::{self, DiffRow, Hunk};
+
+/// Where the text on one side of a comparison comes from
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CompareSource {
+    /// The current file as last saved
+    Saved,
+    /// The editor buffer, edited in place
+    Buffer,
+    File(PathBuf),
+    /// A version from the local file history, which is never written to
+    History,
+}
+
+impl CompareSource {
+    /// Whether the side can be written back to disk
+    pub fn can_save(&self) -> bool {
+        matches!(self, CompareSource::Saved | CompareSource::File(_))
+    }
+}
+
+/// Left or right side of a comparison
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+/// One of the two texts being compared
+pub struct CompareSide {
+    pub source: CompareSource,
+    pub title: String,
+    pub text: String,
+    /// Hunks were copied into this side and it was not saved since
+    pub modified: bool,
+    /// Revision of the editor buffer a `Buffer` side was last copied from
+    pub revision: Option<u64>,
+}
+
+impl CompareSide {
+    pub fn new(source: CompareSource, title: impl Into<String>, text: String) -> Self {
+        Self { source, title: title.into(), text, modified: false, revision: None }
+    }
+
+    /// Read a file from disk
+    pub fn from_file(path: PathBuf) -> Result<Self, String> {
+        let text = std::fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        Ok(Self::new(CompareSource::File(path.clone()), path.display().to_string(), text))
+    }
+}
+
+/// Two texts shown side by side or as a unified diff
+pub struct CompareView {
+    pub left: CompareSide,
+    pub right: CompareSide,
+    pub hunks: Vec<Hunk>,
+    /// Rows of the current layout
+    pub rows: Vec<DiffRow>,
+    /// Hunk selected for navigation and copying
+    pub current: Option<usize>,
+    pub unified: bool,
+    /// Scroll the current hunk into view next frame
+    pub scroll_to_current: bool,
+}
+
+impl CompareView {
+    pub fn new(left: CompareSide, right: CompareSide) -> Self {
+        let mut view = Self {
+            left,
+            right,
+            hunks: Vec::new(),
+            rows: Vec::new(),
+            current: None,
+            unified: false,
+            scroll_to_current: false,
+        };
+        view.recompute();
+        if !view.hunks.is_empty() {
+            view.current = Some(0);
+            view.scroll_to_current = true;
+        }
+        view
+    }
+
+    pub fn side(&self, side: Side) -> &CompareSide {
+        match side {
+            Side::Left => &self.left,
+            Side::Right => &self.right,
+        }
+    }
+
+    pub fn side_mut(&mut self, side: Side) -> &mut CompareSide {
+        match side {
+            Side::Left => &mut self.left,
+            Side::Right => &mut self.right,
+        }
+    }
+
+    /// Replace one side's text, keeping the selected hunk where possible
+    pub fn set_text(&mut self, side: Side, text: String) {
+        if self.side(side).text != text {
+            self.side_mut(side).text = text;
+            self.recompute();
+        }
+    }
+
+    /// Diff the two sides again
+    pub fn recompute(&mut self) {
+        self.hunks = diff::line_hunks(&self.left.text, &self.right.text);
+        self.layout();
+        self.current = self.current.map(|index| index.min(self.hunks.len().saturating_sub(1)));
+        if self.hunks.is_empty() {
+            self.current = None;
+        }
+    }
+
+    /// Switch between side-by-side and unified rows
+    pub fn set_unified(&mut self, unified: bool) {
+        self.unified = unified;
+        self.layout();
+        self.scroll_to_current = true;
+    }
+
+    fn layout(&mut self) {
+        let old_lines = diff::split_lines(&self.left.text).len();
+        let new_lines = diff::split_lines(&self.right.text).len();
+        self.rows = if self.unified {
+            diff::unified_rows(&self.hunks, old_lines, new_lines)
+        } else {
+            diff::side_by_side_rows(&self.hunks, old_lines, new_lines)
+        };
+    }
+
+    /// Select the next hunk, or the previous one when `forward` is false,
+    /// wrapping around at either end
+    pub fn step(&mut self, forward: bool) {
+        let count = self.hunks.len();
+        if count == 0 {
+            return;
+        }
+        self.current = Some(match self.current {
+            None if forward => 0,
+            None => count - 1,
+            Some(index) if forward => (index + 1) % count,
+            Some(index) => (index + count - 1) % count,
+        });
+        self.scroll_to_current = true;
+    }
+
+    /// First row of the current hunk
+    pub fn current_row(&self) -> Option<usize> {
+        let current = self.current?;
+        self.rows.iter().position(|row| row.hunk == Some(current))
+    }
+
+    /// The text of side `to` after copying a hunk over from the other side
+    pub fn copied_hunk(&self, index: usize, to: Side) -> Option<String> {
+        let hunk = self.hunks.get(index)?;
+        Some(match to {
+            Side::Right => diff::revert_hunk(&self.left.text, &self.right.text, hunk),
+            Side::Left => diff::revert_hunk(&self.right.text, &self.left.text, &hunk.reversed()),
+        })
+    }
+}
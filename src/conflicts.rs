@@ -0,0 +1,164 @@
+use std::ops::Range;
+
+/// Length of a conflict marker such as `<<<<<<<`
+const MARKER_LEN: usize = 7;
+
+/// A region left by a merge that could not combine two changes, as lines
+/// of its markers
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Conflict {
+    /// Line of the `<<<<<<<` marker
+    pub start: usize,
+    /// Line of the `|||||||` marker in diff3-style conflicts
+    pub base: Option<usize>,
+    /// Line of the `=======` marker
+    pub separator: usize,
+    /// Line of the `>>>>>>>` marker
+    pub end: usize,
+    /// What follows the opening marker, usually `HEAD`
+    pub ours_label: String,
+    /// What follows the closing marker, usually the merged branch
+    pub theirs_label: String,
+}
+
+impl Conflict {
+    /// Lines of our side
+    pub fn ours(&self) -> Range<usize> {
+        self.start + 1..self.base.unwrap_or(self.separator)
+    }
+
+    /// Lines of the common ancestor, when the markers include it
+    pub fn base_lines(&self) -> Option<Range<usize>> {
+        Some(self.base? + 1..self.separator)
+    }
+
+    /// Lines of their side
+    pub fn theirs(&self) -> Range<usize> {
+        self.separator + 1..self.end
+    }
+}
+
+/// Which side of a conflict to keep
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Resolution {
+    Ours,
+    Theirs,
+    /// Ours followed by theirs
+    Both,
+    /// The common ancestor
+    Base,
+}
+
+/// Label after a marker at the start of a line, if the line is one
+fn marker(line: &str, character: char) -> Option<&str> {
+    let line = line.strip_suffix('\r').unwrap_or(line);
+    let rest = line.strip_prefix(&character.to_string().repeat(MARKER_LEN) as &str)?;
+    if rest.is_empty() {
+        Some("")
+    } else {
+        rest.strip_prefix(' ')
+    }
+}
+
+/// Conflicts in a text, in order; unfinished or nested markers are not
+/// conflicts
+pub fn find_conflicts(text: &str) -> Vec<Conflict> {
+    let mut conflicts = Vec::new();
+    // Opening line, label, base marker line and separator line so far
+    let mut open: Option<(usize, &str, Option<usize>, Option<usize>)> = None;
+    for (index, line) in text.split('\n').enumerate() {
+        if let Some(label) = marker(line, '<') {
+            open = Some((index, label, None, None));
+        } else if let Some((_, _, base, separator)) = &mut open {
+            if marker(line, '|').is_some() && base.is_none() && separator.is_none() {
+                *base = Some(index);
+            } else if marker(line, '=').is_some_and(str::is_empty) && separator.is_none() {
+                *separator = Some(index);
+            } else if let Some(label) = marker(line, '>')
+                && let Some((start, ours_label, base, Some(separator))) = open.take()
+            {
+                conflicts.push(Conflict {
+                    start,
+                    base,
+                    separator,
+                    end: index,
+                    ours_label: ours_label.to_string(),
+                    theirs_label: label.to_string(),
+                });
+            }
+        }
+    }
+    conflicts
+}
+
+/// Lines of a text, joined again
+pub fn lines_text(text: &str, lines: Range<usize>) -> String {
+    text.split('\n').skip(lines.start).take(lines.len()).collect::<Vec<_>>().join("\n")
+}
+
+/// The text a resolution keeps of a conflict
+pub fn resolved_text(text: &str, conflict: &Conflict, resolution: Resolution) -> String {
+    let section = |lines: Range<usize>| lines_text(text, lines);
+    match resolution {
+        Resolution::Ours => section(conflict.ours()),
+        Resolution::Theirs => section(conflict.theirs()),
+        Resolution::Both => {
+            let parts: Vec<String> = [conflict.ours(), conflict.theirs()]
+                .into_iter()
+                .filter(|lines| !lines.is_empty())
+                .map(section)
+                .collect();
+            parts.join("\n")
+        }
+        Resolution::Base => conflict.base_lines().map(section).unwrap_or_default(),
+    }
+}
+
+/// `text` with a conflict and its markers replaced by `replacement`,
+/// which removes the lines entirely when it is empty
+pub fn replace_conflict(text: &str, conflict: &Conflict, replacement: &str) -> String {
+    let lines: Vec<&str> = text.split('\n').collect();
+    let end = (conflict.end + 1).min(lines.len());
+    let mut result: Vec<&str> = lines[..conflict.start].to_vec();
+    if !replacement.is_empty() {
+        result.extend(replacement.split('\n'));
+    }
+    result.extend_from_slice(&lines[end..]);
+    result.join("\n")
+}
+
+/// `text` with a conflict resolved
+pub fn resolve(text: &str, conflict: &Conflict, resolution: Resolution) -> String {
+    replace_conflict(text, conflict, &resolved_text(text, conflict, resolution))
+}
+
+/// Conflicts of the current buffer and the merge view resolving them
+#[derive(Default)]
+pub struct ConflictState {
+    pub conflicts: Vec<Conflict>,
+    pub show_merge_view: bool,
+    /// Conflict shown in the merge view
+    pub current: usize,
+    /// Hand-merged text of the current conflict
+    pub result: String,
+    /// Conflict `result` was started from
+    pub result_for: Option<Conflict>,
+    /// Ask before saving a file that still has conflicts
+    pub show_save_warning: bool,
+    /// Revision of the buffer the conflicts were found in
+    found_in: Option<u64>,
+}
+
+impl ConflictState {
+    /// Look for conflicts again if the buffer's revision changed
+    pub fn refresh(&mut self, text: &str, revision: u64) {
+        if self.found_in != Some(revision) {
+            self.found_in = Some(revision);
+            self.conflicts = find_conflicts(text);
+            self.current = self.current.min(self.conflicts.len().saturating_sub(1));
+            if self.conflicts.is_empty() {
+                self.show_merge_view = false;
+            }
+        }
+    }
+}
@@ -0,0 +1,217 @@
+use std::ops::Range;
+use std::time::{Duration, Instant};
+use similar::{Algorithm, DiffTag};
+
+/// Longest a line diff may take before it settles for a coarser result
+const DIFF_DEADLINE: Duration = Duration::from_millis(200);
+
+/// How a run of lines changed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Modified,
+    Deleted,
+}
+
+/// A run of changed lines between an old and a new text
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Hunk {
+    pub kind: ChangeKind,
+    /// Lines of the old text; empty where lines were added
+    pub old: Range<usize>,
+    /// Lines of the new text; empty where lines were deleted
+    pub new: Range<usize>,
+}
+
+/// Lines of a text without their line endings, so files differing only
+/// in `\r\n` compare equal
+pub fn split_lines(text: &str) -> Vec<&str> {
+    text.split('\n').map(|line| line.strip_suffix('\r').unwrap_or(line)).collect()
+}
+
+/// The changed runs of lines, in order
+pub fn line_hunks(old: &str, new: &str) -> Vec<Hunk> {
+    let (old, new) = (split_lines(old), split_lines(new));
+    let deadline = Instant::now() + DIFF_DEADLINE;
+    let ops = similar::capture_diff_slices_deadline(Algorithm::Myers, &old, &new, Some(deadline));
+    let mut hunks: Vec<Hunk> = Vec::new();
+    for op in ops {
+        let (tag, old, new) = op.as_tag_tuple();
+        if tag == DiffTag::Equal {
+            continue;
+        }
+        // A deletion directly followed by an insertion is a modification
+        if let Some(last) = hunks.last_mut()
+            && last.old.end == old.start
+            && last.new.end == new.start
+        {
+            last.old.end = old.end;
+            last.new.end = new.end;
+            last.kind = ChangeKind::Modified;
+            continue;
+        }
+        let kind = match tag {
+            DiffTag::Insert => ChangeKind::Added,
+            DiffTag::Delete => ChangeKind::Deleted,
+            _ => ChangeKind::Modified,
+        };
+        hunks.push(Hunk { kind, old, new });
+    }
+    hunks
+}
+
+/// Index of the hunk at a line of the new text; deleted lines belong to
+/// the line after them
+pub fn hunk_at(hunks: &[Hunk], line: usize) -> Option<usize> {
+    hunks
+        .iter()
+        .position(|hunk| hunk.new.contains(&line) || (hunk.new.is_empty() && hunk.new.start == line))
+}
+
+/// Index of the first hunk starting after `line`, wrapping around to the
+/// first one
+pub fn next_hunk(hunks: &[Hunk], line: usize) -> Option<usize> {
+    hunks.iter().position(|hunk| hunk.new.start > line).or((!hunks.is_empty()).then_some(0))
+}
+
+/// Index of the last hunk ending before `line`, wrapping around to the
+/// last one
+pub fn previous_hunk(hunks: &[Hunk], line: usize) -> Option<usize> {
+    hunks
+        .iter()
+        .rposition(|hunk| hunk.new.end.max(hunk.new.start + 1) <= line)
+        .or(hunks.len().checked_sub(1))
+}
+
+/// The old text of a hunk, one line per line
+pub fn old_text(old: &str, hunk: &Hunk) -> String {
+    old.split('\n').skip(hunk.old.start).take(hunk.old.len()).collect::<Vec<_>>().join("\n")
+}
+
+/// Lines of a text with their line endings, numbered as `split_lines`
+/// numbers them
+fn lines_with_endings(text: &str) -> Vec<&str> {
+    let mut lines: Vec<&str> = text.split_inclusive('\n').collect();
+    if text.is_empty() || text.ends_with('\n') {
+        lines.push("");
+    }
+    lines
+}
+
+/// `new` with one hunk put back the way it was in `old`, each line keeping
+/// its own line ending
+pub fn revert_hunk(old: &str, new: &str, hunk: &Hunk) -> String {
+    let old_lines = lines_with_endings(old);
+    let mut lines = lines_with_endings(new);
+    let end = hunk.new.end.min(lines.len());
+    lines.splice(hunk.new.start.min(end)..end, old_lines[hunk.old.clone()].iter().copied());
+    // The last line of `old` may now be followed by others
+    let ending = if new.contains("\r\n") { "\r\n" } else { "\n" };
+    let last = lines.len().saturating_sub(1);
+    let mut text = String::with_capacity(new.len());
+    for (index, line) in lines.iter().enumerate() {
+        text.push_str(line);
+        if index < last && !line.ends_with('\n') {
+            text.push_str(ending);
+        }
+    }
+    text
+}
+
+impl Hunk {
+    /// The same change seen from the other side
+    pub fn reversed(&self) -> Hunk {
+        let kind = match self.kind {
+            ChangeKind::Added => ChangeKind::Deleted,
+            ChangeKind::Deleted => ChangeKind::Added,
+            ChangeKind::Modified => ChangeKind::Modified,
+        };
+        Hunk { kind, old: self.new.clone(), new: self.old.clone() }
+    }
+}
+
+/// One row of a diff view: a line of either side or both, and the hunk
+/// it is part of
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DiffRow {
+    pub old: Option<usize>,
+    pub new: Option<usize>,
+    pub hunk: Option<usize>,
+}
+
+/// Rows showing both texts next to each other, the lines of a hunk
+/// paired up and the shorter side padded
+pub fn side_by_side_rows(hunks: &[Hunk], old_lines: usize, new_lines: usize) -> Vec<DiffRow> {
+    diff_rows(hunks, old_lines, new_lines, |rows, index, hunk| {
+        for offset in 0..hunk.old.len().max(hunk.new.len()) {
+            rows.push(DiffRow {
+                old: (offset < hunk.old.len()).then_some(hunk.old.start + offset),
+                new: (offset < hunk.new.len()).then_some(hunk.new.start + offset),
+                hunk: Some(index),
+            });
+        }
+    })
+}
+
+/// Rows of a unified diff, the old lines of every hunk above its new ones
+pub fn unified_rows(hunks: &[Hunk], old_lines: usize, new_lines: usize) -> Vec<DiffRow> {
+    diff_rows(hunks, old_lines, new_lines, |rows, index, hunk| {
+        rows.extend(hunk.old.clone().map(|line| DiffRow { old: Some(line), new: None, hunk: Some(index) }));
+        rows.extend(hunk.new.clone().map(|line| DiffRow { old: None, new: Some(line), hunk: Some(index) }));
+    })
+}
+
+fn diff_rows(
+    hunks: &[Hunk],
+    old_lines: usize,
+    new_lines: usize,
+    mut push_hunk: impl FnMut(&mut Vec<DiffRow>, usize, &Hunk),
+) -> Vec<DiffRow> {
+    let mut rows = Vec::with_capacity(old_lines.max(new_lines));
+    let (mut old, mut new) = (0, 0);
+    for (index, hunk) in hunks.iter().enumerate() {
+        while old < hunk.old.start && new < hunk.new.start {
+            rows.push(DiffRow { old: Some(old), new: Some(new), hunk: None });
+            old += 1;
+            new += 1;
+        }
+        push_hunk(&mut rows, index, hunk);
+        (old, new) = (hunk.old.end, hunk.new.end);
+    }
+    while old < old_lines && new < new_lines {
+        rows.push(DiffRow { old: Some(old), new: Some(new), hunk: None });
+        old += 1;
+        new += 1;
+    }
+    rows
+}
+
+/// Byte ranges of the characters that differ between two versions of a
+/// line, in the old and in the new line
+pub fn inline_changes(old: &str, new: &str) -> (Vec<Range<usize>>, Vec<Range<usize>>) {
+    let old_chars: Vec<(usize, char)> = old.char_indices().collect();
+    let new_chars: Vec<(usize, char)> = new.char_indices().collect();
+    let (old_only, new_only): (Vec<char>, Vec<char>) =
+        (old_chars.iter().map(|c| c.1).collect(), new_chars.iter().map(|c| c.1).collect());
+    let deadline = Instant::now() + DIFF_DEADLINE;
+    let ops = similar::capture_diff_slices_deadline(Algorithm::Myers, &old_only, &new_only, Some(deadline));
+    let bytes = |chars: &[(usize, char)], text: &str, range: Range<usize>| {
+        let start = chars.get(range.start).map_or(text.len(), |c| c.0);
+        let end = chars.get(range.end).map_or(text.len(), |c| c.0);
+        start..end
+    };
+    let (mut old_ranges, mut new_ranges) = (Vec::new(), Vec::new());
+    for op in ops {
+        let (tag, old_range, new_range) = op.as_tag_tuple();
+        if tag == DiffTag::Equal {
+            continue;
+        }
+        if !old_range.is_empty() {
+            old_ranges.push(bytes(&old_chars, old, old_range));
+        }
+        if !new_range.is_empty() {
+            new_ranges.push(bytes(&new_chars, new, new_range));
+        }
+    }
+    (old_ranges, new_ranges)
+}
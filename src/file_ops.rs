@@ -1,18 +1,36 @@
 use std::io;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use eframe::egui;
+use crate::large_file::RopeBuffer;
 
-/// Open a file and return its contents along with metadata
-pub fn open_file(path: PathBuf) -> io::Result<(String, String, PathBuf)> {
-    let contents = std::fs::read_to_string(&path)?;
-    let filename = path
+/// Name of a file as shown to the user
+pub fn file_name(path: &Path) -> String {
+    path
         .file_name()
         .and_then(|n| n.to_str())
         .map(|s| s.to_string())
-        .unwrap_or_else(|| "untitled".to_string());
+        .unwrap_or_else(|| "untitled".to_string())
+}
+
+/// Whether a file's permissions mark it read-only
+pub fn is_read_only(path: &Path) -> bool {
+    std::fs::metadata(path).is_ok_and(|metadata| metadata.permissions().readonly())
+}
+
+/// Open a file and return its contents along with metadata
+pub fn open_file(path: PathBuf) -> io::Result<(String, String, PathBuf)> {
+    let contents = std::fs::read_to_string(&path)?;
+    let filename = file_name(&path);
     
     Ok((contents.clone(), filename, path))
 }
 
+/// Open a file in a rope for large-file mode, along with its name
+pub fn open_large_file(path: PathBuf) -> io::Result<(RopeBuffer, String, PathBuf)> {
+    let buffer = RopeBuffer::load(&path)?;
+    Ok((buffer, file_name(&path), path))
+}
+
 /// Save text to an existing file path
 pub fn save_file(path: &PathBuf, text: &str) -> io::Result<()> {
     std::fs::write(path, text)
@@ -21,11 +39,28 @@ pub fn save_file(path: &PathBuf, text: &str) -> io::Result<()> {
 /// Save text to a new file path and return the filename
 pub fn save_file_as(path: PathBuf, text: &str) -> io::Result<(String, PathBuf)> {
     std::fs::write(&path, text)?;
-    let filename = path
-        .file_name()
-        .and_then(|n| n.to_str())
-        .map(|s| s.to_string())
-        .unwrap_or_else(|| "untitled".to_string());
+    let filename = file_name(&path);
     
     Ok((filename, path))
 }
+
+/// Something dropped onto the window
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Dropped {
+    File(PathBuf),
+    /// Text dropped from another application
+    Text(String),
+}
+
+/// What was dropped: files by path, and text from drops that carry their
+/// contents instead of a path
+pub fn dropped_items(files: &[egui::DroppedFile]) -> Vec<Dropped> {
+    files
+        .iter()
+        .filter_map(|file| match (&file.path, &file.bytes) {
+            (Some(path), _) => Some(Dropped::File(path.clone())),
+            (None, Some(bytes)) => std::str::from_utf8(bytes).ok().map(|text| Dropped::Text(text.to_string())),
+            (None, None) => None,
+        })
+        .collect()
+}
@@ -0,0 +1,153 @@
+use std::ops::Range;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+use std::time::Instant;
+use crate::indent;
+use crate::process::{self, ProcessOutput};
+
+/// The ways of running a shell command from the editor
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShellCommandKind {
+    /// Replace the selection, or the whole buffer, with the command's
+    /// output for it
+    Filter,
+    /// Insert the command's output at the cursor
+    Insert,
+    /// Run the command with the file's path and show its output
+    RunOnFile,
+}
+
+impl ShellCommandKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ShellCommandKind::Filter => "Filter Through Command",
+            ShellCommandKind::Insert => "Insert Command Output",
+            ShellCommandKind::RunOnFile => "Run Command on File",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            ShellCommandKind::Filter => "The selection, or the whole file, is replaced by the command's output for it.",
+            ShellCommandKind::Insert => "The command's output is inserted at the cursor.",
+            ShellCommandKind::RunOnFile => "{file} is replaced by the file's path, which is otherwise added at the end.",
+        }
+    }
+}
+
+/// Where a command's output goes
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ShellAction {
+    /// Replace a char range of the buffer
+    Replace(Range<usize>),
+    /// Insert at a char index
+    Insert(usize),
+    /// Show in the output panel
+    Show,
+}
+
+/// Command line running `command` on a file: `{file}` is replaced by the
+/// quoted path, which is appended when there is no placeholder
+pub fn command_on_file(command: &str, file: &Path) -> String {
+    let quoted = process::shell_quote(&file.to_string_lossy());
+    if command.contains("{file}") {
+        command.replace("{file}", &quoted)
+    } else {
+        format!("{} {}", command, quoted)
+    }
+}
+
+/// The buffer with a command's output applied, and the char range the
+/// output now covers
+///
+/// Commands end their output with a newline, which is dropped unless the
+/// replaced text ended with one too, so filtering part of a line keeps it
+/// on one line.
+pub fn apply_output(text: &str, action: &ShellAction, output: &str) -> Option<(String, Range<usize>)> {
+    let range = match action {
+        ShellAction::Replace(range) => range.clone(),
+        ShellAction::Insert(index) => *index..*index,
+        ShellAction::Show => return None,
+    };
+    let start = indent::byte_index(text, range.start);
+    let end = indent::byte_index(text, range.end);
+    let output = if text[start..end].ends_with('\n') {
+        output
+    } else {
+        let trimmed = output.strip_suffix('\n').unwrap_or(output);
+        trimmed.strip_suffix('\r').unwrap_or(trimmed)
+    };
+    let mut result = text.to_string();
+    result.replace_range(start..end, output);
+    Some((result, range.start..range.start + output.chars().count()))
+}
+
+/// A shell command running in the background
+pub struct ShellJob {
+    pub command: String,
+    pub action: ShellAction,
+    /// The buffer when the command started, so output is not applied to
+    /// text that changed meanwhile
+    pub original: String,
+    pub started: Instant,
+    cancel: Arc<AtomicBool>,
+    result: Receiver<Result<ProcessOutput, String>>,
+}
+
+impl ShellJob {
+    /// Start a command with the shell, with `input` on its stdin
+    ///
+    /// `on_done` is called from a background thread when the command
+    /// finished.
+    pub fn start(
+        command: &str,
+        input: Option<String>,
+        dir: Option<&Path>,
+        action: ShellAction,
+        original: String,
+        on_done: impl Fn() + Send + 'static,
+    ) -> Self {
+        let mut process = process::shell_command(command);
+        if let Some(dir) = dir {
+            process.current_dir(dir);
+        }
+        let cancel = Arc::new(AtomicBool::new(false));
+        let (sender, result) = mpsc::channel();
+        let stop = cancel.clone();
+        thread::spawn(move || {
+            let _ = sender.send(process::run(process, input.as_deref(), None, &stop));
+            on_done();
+        });
+        Self {
+            command: command.to_string(),
+            action,
+            original,
+            started: Instant::now(),
+            cancel,
+            result,
+        }
+    }
+
+    /// Kill the command; `poll` then reports it as cancelled
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    /// The command's result once it finished
+    pub fn poll(&self) -> Option<Result<ProcessOutput, String>> {
+        match self.result.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err(format!("{} stopped unexpectedly", self.command))),
+        }
+    }
+}
+
+impl Drop for ShellJob {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
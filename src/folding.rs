@@ -0,0 +1,570 @@
+use std::collections::BTreeSet;
+use std::ops::Range;
+use crate::brackets::{self, CharKind, PairRules};
+use crate::indent::{leading_whitespace, TAB_WIDTH};
+
+/// Character shown in place of the hidden lines of a folded region
+pub const PLACEHOLDER: char = '…';
+
+/// A foldable range of lines
+///
+/// `start_line` stays visible as the header of the fold; lines
+/// `start_line + 1..=end_line` are hidden when it is folded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FoldRegion {
+    pub start_line: usize,
+    pub end_line: usize,
+    /// Nesting depth, starting at 1 for top-level regions
+    pub level: usize,
+}
+
+/// How fold regions are found for a language
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FoldMethod {
+    Brackets,
+    Indentation,
+}
+
+/// Returns the folding method for a language name as reported by
+/// `syntax::get_language_name`
+pub fn method_for_language(language: &str) -> FoldMethod {
+    match language {
+        "Rust" | "Lua" | "SQL" | "C" | "C++" | "Go" | "JavaScript" | "TypeScript" | "Java" | "JSON"
+        | "CSS" => FoldMethod::Brackets,
+        _ => FoldMethod::Indentation,
+    }
+}
+
+/// Keywords that open and close blocks in addition to brackets
+fn block_keywords(language: &str) -> (&'static [&'static str], &'static [&'static str]) {
+    match language {
+        "Lua" => (&["function", "do", "if", "repeat"], &["end", "until"]),
+        "SQL" => (&["BEGIN", "CASE"], &["END"]),
+        _ => (&[], &[]),
+    }
+}
+
+/// Compute the fold regions of `text` for a language
+pub fn compute_regions(text: &str, language: &str) -> Vec<FoldRegion> {
+    let mut regions = match method_for_language(language) {
+        FoldMethod::Brackets => bracket_regions(text, language),
+        FoldMethod::Indentation => indentation_regions(text),
+    };
+
+    // Keep only the largest region starting on each line
+    regions.sort_by_key(|r| (r.start_line, std::cmp::Reverse(r.end_line)));
+    regions.dedup_by_key(|r| r.start_line);
+    assign_levels(&mut regions);
+    regions
+}
+
+/// Regions spanning from an opening bracket or keyword to the line before
+/// its closing counterpart
+fn bracket_regions(text: &str, language: &str) -> Vec<FoldRegion> {
+    let chars: Vec<char> = text.chars().collect();
+    let kinds = brackets::classify(text, &PairRules::for_language(language));
+    let (openers, closers) = block_keywords(language);
+    let case_sensitive = language != "SQL";
+
+    let mut regions = Vec::new();
+    let mut stack: Vec<usize> = Vec::new();
+    let mut line = 0;
+    let mut i = 0;
+
+    let mut close = |stack: &mut Vec<usize>, line: usize| {
+        if let Some(start) = stack.pop()
+            && line > start + 1
+        {
+            regions.push(FoldRegion { start_line: start, end_line: line - 1, level: 0 });
+        }
+    };
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '\n' {
+            line += 1;
+            i += 1;
+            continue;
+        }
+        if kinds[i] != CharKind::Code {
+            i += 1;
+            continue;
+        }
+        match c {
+            '{' | '[' | '(' => stack.push(line),
+            '}' | ']' | ')' => close(&mut stack, line),
+            c if (c.is_alphabetic() || c == '_')
+                && !openers.is_empty()
+                && (i == 0 || !(chars[i - 1].is_alphanumeric() || chars[i - 1] == '_')) =>
+            {
+                let end = chars[i..]
+                    .iter()
+                    .position(|c| !(c.is_alphanumeric() || *c == '_'))
+                    .map_or(chars.len(), |p| i + p);
+                let mut word: String = chars[i..end].iter().collect();
+                if !case_sensitive {
+                    word = word.to_uppercase();
+                }
+                if openers.contains(&word.as_str()) {
+                    stack.push(line);
+                } else if closers.contains(&word.as_str()) {
+                    close(&mut stack, line);
+                }
+                i = end;
+                continue;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+
+    regions
+}
+
+/// Regions made of a line and the following lines indented deeper than it
+fn indentation_regions(text: &str) -> Vec<FoldRegion> {
+    let mut regions = Vec::new();
+    // (line, indent) of lines that may still gain deeper-indented children
+    let mut stack: Vec<(usize, usize)> = Vec::new();
+    let mut last_content_line = 0;
+
+    for (line, content) in text.lines().enumerate() {
+        if content.trim().is_empty() {
+            continue;
+        }
+        let indent = leading_whitespace(content).chars().fold(0, |col, c| match c {
+            '\t' => col + TAB_WIDTH - col % TAB_WIDTH,
+            _ => col + 1,
+        });
+        while let Some(&(start, start_indent)) = stack.last() {
+            if start_indent < indent {
+                break;
+            }
+            stack.pop();
+            if last_content_line > start {
+                regions.push(FoldRegion { start_line: start, end_line: last_content_line, level: 0 });
+            }
+        }
+        stack.push((line, indent));
+        last_content_line = line;
+    }
+    for (start, _) in stack {
+        if last_content_line > start {
+            regions.push(FoldRegion { start_line: start, end_line: last_content_line, level: 0 });
+        }
+    }
+
+    regions
+}
+
+/// Set the nesting level of regions sorted by start line
+fn assign_levels(regions: &mut [FoldRegion]) {
+    let mut open: Vec<usize> = Vec::new();
+    for region in regions.iter_mut() {
+        while open.last().is_some_and(|&end| end < region.start_line) {
+            open.pop();
+        }
+        region.level = open.len() + 1;
+        open.push(region.end_line);
+    }
+}
+
+/// One stretch of the displayed text: either visible buffer text or the
+/// placeholder standing in for a folded region
+#[derive(Clone, Debug)]
+struct Segment {
+    /// Char range in the displayed text
+    display: Range<usize>,
+    /// Char range in the buffer
+    original: Range<usize>,
+    /// Header line of the fold, for placeholders
+    fold: Option<usize>,
+}
+
+/// The buffer as displayed with folded regions collapsed
+///
+/// Each folded region's hidden lines are replaced by a single
+/// [`PLACEHOLDER`] at the end of its header line.
+#[derive(Clone, Debug, Default)]
+pub struct FoldedView {
+    /// Displayed text; left empty when nothing is folded, since the buffer
+    /// can then be edited directly
+    pub text: String,
+    segments: Vec<Segment>,
+    /// Buffer line number of every displayed line
+    line_map: Vec<usize>,
+    /// Number of lines in the buffer
+    pub line_count: usize,
+}
+
+/// Outcome of mapping an edit of the displayed text back to the buffer
+#[derive(Debug, PartialEq, Eq)]
+pub enum FoldEdit {
+    /// The edited buffer text
+    Applied(String),
+    /// The edit only touched a placeholder; the fold with this header line
+    /// should be opened instead
+    Unfold(usize),
+}
+
+impl FoldedView {
+    /// A view of `text` with nothing folded
+    fn identity(text: &str) -> Self {
+        let len = text.chars().count();
+        let line_count = text.matches('\n').count() + 1;
+        FoldedView {
+            text: String::new(),
+            segments: vec![Segment { display: 0..len, original: 0..len, fold: None }],
+            line_map: (0..line_count).collect(),
+            line_count,
+        }
+    }
+
+    /// Whether the displayed text is the buffer itself
+    pub fn is_identity(&self) -> bool {
+        self.segments.iter().all(|s| s.fold.is_none())
+    }
+
+    /// Map a displayed char index to a buffer char index
+    pub fn to_original(&self, display: usize) -> usize {
+        for segment in &self.segments {
+            if display <= segment.display.end && segment.fold.is_none() {
+                if display >= segment.display.start {
+                    return segment.original.start + display - segment.display.start;
+                }
+            } else if display < segment.display.end {
+                // Inside a placeholder: snap to the end of the hidden text
+                return segment.original.end;
+            }
+        }
+        self.segments.last().map_or(0, |s| s.original.end)
+    }
+
+    /// Map a buffer char index to a displayed char index
+    ///
+    /// Positions inside a folded region map to its placeholder.
+    pub fn to_display(&self, original: usize) -> usize {
+        for segment in &self.segments {
+            if original <= segment.original.end {
+                return match segment.fold {
+                    None => segment.display.start + original.max(segment.original.start)
+                        - segment.original.start,
+                    Some(_) if original == segment.original.end => segment.display.end,
+                    Some(_) => segment.display.start,
+                };
+            }
+        }
+        self.segments.last().map_or(0, |s| s.display.end)
+    }
+
+    /// Whether a buffer char index is shown (not inside a folded region)
+    pub fn is_visible(&self, original: usize) -> bool {
+        !self.segments.iter().any(|s| {
+            s.fold.is_some() && s.original.start < original && original < s.original.end
+        })
+    }
+
+    /// Buffer line number of a displayed line
+    pub fn original_line(&self, display_line: usize) -> usize {
+        self.line_map
+            .get(display_line)
+            .copied()
+            .unwrap_or_else(|| self.line_map.last().copied().unwrap_or(0))
+    }
+
+    /// Displayed line on which a buffer line appears (its fold header when hidden)
+    pub fn display_line(&self, line: usize) -> usize {
+        self.line_map.partition_point(|&l| l <= line).saturating_sub(1)
+    }
+
+    /// Displayed char index and header line of every placeholder
+    pub fn placeholders(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.segments
+            .iter()
+            .filter_map(|s| s.fold.map(|line| (s.display.start, line)))
+    }
+
+    /// Apply an edit of the displayed text to the buffer it was built from
+    ///
+    /// The change is located by comparing the old and new displayed text.
+    /// Deleting or replacing a selection that includes a placeholder removes
+    /// the hidden lines with it; touching only the placeholder itself
+    /// unfolds the region instead.
+    pub fn apply_edit(&self, original: &str, edited: &str) -> FoldEdit {
+        let old: Vec<char> = self.text.chars().collect();
+        let new: Vec<char> = edited.chars().collect();
+        let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+        let max_suffix = old.len().min(new.len()) - prefix;
+        let suffix = old
+            .iter()
+            .rev()
+            .zip(new.iter().rev())
+            .take(max_suffix)
+            .take_while(|(a, b)| a == b)
+            .count();
+        let changed = prefix..old.len() - suffix;
+
+        for segment in &self.segments {
+            if let Some(line) = segment.fold
+                && changed == segment.display
+            {
+                return FoldEdit::Unfold(line);
+            }
+        }
+
+        let start = byte_at(original, self.to_original(changed.start));
+        let end = byte_at(original, self.to_original(changed.end));
+        let replacement: String = new[prefix..new.len() - suffix].iter().collect();
+        let mut result = original.to_string();
+        result.replace_range(start..end, &replacement);
+        FoldEdit::Applied(result)
+    }
+}
+
+fn byte_at(text: &str, char_index: usize) -> usize {
+    text.char_indices()
+        .nth(char_index)
+        .map_or(text.len(), |(i, _)| i)
+}
+
+/// Fold regions of the current buffer and which of them are collapsed
+#[derive(Default)]
+pub struct FoldState {
+    pub regions: Vec<FoldRegion>,
+    /// Header lines of folded regions
+    folded: BTreeSet<usize>,
+    /// Displayed text for the current buffer and folds
+    pub view: FoldedView,
+    /// Revision of the text and the language the regions were computed for
+    computed_for: Option<(u64, String)>,
+    /// Copy of the buffer while anything is folded, used to shift folds
+    /// when lines are inserted or removed above them
+    source: Option<String>,
+}
+
+impl FoldState {
+    /// Recompute regions and the displayed text if the buffer's revision
+    /// or language changed
+    pub fn refresh(&mut self, text: &str, revision: u64, language: &str) {
+        if self.computed_for.as_ref().is_some_and(|(r, l)| *r == revision && l == language) {
+            return;
+        }
+        self.computed_for = Some((revision, language.to_string()));
+
+        if let Some(old) = self.source.take() {
+            self.shift_folds(&old, text);
+        }
+        self.regions = compute_regions(text, language);
+        let regions = &self.regions;
+        self.folded
+            .retain(|line| regions.iter().any(|r| r.start_line == *line));
+        self.rebuild_view(text);
+    }
+
+    /// Forget all folds, e.g. when another file is opened
+    pub fn clear(&mut self) {
+        self.folded.clear();
+        self.computed_for = None;
+        self.source = None;
+    }
+
+    /// Keep folds attached to their lines after an edit above them
+    fn shift_folds(&mut self, old: &str, new: &str) {
+        let old_lines: Vec<&str> = old.split('\n').collect();
+        let new_lines: Vec<&str> = new.split('\n').collect();
+        let prefix = old_lines.iter().zip(&new_lines).take_while(|(a, b)| a == b).count();
+        let max_suffix = old_lines.len().min(new_lines.len()) - prefix;
+        let suffix = old_lines
+            .iter()
+            .rev()
+            .zip(new_lines.iter().rev())
+            .take(max_suffix)
+            .take_while(|(a, b)| a == b)
+            .count();
+        let delta = new_lines.len() as isize - old_lines.len() as isize;
+        let unchanged_from = old_lines.len() - suffix;
+
+        self.folded = self
+            .folded
+            .iter()
+            .filter_map(|&line| {
+                if line < prefix {
+                    Some(line)
+                } else if line >= unchanged_from {
+                    line.checked_add_signed(delta)
+                } else {
+                    // The header line itself was edited: keep it if it still opens a region
+                    Some(line)
+                }
+            })
+            .collect();
+    }
+
+    /// Rebuild the displayed text for `text`
+    fn rebuild_view(&mut self, text: &str) {
+        let hidden = self.hidden_ranges();
+        self.source = if hidden.is_empty() { None } else { Some(text.to_string()) };
+        if hidden.is_empty() {
+            self.view = FoldedView::identity(text);
+            return;
+        }
+
+        let mut view = FoldedView {
+            text: String::with_capacity(text.len()),
+            segments: Vec::new(),
+            line_map: vec![0],
+            line_count: 0,
+        };
+        let mut hidden = hidden.into_iter().peekable();
+        let mut line = 0;
+        let mut display = 0;
+        let mut original = 0;
+        let mut segment_start = (0, 0);
+        let mut in_fold: Option<(usize, usize)> = None;
+
+        for c in text.chars() {
+            if c == '\n' {
+                match in_fold {
+                    // Newline ending the header line: the hidden text starts here
+                    None if hidden.peek().is_some_and(|&(header, _)| header == line) => {
+                        let (header, last) = hidden.next().unwrap_or((line, line));
+                        view.segments.push(Segment {
+                            display: segment_start.0..display,
+                            original: segment_start.1..original,
+                            fold: None,
+                        });
+                        view.text.push(PLACEHOLDER);
+                        in_fold = Some((header, last));
+                        segment_start = (display, original);
+                        display += 1;
+                    }
+                    // Newline ending the last hidden line: visible text resumes
+                    Some((header, last)) if line == last => {
+                        view.segments.push(Segment {
+                            display: segment_start.0..display,
+                            original: segment_start.1..original,
+                            fold: Some(header),
+                        });
+                        in_fold = None;
+                        segment_start = (display, original);
+                    }
+                    _ => {}
+                }
+                line += 1;
+                if in_fold.is_none() {
+                    view.line_map.push(line);
+                }
+            }
+            if in_fold.is_none() {
+                view.text.push(c);
+                display += 1;
+            }
+            original += 1;
+        }
+
+        view.segments.push(Segment {
+            display: segment_start.0..display,
+            original: segment_start.1..original,
+            fold: in_fold.map(|(header, _)| header),
+        });
+        view.line_count = line + 1;
+        self.view = view;
+    }
+
+    /// Header and last hidden line of every folded region that is not
+    /// itself inside another folded region, in order
+    fn hidden_ranges(&self) -> Vec<(usize, usize)> {
+        let mut ranges: Vec<(usize, usize)> = Vec::new();
+        for region in &self.regions {
+            if !self.folded.contains(&region.start_line) {
+                continue;
+            }
+            if ranges.last().is_some_and(|&(_, last)| region.start_line <= last) {
+                continue;
+            }
+            ranges.push((region.start_line, region.end_line));
+        }
+        ranges
+    }
+
+    /// Whether the region starting at `line` is folded
+    pub fn is_folded(&self, line: usize) -> bool {
+        self.folded.contains(&line)
+    }
+
+    /// The region starting at `line`, if any
+    pub fn region_at(&self, line: usize) -> Option<&FoldRegion> {
+        self.regions.iter().find(|r| r.start_line == line)
+    }
+
+    /// The innermost region containing `line`
+    pub fn region_containing(&self, line: usize) -> Option<&FoldRegion> {
+        self.regions
+            .iter()
+            .filter(|r| r.start_line <= line && line <= r.end_line)
+            .max_by_key(|r| r.level)
+    }
+
+    /// Fold or unfold the region starting at `line`
+    pub fn toggle(&mut self, line: usize, text: &str) {
+        if !self.folded.remove(&line) && self.region_at(line).is_some() {
+            self.folded.insert(line);
+        }
+        self.rebuild_view(text);
+    }
+
+    /// Fold the innermost region containing `line`
+    pub fn fold_at(&mut self, line: usize, text: &str) {
+        if let Some(region) = self.region_containing(line) {
+            self.folded.insert(region.start_line);
+            self.rebuild_view(text);
+        }
+    }
+
+    /// Unfold every folded region containing `line`
+    pub fn unfold_at(&mut self, line: usize, text: &str) {
+        let before = self.folded.len();
+        let regions = &self.regions;
+        self.folded.retain(|&start| {
+            !regions
+                .iter()
+                .any(|r| r.start_line == start && start <= line && line <= r.end_line)
+        });
+        if self.folded.len() != before {
+            self.rebuild_view(text);
+        }
+    }
+
+    /// Fold every region
+    pub fn fold_all(&mut self, text: &str) {
+        self.folded = self.regions.iter().map(|r| r.start_line).collect();
+        self.rebuild_view(text);
+    }
+
+    /// Unfold every region
+    pub fn unfold_all(&mut self, text: &str) {
+        self.folded.clear();
+        self.rebuild_view(text);
+    }
+
+    /// Fold every region at nesting level `level`
+    pub fn fold_level(&mut self, level: usize, text: &str) {
+        self.folded
+            .extend(self.regions.iter().filter(|r| r.level == level).map(|r| r.start_line));
+        self.rebuild_view(text);
+    }
+
+    /// Unfold any folded region hiding `line` so it becomes visible
+    pub fn reveal_line(&mut self, line: usize, text: &str) {
+        let before = self.folded.len();
+        let regions = &self.regions;
+        self.folded.retain(|&start| {
+            !regions
+                .iter()
+                .any(|r| r.start_line == start && start < line && line <= r.end_line)
+        });
+        if self.folded.len() != before {
+            self.rebuild_view(text);
+        }
+    }
+}
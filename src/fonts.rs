@@ -0,0 +1,59 @@
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use eframe::egui::{self, FontData, FontDefinitions, FontFamily};
+
+/// Whether `bytes` start like a TrueType, OpenType or font collection file
+///
+/// egui panics on fonts it cannot parse, so files are checked up front.
+pub fn looks_like_font(bytes: &[u8]) -> bool {
+    matches!(
+        bytes.get(..4),
+        Some([0x00, 0x01, 0x00, 0x00] | b"OTTO" | b"true" | b"ttcf")
+    )
+}
+
+/// Read a font file to use in place of a built-in font
+pub fn read_font(path: &Path) -> Result<FontData, String> {
+    let bytes = fs::read(path).map_err(|e| format!("Failed to read font {}: {}", path.display(), e))?;
+    if !looks_like_font(&bytes) {
+        return Err(format!("{} is not a TrueType or OpenType font", path.display()));
+    }
+    Ok(FontData::from_owned(bytes))
+}
+
+/// Font definitions with the given fonts taking precedence over the
+/// built-in monospace and proportional fonts
+///
+/// Fonts that cannot be loaded are skipped and reported in the returned
+/// errors; the built-in fonts stay in each family as fallback.
+pub fn font_definitions(
+    monospace: Option<&Path>,
+    proportional: Option<&Path>,
+) -> (FontDefinitions, Vec<String>) {
+    let mut fonts = FontDefinitions::default();
+    let mut errors = Vec::new();
+
+    for (family, path) in [(FontFamily::Monospace, monospace), (FontFamily::Proportional, proportional)] {
+        let Some(path) = path else {
+            continue;
+        };
+        match read_font(path) {
+            Ok(data) => {
+                let name = format!("user-{}", family);
+                fonts.font_data.insert(name.clone(), Arc::new(data));
+                fonts.families.entry(family).or_default().insert(0, name);
+            }
+            Err(e) => errors.push(e),
+        }
+    }
+
+    (fonts, errors)
+}
+
+/// Install the user's fonts, returning any that failed to load
+pub fn apply_fonts(ctx: &egui::Context, monospace: Option<&Path>, proportional: Option<&Path>) -> Vec<String> {
+    let (fonts, errors) = font_definitions(monospace, proportional);
+    ctx.set_fonts(fonts);
+    errors
+}
@@ -0,0 +1,180 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use std::time::Duration;
+use crate::process;
+use crate::settings::CommandSettings;
+
+/// How long a formatter may run before it is stopped
+pub const FORMAT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Why formatting failed
+#[derive(Debug)]
+pub struct FormatError {
+    pub message: String,
+    /// What the formatter wrote to stderr
+    pub stderr: String,
+}
+
+/// Edition given in the `Cargo.toml` nearest to a file, `2015` when it
+/// names none as Cargo assumes, or the newest edition for a file outside
+/// any package
+pub fn rust_edition(file: Option<&Path>) -> String {
+    let manifest = file
+        .and_then(Path::parent)
+        .into_iter()
+        .flat_map(Path::ancestors)
+        .find_map(|dir| std::fs::read_to_string(dir.join("Cargo.toml")).ok());
+    let Some(manifest) = manifest else {
+        return "2024".to_string();
+    };
+    let manifest: toml::Table = toml::from_str(&manifest).unwrap_or_default();
+    let edition = |table: Option<&toml::Value>| table?.get("edition")?.as_str().map(str::to_string);
+    let package = manifest.get("package");
+    let workspace = manifest.get("workspace").and_then(|w| w.get("package"));
+    edition(package).or_else(|| edition(workspace)).unwrap_or_else(|| "2015".to_string())
+}
+
+/// Arguments with `{file}` replaced by the path of the file being
+/// formatted and `{edition}` by its Rust edition
+pub fn expand_args(args: &[String], file: Option<&Path>) -> Vec<String> {
+    let path = file.map_or(String::new(), |f| f.to_string_lossy().into_owned());
+    args.iter()
+        .map(|arg| {
+            let arg = arg.replace("{file}", &path);
+            if arg.contains("{edition}") { arg.replace("{edition}", &rust_edition(file)) } else { arg }
+        })
+        .collect()
+}
+
+/// Pipe `text` through a formatter, run from the file's directory so it
+/// finds the project's configuration
+pub fn run_formatter(
+    formatter: &CommandSettings,
+    text: &str,
+    file: Option<&Path>,
+    timeout: Duration,
+) -> Result<String, FormatError> {
+    format_until(formatter, text, file, timeout, &AtomicBool::new(false))
+}
+
+fn format_until(
+    formatter: &CommandSettings,
+    text: &str,
+    file: Option<&Path>,
+    timeout: Duration,
+    cancel: &AtomicBool,
+) -> Result<String, FormatError> {
+    let args = expand_args(&formatter.args, file);
+    let dir = file.and_then(Path::parent).filter(|dir| dir.is_dir());
+    let mut command = Command::new(&formatter.command);
+    command.args(args);
+    if let Some(dir) = dir {
+        command.current_dir(dir);
+    }
+    let output = process::run(command, Some(text), Some(timeout), cancel)
+        .map_err(|message| FormatError { message, stderr: String::new() })?;
+    if !output.status.success() {
+        return Err(FormatError {
+            message: format!("{} failed ({})", formatter.command, output.status),
+            stderr: output.stderr,
+        });
+    }
+    if output.stdout.is_empty() && !text.trim().is_empty() {
+        return Err(FormatError {
+            message: format!("{} produced no output", formatter.command),
+            stderr: output.stderr,
+        });
+    }
+    Ok(output.stdout)
+}
+
+/// A formatter running in the background on a copy of the buffer
+pub struct FormatJob {
+    pub command: String,
+    /// Revision of the buffer the formatter was given; the result only
+    /// applies to that revision
+    pub revision: u64,
+    /// Save the file again once the result is applied
+    pub save: bool,
+    cancel: Arc<AtomicBool>,
+    result: Receiver<Result<String, FormatError>>,
+}
+
+impl FormatJob {
+    /// Start formatting `text`
+    ///
+    /// `on_done` is called from a background thread when the formatter
+    /// finished.
+    pub fn start(
+        formatter: &CommandSettings,
+        text: String,
+        file: Option<PathBuf>,
+        revision: u64,
+        save: bool,
+        on_done: impl Fn() + Send + 'static,
+    ) -> Self {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let (sender, result) = mpsc::channel();
+        let stop = cancel.clone();
+        let formatter_copy = formatter.clone();
+        thread::spawn(move || {
+            let _ = sender.send(format_until(&formatter_copy, &text, file.as_deref(), FORMAT_TIMEOUT, &stop));
+            on_done();
+        });
+        Self { command: formatter.command.clone(), revision, save, cancel, result }
+    }
+
+    /// The formatted text once the formatter finished
+    pub fn poll(&self) -> Option<Result<String, FormatError>> {
+        match self.result.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err(FormatError {
+                message: format!("{} stopped unexpectedly", self.command),
+                stderr: String::new(),
+            })),
+        }
+    }
+}
+
+impl Drop for FormatJob {
+    /// Stop a formatter whose result is no longer wanted
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Char index in `new` matching a char index in `old`, where `new` is `old`
+/// reformatted and so mostly differs in whitespace
+///
+/// The cursor keeps its place among the non-whitespace characters: before
+/// the same character, or after it when it was followed by whitespace.
+pub fn map_cursor(old: &str, new: &str, index: usize) -> usize {
+    let old: Vec<char> = old.chars().collect();
+    let index = index.min(old.len());
+    let significant = old[..index].iter().filter(|c| !c.is_whitespace()).count();
+    let before_significant = old.get(index).is_some_and(|c| !c.is_whitespace());
+    if significant == 0 && !before_significant {
+        return 0;
+    }
+    let mut seen = 0;
+    let mut length = 0;
+    for (i, c) in new.chars().enumerate() {
+        length = i + 1;
+        if c.is_whitespace() {
+            continue;
+        }
+        if seen == significant && before_significant {
+            return i;
+        }
+        seen += 1;
+        if seen == significant && !before_significant {
+            return i + 1;
+        }
+    }
+    length
+}
@@ -0,0 +1,217 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::diff::{self, Hunk};
+use crate::process;
+
+/// How long reading a file from the repository may take
+const GIT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How often the repository is checked for a new commit or branch
+const CHECK_INTERVAL: Duration = Duration::from_secs(2);
+
+/// A git working tree found on disk
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Repository {
+    /// Top directory of the working tree
+    pub root: PathBuf,
+    /// The `.git` directory, or the one a `.git` file points to
+    pub git_dir: PathBuf,
+}
+
+impl Repository {
+    /// The repository containing a path, if any
+    pub fn discover(path: &Path) -> Option<Self> {
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        for dir in path.ancestors() {
+            let dot_git = dir.join(".git");
+            if dot_git.is_dir() {
+                return Some(Self { root: dir.to_path_buf(), git_dir: dot_git });
+            }
+            // Worktrees and submodules have a file pointing elsewhere
+            if let Ok(contents) = fs::read_to_string(&dot_git)
+                && let Some(target) = contents.trim().strip_prefix("gitdir:")
+            {
+                return Some(Self { root: dir.to_path_buf(), git_dir: dir.join(target.trim()) });
+            }
+        }
+        None
+    }
+
+    /// Name of the checked-out branch, or the abbreviated commit when no
+    /// branch is checked out
+    pub fn branch(&self) -> Option<String> {
+        let head = fs::read_to_string(self.git_dir.join("HEAD")).ok()?;
+        let head = head.trim();
+        match head.strip_prefix("ref:") {
+            Some(reference) => {
+                let reference = reference.trim();
+                Some(reference.strip_prefix("refs/heads/").unwrap_or(reference).to_string())
+            }
+            None => Some(head.chars().take(7).collect()),
+        }
+    }
+
+    /// Commit id HEAD points to, read from the loose or packed refs
+    pub fn head_commit(&self) -> Option<String> {
+        let head = fs::read_to_string(self.git_dir.join("HEAD")).ok()?;
+        let Some(reference) = head.trim().strip_prefix("ref:").map(str::trim) else {
+            return Some(head.trim().to_string());
+        };
+        // Linked worktrees keep shared refs in the main repository
+        let common = fs::read_to_string(self.git_dir.join("commondir"))
+            .map(|dir| self.git_dir.join(dir.trim()))
+            .unwrap_or_else(|_| self.git_dir.clone());
+        for dir in [&self.git_dir, &common] {
+            if let Ok(id) = fs::read_to_string(dir.join(reference)) {
+                return Some(id.trim().to_string());
+            }
+        }
+        let packed = fs::read_to_string(common.join("packed-refs")).ok()?;
+        packed.lines().find_map(|line| {
+            let (id, name) = line.split_once(' ')?;
+            (name == reference).then(|| id.to_string())
+        })
+    }
+
+    /// A file's contents in the HEAD commit, or `None` when it is not
+    /// committed
+    pub fn committed_text(&self, file: &Path) -> Option<String> {
+        let file = file.canonicalize().ok()?;
+        let relative = file.strip_prefix(&self.root).ok()?;
+        let spec = format!("HEAD:{}", relative.to_string_lossy().replace('\\', "/"));
+        let mut command = std::process::Command::new("git");
+        command.arg("-C").arg(&self.root).args(["show", &spec]);
+        let output = process::run(command, None, Some(GIT_TIMEOUT), &AtomicBool::new(false)).ok()?;
+        output.status.success().then_some(output.stdout)
+    }
+}
+
+/// What a background diff found
+struct DiffResult {
+    /// The committed file, when it had to be read again
+    base: Option<Option<Arc<str>>>,
+    hunks: Vec<Hunk>,
+}
+
+/// A diff against HEAD running in the background, for one revision of
+/// the buffer
+struct DiffJob {
+    revision: u64,
+    result: Receiver<DiffResult>,
+}
+
+/// The current file's repository and its changes against HEAD
+#[derive(Default)]
+pub struct GitState {
+    pub repository: Option<Repository>,
+    pub branch: Option<String>,
+    /// The file as committed in HEAD
+    pub base: Option<Arc<str>>,
+    /// Changed lines of the buffer against `base`
+    pub hunks: Vec<Hunk>,
+    /// Hunk whose original text is shown, and where
+    pub shown_hunk: Option<(usize, eframe::egui::Pos2)>,
+    file: Option<PathBuf>,
+    head: Option<String>,
+    last_check: Option<Instant>,
+    /// `base` has to be read from the repository again
+    base_outdated: bool,
+    /// Revision of the buffer the hunks were computed for
+    diffed: Option<u64>,
+    job: Option<DiffJob>,
+}
+
+impl GitState {
+    /// Look up the repository of a newly opened or saved file
+    ///
+    /// The committed file is read by the next `refresh`.
+    pub fn open(&mut self, file: Option<&Path>) {
+        *self = Self::default();
+        let Some(file) = file else {
+            return;
+        };
+        self.file = Some(file.to_path_buf());
+        self.repository = Repository::discover(file.parent().unwrap_or(file));
+        self.reload();
+    }
+
+    /// Read the branch again, and the committed file in the background
+    fn reload(&mut self) {
+        let Some(repository) = &self.repository else {
+            return;
+        };
+        self.branch = repository.branch();
+        self.head = repository.head_commit();
+        self.base_outdated = true;
+        self.diffed = None;
+        self.last_check = Some(Instant::now());
+    }
+
+    /// Whether the hunks are those of a revision of the buffer
+    pub fn is_current(&self, revision: u64) -> bool {
+        self.diffed == Some(revision)
+    }
+
+    /// Recompute the changes when the buffer changed, and pick up commits
+    /// and branch switches made outside the editor
+    ///
+    /// Reading the committed file and diffing run on a background thread;
+    /// the previous hunks are kept until it is done, and `on_done` is
+    /// called from it then.
+    pub fn refresh(&mut self, text: &str, revision: u64, on_done: impl Fn() + Send + 'static) {
+        if let Some(repository) = &self.repository
+            && self.last_check.is_none_or(|check| check.elapsed() >= CHECK_INTERVAL)
+        {
+            if repository.head_commit() != self.head || repository.branch() != self.branch {
+                self.reload();
+            }
+            self.last_check = Some(Instant::now());
+        }
+        if let Some(job) = &self.job {
+            match job.result.try_recv() {
+                Ok(result) => {
+                    if let Some(base) = result.base {
+                        self.base = base;
+                    }
+                    self.hunks = result.hunks;
+                    self.diffed = Some(job.revision);
+                    self.shown_hunk = None;
+                    self.job = None;
+                }
+                Err(TryRecvError::Empty) => return,
+                Err(TryRecvError::Disconnected) => self.job = None,
+            }
+        }
+        let (Some(repository), Some(file)) = (&self.repository, &self.file) else {
+            self.hunks.clear();
+            return;
+        };
+        if !self.base_outdated && (self.base.is_none() || self.diffed == Some(revision)) {
+            if self.base.is_none() {
+                self.hunks.clear();
+            }
+            return;
+        }
+
+        let read = self.base_outdated.then(|| (repository.clone(), file.clone()));
+        self.base_outdated = false;
+        let base = self.base.clone();
+        let text = text.to_string();
+        let (sender, result) = mpsc::channel();
+        thread::spawn(move || {
+            let read_base = read.map(|(repository, file)| repository.committed_text(&file).map(Arc::from));
+            let hunks = match read_base.as_ref().unwrap_or(&base) {
+                Some(base) => diff::line_hunks(base, &text),
+                None => Vec::new(),
+            };
+            let _ = sender.send(DiffResult { base: read_base, hunks });
+            on_done();
+        });
+        self.job = Some(DiffJob { revision, result });
+    }
+}
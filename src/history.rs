@@ -0,0 +1,173 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use crate::settings::FileSettings;
+
+/// Name of the file recording which file a history directory belongs to
+const SOURCE_FILE: &str = "source";
+
+/// A saved version of a file in the local history
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Version {
+    /// Where the copy is stored
+    pub path: PathBuf,
+    pub time: SystemTime,
+    /// Size in bytes
+    pub size: u64,
+}
+
+/// Copies of saved files, one directory per file, kept within limits on
+/// their number and age
+pub struct HistoryStore {
+    pub root: PathBuf,
+    pub max_versions: usize,
+    /// Versions older than this are dropped; `None` keeps them
+    pub max_age: Option<Duration>,
+}
+
+impl HistoryStore {
+    pub fn new(root: PathBuf, files: &FileSettings) -> Self {
+        Self {
+            root,
+            max_versions: files.history_versions.max(1),
+            max_age: (files.history_days > 0).then(|| Duration::from_secs(files.history_days * 24 * 60 * 60)),
+        }
+    }
+
+    /// Directory holding the versions of a file
+    fn directory(&self, file: &Path) -> PathBuf {
+        let file = file.canonicalize().unwrap_or_else(|_| file.to_path_buf());
+        self.root.join(format!("{:016x}", fnv1a(file.as_os_str().as_encoded_bytes())))
+    }
+
+    /// Saved versions of a file, newest first
+    pub fn versions(&self, file: &Path) -> Vec<Version> {
+        let Ok(entries) = fs::read_dir(self.directory(file)) else {
+            return Vec::new();
+        };
+        let mut versions: Vec<(u128, Version)> = entries
+            .flatten()
+            .filter_map(|entry| {
+                let path = entry.path();
+                let millis: u128 = path.file_stem()?.to_str()?.parse().ok()?;
+                let time = UNIX_EPOCH + Duration::from_millis(u64::try_from(millis).ok()?);
+                let size = entry.metadata().ok()?.len();
+                Some((millis, Version { path, time, size }))
+            })
+            .collect();
+        versions.sort_by_key(|(millis, _)| std::cmp::Reverse(*millis));
+        versions.into_iter().map(|(_, version)| version).collect()
+    }
+
+    /// Store `text` as the newest version of a file, unless it is the same
+    /// as the newest one; returns whether a version was added
+    pub fn record(&self, file: &Path, text: &str) -> io::Result<bool> {
+        let versions = self.versions(file);
+        if let Some(newest) = versions.first()
+            && fs::read_to_string(&newest.path).is_ok_and(|stored| stored == text)
+        {
+            return Ok(false);
+        }
+        let directory = self.directory(file);
+        fs::create_dir_all(&directory)?;
+        fs::write(directory.join(SOURCE_FILE), file.to_string_lossy().as_bytes())?;
+        // Keep names unique and ordered when saving twice in a millisecond
+        let newest = versions.first().and_then(|v| v.path.file_stem()?.to_str()?.parse::<u128>().ok());
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis();
+        let millis = newest.map_or(now, |newest| now.max(newest + 1));
+        fs::write(directory.join(format!("{}.txt", millis)), text)?;
+        self.prune(file);
+        Ok(true)
+    }
+
+    /// Drop the versions beyond the limits, always keeping the newest
+    fn prune(&self, file: &Path) {
+        let now = SystemTime::now();
+        for (index, version) in self.versions(file).iter().enumerate().skip(1) {
+            let too_old = self
+                .max_age
+                .is_some_and(|max_age| now.duration_since(version.time).is_ok_and(|age| age > max_age));
+            if index >= self.max_versions || too_old {
+                let _ = fs::remove_file(&version.path);
+            }
+        }
+    }
+}
+
+/// Contents of a stored version
+pub fn read_version(version: &Version) -> io::Result<String> {
+    fs::read_to_string(&version.path)
+}
+
+/// How long ago a time was, such as "5 minutes ago"
+pub fn format_age(time: SystemTime, now: SystemTime) -> String {
+    let seconds = now.duration_since(time).unwrap_or_default().as_secs();
+    let (count, unit) = match seconds {
+        0..60 => return "just now".to_string(),
+        60..3600 => (seconds / 60, "minute"),
+        3600..86_400 => (seconds / 3600, "hour"),
+        _ => (seconds / 86_400, "day"),
+    };
+    format!("{} {}{} ago", count, unit, if count == 1 { "" } else { "s" })
+}
+
+/// A time as `YYYY-MM-DD HH:MM:SS` in UTC
+pub fn format_time(time: SystemTime) -> String {
+    let seconds = time.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let (days, rest) = (seconds / 86_400, seconds % 86_400);
+    // Civil date from days since the epoch (Howard Hinnant's algorithm)
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        rest / 3600,
+        rest % 3600 / 60,
+        rest % 60
+    )
+}
+
+/// A size in bytes for people, such as "1.5 KB"
+pub fn format_size(size: u64) -> String {
+    match size {
+        0..1024 => format!("{} B", size),
+        1024..1_048_576 => format!("{:.1} KB", size as f64 / 1024.0),
+        _ => format!("{:.1} MB", size as f64 / 1_048_576.0),
+    }
+}
+
+/// 64-bit FNV-1a hash, which unlike the standard library's hasher gives
+/// the same directory names in every release
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3))
+}
+
+/// The File History panel and the versions it lists
+#[derive(Default)]
+pub struct HistoryState {
+    pub show_panel: bool,
+    /// File the versions belong to
+    pub file: Option<PathBuf>,
+    pub versions: Vec<Version>,
+}
+
+impl HistoryState {
+    /// List the versions of a file again
+    pub fn reload(&mut self, store: Option<&HistoryStore>, file: Option<&Path>) {
+        self.file = file.map(Path::to_path_buf);
+        self.versions = match (store, file) {
+            (Some(store), Some(file)) => store.versions(file),
+            _ => Vec::new(),
+        };
+    }
+}
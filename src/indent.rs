@@ -0,0 +1,367 @@
+use std::ops::Range;
+
+/// Number of columns a tab character advances to when measuring indentation
+pub const TAB_WIDTH: usize = 4;
+
+/// How one level of indentation is written
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IndentStyle {
+    Tabs,
+    Spaces(usize),
+}
+
+impl Default for IndentStyle {
+    fn default() -> Self {
+        IndentStyle::Spaces(4)
+    }
+}
+
+impl IndentStyle {
+    /// The text inserted for a single indentation level
+    pub fn unit(&self) -> String {
+        match self {
+            IndentStyle::Tabs => "\t".to_string(),
+            IndentStyle::Spaces(n) => " ".repeat((*n).max(1)),
+        }
+    }
+
+    /// Number of columns a single indentation level occupies
+    pub fn width(&self) -> usize {
+        match self {
+            IndentStyle::Tabs => TAB_WIDTH,
+            IndentStyle::Spaces(n) => (*n).max(1),
+        }
+    }
+
+    /// Short human-readable description, e.g. "Spaces: 4"
+    pub fn label(&self) -> String {
+        match self {
+            IndentStyle::Tabs => "Tabs".to_string(),
+            IndentStyle::Spaces(n) => format!("Spaces: {}", n),
+        }
+    }
+}
+
+/// Returns the conventional indentation for a language name as reported by
+/// `syntax::get_language_name`, if it has one
+pub fn default_for_language(language: &str) -> Option<IndentStyle> {
+    crate::languages::registry().by_name(language).and_then(|l| l.indent)
+}
+
+/// Guess the indentation used by existing text
+///
+/// Lines indented with tabs are counted against lines indented with spaces;
+/// for spaces the most common increase in indentation between consecutive
+/// lines is taken as the indent size. Returns `None` when the text has no
+/// indented lines.
+pub fn detect_indent(text: &str) -> Option<IndentStyle> {
+    let mut tab_lines = 0;
+    let mut space_lines = 0;
+    // Index is the increase in columns from the previous non-blank line
+    let mut steps = [0usize; 9];
+    let mut previous = 0;
+
+    for line in text.lines() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if line.starts_with('\t') {
+            tab_lines += 1;
+            previous = 0;
+            continue;
+        }
+        let spaces = line.len() - line.trim_start_matches(' ').len();
+        if spaces > 0 {
+            space_lines += 1;
+        }
+        if spaces > previous && spaces - previous < steps.len() {
+            steps[spaces - previous] += 1;
+        }
+        previous = spaces;
+    }
+
+    if tab_lines == 0 && space_lines == 0 {
+        return None;
+    }
+    if tab_lines > space_lines {
+        return Some(IndentStyle::Tabs);
+    }
+
+    // A step of one column is usually a block comment continuation (" * "),
+    // so it only counts when nothing else was seen
+    let best = (2..steps.len())
+        .filter(|&step| steps[step] > 0)
+        .max_by_key(|&step| (steps[step], std::cmp::Reverse(step)));
+    match best {
+        Some(step) => Some(IndentStyle::Spaces(step)),
+        None if steps[1] > 0 => Some(IndentStyle::Spaces(1)),
+        None => None,
+    }
+}
+
+/// Returns the leading spaces and tabs of a line
+pub fn leading_whitespace(line: &str) -> &str {
+    &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+}
+
+/// Converts a char index (as used by the editor cursor) into a byte index
+pub fn byte_index(text: &str, char_index: usize) -> usize {
+    text.char_indices()
+        .nth(char_index)
+        .map_or(text.len(), |(i, _)| i)
+}
+
+/// Converts a byte index into a char index
+pub fn char_index(text: &str, byte_index: usize) -> usize {
+    text[..byte_index].chars().count()
+}
+
+/// Byte index of the start of the line containing `byte_index`
+pub fn line_start(text: &str, byte_index: usize) -> usize {
+    text[..byte_index].rfind('\n').map_or(0, |i| i + 1)
+}
+
+/// Byte index of the end of the line containing `byte_index` (before the newline)
+pub fn line_end(text: &str, byte_index: usize) -> usize {
+    text[byte_index..].find('\n').map_or(text.len(), |i| byte_index + i)
+}
+
+/// Check whether a line (already trimmed at the end) opens a new block,
+/// so the following line should be indented one level deeper
+pub fn opens_block(line: &str, language: &str) -> bool {
+    let Some(last) = line.chars().last() else {
+        return false;
+    };
+    if matches!(last, '{' | '[' | '(') {
+        return true;
+    }
+    match language {
+        "Python" | "YAML" => last == ':',
+        "Lua" => {
+            ends_with_word(line, "then")
+                || ends_with_word(line, "do")
+                || ends_with_word(line, "else")
+                || ends_with_word(line, "repeat")
+                || (line.trim_start().starts_with("function") || line.contains("function("))
+                    && !ends_with_word(line, "end")
+        }
+        "Shell" => {
+            ends_with_word(line, "then")
+                || ends_with_word(line, "do")
+                || ends_with_word(line, "else")
+        }
+        _ => false,
+    }
+}
+
+fn ends_with_word(line: &str, word: &str) -> bool {
+    line.strip_suffix(word).is_some_and(|rest| {
+        !rest
+            .chars()
+            .last()
+            .is_some_and(|c| c.is_alphanumeric() || c == '_')
+    })
+}
+
+fn closing_for(open: char) -> Option<char> {
+    match open {
+        '{' => Some('}'),
+        '[' => Some(']'),
+        '(' => Some(')'),
+        _ => None,
+    }
+}
+
+/// Insert a newline in place of `selection`, keeping the current line's
+/// indentation and adding a level after a block opener
+///
+/// When the cursor sits between an opening and its closing bracket, the
+/// closing bracket is moved to its own line at the original indentation.
+/// Returns the new cursor position as a char index.
+pub fn insert_newline(
+    text: &mut String,
+    selection: Range<usize>,
+    style: IndentStyle,
+    language: &str,
+) -> usize {
+    let start = byte_index(text, selection.start);
+    let end = byte_index(text, selection.end);
+    text.replace_range(start..end, "");
+
+    let cursor = start;
+    let before = &text[line_start(text, cursor)..cursor];
+    let indent = leading_whitespace(before).to_string();
+    let trimmed = before.trim_end();
+    let opens = opens_block(trimmed, language);
+
+    let after = &text[cursor..line_end(text, cursor)];
+    let rest = after.trim_start_matches([' ', '\t']);
+    let skipped = after.len() - rest.len();
+
+    let mut insert = String::from("\n");
+    insert.push_str(&indent);
+    if opens {
+        insert.push_str(&style.unit());
+    }
+    let cursor_offset = insert.len();
+    let closes = trimmed
+        .chars()
+        .last()
+        .and_then(closing_for)
+        .is_some_and(|close| rest.starts_with(close));
+    if opens && closes {
+        insert.push('\n');
+        insert.push_str(&indent);
+    }
+
+    text.replace_range(cursor..cursor + skipped, &insert);
+    char_index(text, cursor + cursor_offset)
+}
+
+/// Type a closing bracket at `cursor`, first removing one indentation level
+/// if it is the first non-blank character on the line
+///
+/// Returns the new cursor position, or `None` if no dedent applies and the
+/// character should be inserted normally.
+pub fn dedent_before_closing(
+    text: &mut String,
+    cursor: usize,
+    typed: char,
+    style: IndentStyle,
+) -> Option<usize> {
+    if !matches!(typed, '}' | ']' | ')') {
+        return None;
+    }
+    let cursor = byte_index(text, cursor);
+    let start = line_start(text, cursor);
+    let before = &text[start..cursor];
+    if before.is_empty() || !before.chars().all(|c| c == ' ' || c == '\t') {
+        return None;
+    }
+
+    let removed = outdent_width(before, style);
+    text.replace_range(cursor - removed..cursor, &typed.to_string());
+    Some(char_index(text, cursor - removed) + 1)
+}
+
+/// Number of bytes of leading whitespace to remove to outdent one level
+fn outdent_width(indent: &str, style: IndentStyle) -> usize {
+    if indent.starts_with('\t') {
+        return 1;
+    }
+    let spaces = indent.len() - indent.trim_start_matches(' ').len();
+    let width = style.width();
+    match spaces % width {
+        0 => spaces.min(width),
+        partial => partial,
+    }
+}
+
+/// Byte ranges of the starts of every line touched by a selection
+///
+/// A non-empty selection that ends at the very start of a line does not
+/// include that line.
+fn selected_line_starts(text: &str, start: usize, end: usize) -> Vec<usize> {
+    let end = if end > start && line_start(text, end) == end {
+        end - 1
+    } else {
+        end
+    };
+    let mut starts = vec![line_start(text, start)];
+    for (i, c) in text[start..end].char_indices() {
+        if c == '\n' {
+            starts.push(start + i + 1);
+        }
+    }
+    starts
+}
+
+/// Indent the lines covered by `selection` by one level
+///
+/// With an empty selection, one indentation level is inserted at the cursor
+/// instead (spaces pad to the next indent stop). Returns the new selection
+/// as char indices.
+pub fn indent_lines(text: &mut String, selection: Range<usize>, style: IndentStyle) -> Range<usize> {
+    let start = byte_index(text, selection.start);
+    let end = byte_index(text, selection.end);
+
+    if start == end {
+        let insert = match style {
+            IndentStyle::Tabs => "\t".to_string(),
+            IndentStyle::Spaces(_) => {
+                let column = text[line_start(text, start)..start].chars().count();
+                " ".repeat(style.width() - column % style.width())
+            }
+        };
+        text.insert_str(start, &insert);
+        let cursor = char_index(text, start + insert.len());
+        return cursor..cursor;
+    }
+
+    let unit = style.unit();
+    let starts = selected_line_starts(text, start, end);
+    let first = starts[0];
+    let mut last_end = line_end(text, *starts.last().unwrap_or(&first));
+    for &line in starts.iter().rev() {
+        if text[line..line_end(text, line)].trim().is_empty() {
+            continue;
+        }
+        text.insert_str(line, &unit);
+        last_end += unit.len();
+    }
+    char_index(text, first)..char_index(text, last_end)
+}
+
+/// Remove one level of indentation from the lines covered by `selection`
+///
+/// Returns the new selection as char indices. An empty selection stays a
+/// cursor and moves with the text it was in.
+pub fn outdent_lines(text: &mut String, selection: Range<usize>, style: IndentStyle) -> Range<usize> {
+    let start = byte_index(text, selection.start);
+    let end = byte_index(text, selection.end);
+    let starts = selected_line_starts(text, start, end);
+    let first = starts[0];
+    let mut last_end = line_end(text, *starts.last().unwrap_or(&first));
+    let mut first_removed = 0;
+
+    for &line in starts.iter().rev() {
+        let removed = outdent_width(leading_whitespace(&text[line..]), style);
+        let removed = removed.min(leading_whitespace(&text[line..]).len());
+        text.replace_range(line..line + removed, "");
+        last_end -= removed;
+        if line == first {
+            first_removed = removed;
+        }
+    }
+
+    if start == end {
+        let cursor = char_index(text, start.saturating_sub(first_removed).max(first));
+        cursor..cursor
+    } else {
+        char_index(text, first)..char_index(text, last_end)
+    }
+}
+
+/// Re-indent every line of `text` from one indentation style to another
+///
+/// The leading whitespace of each line is measured in columns using the
+/// width of `from`, converted into whole levels of `to`, and any remainder
+/// is kept as spaces.
+pub fn convert_indentation(text: &str, from: IndentStyle, to: IndentStyle) -> String {
+    let from_width = from.width();
+    let unit = to.unit();
+    let mut result = String::with_capacity(text.len());
+
+    for line in text.split_inclusive('\n') {
+        let indent = leading_whitespace(line);
+        let columns = indent.chars().fold(0, |col, c| match c {
+            '\t' => col + from_width - col % from_width,
+            _ => col + 1,
+        });
+        result.push_str(&unit.repeat(columns / from_width));
+        result.push_str(&" ".repeat(columns % from_width));
+        result.push_str(&line[indent.len()..]);
+    }
+
+    result
+}
@@ -1,111 +1,252 @@
 use eframe::egui;
 use crate::app::MyApp;
+use crate::brackets;
+use crate::filter::ShellCommandKind;
+use crate::indent;
+use crate::keybindings::Command;
+use crate::ui::large_file as large_file_ui;
 
-pub struct Shortcuts {
-    pub undo: bool,
-    pub redo: bool,
-    pub toggle_find: bool,
-    pub new_file: bool,
-    pub open_file: bool,
-    pub save_file: bool,
-    pub quit_app: bool,
-    pub go_to_line: bool,
-}
+/// Run the commands whose keyboard shortcuts were pressed
+pub fn handle_shortcuts(ctx: &egui::Context, app: &mut MyApp) {
+    let mut commands = ctx.input(|i| app.keybindings.pressed(i));
+    if app.terminal.focused {
+        // Keys belong to the shell, apart from the one leaving it
+        commands.retain(|&command| command == Command::ToggleTerminal);
+    }
+    if app.large_file.is_some() {
+        commands.retain(Command::works_on_large_files);
+    }
+    if app.read_only {
+        commands.retain(|command| !matches!(command, Command::Undo | Command::Redo));
+    }
 
-impl Default for Shortcuts {
-    fn default() -> Self {
-        Self {
-            undo: false,
-            redo: false,
-            toggle_find: false,
-            new_file: false,
-            open_file: false,
-            save_file: false,
-            quit_app: false,
-            go_to_line: false,
+    for command in commands {
+        match command {
+            Command::Undo if let Some(view) = &mut app.large_file => view.undo(),
+            Command::Redo if let Some(view) = &mut app.large_file => view.redo(),
+            Command::Undo => {
+                app.save_undo_state();
+                app.handle_undo();
+            }
+            Command::Redo => {
+                app.save_undo_state();
+                app.handle_redo();
+            }
+            Command::Find => app.search.show_bar = !app.search.show_bar,
+            Command::NewFile => app.handle_new_action(),
+            Command::OpenFile => app.handle_open_action(),
+            Command::SaveFile => app.handle_save_action(),
+            Command::Quit => app.handle_quit_action(ctx),
+            Command::Preferences => app.open_preferences(),
+            Command::GoToLine => {
+                app.show_goto_line_dialog = true;
+                app.goto_line_input.clear();
+            }
+            Command::GoToSymbol => app.open_goto_symbol(),
+            Command::GoToMatchingBracket => app.go_to_matching_bracket(ctx),
+            Command::ToggleOutline => app.outline.show_panel = !app.outline.show_panel,
+            Command::TriggerCompletion => app.trigger_completion(ctx),
+            Command::ShowHover => app.show_hover(ctx),
+            Command::GoToDefinition => app.go_to_definition(ctx),
+            Command::RenameSymbol => app.open_rename(ctx),
+            Command::FormatDocument => app.format_document(ctx),
+            Command::ToggleProblems => app.lsp.show_problems = !app.lsp.show_problems,
+            Command::FilterThroughCommand => app.open_shell_dialog(ShellCommandKind::Filter),
+            Command::InsertCommandOutput => app.open_shell_dialog(ShellCommandKind::Insert),
+            Command::RunCommandOnFile => app.open_shell_dialog(ShellCommandKind::RunOnFile),
+            Command::RunFile => app.run_file(ctx),
+            Command::StopRun => app.stop_run(),
+            Command::ToggleTerminal => app.toggle_terminal(ctx),
+            Command::NextChange => app.go_to_change(ctx, true),
+            Command::PreviousChange => app.go_to_change(ctx, false),
+            Command::RevertChange => app.revert_change_at_cursor(ctx),
+            Command::CompareWithSaved => app.compare_with_saved(),
+            Command::CompareWithFile => app.compare_with_file(),
+            Command::MergeConflicts => app.open_merge_view(None),
+            Command::ToggleFileHistory => app.toggle_file_history(),
+            Command::ReopenClosedFile => app.reopen_closed_file(),
+            Command::ToggleReadOnly => app.toggle_read_only(),
+            Command::Fold => app.fold_at_cursor(ctx),
+            Command::Unfold => app.unfold_at_cursor(ctx),
+            Command::ToggleWordWrap => app.toggle_word_wrap(),
+            Command::ZoomIn => app.zoom(ctx, 1),
+            Command::ZoomOut => app.zoom(ctx, -1),
+            Command::ResetZoom => app.zoom(ctx, 0),
         }
     }
 }
 
-pub fn handle_shortcuts(ctx: &egui::Context, app: &mut MyApp) {
-    let mut shortcuts = Shortcuts::default();
-
-    ctx.input(|i| {
-        // Ctrl+N - New file
-        if i.modifiers.command && i.key_pressed(egui::Key::N) {
-            shortcuts.new_file = true;
-        }
-        
-        // Ctrl+O - Open file
-        if i.modifiers.command && i.key_pressed(egui::Key::O) {
-            shortcuts.open_file = true;
-        }
-        
-        // Ctrl+S - Save file
-        if i.modifiers.command && i.key_pressed(egui::Key::S) {
-            shortcuts.save_file = true;
-        }
-        
-        // Ctrl+Q - Quit
-        if i.modifiers.command && i.key_pressed(egui::Key::Q) {
-            shortcuts.quit_app = true;
-        }
-
-        // Ctrl+F - Find
-        if i.modifiers.command && i.key_pressed(egui::Key::F) {
-            shortcuts.toggle_find = true;
-        }
-        
-        // Ctrl+Z - Undo
-        if i.modifiers.command && !i.modifiers.shift && i.key_pressed(egui::Key::Z) {
-            shortcuts.undo = true;
-        }
-        
-        // Ctrl+Y or Ctrl+Shift+Z - Redo
-        if i.modifiers.command && (i.key_pressed(egui::Key::Y) || (i.modifiers.shift && i.key_pressed(egui::Key::Z))) {
-            shortcuts.redo = true;
+/// Type into the large-file editor and move its cursor
+pub fn handle_large_file_keys(ctx: &egui::Context, app: &mut MyApp) {
+    let Some(view) = &mut app.large_file else {
+        return;
+    };
+    if !ctx.memory(|m| m.has_focus(large_file_ui::editor_id())) {
+        return;
+    }
+    let page = view.visible_lines.len().saturating_sub(1).max(1) as isize;
+    // Read-only files can still be moved around in
+    let editable = !app.read_only;
+    let events = ctx.input(|i| i.events.clone());
+    for event in events {
+        match event {
+            egui::Event::Text(text) | egui::Event::Paste(text) if editable => view.insert(&text),
+            egui::Event::Copy => {
+                if let Some(selected) = view.selected_text() {
+                    ctx.copy_text(selected);
+                }
+            }
+            egui::Event::Cut => {
+                if let Some(selected) = view.selected_text() {
+                    ctx.copy_text(selected);
+                    if editable {
+                        view.delete_selection();
+                    }
+                }
+            }
+            egui::Event::Key { key, pressed: true, modifiers, .. } => match key {
+                egui::Key::Enter if editable => view.insert("\n"),
+                egui::Key::Tab if editable => view.insert(&app.indent_style.unit()),
+                egui::Key::Backspace if editable => view.backspace(),
+                egui::Key::Delete if editable => view.delete(),
+                egui::Key::A if modifiers.command => view.select_all(),
+                egui::Key::ArrowLeft
+                | egui::Key::ArrowRight
+                | egui::Key::ArrowUp
+                | egui::Key::ArrowDown
+                | egui::Key::PageUp
+                | egui::Key::PageDown
+                | egui::Key::Home
+                | egui::Key::End => {
+                    // Shift extends the selection as the cursor moves
+                    view.begin_move(modifiers.shift);
+                    match key {
+                        egui::Key::ArrowLeft => view.move_horizontally(false),
+                        egui::Key::ArrowRight => view.move_horizontally(true),
+                        egui::Key::ArrowUp => view.move_vertically(-1),
+                        egui::Key::ArrowDown => view.move_vertically(1),
+                        egui::Key::PageUp => view.move_vertically(-page),
+                        egui::Key::PageDown => view.move_vertically(page),
+                        egui::Key::Home if modifiers.command => view.move_to_text_edge(false),
+                        egui::Key::End if modifiers.command => view.move_to_text_edge(true),
+                        egui::Key::Home => view.move_to_line_edge(false),
+                        _ => view.move_to_line_edge(true),
+                    }
+                }
+                _ => {}
+            },
+            _ => {}
         }
+    }
+}
 
-        // Ctrl+G - Go to Line
-        if i.modifiers.command && i.key_pressed(egui::Key::G) {
-            shortcuts.go_to_line = true;
-        }
+/// Move through, accept or dismiss the completion list with the arrow
+/// keys, Enter or Tab, and Escape
+///
+/// Must run before the editor is shown so the consumed key events never
+/// reach it.
+pub fn handle_completion_keys(ctx: &egui::Context, app: &mut MyApp) {
+    let Some(count) = app.completion_matches(ctx).map(|m| m.len()) else {
+        return;
+    };
+    let Some(popup) = &mut app.lsp.completion else {
+        return;
+    };
+    let (up, down, accept, dismiss) = ctx.input_mut(|i| {
+        (
+            i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp),
+            i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown),
+            count > 0
+                && (i.consume_key(egui::Modifiers::NONE, egui::Key::Enter)
+                    || i.consume_key(egui::Modifiers::NONE, egui::Key::Tab)),
+            i.consume_key(egui::Modifiers::NONE, egui::Key::Escape),
+        )
     });
-    
-    // Execute actions
-    // Execute actions
-    if shortcuts.undo {
-        app.save_undo_state();
-        app.handle_redo();
-    }
-    
-    if shortcuts.redo {
-        app.save_undo_state();
-        app.handle_undo();
+    if up {
+        popup.selected = popup.selected.saturating_sub(1);
     }
-    
-    if shortcuts.toggle_find {
-        app.search.show_bar = !app.search.show_bar;
+    if down && popup.selected + 1 < count {
+        popup.selected += 1;
     }
-    
-    if shortcuts.new_file {
-        app.handle_new_action();
+    if accept {
+        let selected = popup.selected;
+        app.accept_completion(ctx, selected);
     }
-    
-    if shortcuts.open_file {
-        app.handle_open_action();
+    if dismiss {
+        app.lsp.completion = None;
     }
-    
-    if shortcuts.save_file {
-        app.handle_save_action();
+}
+
+/// Apply the editing keys the code editor does not handle itself:
+/// auto-indent on Enter, Tab/Shift+Tab to indent or outdent the selected
+/// lines, dedent when a closing bracket starts a line, and auto-closing
+/// of brackets and quotes.
+///
+/// Must run before the editor is shown so the consumed key events never
+/// reach it.
+pub fn handle_editor_keys(ctx: &egui::Context, app: &mut MyApp) {
+    let Some(id) = app.editor_id else {
+        return;
+    };
+    if app.read_only {
+        return;
     }
-    
-    if shortcuts.quit_app {
-        app.handle_quit_action(ctx);
+    if !ctx.memory(|m| m.has_focus(id)) {
+        return;
     }
+    let Some(selection) = app.editor_selection(ctx) else {
+        return;
+    };
+    let language = app.language;
+    let style = app.indent_style;
+
+    let new_selection = ctx.input_mut(|i| {
+        if app.settings.editor.auto_indent && i.consume_key(egui::Modifiers::NONE, egui::Key::Enter) {
+            let cursor = indent::insert_newline(&mut app.text, selection.clone(), style, language);
+            return Some(cursor..cursor);
+        }
+        if i.consume_key(egui::Modifiers::SHIFT, egui::Key::Tab) {
+            return Some(indent::outdent_lines(&mut app.text, selection.clone(), style));
+        }
+        if i.consume_key(egui::Modifiers::NONE, egui::Key::Tab) {
+            return Some(indent::indent_lines(&mut app.text, selection.clone(), style));
+        }
+        if app.settings.editor.auto_close_brackets
+            && selection.is_empty()
+            && i.modifiers.is_none()
+            && i.key_pressed(egui::Key::Backspace)
+            && let Some(cursor) = brackets::delete_pair(&mut app.text, selection.start, language)
+        {
+            i.consume_key(egui::Modifiers::NONE, egui::Key::Backspace);
+            return Some(cursor..cursor);
+        }
 
-    if shortcuts.go_to_line {
-        app.show_goto_line_dialog = true;
-        app.goto_line_input.clear();
+        let typed = i.events.iter().position(|e| {
+            matches!(e, egui::Event::Text(t) if t.chars().count() == 1)
+        })?;
+        let egui::Event::Text(t) = &i.events[typed] else {
+            return None;
+        };
+        let ch = t.chars().next()?;
+        let new_selection = if app.settings.editor.auto_close_brackets {
+            brackets::type_char(&mut app.text, selection.clone(), ch, language)
+        } else {
+            None
+        };
+        let new_selection = new_selection.or_else(|| {
+            if !app.settings.editor.auto_indent || !selection.is_empty() {
+                return None;
+            }
+            indent::dedent_before_closing(&mut app.text, selection.start, ch, style)
+                .map(|cursor| cursor..cursor)
+        })?;
+        i.events.remove(typed);
+        Some(new_selection)
+    });
+
+    if let Some(selection) = new_selection {
+        app.text_changed();
+        app.set_editor_selection(ctx, selection);
     }
 }
+
@@ -0,0 +1,215 @@
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+
+/// Command-line help
+pub const USAGE: &str = "Usage: writer [--new-window] [--readonly] [FILE[:LINE]]...
+
+Files are opened in the Writer window that is already running, if any.
+
+  --new-window  Start a separate window instead
+  --readonly    Open the files without allowing edits
+  -h, --help    Show this help";
+
+/// How long to wait for a running instance to answer
+const REPLY_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// A file to open, optionally at a line (1-based)
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OpenRequest {
+    pub path: PathBuf,
+    pub line: Option<usize>,
+    /// Open the file read-only whatever its permissions
+    #[serde(default)]
+    pub read_only: bool,
+}
+
+impl OpenRequest {
+    /// Read a command-line argument such as `notes.txt` or `main.rs:42`,
+    /// resolving relative paths against `cwd`
+    ///
+    /// A file whose name really ends in `:<digits>` is taken as is.
+    pub fn parse(arg: &str, cwd: &Path) -> Self {
+        let (path, line) = match arg.rsplit_once(':') {
+            Some((path, line))
+                if !path.is_empty()
+                    && !cwd.join(arg).exists()
+                    && let Ok(line) = line.parse::<usize>() =>
+            {
+                (path, Some(line))
+            }
+            _ => (arg, None),
+        };
+        Self { path: cwd.join(path), line, read_only: false }
+    }
+}
+
+/// What the command line asked for
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Args {
+    pub files: Vec<OpenRequest>,
+    /// Start a window of its own rather than using a running one
+    pub new_window: bool,
+    /// Open the files read-only
+    pub read_only: bool,
+    pub help: bool,
+}
+
+/// Parse the command-line arguments after the program name
+pub fn parse_args(args: impl IntoIterator<Item = String>, cwd: &Path) -> Result<Args, String> {
+    let mut parsed = Args::default();
+    let mut options = true;
+    for arg in args {
+        match arg.as_str() {
+            "--" if options => options = false,
+            "--new-window" if options => parsed.new_window = true,
+            "--readonly" if options => parsed.read_only = true,
+            "-h" | "--help" if options => parsed.help = true,
+            option if options && option.starts_with('-') && option != "-" => {
+                return Err(format!("unknown option {}", option));
+            }
+            file => parsed.files.push(OpenRequest::parse(file, cwd)),
+        }
+    }
+    for file in &mut parsed.files {
+        file.read_only = parsed.read_only;
+    }
+    Ok(parsed)
+}
+
+/// Socket a running instance listens on: `writer.sock` in
+/// `$XDG_RUNTIME_DIR`, falling back to a directory of the user's own in
+/// the temporary directory
+pub fn socket_path() -> Option<PathBuf> {
+    if let Some(dir) = std::env::var_os("XDG_RUNTIME_DIR").filter(|dir| !dir.is_empty()) {
+        return Some(PathBuf::from(dir).join("writer.sock"));
+    }
+    let user = std::env::var("USER").unwrap_or_default();
+    Some(std::env::temp_dir().join(format!("writer-{}", user)).join("writer.sock"))
+}
+
+/// Make sure `dir` exists and only its owner can get into it, creating it
+/// with mode 0700 if needed
+///
+/// A socket inside such a directory cannot be reached by other users, even
+/// in the moment between binding it and setting its permissions.
+#[cfg(unix)]
+fn private_dir(dir: &Path) -> io::Result<()> {
+    use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+    match std::fs::DirBuilder::new().mode(0o700).create(dir) {
+        Err(e) if e.kind() != io::ErrorKind::AlreadyExists => return Err(e),
+        _ => {}
+    }
+    // Not following links, so nobody can point the socket somewhere else
+    let metadata = std::fs::symlink_metadata(dir)?;
+    if !metadata.is_dir() || metadata.permissions().mode() & 0o077 != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("{} is open to other users", dir.display()),
+        ));
+    }
+    Ok(())
+}
+
+/// Hand files to the instance listening on `socket`, which opens them and
+/// comes to the front
+///
+/// Fails when no instance answers, such as when the socket is left over
+/// from one that exited.
+#[cfg(unix)]
+pub fn forward(socket: &Path, files: &[OpenRequest]) -> io::Result<()> {
+    let mut stream = std::os::unix::net::UnixStream::connect(socket)?;
+    stream.set_read_timeout(Some(REPLY_TIMEOUT))?;
+    stream.set_write_timeout(Some(REPLY_TIMEOUT))?;
+    let message = serde_json::to_string(files).map_err(io::Error::other)?;
+    writeln!(stream, "{}", message)?;
+    let mut reply = String::new();
+    BufReader::new(&stream).read_line(&mut reply)?;
+    if reply.trim() == "ok" {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!("unexpected reply {:?}", reply.trim())))
+    }
+}
+
+#[cfg(not(unix))]
+pub fn forward(_socket: &Path, _files: &[OpenRequest]) -> io::Result<()> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+/// Listens for files sent by later `writer` invocations
+pub struct InstanceServer {
+    socket: PathBuf,
+    requests: Receiver<Vec<OpenRequest>>,
+}
+
+impl InstanceServer {
+    /// Listen on `socket`, replacing a stale socket file
+    ///
+    /// The socket's directory must be private to the user. Fails when
+    /// another instance is already listening. `on_request` is called from a
+    /// background thread whenever files arrive.
+    #[cfg(unix)]
+    pub fn start(socket: &Path, on_request: impl Fn() + Send + 'static) -> io::Result<Self> {
+        use std::os::unix::fs::PermissionsExt;
+        use std::os::unix::net::{UnixListener, UnixStream};
+        if let Some(dir) = socket.parent() {
+            private_dir(dir)?;
+        }
+        // Only a socket nobody answers on is left over from an instance
+        // that exited
+        match UnixStream::connect(socket) {
+            Ok(_) => {
+                return Err(io::Error::new(io::ErrorKind::AddrInUse, "another instance is listening"));
+            }
+            Err(e) if e.kind() == io::ErrorKind::ConnectionRefused => std::fs::remove_file(socket)?,
+            Err(_) => {}
+        }
+        let listener = UnixListener::bind(socket)?;
+        // Only the user may hand files to their editor
+        std::fs::set_permissions(socket, std::fs::Permissions::from_mode(0o600))?;
+        let (sender, requests) = mpsc::channel();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else {
+                    continue;
+                };
+                let _ = stream.set_read_timeout(Some(REPLY_TIMEOUT));
+                let mut message = String::new();
+                if BufReader::new(&stream).read_line(&mut message).is_err() {
+                    continue;
+                }
+                let Ok(files) = serde_json::from_str::<Vec<OpenRequest>>(&message) else {
+                    let _ = writeln!(stream, "error");
+                    continue;
+                };
+                let _ = writeln!(stream, "ok");
+                if sender.send(files).is_err() {
+                    break;
+                }
+                on_request();
+            }
+        });
+        Ok(Self { socket: socket.to_path_buf(), requests })
+    }
+
+    #[cfg(not(unix))]
+    pub fn start(_socket: &Path, _on_request: impl Fn() + Send + 'static) -> io::Result<Self> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    /// Files sent since the last call, one list per invocation; an empty
+    /// list only asks to come to the front
+    pub fn poll(&self) -> Vec<Vec<OpenRequest>> {
+        self.requests.try_iter().collect()
+    }
+}
+
+impl Drop for InstanceServer {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.socket);
+    }
+}
@@ -0,0 +1,392 @@
+use std::collections::BTreeMap;
+use eframe::egui::{self, Key, KeyboardShortcut, Modifiers};
+
+/// Commands that can be bound to keyboard shortcuts
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Command {
+    NewFile,
+    OpenFile,
+    SaveFile,
+    Quit,
+    Preferences,
+    Undo,
+    Redo,
+    Find,
+    GoToLine,
+    GoToSymbol,
+    GoToMatchingBracket,
+    ToggleOutline,
+    TriggerCompletion,
+    ShowHover,
+    GoToDefinition,
+    RenameSymbol,
+    FormatDocument,
+    ToggleProblems,
+    FilterThroughCommand,
+    InsertCommandOutput,
+    RunCommandOnFile,
+    RunFile,
+    StopRun,
+    ToggleTerminal,
+    NextChange,
+    PreviousChange,
+    RevertChange,
+    CompareWithSaved,
+    CompareWithFile,
+    MergeConflicts,
+    ToggleFileHistory,
+    ReopenClosedFile,
+    ToggleReadOnly,
+    Fold,
+    Unfold,
+    ToggleWordWrap,
+    ZoomIn,
+    ZoomOut,
+    ResetZoom,
+}
+
+impl Command {
+    pub const ALL: [Command; 39] = [
+        Command::NewFile,
+        Command::OpenFile,
+        Command::SaveFile,
+        Command::Quit,
+        Command::Preferences,
+        Command::Undo,
+        Command::Redo,
+        Command::Find,
+        Command::GoToLine,
+        Command::GoToSymbol,
+        Command::GoToMatchingBracket,
+        Command::ToggleOutline,
+        Command::TriggerCompletion,
+        Command::ShowHover,
+        Command::GoToDefinition,
+        Command::RenameSymbol,
+        Command::FormatDocument,
+        Command::ToggleProblems,
+        Command::FilterThroughCommand,
+        Command::InsertCommandOutput,
+        Command::RunCommandOnFile,
+        Command::RunFile,
+        Command::StopRun,
+        Command::ToggleTerminal,
+        Command::NextChange,
+        Command::PreviousChange,
+        Command::RevertChange,
+        Command::CompareWithSaved,
+        Command::CompareWithFile,
+        Command::MergeConflicts,
+        Command::ToggleFileHistory,
+        Command::ReopenClosedFile,
+        Command::ToggleReadOnly,
+        Command::Fold,
+        Command::Unfold,
+        Command::ToggleWordWrap,
+        Command::ZoomIn,
+        Command::ZoomOut,
+        Command::ResetZoom,
+    ];
+
+    /// Name used for the command in the settings file
+    pub fn id(&self) -> &'static str {
+        match self {
+            Command::NewFile => "new_file",
+            Command::OpenFile => "open_file",
+            Command::SaveFile => "save_file",
+            Command::Quit => "quit",
+            Command::Preferences => "preferences",
+            Command::Undo => "undo",
+            Command::Redo => "redo",
+            Command::Find => "find",
+            Command::GoToLine => "go_to_line",
+            Command::GoToSymbol => "go_to_symbol",
+            Command::ToggleOutline => "toggle_outline",
+            Command::TriggerCompletion => "trigger_completion",
+            Command::ShowHover => "show_hover",
+            Command::GoToDefinition => "go_to_definition",
+            Command::RenameSymbol => "rename_symbol",
+            Command::FormatDocument => "format_document",
+            Command::ToggleProblems => "toggle_problems",
+            Command::FilterThroughCommand => "filter_through_command",
+            Command::InsertCommandOutput => "insert_command_output",
+            Command::RunCommandOnFile => "run_command_on_file",
+            Command::RunFile => "run_file",
+            Command::StopRun => "stop_run",
+            Command::ToggleTerminal => "toggle_terminal",
+            Command::NextChange => "next_change",
+            Command::PreviousChange => "previous_change",
+            Command::RevertChange => "revert_change",
+            Command::CompareWithSaved => "compare_with_saved",
+            Command::CompareWithFile => "compare_with_file",
+            Command::MergeConflicts => "merge_conflicts",
+            Command::ToggleFileHistory => "toggle_file_history",
+            Command::ReopenClosedFile => "reopen_closed_file",
+            Command::ToggleReadOnly => "toggle_read_only",
+            Command::GoToMatchingBracket => "go_to_matching_bracket",
+            Command::Fold => "fold",
+            Command::Unfold => "unfold",
+            Command::ToggleWordWrap => "toggle_word_wrap",
+            Command::ZoomIn => "zoom_in",
+            Command::ZoomOut => "zoom_out",
+            Command::ResetZoom => "reset_zoom",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Command::NewFile => "New File",
+            Command::OpenFile => "Open File",
+            Command::SaveFile => "Save File",
+            Command::Quit => "Quit",
+            Command::Preferences => "Preferences",
+            Command::Undo => "Undo",
+            Command::Redo => "Redo",
+            Command::Find => "Find",
+            Command::GoToLine => "Go to Line",
+            Command::GoToSymbol => "Go to Symbol",
+            Command::ToggleOutline => "Toggle Outline",
+            Command::TriggerCompletion => "Trigger Completion",
+            Command::ShowHover => "Show Hover",
+            Command::GoToDefinition => "Go to Definition",
+            Command::RenameSymbol => "Rename Symbol",
+            Command::FormatDocument => "Format Document",
+            Command::ToggleProblems => "Toggle Problems",
+            Command::FilterThroughCommand => "Filter Through Command",
+            Command::InsertCommandOutput => "Insert Command Output",
+            Command::RunCommandOnFile => "Run Command on File",
+            Command::RunFile => "Run File",
+            Command::StopRun => "Stop Running",
+            Command::ToggleTerminal => "Toggle Terminal",
+            Command::NextChange => "Next Change",
+            Command::PreviousChange => "Previous Change",
+            Command::RevertChange => "Revert Change",
+            Command::CompareWithSaved => "Compare with Saved",
+            Command::CompareWithFile => "Compare With File...",
+            Command::MergeConflicts => "Merge Conflicts...",
+            Command::ToggleFileHistory => "Toggle File History",
+            Command::ReopenClosedFile => "Reopen Closed File",
+            Command::ToggleReadOnly => "Toggle Read-Only",
+            Command::GoToMatchingBracket => "Go to Matching Bracket",
+            Command::Fold => "Fold",
+            Command::Unfold => "Unfold",
+            Command::ToggleWordWrap => "Toggle Word Wrap",
+            Command::ZoomIn => "Zoom In",
+            Command::ZoomOut => "Zoom Out",
+            Command::ResetZoom => "Reset Zoom",
+        }
+    }
+
+    /// Default shortcuts, separated by spaces when there is more than one;
+    /// empty when the command is unbound
+    pub fn default_keys(&self) -> &'static str {
+        match self {
+            Command::NewFile => "Ctrl+N",
+            Command::OpenFile => "Ctrl+O",
+            Command::SaveFile => "Ctrl+S",
+            Command::Quit => "Ctrl+Q",
+            Command::Preferences => "Ctrl+Comma",
+            Command::Undo => "Ctrl+Z",
+            Command::Redo => "Ctrl+Y Ctrl+Shift+Z",
+            Command::Find => "Ctrl+F",
+            Command::GoToLine => "Ctrl+G",
+            Command::GoToSymbol => "Ctrl+Shift+O",
+            Command::ToggleOutline => "",
+            Command::TriggerCompletion => "Ctrl+Space",
+            Command::ShowHover => "Ctrl+K",
+            Command::GoToDefinition => "F12",
+            Command::RenameSymbol => "F2",
+            Command::FormatDocument => "Shift+Alt+F",
+            Command::ToggleProblems => "Ctrl+Shift+M",
+            Command::FilterThroughCommand => "",
+            Command::InsertCommandOutput => "",
+            Command::RunCommandOnFile => "",
+            Command::RunFile => "F5",
+            Command::StopRun => "Shift+F5",
+            Command::ToggleTerminal => "Ctrl+Backtick",
+            Command::NextChange => "Alt+F5",
+            Command::PreviousChange => "Shift+Alt+F5",
+            Command::RevertChange => "",
+            Command::CompareWithSaved => "",
+            Command::CompareWithFile => "",
+            Command::MergeConflicts => "",
+            Command::ToggleFileHistory => "",
+            Command::ReopenClosedFile => "Ctrl+Shift+T",
+            Command::ToggleReadOnly => "",
+            Command::GoToMatchingBracket => "Ctrl+Shift+Backslash",
+            Command::Fold => "Ctrl+Shift+OpenBracket",
+            Command::Unfold => "Ctrl+Shift+CloseBracket",
+            Command::ToggleWordWrap => "Alt+Z",
+            Command::ZoomIn => "Ctrl+Equals Ctrl+Plus",
+            Command::ZoomOut => "Ctrl+Minus",
+            Command::ResetZoom => "Ctrl+0",
+        }
+    }
+
+    /// Whether the command works in large-file mode, where the file is
+    /// not held as one string for the language features
+    pub fn works_on_large_files(&self) -> bool {
+        matches!(
+            self,
+            Command::NewFile
+                | Command::OpenFile
+                | Command::SaveFile
+                | Command::Quit
+                | Command::Preferences
+                | Command::Undo
+                | Command::Redo
+                | Command::Find
+                | Command::GoToLine
+                | Command::ToggleProblems
+                | Command::RunFile
+                | Command::StopRun
+                | Command::ToggleTerminal
+                | Command::ReopenClosedFile
+                | Command::ToggleReadOnly
+                | Command::ZoomIn
+                | Command::ZoomOut
+                | Command::ResetZoom
+        )
+    }
+
+    pub fn from_id(id: &str) -> Option<Command> {
+        Command::ALL.into_iter().find(|c| c.id() == id)
+    }
+}
+
+/// Parse a shortcut such as `Ctrl+Shift+Z`
+///
+/// `Ctrl` and `Cmd` both mean the platform's command key.
+pub fn parse_shortcut(text: &str) -> Result<KeyboardShortcut, String> {
+    let mut parts: Vec<&str> = text.split('+').map(str::trim).collect();
+    // `Ctrl++` binds the plus key
+    if text.ends_with("++") {
+        parts.truncate(parts.len() - 2);
+        parts.push("+");
+    }
+    let (key, modifier_names) = parts.split_last().ok_or("empty shortcut")?;
+
+    let mut modifiers = Modifiers::NONE;
+    for name in modifier_names {
+        match name.to_ascii_lowercase().as_str() {
+            "ctrl" | "cmd" | "command" => modifiers |= Modifiers::COMMAND,
+            "alt" | "option" => modifiers |= Modifiers::ALT,
+            "shift" => modifiers |= Modifiers::SHIFT,
+            _ => return Err(format!("unknown modifier \"{}\" in \"{}\"", name, text)),
+        }
+    }
+    let key = Key::from_name(key)
+        .or_else(|| Key::from_name(&key.to_ascii_uppercase()))
+        .ok_or_else(|| format!("unknown key \"{}\" in \"{}\"", key, text))?;
+    Ok(KeyboardShortcut::new(modifiers, key))
+}
+
+/// Format a shortcut the way `parse_shortcut` reads it
+pub fn format_shortcut(shortcut: &KeyboardShortcut) -> String {
+    let modifiers = shortcut.modifiers;
+    let mut text = String::new();
+    if modifiers.command || modifiers.ctrl {
+        text.push_str("Ctrl+");
+    }
+    if modifiers.alt {
+        text.push_str("Alt+");
+    }
+    if modifiers.shift {
+        text.push_str("Shift+");
+    }
+    text.push_str(shortcut.logical_key.name());
+    text
+}
+
+/// Parse space-separated alternative shortcuts
+fn parse_keys(keys: &str) -> Result<Vec<KeyboardShortcut>, String> {
+    keys.split_whitespace().map(parse_shortcut).collect()
+}
+
+/// Shortcuts for every command, with the user's overrides applied
+#[derive(Clone, Debug)]
+pub struct KeyBindings {
+    /// Most specific shortcuts (most modifiers) first
+    bindings: Vec<(KeyboardShortcut, Command)>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self::new(&BTreeMap::new()).unwrap_or(Self { bindings: Vec::new() })
+    }
+}
+
+impl KeyBindings {
+    /// Build bindings from overrides mapping command ids to shortcuts
+    ///
+    /// An empty override unbinds the command. Every unknown command and
+    /// invalid shortcut is reported.
+    pub fn new(overrides: &BTreeMap<String, String>) -> Result<Self, Vec<String>> {
+        let mut errors: Vec<String> = overrides
+            .keys()
+            .filter(|id| Command::from_id(id).is_none())
+            .map(|id| format!("unknown command \"{}\" in keybindings", id))
+            .collect();
+
+        let mut bindings = Vec::new();
+        for command in Command::ALL {
+            let keys = overrides
+                .get(command.id())
+                .map_or(command.default_keys(), String::as_str);
+            match parse_keys(keys) {
+                Ok(shortcuts) => bindings.extend(shortcuts.into_iter().map(|s| (s, command))),
+                Err(e) => errors.push(format!("{}: {}", command.id(), e)),
+            }
+        }
+
+        for (i, (shortcut, command)) in bindings.iter().enumerate() {
+            if let Some((_, other)) = bindings[..i].iter().find(|(s, _)| s == shortcut) {
+                errors.push(format!(
+                    "{} is bound to both {} and {}",
+                    format_shortcut(shortcut),
+                    other.id(),
+                    command.id()
+                ));
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        bindings.sort_by_key(|(s, _)| {
+            let m = s.modifiers;
+            std::cmp::Reverse(m.command as u8 + m.alt as u8 + m.shift as u8)
+        });
+        Ok(Self { bindings })
+    }
+
+    /// First shortcut of a command, for menus
+    pub fn shortcut_text(&self, command: Command) -> String {
+        self.bindings
+            .iter()
+            .find(|(_, c)| *c == command)
+            .map(|(s, _)| format_shortcut(s))
+            .unwrap_or_default()
+    }
+
+    /// Commands whose shortcut was pressed this frame
+    ///
+    /// Extra Shift or Alt is ignored unless a more specific shortcut
+    /// matches, so Ctrl+Shift+Z runs redo rather than undo.
+    pub fn pressed(&self, input: &egui::InputState) -> Vec<Command> {
+        let mut commands = Vec::new();
+        let mut keys = Vec::new();
+        for (shortcut, command) in &self.bindings {
+            let key = shortcut.logical_key;
+            if keys.contains(&key)
+                || !input.key_pressed(key)
+                || !input.modifiers.matches_logically(shortcut.modifiers)
+            {
+                continue;
+            }
+            keys.push(key);
+            commands.push(*command);
+        }
+        commands
+    }
+}
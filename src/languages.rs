@@ -0,0 +1,676 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+use std::sync::{LazyLock, PoisonError, RwLock, RwLockReadGuard};
+use egui_code_editor::Syntax;
+use serde::Deserialize;
+use crate::indent::IndentStyle;
+use crate::syntax;
+
+/// A language the editor knows how to recognise and highlight
+#[derive(Clone, Debug)]
+pub struct Language {
+    pub name: &'static str,
+    /// Extensions without the leading dot, matched case-insensitively
+    pub extensions: &'static [&'static str],
+    /// Exact file names such as `Makefile`
+    pub filenames: &'static [&'static str],
+    /// File name patterns where `*` matches any run of characters and `?`
+    /// a single character
+    pub globs: &'static [&'static str],
+    /// Lowercase names used for the language in Vim and Emacs modelines
+    pub aliases: &'static [&'static str],
+    /// Programs named in `#!` lines of scripts in the language
+    pub interpreters: &'static [&'static str],
+    pub line_comment: Option<&'static str>,
+    pub block_comment: Option<(&'static str, &'static str)>,
+    /// Characters that open and close strings
+    pub quotes: &'static [char],
+    /// Conventional indentation, used when a file's own can't be detected
+    pub indent: Option<IndentStyle>,
+    pub syntax: Syntax,
+}
+
+impl Language {
+    fn new(name: &'static str, syntax: Syntax) -> Self {
+        Self {
+            name,
+            extensions: &[],
+            filenames: &[],
+            globs: &[],
+            aliases: &[],
+            interpreters: &[],
+            line_comment: None,
+            block_comment: None,
+            quotes: &['"'],
+            indent: None,
+            syntax,
+        }
+    }
+
+    fn extensions(self, extensions: &'static [&'static str]) -> Self {
+        Self { extensions, ..self }
+    }
+
+    fn filenames(self, filenames: &'static [&'static str]) -> Self {
+        Self { filenames, ..self }
+    }
+
+    fn globs(self, globs: &'static [&'static str]) -> Self {
+        Self { globs, ..self }
+    }
+
+    fn aliases(self, aliases: &'static [&'static str]) -> Self {
+        Self { aliases, ..self }
+    }
+
+    fn interpreters(self, interpreters: &'static [&'static str]) -> Self {
+        Self { interpreters, ..self }
+    }
+
+    fn comments(self, line: Option<&'static str>, block: Option<(&'static str, &'static str)>) -> Self {
+        Self {
+            line_comment: line,
+            block_comment: block,
+            ..self
+        }
+    }
+
+    fn quotes(self, quotes: &'static [char]) -> Self {
+        Self { quotes, ..self }
+    }
+
+    fn indent(self, indent: IndentStyle) -> Self {
+        Self {
+            indent: Some(indent),
+            ..self
+        }
+    }
+}
+
+/// Whether `name` matches a pattern containing `*` and `?` wildcards
+pub fn glob_matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Position after the last `*` and the name position it was tried at
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p + 1, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((after, tried)) => {
+                    p = after;
+                    n = tried + 1;
+                    star = Some((after, tried + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Program named by a `#!` line, looking through `/usr/bin/env`
+pub fn shebang_interpreter(text: &str) -> Option<&str> {
+    let line = text.lines().next()?.strip_prefix("#!")?;
+    let mut words = line.split_whitespace();
+    let program = words.next()?.rsplit('/').next()?;
+    if program == "env" {
+        // Skip flags such as `-S` and variable assignments
+        return words.find(|w| !w.starts_with('-') && !w.contains('='));
+    }
+    Some(program)
+}
+
+/// Language named by a Vim or Emacs modeline in the first or last five
+/// lines, as written in the modeline
+pub fn modeline_language(text: &str) -> Option<&str> {
+    let lines: Vec<&str> = text.lines().collect();
+    let tail = lines.len().saturating_sub(5).max(5.min(lines.len()));
+    lines[..5.min(lines.len())]
+        .iter()
+        .chain(&lines[tail..])
+        .find_map(|line| emacs_modeline(line).or_else(|| vim_modeline(line)))
+}
+
+/// `-*- mode: python -*-` or `-*- python -*-`
+fn emacs_modeline(line: &str) -> Option<&str> {
+    let (_, rest) = line.split_once("-*-")?;
+    let (inner, _) = rest.split_once("-*-")?;
+    let mode = if inner.contains(':') {
+        inner.split(';').find_map(|variable| {
+            let (key, value) = variable.split_once(':')?;
+            key.trim().eq_ignore_ascii_case("mode").then_some(value)
+        })?
+    } else {
+        inner
+    };
+    Some(mode.trim()).filter(|m| !m.is_empty())
+}
+
+/// `vim: set ft=python:` or `vi: filetype=python`
+fn vim_modeline(line: &str) -> Option<&str> {
+    let start = ["vim:", "vi:", "ex:"]
+        .iter()
+        .filter_map(|marker| {
+            let index = line.find(marker)?;
+            let at_word_start = line[..index].chars().next_back().is_none_or(char::is_whitespace);
+            at_word_start.then_some(index + marker.len())
+        })
+        .min()?;
+    line[start..]
+        .split(|c: char| c == ':' || c.is_whitespace())
+        .find_map(|option| {
+            let (key, value) = option.split_once('=')?;
+            matches!(key, "ft" | "filetype" | "syntax" | "syn").then_some(value)
+        })
+        .filter(|v| !v.is_empty())
+}
+
+/// Guess the language of text that has no file name from its first lines
+pub fn guess_from_content(text: &str) -> Option<&'static str> {
+    let lines: Vec<&str> = text
+        .lines()
+        .map(str::trim_end)
+        .filter(|l| !l.trim().is_empty())
+        .take(30)
+        .collect();
+    let first = lines.first()?.trim_start();
+    let any = |f: &dyn Fn(&str) -> bool| lines.iter().any(|l| f(l));
+
+    let lowercase = first.to_ascii_lowercase();
+    if lowercase.starts_with("<!doctype html") || lowercase.starts_with("<html") {
+        return Some("HTML");
+    }
+    let after_bracket = first.strip_prefix('[').map(|rest| rest.trim_start().chars().next());
+    if first.starts_with('{')
+        || matches!(after_bracket, Some(None | Some('{' | '[' | '"' | ']' | '0'..='9')))
+    {
+        return Some("JSON");
+    }
+    if first == "---" {
+        return Some("YAML");
+    }
+    if first.starts_with("FROM ") {
+        return Some("Dockerfile");
+    }
+    if any(&|l| l.starts_with("fn ") || l.starts_with("pub fn ") || l.starts_with("use std::") || l.starts_with("#[derive")) {
+        return Some("Rust");
+    }
+    if any(&|l| (l.starts_with("def ") || l.starts_with("class ")) && l.ends_with(':'))
+        || any(&|l| l.starts_with("from ") && l.contains(" import "))
+    {
+        return Some("Python");
+    }
+    if any(&|l| l.starts_with("#include")) {
+        let cpp = any(&|l| l.contains("std::") || l.starts_with("class ") || l.starts_with("namespace "));
+        return Some(if cpp { "C++" } else { "C" });
+    }
+    if let Some(package) = lines.iter().find_map(|l| l.strip_prefix("package ")) {
+        return Some(if package.ends_with(';') { "Java" } else { "Go" });
+    }
+    let sql = ["select ", "create table", "insert into", "update ", "delete from"];
+    if any(&|l| sql.iter().any(|keyword| l.to_ascii_lowercase().starts_with(keyword))) {
+        return Some("SQL");
+    }
+    if any(&|l| l.starts_with('[') && l.ends_with(']')) && any(&|l| l.contains(" = ")) {
+        return Some("TOML");
+    }
+    if any(&|l| l.starts_with("```") || l.starts_with("## ") || (l.contains("](") && l.contains('['))) {
+        return Some("Markdown");
+    }
+    None
+}
+
+const C_COMMENTS: (Option<&str>, Option<(&str, &str)>) = (Some("//"), Some(("/*", "*/")));
+
+fn builtin_languages() -> Vec<Language> {
+    let (line, block) = C_COMMENTS;
+    vec![
+        Language::new("Plain Text", syntax::plain_text())
+            .extensions(&["txt", "text"])
+            .aliases(&["text", "txt", "plain"]),
+        Language::new("Rust", Syntax::rust())
+            .extensions(&["rs"])
+            .aliases(&["rust", "rs"])
+            .comments(line, block)
+            .quotes(&['"', '\'']),
+        Language::new("Python", Syntax::python())
+            .extensions(&["py", "py3", "pyw", "pyi"])
+            .aliases(&["python", "py"])
+            .interpreters(&["python", "python2", "python3", "pypy", "pypy3"])
+            .comments(Some("#"), None)
+            .quotes(&['"', '\'']),
+        Language::new("Lua", Syntax::lua())
+            .extensions(&["lua"])
+            .aliases(&["lua"])
+            .interpreters(&["lua", "luajit"])
+            .comments(Some("--"), Some(("--[[", "]]")))
+            .quotes(&['"', '\''])
+            .indent(IndentStyle::Spaces(2)),
+        Language::new("Shell", Syntax::shell())
+            .extensions(&["sh", "bash", "zsh", "ksh"])
+            .filenames(&[".bashrc", ".bash_profile", ".zshrc", ".profile", "PKGBUILD"])
+            .aliases(&["sh", "bash", "zsh", "ksh", "shell", "shell-script"])
+            .interpreters(&["sh", "bash", "zsh", "ksh", "dash"])
+            .comments(Some("#"), None)
+            .quotes(&['"', '\'', '`'])
+            .indent(IndentStyle::Spaces(2)),
+        Language::new("SQL", Syntax::sql())
+            .extensions(&["sql"])
+            .aliases(&["sql"])
+            .comments(Some("--"), Some(("/*", "*/")))
+            .quotes(&['"', '\''])
+            .indent(IndentStyle::Spaces(2)),
+        Language::new("Assembly", Syntax::asm())
+            .extensions(&["asm", "s"])
+            .aliases(&["asm", "nasm"])
+            .comments(Some(";"), None)
+            .indent(IndentStyle::Tabs),
+        Language::new("JSON", syntax::json())
+            .extensions(&["json", "jsonc"])
+            .aliases(&["json", "jsonc"])
+            .comments(line, block)
+            .indent(IndentStyle::Spaces(2)),
+        Language::new("TOML", syntax::toml())
+            .extensions(&["toml"])
+            .aliases(&["toml", "conf-toml"])
+            .filenames(&["Cargo.lock", "Pipfile"])
+            .comments(Some("#"), None)
+            .quotes(&['"', '\'']),
+        Language::new("YAML", syntax::yaml())
+            .extensions(&["yaml", "yml"])
+            .aliases(&["yaml", "yml"])
+            .comments(Some("#"), None)
+            .indent(IndentStyle::Spaces(2)),
+        Language::new("Markdown", syntax::markdown())
+            .extensions(&["md", "markdown"])
+            .aliases(&["markdown", "md"])
+            .comments(None, Some(("<!--", "-->"))),
+        Language::new("C", syntax::c())
+            .extensions(&["c", "h"])
+            .aliases(&["c"])
+            .comments(line, block)
+            .quotes(&['"', '\'']),
+        Language::new("C++", syntax::cpp())
+            .extensions(&["cpp", "cc", "cxx", "hpp", "hh", "hxx"])
+            .aliases(&["cpp", "c++"])
+            .comments(line, block)
+            .quotes(&['"', '\'']),
+        Language::new("Go", syntax::go())
+            .extensions(&["go"])
+            .aliases(&["go"])
+            .comments(line, block)
+            .quotes(&['"', '\'', '`'])
+            .indent(IndentStyle::Tabs),
+        Language::new("JavaScript", syntax::javascript())
+            .extensions(&["js", "mjs", "cjs", "jsx"])
+            .aliases(&["javascript", "js"])
+            .interpreters(&["node", "nodejs"])
+            .comments(line, block)
+            .quotes(&['"', '\'', '`'])
+            .indent(IndentStyle::Spaces(2)),
+        Language::new("TypeScript", syntax::typescript())
+            .extensions(&["ts", "mts", "cts", "tsx"])
+            .aliases(&["typescript", "ts"])
+            .interpreters(&["ts-node"])
+            .comments(line, block)
+            .quotes(&['"', '\'', '`'])
+            .indent(IndentStyle::Spaces(2)),
+        Language::new("HTML", syntax::html())
+            .extensions(&["html", "htm", "xhtml"])
+            .aliases(&["html"])
+            .comments(None, Some(("<!--", "-->")))
+            .quotes(&['"', '\''])
+            .indent(IndentStyle::Spaces(2)),
+        Language::new("CSS", syntax::css())
+            .extensions(&["css"])
+            .aliases(&["css"])
+            .comments(None, Some(("/*", "*/")))
+            .quotes(&['"', '\''])
+            .indent(IndentStyle::Spaces(2)),
+        Language::new("Java", syntax::java())
+            .extensions(&["java"])
+            .aliases(&["java"])
+            .comments(line, block)
+            .quotes(&['"', '\'']),
+        Language::new("Makefile", syntax::makefile())
+            .extensions(&["mk"])
+            .filenames(&["Makefile", "makefile", "GNUmakefile"])
+            .globs(&["Makefile.*"])
+            .aliases(&["make", "makefile"])
+            .interpreters(&["make"])
+            .comments(Some("#"), None)
+            .indent(IndentStyle::Tabs),
+        Language::new("Dockerfile", syntax::dockerfile())
+            .filenames(&["Dockerfile", "Containerfile"])
+            .globs(&["Dockerfile.*", "*.dockerfile"])
+            .aliases(&["dockerfile"])
+            .comments(Some("#"), None),
+    ]
+}
+
+/// Format of a syntax definition file
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DefinitionFormat {
+    Toml,
+    Json,
+}
+
+impl DefinitionFormat {
+    /// Format for a file extension, if definitions can be written in it
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension {
+            "toml" => Some(DefinitionFormat::Toml),
+            "json" => Some(DefinitionFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+/// A syntax definition as written by the user
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DefinitionFile {
+    name: String,
+    #[serde(default)]
+    extensions: Vec<String>,
+    #[serde(default)]
+    filenames: Vec<String>,
+    #[serde(default)]
+    globs: Vec<String>,
+    #[serde(default)]
+    aliases: Vec<String>,
+    #[serde(default = "default_case_sensitive")]
+    case_sensitive: bool,
+    line_comment: Option<String>,
+    block_comment: Option<[String; 2]>,
+    #[serde(default = "default_quotes")]
+    quotes: Vec<String>,
+    #[serde(default)]
+    keywords: Vec<String>,
+    #[serde(default)]
+    types: Vec<String>,
+    #[serde(default)]
+    special: Vec<String>,
+    tab_size: Option<usize>,
+    insert_spaces: Option<bool>,
+}
+
+fn default_case_sensitive() -> bool {
+    true
+}
+
+fn default_quotes() -> Vec<String> {
+    vec!["\"".to_string(), "'".to_string()]
+}
+
+fn leak(s: String) -> &'static str {
+    Box::leak(s.into_boxed_str())
+}
+
+fn leak_list(list: Vec<String>) -> &'static [&'static str] {
+    Box::leak(list.into_iter().map(leak).collect::<Vec<_>>().into_boxed_slice())
+}
+
+/// Parse a user syntax definition
+///
+/// `Syntax` only holds `&'static str`, so the strings of a parsed
+/// definition are leaked. Definitions are only loaded on startup and on
+/// request.
+pub fn parse_definition(source: &str, format: DefinitionFormat) -> Result<Language, String> {
+    let file: DefinitionFile = match format {
+        DefinitionFormat::Toml => toml::from_str(source).map_err(|e| {
+            let line = e.span().map_or(1, |span| source[..span.start].matches('\n').count() + 1);
+            format!("line {}: {}", line, e.message().trim())
+        })?,
+        DefinitionFormat::Json => serde_json::from_str(source).map_err(|e| e.to_string())?,
+    };
+
+    let mut errors = Vec::new();
+    if file.name.trim().is_empty() {
+        errors.push("name must not be empty".to_string());
+    }
+    if file.extensions.is_empty() && file.filenames.is_empty() && file.globs.is_empty() {
+        errors.push("no extensions, filenames or globs to select the syntax by".to_string());
+    }
+    if file.line_comment.as_deref() == Some("") {
+        errors.push("line_comment must not be empty".to_string());
+    }
+    if file.block_comment.as_ref().is_some_and(|[open, close]| open.is_empty() || close.is_empty()) {
+        errors.push("block_comment delimiters must not be empty".to_string());
+    }
+    let mut quotes = Vec::new();
+    for quote in &file.quotes {
+        let mut chars = quote.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => quotes.push(c),
+            _ => errors.push(format!("quote \"{}\" must be a single character", quote)),
+        }
+    }
+    for word in file.keywords.iter().chain(&file.types).chain(&file.special) {
+        if word.is_empty() || word.contains(char::is_whitespace) {
+            errors.push(format!("\"{}\" is not a single word", word));
+        }
+    }
+    let indent = match (file.insert_spaces, file.tab_size) {
+        (_, Some(size)) if !(1..=16).contains(&size) => {
+            errors.push(format!("tab_size must be between 1 and 16, not {}", size));
+            None
+        }
+        (Some(false), _) => Some(IndentStyle::Tabs),
+        (_, Some(size)) => Some(IndentStyle::Spaces(size)),
+        (Some(true), None) => Some(IndentStyle::default()),
+        (None, None) => None,
+    };
+    if !errors.is_empty() {
+        return Err(errors.join(", "));
+    }
+
+    // Words are compared in upper case when the language ignores case
+    let case_sensitive = file.case_sensitive;
+    let words = |list: Vec<String>| -> BTreeSet<&'static str> {
+        list.into_iter()
+            .map(|w| leak(if case_sensitive { w } else { w.to_ascii_uppercase() }))
+            .collect()
+    };
+    let name = leak(file.name);
+    let line_comment = file.line_comment.map(leak);
+    let block_comment = file.block_comment.map(|[open, close]| (leak(open), leak(close)));
+    let comment = line_comment.unwrap_or(syntax::NO_COMMENT);
+    let syntax = Syntax {
+        language: name,
+        case_sensitive,
+        comment,
+        comment_multiline: block_comment.map_or([comment; 2], |(open, close)| [open, close]),
+        hyperlinks: BTreeSet::from(["http"]),
+        keywords: words(file.keywords),
+        types: words(file.types),
+        special: words(file.special),
+    };
+    let aliases = file.aliases.into_iter().map(|a| a.to_ascii_lowercase()).collect();
+
+    Ok(Language {
+        name,
+        extensions: leak_list(file.extensions),
+        filenames: leak_list(file.filenames),
+        globs: leak_list(file.globs),
+        aliases: leak_list(aliases),
+        interpreters: &[],
+        line_comment,
+        block_comment,
+        quotes: Box::leak(quotes.into_boxed_slice()),
+        indent,
+        syntax,
+    })
+}
+
+static BUILTIN: LazyLock<LanguageRegistry> = LazyLock::new(LanguageRegistry::default);
+
+static ACTIVE: LazyLock<RwLock<LanguageRegistry>> = LazyLock::new(|| RwLock::new(LanguageRegistry::default()));
+
+/// The languages that ship with the editor
+pub fn builtin() -> &'static LanguageRegistry {
+    &BUILTIN
+}
+
+/// The languages in use: the built-in ones plus the user's
+pub fn registry() -> RwLockReadGuard<'static, LanguageRegistry> {
+    ACTIVE.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Replace the languages in use
+pub fn install(registry: LanguageRegistry) {
+    *ACTIVE.write().unwrap_or_else(PoisonError::into_inner) = registry;
+}
+
+/// Languages by name, file name and extension
+///
+/// Languages added later take precedence when several match a file.
+#[derive(Clone, Debug)]
+pub struct LanguageRegistry {
+    languages: Vec<Language>,
+    /// Problems found while loading user syntax definitions
+    pub errors: Vec<String>,
+}
+
+impl Default for LanguageRegistry {
+    fn default() -> Self {
+        Self {
+            languages: builtin_languages(),
+            errors: Vec::new(),
+        }
+    }
+}
+
+impl LanguageRegistry {
+    /// Load the built-in languages and every `*.toml` and `*.json` syntax
+    /// definition in `dir`
+    pub fn load(dir: Option<&Path>) -> Self {
+        let mut registry = Self::default();
+        let Some(entries) = dir.and_then(|dir| fs::read_dir(dir).ok()) else {
+            return registry;
+        };
+
+        let mut paths: Vec<_> = entries
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| {
+                path.extension()
+                    .and_then(|ext| ext.to_str())
+                    .and_then(DefinitionFormat::from_extension)
+                    .is_some()
+            })
+            .collect();
+        paths.sort();
+
+        for path in paths {
+            let format = path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .and_then(DefinitionFormat::from_extension)
+                .unwrap_or(DefinitionFormat::Toml);
+            let result = fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|source| parse_definition(&source, format));
+            match result {
+                Ok(language) => registry.add(language),
+                Err(e) => registry.errors.push(format!("{}: {}", path.display(), e)),
+            }
+        }
+        registry
+    }
+
+    /// Add a language, replacing any language with the same name
+    pub fn add(&mut self, language: Language) {
+        self.languages.retain(|l| l.name != language.name);
+        self.languages.push(language);
+    }
+
+    pub fn languages(&self) -> &[Language] {
+        &self.languages
+    }
+
+    pub fn by_name(&self, name: &str) -> Option<&Language> {
+        self.languages.iter().find(|l| l.name == name)
+    }
+
+    /// Language named in a modeline, matching names and aliases
+    /// case-insensitively
+    pub fn by_alias(&self, alias: &str) -> Option<&Language> {
+        let alias = alias.to_ascii_lowercase();
+        self.languages
+            .iter()
+            .rev()
+            .find(|l| l.name.eq_ignore_ascii_case(&alias) || l.aliases.contains(&alias.as_str()))
+    }
+
+    /// Language run by an interpreter; version suffixes such as the `3.12`
+    /// of `python3.12` are ignored when there is no exact match
+    pub fn by_interpreter(&self, program: &str) -> Option<&Language> {
+        let find = |program: &str| self.languages.iter().rev().find(|l| l.interpreters.contains(&program));
+        find(program).or_else(|| find(program.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.')))
+    }
+
+    /// Language for a file name or path
+    ///
+    /// Exact file names take precedence over globs, and globs over
+    /// extensions.
+    pub fn for_filename(&self, filename: &str) -> Option<&Language> {
+        let name = Path::new(filename)
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or(filename);
+        if let Some(language) = self.languages.iter().rev().find(|l| l.filenames.contains(&name)) {
+            return Some(language);
+        }
+        if let Some(language) = self
+            .languages
+            .iter()
+            .rev()
+            .find(|l| l.globs.iter().any(|glob| glob_matches(glob, name)))
+        {
+            return Some(language);
+        }
+        let extension = syntax::get_file_extension(filename)?;
+        self.languages
+            .iter()
+            .rev()
+            .find(|l| l.extensions.iter().any(|e| e.eq_ignore_ascii_case(extension)))
+    }
+
+    /// Language for a document, falling back to plain text
+    pub fn detect(&self, filename: Option<&String>) -> &Language {
+        filename
+            .and_then(|f| self.for_filename(f))
+            .or_else(|| self.by_name("Plain Text"))
+            .unwrap_or(&self.languages[0])
+    }
+
+    /// Language of a document from its name and contents
+    ///
+    /// A modeline takes precedence over the file name, which takes
+    /// precedence over a `#!` line. Documents that were never saved are
+    /// also recognised from their contents.
+    pub fn detect_document(&self, filename: Option<&String>, text: &str) -> &Language {
+        modeline_language(text)
+            .and_then(|alias| self.by_alias(alias))
+            .or_else(|| filename.and_then(|f| self.for_filename(f)))
+            .or_else(|| shebang_interpreter(text).and_then(|program| self.by_interpreter(program)))
+            .or_else(|| {
+                filename
+                    .is_none()
+                    .then(|| guess_from_content(text))
+                    .flatten()
+                    .and_then(|name| self.by_name(name))
+            })
+            .unwrap_or_else(|| self.detect(None))
+    }
+}
@@ -0,0 +1,475 @@
+use std::fs;
+use std::io::{self, BufReader, BufWriter};
+use std::ops::Range;
+use std::path::Path;
+use ropey::Rope;
+use crate::indent::TAB_WIDTH;
+
+/// Characters of a line drawn on screen; the rest of longer lines is cut
+/// off so one enormous line cannot stall the frame
+pub const MAX_SHOWN_LINE: usize = 10_000;
+
+/// A change to the buffer as it can be undone and redone
+#[derive(Clone, Debug)]
+struct Edit {
+    at: usize,
+    removed: String,
+    inserted: String,
+    /// Revisions of the buffer before and after the change
+    before: u64,
+    after: u64,
+}
+
+/// Text held in a rope, for files too large to edit as one string
+///
+/// Every change gets a new revision, so callers notice edits by comparing
+/// revisions rather than the text itself.
+pub struct RopeBuffer {
+    rope: Rope,
+    revision: u64,
+    saved_revision: u64,
+    next_revision: u64,
+    undo: Vec<Edit>,
+    redo: Vec<Edit>,
+}
+
+impl RopeBuffer {
+    pub fn from_text(text: &str) -> Self {
+        Self::from_rope(Rope::from_str(text))
+    }
+
+    /// Read a file without holding a second copy of it in memory
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Ok(Self::from_rope(Rope::from_reader(BufReader::new(fs::File::open(path)?))?))
+    }
+
+    fn from_rope(rope: Rope) -> Self {
+        Self { rope, revision: 0, saved_revision: 0, next_revision: 1, undo: Vec::new(), redo: Vec::new() }
+    }
+
+    /// Write the text to a file and count it as saved
+    ///
+    /// The text is written to a temporary file next to it, which then
+    /// replaces the file, so a write failing partway leaves it as it was.
+    pub fn save(&mut self, path: &Path) -> io::Result<()> {
+        // Replace the file a link points to rather than the link
+        let target = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        let Some(name) = target.file_name() else {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "not a file name"));
+        };
+        let mut temporary_name = std::ffi::OsString::from(".");
+        temporary_name.push(name);
+        temporary_name.push(format!(".{}.tmp", std::process::id()));
+        let temporary = target.with_file_name(temporary_name);
+        let permissions = fs::metadata(&target).ok().map(|metadata| metadata.permissions());
+        let saved = self
+            .write_new_file(&temporary, permissions)
+            .and_then(|()| fs::rename(&temporary, &target));
+        if saved.is_err() {
+            let _ = fs::remove_file(&temporary);
+        }
+        saved?;
+        self.saved_revision = self.revision;
+        Ok(())
+    }
+
+    /// Write the text to a new file, on disk before returning
+    fn write_new_file(&self, path: &Path, permissions: Option<fs::Permissions>) -> io::Result<()> {
+        let mut writer = BufWriter::new(fs::File::create(path)?);
+        self.rope.write_to(&mut writer)?;
+        let file = writer.into_inner().map_err(io::IntoInnerError::into_error)?;
+        if let Some(permissions) = permissions {
+            file.set_permissions(permissions)?;
+        }
+        file.sync_all()
+    }
+
+    /// Changes since the buffer was loaded or saved
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.revision != self.saved_revision
+    }
+
+    pub fn len_chars(&self) -> usize {
+        self.rope.len_chars()
+    }
+
+    pub fn len_bytes(&self) -> usize {
+        self.rope.len_bytes()
+    }
+
+    pub fn len_lines(&self) -> usize {
+        self.rope.len_lines()
+    }
+
+    /// Char index where a line starts
+    pub fn line_to_char(&self, line: usize) -> usize {
+        self.rope.line_to_char(line.min(self.len_lines() - 1))
+    }
+
+    /// Line holding a char index
+    pub fn char_to_line(&self, index: usize) -> usize {
+        self.rope.char_to_line(index.min(self.len_chars()))
+    }
+
+    /// Length of a line in chars, without its line ending
+    pub fn line_len(&self, line: usize) -> usize {
+        let slice = self.rope.line(line.min(self.len_lines() - 1));
+        let mut len = slice.len_chars();
+        for ending in ['\n', '\r'] {
+            if len > 0 && slice.char(len - 1) == ending {
+                len -= 1;
+            }
+        }
+        len
+    }
+
+    /// Up to `MAX_SHOWN_LINE` chars of a line, without its line ending
+    pub fn shown_line(&self, line: usize) -> String {
+        let len = self.line_len(line).min(MAX_SHOWN_LINE);
+        self.rope.line(line.min(self.len_lines() - 1)).slice(..len).to_string()
+    }
+
+    /// The text in a range of chars
+    pub fn slice(&self, range: Range<usize>) -> String {
+        self.rope.slice(range).to_string()
+    }
+
+    /// Char index of the first match of `query` at or after `from`, or of
+    /// the last one before it when going backwards, wrapping around the
+    /// ends of the text
+    ///
+    /// Lines are searched one at a time, so a match cannot span lines.
+    pub fn find(&self, query: &str, from: usize, forward: bool) -> Option<usize> {
+        if query.is_empty() || query.contains('\n') {
+            return None;
+        }
+        let lines = self.len_lines();
+        let from = from.min(self.len_chars());
+        let first = self.char_to_line(from);
+        // Every line once, then the part of the first line not yet searched
+        for step in 0..=lines {
+            let line = if forward { (first + step) % lines } else { (first + lines - step % lines) % lines };
+            let text = String::from(self.rope.line(line));
+            let line_byte = self.rope.line_to_byte(line);
+            let mut matches = text.match_indices(query).map(|(i, _)| self.rope.byte_to_char(line_byte + i));
+            let found = match (forward, step) {
+                (true, 0) => matches.find(|&i| i >= from),
+                (true, step) if step == lines => matches.find(|&i| i < from),
+                (true, _) => matches.next(),
+                (false, 0) => matches.filter(|&i| i < from).last(),
+                (false, step) if step == lines => matches.filter(|&i| i >= from).last(),
+                (false, _) => matches.last(),
+            };
+            if found.is_some() {
+                return found;
+            }
+        }
+        None
+    }
+
+    /// Replace a range of chars, recording the change for undo
+    ///
+    /// Consecutive typing without line breaks is undone as one change.
+    pub fn replace(&mut self, range: Range<usize>, text: &str) {
+        let range = range.start.min(self.len_chars())..range.end.min(self.len_chars());
+        if range.is_empty() && text.is_empty() {
+            return;
+        }
+        let removed = self.slice(range.clone());
+        self.rope.remove(range.clone());
+        self.rope.insert(range.start, text);
+        let before = self.revision;
+        self.revision = self.next_revision;
+        self.next_revision += 1;
+        self.redo.clear();
+        if let Some(last) = self.undo.last_mut()
+            && removed.is_empty()
+            && last.removed.is_empty()
+            && !text.contains('\n')
+            && !last.inserted.ends_with('\n')
+            && last.after == before
+            && last.at + last.inserted.chars().count() == range.start
+        {
+            last.inserted.push_str(text);
+            last.after = self.revision;
+            return;
+        }
+        self.undo.push(Edit { at: range.start, removed, inserted: text.to_string(), before, after: self.revision });
+    }
+
+    /// Undo the last change, returning where the cursor goes
+    pub fn undo(&mut self) -> Option<usize> {
+        let edit = self.undo.pop()?;
+        let end = edit.at + edit.inserted.chars().count();
+        self.rope.remove(edit.at..end);
+        self.rope.insert(edit.at, &edit.removed);
+        self.revision = edit.before;
+        let cursor = edit.at + edit.removed.chars().count();
+        self.redo.push(edit);
+        Some(cursor)
+    }
+
+    /// Redo the last undone change, returning where the cursor goes
+    pub fn redo(&mut self) -> Option<usize> {
+        let edit = self.redo.pop()?;
+        let end = edit.at + edit.removed.chars().count();
+        self.rope.remove(edit.at..end);
+        self.rope.insert(edit.at, &edit.inserted);
+        self.revision = edit.after;
+        let cursor = edit.at + edit.inserted.chars().count();
+        self.undo.push(edit);
+        Some(cursor)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+}
+
+/// Screen column of a char column in a line, with tabs expanded
+pub fn display_column(line: &str, column: usize) -> usize {
+    line.chars().take(column).fold(0, |x, c| if c == '\t' { (x / TAB_WIDTH + 1) * TAB_WIDTH } else { x + 1 })
+}
+
+/// Char column nearest to a screen column in a line
+pub fn char_column(line: &str, display: usize) -> usize {
+    let mut x = 0;
+    for (column, c) in line.chars().enumerate() {
+        let next = if c == '\t' { (x / TAB_WIDTH + 1) * TAB_WIDTH } else { x + 1 };
+        if display < next {
+            // Past the middle of a character counts as after it
+            return if display - x > (next - x) / 2 { column + 1 } else { column };
+        }
+        x = next;
+    }
+    line.chars().count()
+}
+
+/// A line with its tabs expanded to spaces, as it is drawn
+pub fn expand_tabs(line: &str) -> String {
+    let mut expanded = String::with_capacity(line.len());
+    for c in line.chars() {
+        if c == '\t' {
+            let width = TAB_WIDTH - expanded.chars().count() % TAB_WIDTH;
+            expanded.extend(std::iter::repeat_n(' ', width));
+        } else {
+            expanded.push(c);
+        }
+    }
+    expanded
+}
+
+/// A file opened in large-file mode: the buffer, its cursor and selection
+pub struct LargeFileView {
+    pub buffer: RopeBuffer,
+    /// Char index of the cursor
+    pub cursor: usize,
+    /// Char index of the other end of the selection, if any
+    anchor: Option<usize>,
+    /// Screen column kept while moving through shorter lines
+    goal_column: Option<usize>,
+    /// Scroll the cursor into view next frame
+    pub scroll_to_cursor: bool,
+    /// Lines shown in the last frame
+    pub visible_lines: Range<usize>,
+    /// The last search found no match
+    pub not_found: bool,
+}
+
+impl LargeFileView {
+    pub fn new(buffer: RopeBuffer) -> Self {
+        Self {
+            buffer,
+            cursor: 0,
+            anchor: None,
+            goal_column: None,
+            scroll_to_cursor: true,
+            visible_lines: 0..0,
+            not_found: false,
+        }
+    }
+
+    /// Selected chars, empty when nothing is selected
+    pub fn selection(&self) -> Range<usize> {
+        let anchor = self.anchor.unwrap_or(self.cursor);
+        anchor.min(self.cursor)..anchor.max(self.cursor)
+    }
+
+    /// The selected text, for copying
+    pub fn selected_text(&self) -> Option<String> {
+        let selection = self.selection();
+        (!selection.is_empty()).then(|| self.buffer.slice(selection))
+    }
+
+    /// Select a range of chars, leaving the cursor at its end
+    pub fn select(&mut self, range: Range<usize>) {
+        self.anchor = Some(range.start.min(self.buffer.len_chars()));
+        self.move_to(range.end);
+    }
+
+    pub fn select_all(&mut self) {
+        self.select(0..self.buffer.len_chars());
+    }
+
+    /// Get ready to move the cursor, selecting what it passes over when
+    /// `select` is set and dropping the selection otherwise
+    pub fn begin_move(&mut self, select: bool) {
+        if select {
+            self.anchor.get_or_insert(self.cursor);
+        } else {
+            self.anchor = None;
+        }
+    }
+
+    /// Select the next match of `query` after the selection, or the
+    /// previous one before it; returns whether one was found
+    pub fn find(&mut self, query: &str, forward: bool) -> bool {
+        let selection = self.selection();
+        let from = if forward { selection.end } else { selection.start };
+        let found = self.buffer.find(query, from, forward);
+        if let Some(start) = found {
+            self.select(start..start + query.chars().count());
+        }
+        self.not_found = found.is_none();
+        found.is_some()
+    }
+
+    /// Line and char column of the cursor, both 0-based
+    pub fn cursor_position(&self) -> (usize, usize) {
+        let line = self.buffer.char_to_line(self.cursor);
+        (line, self.cursor - self.buffer.line_to_char(line))
+    }
+
+    fn move_to(&mut self, index: usize) {
+        self.cursor = index.min(self.buffer.len_chars());
+        self.goal_column = None;
+        self.scroll_to_cursor = true;
+    }
+
+    fn place_cursor(&mut self, line: usize, column: usize) {
+        let line = line.min(self.buffer.len_lines() - 1);
+        self.move_to(self.buffer.line_to_char(line) + column.min(self.buffer.line_len(line)));
+    }
+
+    /// Put the cursor at a line and char column, within the line, dropping
+    /// the selection
+    pub fn set_cursor(&mut self, line: usize, column: usize) {
+        self.anchor = None;
+        self.place_cursor(line, column);
+    }
+
+    /// Move the cursor to a line and char column, selecting from where it
+    /// was or from the start of the selection
+    pub fn select_to(&mut self, line: usize, column: usize) {
+        self.begin_move(true);
+        self.place_cursor(line, column);
+    }
+
+    /// Type text over the selection, or at the cursor
+    pub fn insert(&mut self, text: &str) {
+        let selection = self.selection();
+        self.buffer.replace(selection.clone(), text);
+        self.anchor = None;
+        self.move_to(selection.start + text.chars().count());
+    }
+
+    /// Delete the selection; returns whether there was one
+    pub fn delete_selection(&mut self) -> bool {
+        let selection = self.selection();
+        self.anchor = None;
+        if selection.is_empty() {
+            return false;
+        }
+        self.buffer.replace(selection.clone(), "");
+        self.move_to(selection.start);
+        true
+    }
+
+    /// Delete the selection, or the char before the cursor or a whole `\r\n`
+    pub fn backspace(&mut self) {
+        if self.delete_selection() || self.cursor == 0 {
+            return;
+        }
+        let mut start = self.cursor - 1;
+        if start > 0 && self.buffer.slice(start - 1..self.cursor) == "\r\n" {
+            start -= 1;
+        }
+        self.buffer.replace(start..self.cursor, "");
+        self.move_to(start);
+    }
+
+    /// Delete the selection, or the char after the cursor or a whole `\r\n`
+    pub fn delete(&mut self) {
+        let len = self.buffer.len_chars();
+        if self.delete_selection() || self.cursor >= len {
+            return;
+        }
+        let mut end = self.cursor + 1;
+        if end < len && self.buffer.slice(self.cursor..end + 1) == "\r\n" {
+            end += 1;
+        }
+        self.buffer.replace(self.cursor..end, "");
+        self.scroll_to_cursor = true;
+    }
+
+    /// Move one char left or right, across line breaks
+    pub fn move_horizontally(&mut self, forward: bool) {
+        let (line, column) = self.cursor_position();
+        if forward && column >= self.buffer.line_len(line) {
+            if line + 1 < self.buffer.len_lines() {
+                self.place_cursor(line + 1, 0);
+            }
+        } else if forward {
+            self.move_to(self.cursor + 1);
+        } else if column == 0 && line > 0 {
+            self.place_cursor(line - 1, usize::MAX);
+        } else {
+            self.move_to(self.cursor.saturating_sub(1));
+        }
+    }
+
+    /// Move up or down a number of lines, keeping the screen column
+    pub fn move_vertically(&mut self, lines: isize) {
+        let (line, column) = self.cursor_position();
+        let goal = self
+            .goal_column
+            .unwrap_or_else(|| display_column(&self.buffer.shown_line(line), column));
+        let target = line.saturating_add_signed(lines).min(self.buffer.len_lines() - 1);
+        let column = char_column(&self.buffer.shown_line(target), goal);
+        self.place_cursor(target, column);
+        self.goal_column = Some(goal);
+    }
+
+    /// Move to the start or end of the cursor's line
+    pub fn move_to_line_edge(&mut self, end: bool) {
+        let (line, _) = self.cursor_position();
+        self.place_cursor(line, if end { usize::MAX } else { 0 });
+    }
+
+    /// Move to the start or end of the text
+    pub fn move_to_text_edge(&mut self, end: bool) {
+        self.move_to(if end { self.buffer.len_chars() } else { 0 });
+    }
+
+    pub fn undo(&mut self) {
+        if let Some(cursor) = self.buffer.undo() {
+            self.anchor = None;
+            self.move_to(cursor);
+        }
+    }
+
+    pub fn redo(&mut self) {
+        if let Some(cursor) = self.buffer.redo() {
+            self.anchor = None;
+            self.move_to(cursor);
+        }
+    }
+}
@@ -5,6 +5,32 @@ pub mod ui;
 pub mod search;
 pub mod undo;
 pub mod syntax;
+pub mod languages;
+pub mod treesitter;
+pub mod outline;
+pub mod lsp;
+pub mod process;
+pub mod format;
+pub mod filter;
+pub mod run;
+pub mod terminal;
+pub mod diff;
+pub mod git;
+pub mod compare;
+pub mod conflicts;
+pub mod history;
+pub mod recent;
+pub mod instance;
+pub mod large_file;
+pub mod output;
 pub mod input;
+pub mod indent;
+pub mod brackets;
+pub mod folding;
+pub mod wrap;
+pub mod settings;
+pub mod theme;
+pub mod fonts;
+pub mod keybindings;
 
 pub use app::MyApp;
@@ -0,0 +1,779 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+use serde_json::{json, Value};
+use crate::treesitter::changed_range;
+
+/// A place in a document as the protocol counts it: 0-based line and
+/// UTF-16 code units into the line
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Position {
+    pub line: usize,
+    pub character: usize,
+}
+
+impl Position {
+    /// Position of a byte offset in `text`
+    pub fn from_offset(text: &str, offset: usize) -> Self {
+        let before = &text[..offset];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        Self {
+            line: before.matches('\n').count(),
+            character: before[line_start..].encode_utf16().count(),
+        }
+    }
+
+    /// Byte offset of the position in `text`, clamped to the end of its line
+    /// and of the text
+    pub fn to_offset(self, text: &str) -> usize {
+        let mut line_start = 0;
+        for _ in 0..self.line {
+            match text[line_start..].find('\n') {
+                Some(i) => line_start += i + 1,
+                None => return text.len(),
+            }
+        }
+        let line_end = text[line_start..].find('\n').map_or(text.len(), |i| line_start + i);
+        let mut units = 0;
+        for (i, c) in text[line_start..line_end].char_indices() {
+            if units >= self.character {
+                return line_start + i;
+            }
+            units += c.len_utf16();
+        }
+        line_end
+    }
+
+    fn to_json(self) -> Value {
+        json!({ "line": self.line, "character": self.character })
+    }
+
+    fn from_json(value: &Value) -> Option<Self> {
+        Some(Self {
+            line: value.get("line")?.as_u64()? as usize,
+            character: value.get("character")?.as_u64()? as usize,
+        })
+    }
+}
+
+/// Start and end of a protocol `Range`
+fn range_from_json(value: &Value) -> Option<(Position, Position)> {
+    Some((Position::from_json(value.get("start")?)?, Position::from_json(value.get("end")?)?))
+}
+
+fn range_to_json(start: Position, end: Position) -> Value {
+    json!({ "start": start.to_json(), "end": end.to_json() })
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+    Information,
+    Hint,
+}
+
+impl Severity {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Information => "info",
+            Severity::Hint => "hint",
+        }
+    }
+}
+
+/// A problem reported by a language server
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub start: Position,
+    pub end: Position,
+    pub severity: Severity,
+    pub message: String,
+    /// Tool that found the problem, such as `rustc`
+    pub source: Option<String>,
+}
+
+impl Diagnostic {
+    fn from_json(value: &Value) -> Option<Self> {
+        let (start, end) = range_from_json(value.get("range")?)?;
+        let severity = match value.get("severity").and_then(Value::as_u64) {
+            Some(2) => Severity::Warning,
+            Some(3) => Severity::Information,
+            Some(4) => Severity::Hint,
+            // Servers leaving out the severity usually mean an error
+            _ => Severity::Error,
+        };
+        Some(Self {
+            start,
+            end,
+            severity,
+            message: value.get("message")?.as_str()?.to_string(),
+            source: value.get("source").and_then(Value::as_str).map(str::to_string),
+        })
+    }
+}
+
+/// Replacement of a range of a document
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextEdit {
+    pub start: Position,
+    pub end: Position,
+    pub new_text: String,
+}
+
+impl TextEdit {
+    fn from_json(value: &Value) -> Option<Self> {
+        let (start, end) = range_from_json(value.get("range")?)?;
+        let new_text = value.get("newText")?.as_str()?.to_string();
+        Some(Self { start, end, new_text })
+    }
+}
+
+/// `text` with the edits applied
+///
+/// Edit ranges refer to the original text; edits inserting at the same
+/// position appear in the order given, as the protocol requires.
+pub fn apply_edits(text: &str, edits: &[TextEdit]) -> String {
+    let mut ranges: Vec<(usize, usize, usize)> = edits
+        .iter()
+        .enumerate()
+        .map(|(index, edit)| {
+            let start = edit.start.to_offset(text);
+            (start, edit.end.to_offset(text).max(start), index)
+        })
+        .collect();
+    ranges.sort();
+    let mut result = text.to_string();
+    for &(start, end, index) in ranges.iter().rev() {
+        result.replace_range(start..end, &edits[index].new_text);
+    }
+    result
+}
+
+/// An entry of a completion list
+#[derive(Clone, Debug, PartialEq)]
+pub struct CompletionItem {
+    pub label: String,
+    pub detail: Option<String>,
+    /// Text inserted in place of the word before the cursor
+    pub insert_text: String,
+}
+
+impl CompletionItem {
+    fn from_json(value: &Value) -> Option<Self> {
+        let label = value.get("label")?.as_str()?.to_string();
+        let insert_text = value
+            .pointer("/textEdit/newText")
+            .or_else(|| value.get("insertText"))
+            .and_then(Value::as_str)
+            .unwrap_or(&label)
+            .to_string();
+        Some(Self {
+            detail: value.get("detail").and_then(Value::as_str).map(str::to_string),
+            label,
+            insert_text,
+        })
+    }
+}
+
+/// Plain text of hover contents, which may be markup, a marked string or a
+/// list of them
+fn hover_text(contents: &Value) -> String {
+    match contents {
+        Value::String(text) => text.clone(),
+        Value::Array(parts) => parts
+            .iter()
+            .map(hover_text)
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n\n"),
+        Value::Object(_) => contents.get("value").and_then(Value::as_str).unwrap_or_default().to_string(),
+        _ => String::new(),
+    }
+}
+
+/// Edits by document URI from a `WorkspaceEdit`
+fn workspace_edits(value: &Value) -> Vec<(String, Vec<TextEdit>)> {
+    let edits = |list: &Value| -> Vec<TextEdit> {
+        list.as_array().into_iter().flatten().filter_map(TextEdit::from_json).collect()
+    };
+    if let Some(changes) = value.get("documentChanges").and_then(Value::as_array) {
+        return changes
+            .iter()
+            .filter_map(|change| {
+                let uri = change.pointer("/textDocument/uri")?.as_str()?;
+                Some((uri.to_string(), edits(change.get("edits")?)))
+            })
+            .collect();
+    }
+    value
+        .get("changes")
+        .and_then(Value::as_object)
+        .into_iter()
+        .flatten()
+        .map(|(uri, list)| (uri.clone(), edits(list)))
+        .collect()
+}
+
+/// Frame a JSON-RPC message with its `Content-Length` header
+pub fn encode_message(message: &Value) -> Vec<u8> {
+    let body = message.to_string();
+    let mut bytes = format!("Content-Length: {}\r\n\r\n", body.len()).into_bytes();
+    bytes.extend_from_slice(body.as_bytes());
+    bytes
+}
+
+/// Read one framed message; `None` at the end of the stream
+pub fn read_message(reader: &mut impl BufRead) -> io::Result<Option<Value>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            if length.is_some() {
+                break;
+            }
+            continue;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.eq_ignore_ascii_case("content-length")
+        {
+            length = value.trim().parse::<usize>().ok();
+        }
+    }
+    let mut body = vec![0; length.unwrap_or_default()];
+    reader.read_exact(&mut body)?;
+    serde_json::from_slice(&body).map(Some).map_err(io::Error::from)
+}
+
+/// `file://` URI of an absolute path
+pub fn path_to_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for byte in path.to_string_lossy().bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-_.~".contains(&byte) {
+            uri.push(byte as char);
+        } else {
+            uri.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    uri
+}
+
+/// Path of a `file://` URI
+pub fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let encoded = uri.strip_prefix("file://")?.as_bytes();
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut i = 0;
+    while i < encoded.len() {
+        let escaped = (encoded[i] == b'%')
+            .then(|| encoded.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escaped {
+            Some(byte) => {
+                bytes.push(byte);
+                i += 3;
+            }
+            None => {
+                bytes.push(encoded[i]);
+                i += 1;
+            }
+        }
+    }
+    Some(PathBuf::from(String::from_utf8(bytes).ok()?))
+}
+
+/// Files and directories marking the root of a project
+const ROOT_MARKERS: &[&str] = &[".git", "Cargo.toml", "pyproject.toml", "setup.py", "package.json"];
+
+/// Workspace folder for a file: the closest directory containing a project
+/// marker, or the file's own directory
+pub fn project_root(file: &Path) -> PathBuf {
+    let directory = file.parent().unwrap_or(file);
+    directory
+        .ancestors()
+        .find(|dir| ROOT_MARKERS.iter().any(|marker| dir.join(marker).exists()))
+        .unwrap_or(directory)
+        .to_path_buf()
+}
+
+/// Language id the protocol uses for a language name from the registry
+pub fn language_id(language: &str) -> String {
+    match language {
+        "C++" => "cpp".to_string(),
+        "Shell" => "shellscript".to_string(),
+        "Plain Text" => "plaintext".to_string(),
+        _ => language.to_ascii_lowercase(),
+    }
+}
+
+/// Something a language server sent that the editor should act on
+#[derive(Clone, Debug, PartialEq)]
+pub enum LspEvent {
+    Diagnostics { uri: String, diagnostics: Vec<Diagnostic> },
+    Completion(Vec<CompletionItem>),
+    Hover(String),
+    Definition { uri: String, position: Position },
+    /// Edits by document URI, from formatting or a rename
+    Edits(Vec<(String, Vec<TextEdit>)>),
+    /// Error or message to show to the user
+    Message(String),
+}
+
+/// What a request still waiting for its response asked for
+enum Request {
+    Initialize,
+    Completion,
+    Hover,
+    Definition,
+    Rename,
+    Formatting { uri: String },
+    Shutdown,
+}
+
+/// Connection to a language server process over stdio
+pub struct LspClient {
+    /// Taken when the client is dropped, to be shut down in the background
+    process: Option<Child>,
+    /// Messages for the thread writing to the server's stdin
+    outgoing: Sender<Value>,
+    messages: Receiver<Value>,
+    next_id: u64,
+    pending: HashMap<u64, Request>,
+    /// Messages held back until the server has answered `initialize`
+    queued: Option<Vec<Value>>,
+    /// The server accepts changed ranges rather than the whole text
+    incremental: bool,
+    versions: HashMap<String, i64>,
+    exited: bool,
+}
+
+impl LspClient {
+    /// Start a server and begin initializing it for a workspace folder
+    ///
+    /// `on_message` is called from a background thread whenever the server
+    /// sent something, so the caller can wake up and `poll`.
+    pub fn start(
+        command: &str,
+        args: &[String],
+        root: &Path,
+        on_message: impl Fn() + Send + 'static,
+    ) -> io::Result<Self> {
+        let mut process = Command::new(command)
+            .args(args)
+            .current_dir(root)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdin = process.stdin.take().ok_or_else(|| io::Error::other("no stdin"))?;
+        let stdout = process.stdout.take().ok_or_else(|| io::Error::other("no stdout"))?;
+        let (sender, messages) = mpsc::channel();
+        thread::spawn(move || {
+            let mut reader = BufReader::new(stdout);
+            while let Ok(Some(message)) = read_message(&mut reader) {
+                if sender.send(message).is_err() {
+                    break;
+                }
+                on_message();
+            }
+            // Wake the caller to notice the exit
+            drop(sender);
+            on_message();
+        });
+        let (outgoing, to_write) = mpsc::channel();
+        thread::spawn(move || write_messages(stdin, to_write));
+
+        let mut client = Self {
+            process: Some(process),
+            outgoing,
+            messages,
+            next_id: 1,
+            pending: HashMap::new(),
+            queued: None,
+            incremental: false,
+            versions: HashMap::new(),
+            exited: false,
+        };
+        let root_uri = path_to_uri(root);
+        let name = root.file_name().map_or(String::new(), |n| n.to_string_lossy().into_owned());
+        client.request(
+            "initialize",
+            json!({
+                "processId": std::process::id(),
+                "clientInfo": { "name": "Writer" },
+                "rootUri": root_uri,
+                "workspaceFolders": [{ "uri": root_uri, "name": name }],
+                "capabilities": {
+                    "textDocument": {
+                        "synchronization": { "didSave": true },
+                        "publishDiagnostics": {},
+                        "completion": { "completionItem": { "snippetSupport": false } },
+                        "hover": { "contentFormat": ["plaintext", "markdown"] },
+                        "definition": {},
+                        "rename": {},
+                        "formatting": {},
+                    },
+                    "workspace": { "workspaceEdit": { "documentChanges": true } },
+                },
+            }),
+            Request::Initialize,
+        );
+        client.queued = Some(Vec::new());
+        Ok(client)
+    }
+
+    fn send(&mut self, message: Value) {
+        match &mut self.queued {
+            Some(queued) => queued.push(message),
+            // A server that died is reported by `poll`
+            None => {
+                let _ = self.outgoing.send(message);
+            }
+        }
+    }
+
+    fn notify(&mut self, method: &str, params: Value) {
+        self.send(json!({ "jsonrpc": "2.0", "method": method, "params": params }));
+    }
+
+    fn request(&mut self, method: &str, params: Value, kind: Request) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.pending.insert(id, kind);
+        self.send(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }));
+        id
+    }
+
+    fn document(uri: &str) -> Value {
+        json!({ "uri": uri })
+    }
+
+    fn document_position(uri: &str, position: Position) -> Value {
+        json!({ "textDocument": Self::document(uri), "position": position.to_json() })
+    }
+
+    pub fn did_open(&mut self, uri: &str, language: &str, text: &str) {
+        self.versions.insert(uri.to_string(), 1);
+        self.notify(
+            "textDocument/didOpen",
+            json!({
+                "textDocument": { "uri": uri, "languageId": language_id(language), "version": 1, "text": text },
+            }),
+        );
+    }
+
+    /// Send an edit of an open document, as the changed range when the
+    /// server supports incremental sync
+    pub fn did_change(&mut self, uri: &str, old: &str, new: &str) {
+        let version = self.versions.entry(uri.to_string()).or_insert(0);
+        *version += 1;
+        let version = *version;
+        let change = if self.incremental {
+            let (range, inserted) = changed_range(old, new);
+            json!({
+                "range": range_to_json(Position::from_offset(old, range.start), Position::from_offset(old, range.end)),
+                "text": &new[range.start..range.start + inserted],
+            })
+        } else {
+            json!({ "text": new })
+        };
+        self.notify(
+            "textDocument/didChange",
+            json!({
+                "textDocument": { "uri": uri, "version": version },
+                "contentChanges": [change],
+            }),
+        );
+    }
+
+    pub fn did_save(&mut self, uri: &str, text: &str) {
+        self.notify("textDocument/didSave", json!({ "textDocument": Self::document(uri), "text": text }));
+    }
+
+    pub fn did_close(&mut self, uri: &str) {
+        self.versions.remove(uri);
+        self.notify("textDocument/didClose", json!({ "textDocument": Self::document(uri) }));
+    }
+
+    pub fn completion(&mut self, uri: &str, position: Position) {
+        self.request("textDocument/completion", Self::document_position(uri, position), Request::Completion);
+    }
+
+    pub fn hover(&mut self, uri: &str, position: Position) {
+        self.request("textDocument/hover", Self::document_position(uri, position), Request::Hover);
+    }
+
+    pub fn definition(&mut self, uri: &str, position: Position) {
+        self.request("textDocument/definition", Self::document_position(uri, position), Request::Definition);
+    }
+
+    pub fn rename(&mut self, uri: &str, position: Position, new_name: &str) {
+        let mut params = Self::document_position(uri, position);
+        params["newName"] = json!(new_name);
+        self.request("textDocument/rename", params, Request::Rename);
+    }
+
+    pub fn formatting(&mut self, uri: &str, tab_size: usize, insert_spaces: bool) {
+        self.request(
+            "textDocument/formatting",
+            json!({
+                "textDocument": Self::document(uri),
+                "options": { "tabSize": tab_size, "insertSpaces": insert_spaces },
+            }),
+            Request::Formatting { uri: uri.to_string() },
+        );
+    }
+
+    /// Whether the server process is still running
+    pub fn is_running(&self) -> bool {
+        !self.exited
+    }
+
+    /// Handle everything the server sent since the last call, without
+    /// blocking
+    pub fn poll(&mut self) -> Vec<LspEvent> {
+        let mut events = Vec::new();
+        loop {
+            match self.messages.try_recv() {
+                Ok(message) => self.handle_message(message, &mut events),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    if !self.exited {
+                        self.exited = true;
+                        events.push(LspEvent::Message("The language server exited".to_string()));
+                    }
+                    break;
+                }
+            }
+        }
+        events
+    }
+
+    fn handle_message(&mut self, message: Value, events: &mut Vec<LspEvent>) {
+        let method = message.get("method").and_then(Value::as_str);
+        let id = message.get("id").cloned();
+        match (method, id) {
+            // Request from the server
+            (Some(method), Some(id)) => {
+                let result = match method {
+                    // One (empty) setting for every item asked for
+                    "workspace/configuration" => {
+                        let count = message.pointer("/params/items").and_then(Value::as_array).map_or(0, Vec::len);
+                        Value::Array(vec![Value::Null; count])
+                    }
+                    _ => Value::Null,
+                };
+                self.send(json!({ "jsonrpc": "2.0", "id": id, "result": result }));
+            }
+            // Notification
+            (Some(method), None) => {
+                let params = message.get("params").cloned().unwrap_or_default();
+                match method {
+                    "textDocument/publishDiagnostics" => {
+                        let uri = params.get("uri").and_then(Value::as_str).unwrap_or_default().to_string();
+                        let diagnostics = params
+                            .get("diagnostics")
+                            .and_then(Value::as_array)
+                            .into_iter()
+                            .flatten()
+                            .filter_map(Diagnostic::from_json)
+                            .collect();
+                        events.push(LspEvent::Diagnostics { uri, diagnostics });
+                    }
+                    // Only errors and warnings interrupt the user
+                    "window/showMessage" if params.get("type").and_then(Value::as_u64).is_some_and(|t| t <= 2) => {
+                        let text = params.get("message").and_then(Value::as_str).unwrap_or_default();
+                        events.push(LspEvent::Message(text.to_string()));
+                    }
+                    _ => {}
+                }
+            }
+            // Response to one of our requests
+            (None, Some(id)) => {
+                let Some(kind) = id.as_u64().and_then(|id| self.pending.remove(&id)) else {
+                    return;
+                };
+                if let Some(error) = message.get("error") {
+                    let text = error.get("message").and_then(Value::as_str).unwrap_or("request failed");
+                    events.push(LspEvent::Message(format!("Language server error: {}", text)));
+                    return;
+                }
+                let result = message.get("result").cloned().unwrap_or_default();
+                self.handle_response(kind, result, events);
+            }
+            (None, None) => {}
+        }
+    }
+
+    fn handle_response(&mut self, kind: Request, result: Value, events: &mut Vec<LspEvent>) {
+        match kind {
+            Request::Initialize => {
+                let sync = result.pointer("/capabilities/textDocumentSync");
+                let change = sync.and_then(|s| s.get("change").unwrap_or(s).as_u64());
+                self.incremental = change == Some(2);
+                let queued = self.queued.take().unwrap_or_default();
+                self.notify("initialized", json!({}));
+                for message in queued {
+                    self.send(message);
+                }
+            }
+            Request::Completion => {
+                // Either a list of items or a `CompletionList`
+                let items = result.get("items").unwrap_or(&result);
+                let items = items.as_array().into_iter().flatten().filter_map(CompletionItem::from_json).collect();
+                events.push(LspEvent::Completion(items));
+            }
+            Request::Hover => {
+                let text = result.get("contents").map(hover_text).unwrap_or_default();
+                events.push(LspEvent::Hover(text));
+            }
+            Request::Definition => {
+                // A location, a list of locations or a list of location links
+                let location = match &result {
+                    Value::Array(locations) => locations.first(),
+                    Value::Null => None,
+                    location => Some(location),
+                };
+                let target = location.and_then(|l| {
+                    let uri = l.get("uri").or_else(|| l.get("targetUri"))?.as_str()?;
+                    let range = l.get("range").or_else(|| l.get("targetSelectionRange"))?;
+                    Some(LspEvent::Definition { uri: uri.to_string(), position: range_from_json(range)?.0 })
+                });
+                events.push(target.unwrap_or_else(|| LspEvent::Message("No definition found".to_string())));
+            }
+            Request::Rename => events.push(LspEvent::Edits(workspace_edits(&result))),
+            Request::Formatting { uri } => {
+                let edits = result.as_array().into_iter().flatten().filter_map(TextEdit::from_json).collect();
+                events.push(LspEvent::Edits(vec![(uri, edits)]));
+            }
+            Request::Shutdown => {}
+        }
+    }
+}
+
+/// Write messages to a server's stdin until the client is dropped or the
+/// server stops reading, so a busy server never blocks the caller
+fn write_messages(mut stdin: ChildStdin, messages: Receiver<Value>) {
+    for message in messages {
+        if stdin.write_all(&encode_message(&message)).and_then(|()| stdin.flush()).is_err() {
+            break;
+        }
+    }
+}
+
+impl Drop for LspClient {
+    /// Ask the server to shut down, then make sure it exits
+    ///
+    /// The handshake runs on a thread of its own, so a slow server does not
+    /// hold up the caller.
+    fn drop(&mut self) {
+        let Some(mut process) = self.process.take() else {
+            return;
+        };
+        let shutdown = (self.queued.is_none() && !self.exited)
+            .then(|| self.request("shutdown", Value::Null, Request::Shutdown));
+        let messages = std::mem::replace(&mut self.messages, mpsc::channel().1);
+        let outgoing = self.outgoing.clone();
+        thread::spawn(move || {
+            if let Some(id) = shutdown {
+                let deadline = Instant::now() + Duration::from_millis(500);
+                while let Some(left) = deadline.checked_duration_since(Instant::now()) {
+                    match messages.recv_timeout(left) {
+                        Ok(message) if message.get("id").and_then(Value::as_u64) == Some(id) => break,
+                        Ok(_) => {}
+                        Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => break,
+                    }
+                }
+                let _ = outgoing.send(json!({ "jsonrpc": "2.0", "method": "exit", "params": Value::Null }));
+                let deadline = Instant::now() + Duration::from_millis(500);
+                while Instant::now() < deadline {
+                    if let Ok(Some(_)) = process.try_wait() {
+                        return;
+                    }
+                    thread::sleep(Duration::from_millis(10));
+                }
+            }
+            let _ = process.kill();
+            let _ = process.wait();
+        });
+    }
+}
+
+/// The document open in a language server
+pub struct SyncedDocument {
+    pub uri: String,
+    pub language: &'static str,
+    /// Text as the server last saw it
+    pub text: String,
+    /// Revision of the buffer `text` was copied from
+    pub revision: u64,
+}
+
+/// Completion list shown at the cursor
+pub struct CompletionPopup {
+    pub items: Vec<CompletionItem>,
+    pub selected: usize,
+    /// Char index where the word being completed starts
+    pub word_start: usize,
+}
+
+/// Language servers for the open document and what they reported
+#[derive(Default)]
+pub struct LspState {
+    /// Running servers by language name
+    pub clients: HashMap<&'static str, LspClient>,
+    /// Languages whose server could not be started or stopped, with why,
+    /// so it is not retried on every frame
+    pub failed: HashMap<&'static str, String>,
+    pub document: Option<SyncedDocument>,
+    /// Latest diagnostics by document URI
+    pub diagnostics: BTreeMap<String, Vec<Diagnostic>>,
+    pub show_problems: bool,
+    pub completion: Option<CompletionPopup>,
+    /// Hover text and the char index it describes
+    pub hover: Option<(String, usize)>,
+    pub show_rename_dialog: bool,
+    pub rename_input: String,
+}
+
+impl LspState {
+    /// Server for the open document
+    pub fn client(&mut self) -> Option<(&mut LspClient, &str)> {
+        let document = self.document.as_ref()?;
+        let client = self.clients.get_mut(document.language)?;
+        Some((client, document.uri.as_str()))
+    }
+
+    /// Stop every server, so they start again with new settings
+    pub fn stop_servers(&mut self) {
+        self.clients.clear();
+        self.failed.clear();
+        self.document = None;
+        self.diagnostics.clear();
+        self.completion = None;
+        self.hover = None;
+    }
+
+    /// Diagnostics of the open document, most severe first
+    pub fn document_diagnostics(&self) -> Vec<&Diagnostic> {
+        let mut diagnostics: Vec<&Diagnostic> = self
+            .document
+            .as_ref()
+            .and_then(|d| self.diagnostics.get(&d.uri))
+            .into_iter()
+            .flatten()
+            .collect();
+        diagnostics.sort_by_key(|d| (d.severity, d.start));
+        diagnostics
+    }
+}
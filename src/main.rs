@@ -1,10 +1,31 @@
 use writer::MyApp;
+use writer::instance;
 
 fn main() -> Result<(), eframe::Error> {
+    let cwd = std::env::current_dir().unwrap_or_default();
+    let args = match instance::parse_args(std::env::args().skip(1), &cwd) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("writer: {}\n\n{}", e, instance::USAGE);
+            std::process::exit(2);
+        }
+    };
+    if args.help {
+        println!("{}", instance::USAGE);
+        return Ok(());
+    }
+    // Hand the files to a running window, starting normally when there
+    // is none
+    let socket = if args.new_window { None } else { instance::socket_path() };
+    if let Some(socket) = &socket
+        && instance::forward(socket, &args.files).is_ok()
+    {
+        return Ok(());
+    }
     let options = eframe::NativeOptions::default();
     eframe::run_native(
         "Writer",
         options,
-        Box::new(|_cc| Ok(Box::new(MyApp::default()))),
+        Box::new(move |cc| Ok(Box::new(MyApp::with_files(cc, args.files, socket.as_deref())))),
     )
-}
\ No newline at end of file
+}
@@ -0,0 +1,275 @@
+use crate::indent::TAB_WIDTH;
+
+/// What a symbol in the outline declares
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SymbolKind {
+    Function,
+    Struct,
+    Enum,
+    Trait,
+    Impl,
+    Module,
+    Class,
+    Constant,
+    Table,
+    View,
+    Index,
+    Heading,
+}
+
+impl SymbolKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            SymbolKind::Function => "fn",
+            SymbolKind::Struct => "struct",
+            SymbolKind::Enum => "enum",
+            SymbolKind::Trait => "trait",
+            SymbolKind::Impl => "impl",
+            SymbolKind::Module => "mod",
+            SymbolKind::Class => "class",
+            SymbolKind::Constant => "const",
+            SymbolKind::Table => "table",
+            SymbolKind::View => "view",
+            SymbolKind::Index => "index",
+            SymbolKind::Heading => "#",
+        }
+    }
+}
+
+/// A named declaration or heading in a document
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    /// 0-based line of the declaration
+    pub line: usize,
+    /// Nesting level, from indentation or heading level
+    pub depth: usize,
+}
+
+/// Leading identifier of `text`
+fn identifier(text: &str) -> Option<&str> {
+    let end = text
+        .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .unwrap_or(text.len());
+    Some(&text[..end]).filter(|name| !name.is_empty())
+}
+
+/// `line` without any of the leading `words`, such as visibility modifiers
+fn strip_words<'a>(mut line: &'a str, words: &[&str]) -> &'a str {
+    loop {
+        let before = line;
+        for word in words {
+            if let Some(rest) = line.strip_prefix(word)
+                && rest.starts_with(' ')
+            {
+                line = rest.trim_start();
+            }
+        }
+        if line == before {
+            return line;
+        }
+    }
+}
+
+fn rust_symbol(line: &str) -> Option<(SymbolKind, String)> {
+    let line = strip_words(
+        line,
+        &["pub(crate)", "pub(super)", "pub", "async", "unsafe", "extern \"C\"", "default"],
+    );
+    let kinds = [
+        ("fn ", SymbolKind::Function),
+        ("const fn ", SymbolKind::Function),
+        ("struct ", SymbolKind::Struct),
+        ("enum ", SymbolKind::Enum),
+        ("union ", SymbolKind::Struct),
+        ("trait ", SymbolKind::Trait),
+        ("mod ", SymbolKind::Module),
+        ("const ", SymbolKind::Constant),
+        ("static ", SymbolKind::Constant),
+        ("macro_rules! ", SymbolKind::Function),
+    ];
+    for (keyword, kind) in kinds {
+        if let Some(rest) = line.strip_prefix(keyword) {
+            return identifier(rest.trim_start_matches("mut ")).map(|name| (kind, name.to_string()));
+        }
+    }
+    // `impl<T> Trait for Type<T> {` is listed as `Trait for Type<T>`
+    let rest = line.strip_prefix("impl")?;
+    if !(rest.starts_with(' ') || rest.starts_with('<')) {
+        return None;
+    }
+    let mut rest = rest.trim_start();
+    if rest.starts_with('<') {
+        let mut depth = 0;
+        let end = rest.char_indices().find_map(|(i, c)| {
+            match c {
+                '<' => depth += 1,
+                '>' => depth -= 1,
+                _ => {}
+            }
+            (depth == 0).then_some(i + 1)
+        })?;
+        rest = rest[end..].trim_start();
+    }
+    let name = rest.split(['{', ';']).next()?.split(" where").next()?.trim();
+    Some(name).filter(|n| !n.is_empty()).map(|n| (SymbolKind::Impl, n.to_string()))
+}
+
+fn python_symbol(line: &str) -> Option<(SymbolKind, String)> {
+    let line = strip_words(line, &["async"]);
+    if let Some(rest) = line.strip_prefix("def ") {
+        return identifier(rest).map(|name| (SymbolKind::Function, name.to_string()));
+    }
+    let rest = line.strip_prefix("class ")?;
+    identifier(rest).map(|name| (SymbolKind::Class, name.to_string()))
+}
+
+fn lua_symbol(line: &str) -> Option<(SymbolKind, String)> {
+    let line = strip_words(line, &["local"]);
+    let name = if let Some(rest) = line.strip_prefix("function ") {
+        rest.split('(').next()?.trim()
+    } else {
+        // `name = function(...)`
+        let (name, value) = line.split_once('=')?;
+        if !value.trim_start().starts_with("function") {
+            return None;
+        }
+        name.trim()
+    };
+    let valid = !name.is_empty()
+        && name.chars().all(|c| c.is_alphanumeric() || matches!(c, '_' | '.' | ':'));
+    valid.then(|| (SymbolKind::Function, name.to_string()))
+}
+
+fn sql_symbol(line: &str) -> Option<(SymbolKind, String)> {
+    let upper = line.to_ascii_uppercase();
+    let rest = upper.strip_prefix("CREATE ")?;
+    let rest = strip_words(rest, &["OR REPLACE", "TEMPORARY", "TEMP", "UNIQUE", "MATERIALIZED"]);
+    let kinds = [
+        ("TABLE ", SymbolKind::Table),
+        ("VIEW ", SymbolKind::View),
+        ("INDEX ", SymbolKind::Index),
+        ("FUNCTION ", SymbolKind::Function),
+        ("PROCEDURE ", SymbolKind::Function),
+        ("TRIGGER ", SymbolKind::Function),
+    ];
+    let (keyword, kind) = kinds.into_iter().find(|(k, _)| rest.starts_with(k))?;
+    let rest = strip_words(rest[keyword.len()..].trim_start(), &["IF NOT EXISTS"]);
+    // Take the name from the original line to keep its case
+    let start = line.len() - rest.len();
+    let name = line[start..]
+        .split(|c: char| c.is_whitespace() || c == '(' || c == ';')
+        .next()?
+        .trim_matches(['"', '`']);
+    Some(name).filter(|n| !n.is_empty()).map(|n| (kind, n.to_string()))
+}
+
+fn markdown_symbol(line: &str) -> Option<(usize, String)> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    let title = line[level..].strip_prefix(' ')?.trim().trim_end_matches('#').trim();
+    ((1..=6).contains(&level) && !title.is_empty()).then(|| (level - 1, title.to_string()))
+}
+
+/// Symbols declared in `text`, for a language name from the registry
+///
+/// Symbols are recognised line by line, so declarations split across
+/// lines are only found by their first line. Languages without an outline
+/// give no symbols.
+pub fn symbols(text: &str, language: &str) -> Vec<Symbol> {
+    let mut symbols = Vec::new();
+    let mut in_fence = false;
+    for (line_number, line) in text.lines().enumerate() {
+        let trimmed = line.trim_start();
+        let indent: usize = line[..line.len() - trimmed.len()]
+            .chars()
+            .map(|c| if c == '\t' { TAB_WIDTH } else { 1 })
+            .sum();
+        let found = match language {
+            "Rust" => rust_symbol(trimmed).map(|(kind, name)| (kind, name, indent / 4)),
+            "Python" => python_symbol(trimmed).map(|(kind, name)| (kind, name, indent / 4)),
+            "Lua" => lua_symbol(trimmed).map(|(kind, name)| (kind, name, indent / 2)),
+            "SQL" => sql_symbol(trimmed).map(|(kind, name)| (kind, name, 0)),
+            "Markdown" => {
+                if line.starts_with("```") || line.starts_with("~~~") {
+                    in_fence = !in_fence;
+                }
+                if in_fence {
+                    None
+                } else {
+                    markdown_symbol(line).map(|(depth, name)| (SymbolKind::Heading, name, depth))
+                }
+            }
+            _ => None,
+        };
+        if let Some((kind, name, depth)) = found {
+            symbols.push(Symbol { name, kind, line: line_number, depth });
+        }
+    }
+    symbols
+}
+
+/// Score of `candidate` for a fuzzy `query`, or `None` when the query's
+/// characters don't all appear in order
+///
+/// Matching ignores case; consecutive characters and characters at the
+/// start of words score higher.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i32> {
+    let candidate: Vec<char> = candidate.chars().collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous: Option<usize> = None;
+    for q in query.chars().filter(|c| !c.is_whitespace()) {
+        let q = q.to_lowercase().next()?;
+        let found = (position..candidate.len()).find(|&i| candidate[i].to_lowercase().next() == Some(q))?;
+        let word_start = found == 0
+            || !candidate[found - 1].is_alphanumeric()
+            || (candidate[found].is_uppercase() && candidate[found - 1].is_lowercase());
+        score += 1;
+        if previous.is_some_and(|p| p + 1 == found) {
+            score += 5;
+        }
+        if word_start {
+            score += 3;
+        }
+        // Prefer matches near the start
+        score -= (found as i32 - position as i32).min(3);
+        previous = Some(found);
+        position = found + 1;
+    }
+    Some(score)
+}
+
+/// Symbols matching a fuzzy query, best first; an empty query keeps the
+/// document order
+pub fn filter_symbols<'a>(symbols: &'a [Symbol], query: &str) -> Vec<&'a Symbol> {
+    let mut matches: Vec<(i32, &Symbol)> = symbols
+        .iter()
+        .filter_map(|symbol| fuzzy_score(query, &symbol.name).map(|score| (score, symbol)))
+        .collect();
+    if !query.trim().is_empty() {
+        matches.sort_by_key(|(score, symbol)| (std::cmp::Reverse(*score), symbol.line));
+    }
+    matches.into_iter().map(|(_, symbol)| symbol).collect()
+}
+
+/// Symbols of the current document and the state of the outline panel
+#[derive(Default)]
+pub struct OutlineState {
+    pub show_panel: bool,
+    /// Filter typed into the outline panel
+    pub filter: String,
+    pub symbols: Vec<Symbol>,
+    /// Revision of the text and the language the symbols were found in
+    found_in: Option<(u64, String)>,
+}
+
+impl OutlineState {
+    /// Find the symbols again if the buffer's revision or language changed
+    pub fn refresh(&mut self, text: &str, revision: u64, language: &str) {
+        if !self.found_in.as_ref().is_some_and(|(r, l)| *r == revision && l == language) {
+            self.found_in = Some((revision, language.to_string()));
+            self.symbols = symbols(text, language);
+        }
+    }
+}
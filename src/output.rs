@@ -0,0 +1,321 @@
+use std::ops::Range;
+use std::path::PathBuf;
+
+/// Output kept before the oldest lines are dropped, in bytes
+const MAX_OUTPUT: usize = 4 * 1024 * 1024;
+
+/// A color set by an ANSI escape sequence
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AnsiColor {
+    /// One of the 256 palette colors; 0–15 are the basic and bright ones
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+}
+
+/// Text attributes set by ANSI escape sequences
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AnsiStyle {
+    pub foreground: Option<AnsiColor>,
+    pub background: Option<AnsiColor>,
+    pub bold: bool,
+    pub dim: bool,
+    pub italic: bool,
+    pub underline: bool,
+}
+
+impl AnsiStyle {
+    /// Apply the parameters of a Select Graphic Rendition sequence, such
+    /// as `1;31` in `ESC[1;31m`
+    pub fn apply_sgr(&mut self, params: &str) {
+        let codes: Vec<u16> = params.split(';').map(|p| p.parse().unwrap_or(0)).collect();
+        let mut i = 0;
+        while i < codes.len() {
+            match codes[i] {
+                0 => *self = Self::default(),
+                1 => self.bold = true,
+                2 => self.dim = true,
+                3 => self.italic = true,
+                4 => self.underline = true,
+                22 => {
+                    self.bold = false;
+                    self.dim = false;
+                }
+                23 => self.italic = false,
+                24 => self.underline = false,
+                code @ 30..=37 => self.foreground = Some(AnsiColor::Indexed(code as u8 - 30)),
+                code @ 90..=97 => self.foreground = Some(AnsiColor::Indexed(code as u8 - 90 + 8)),
+                39 => self.foreground = None,
+                code @ 40..=47 => self.background = Some(AnsiColor::Indexed(code as u8 - 40)),
+                code @ 100..=107 => self.background = Some(AnsiColor::Indexed(code as u8 - 100 + 8)),
+                49 => self.background = None,
+                code @ (38 | 48) => {
+                    let (color, used) = extended_color(&codes[i + 1..]);
+                    if code == 38 {
+                        self.foreground = color;
+                    } else {
+                        self.background = color;
+                    }
+                    i += used;
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+    }
+}
+
+/// Color after `38;` or `48;`, and how many codes it took
+fn extended_color(codes: &[u16]) -> (Option<AnsiColor>, usize) {
+    let byte = |i: usize| codes.get(i).map(|&c| c.min(255) as u8);
+    match codes.first() {
+        Some(5) => (byte(1).map(AnsiColor::Indexed), 2),
+        Some(2) => match (byte(1), byte(2), byte(3)) {
+            (Some(r), Some(g), Some(b)) => (Some(AnsiColor::Rgb(r, g, b)), 4),
+            _ => (None, codes.len()),
+        },
+        _ => (None, codes.len()),
+    }
+}
+
+/// A `file:line:column` reference found in a line of output
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OutputLocation {
+    /// Byte range of the reference in the line
+    pub range: Range<usize>,
+    pub path: String,
+    /// 1-based, as tools print them
+    pub line: usize,
+    pub column: Option<usize>,
+}
+
+/// References to source locations in a line of output: `path:line` and
+/// `path:line:column` as compilers print them, and Python's
+/// `File "path", line N`
+pub fn find_locations(line: &str) -> Vec<OutputLocation> {
+    let mut found = Vec::new();
+    if let Some(start) = line.find("File \"")
+        && let Some(len) = line[start + 6..].find("\", line ")
+    {
+        let path_start = start + 6;
+        let number_start = path_start + len + 8;
+        let digits = line[number_start..].bytes().take_while(u8::is_ascii_digit).count();
+        if let Ok(number) = line[number_start..number_start + digits].parse() {
+            found.push(OutputLocation {
+                range: start..number_start + digits,
+                path: line[path_start..path_start + len].to_string(),
+                line: number,
+                column: None,
+            });
+            return found;
+        }
+    }
+    let separator = |c: char| c.is_whitespace() || "\"'`()[]<>,;|".contains(c);
+    // Words with their byte offsets; separators such as U+00A0 take more
+    // than one byte
+    let mut words = Vec::new();
+    let mut word_start = 0;
+    for (i, c) in line.char_indices() {
+        if separator(c) {
+            words.push((word_start, &line[word_start..i]));
+            word_start = i + c.len_utf8();
+        }
+    }
+    words.push((word_start, &line[word_start..]));
+    for (start, word) in words {
+        if word.contains("://") {
+            continue;
+        }
+        let parts: Vec<&str> = word.split(':').collect();
+        let number = |part: &str| (!part.is_empty() && part.bytes().all(|b| b.is_ascii_digit())).then(|| part.parse().ok()).flatten();
+        // The first number after a path-like part is the line
+        let Some(line_part) = (1..parts.len()).find(|&i| number(parts[i]).is_some()) else {
+            continue;
+        };
+        let path = parts[..line_part].join(":");
+        if !(path.contains('.') || path.contains('/') || path.contains('\\')) {
+            continue;
+        }
+        let column = parts.get(line_part + 1).and_then(|p| number(p));
+        let used = line_part + 1 + usize::from(column.is_some());
+        let len = parts[..used].iter().map(|p| p.len() + 1).sum::<usize>() - 1;
+        found.push(OutputLocation {
+            range: start..start + len,
+            path,
+            line: number(parts[line_part]).unwrap_or(1),
+            column,
+        });
+    }
+    found
+}
+
+/// Text shown in the output panel, such as a formatter's error messages
+/// or the output of a running program
+#[derive(Default)]
+pub struct OutputLog {
+    pub show_panel: bool,
+    /// What produced the output
+    pub title: String,
+    /// Directory relative paths in the output are resolved against
+    pub directory: Option<PathBuf>,
+    text: String,
+    /// Byte offsets where each line after the first starts
+    line_starts: Vec<usize>,
+    /// Byte offsets where the style changes, each style lasting until the
+    /// next change
+    styles: Vec<(usize, AnsiStyle)>,
+    /// Style set by the escape sequences so far
+    style: AnsiStyle,
+    /// Escape sequence, or carriage return, split across appends
+    pending: String,
+}
+
+impl OutputLog {
+    /// Replace the output and show the panel
+    pub fn show(&mut self, title: &str, text: &str) {
+        self.clear();
+        self.title = title.to_string();
+        self.append(text, false);
+        self.show_panel = true;
+    }
+
+    pub fn clear(&mut self) {
+        self.title.clear();
+        self.directory = None;
+        self.text.clear();
+        self.line_starts.clear();
+        self.styles.clear();
+        self.style = AnsiStyle::default();
+        self.pending.clear();
+    }
+
+    /// The output without escape sequences
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len() + 1
+    }
+
+    /// Byte range of a line, without its newline
+    pub fn line_range(&self, line: usize) -> Range<usize> {
+        let start = if line == 0 { 0 } else { self.line_starts[line - 1] };
+        let end = self.line_starts.get(line).map_or(self.text.len(), |&next| next - 1);
+        start..end
+    }
+
+    /// A line split into runs of one style, with byte ranges relative to
+    /// the line
+    pub fn styled_line(&self, line: usize) -> Vec<(Range<usize>, AnsiStyle)> {
+        let range = self.line_range(line);
+        let first = self.styles.partition_point(|&(start, _)| start <= range.start).saturating_sub(1);
+        let mut runs = Vec::new();
+        for (i, &(start, style)) in self.styles.iter().enumerate().skip(first) {
+            if start >= range.end {
+                break;
+            }
+            let end = self.styles.get(i + 1).map_or(range.end, |&(next, _)| next.min(range.end));
+            let start = start.max(range.start);
+            if start < end {
+                runs.push((start - range.start..end - range.start, style));
+            }
+        }
+        if runs.is_empty() {
+            runs.push((0..range.len(), AnsiStyle::default()));
+        }
+        runs
+    }
+
+    /// Add output, interpreting ANSI escape sequences; error output
+    /// without a color of its own is shown in red
+    pub fn append(&mut self, chunk: &str, error: bool) {
+        let input = std::mem::take(&mut self.pending) + chunk;
+        let mut rest = input.as_str();
+        while let Some(escape) = rest.find(['\x1b', '\r']) {
+            self.push_text(&rest[..escape], error);
+            let sequence = &rest[escape..];
+            let Some(len) = escape_len(sequence) else {
+                // Wait for the rest, unless it is garbage that never ends
+                if sequence.len() < 256 {
+                    self.pending = sequence.to_string();
+                    return;
+                }
+                self.push_text(&sequence.replace('\x1b', ""), error);
+                rest = "";
+                break;
+            };
+            if let Some(after) = sequence.strip_prefix('\r') {
+                if !after.starts_with('\n') {
+                    // A lone carriage return rewrites the line, as
+                    // progress bars do
+                    self.truncate(self.line_range(self.line_count() - 1).start);
+                }
+            } else if sequence.starts_with("\x1b[") && sequence[..len].ends_with('m') {
+                self.style.apply_sgr(&sequence[2..len - 1]);
+            }
+            rest = &sequence[len..];
+        }
+        self.push_text(rest, error);
+        if self.text.len() > MAX_OUTPUT {
+            self.drop_front(self.text.len() - MAX_OUTPUT / 2);
+        }
+    }
+
+    fn push_text(&mut self, text: &str, error: bool) {
+        if text.is_empty() {
+            return;
+        }
+        let mut style = self.style;
+        if error && style.foreground.is_none() {
+            style.foreground = Some(AnsiColor::Indexed(1));
+        }
+        if self.styles.last().map(|&(_, s)| s) != Some(style) {
+            self.styles.push((self.text.len(), style));
+        }
+        let start = self.text.len();
+        self.text.push_str(text);
+        self.line_starts.extend(text.match_indices('\n').map(|(i, _)| start + i + 1));
+    }
+
+    fn truncate(&mut self, len: usize) {
+        self.text.truncate(len);
+        self.line_starts.retain(|&start| start <= len);
+        while self.styles.last().is_some_and(|&(start, _)| start >= len) {
+            self.styles.pop();
+        }
+    }
+
+    /// Drop whole lines from the start, keeping at least `len` bytes fewer
+    fn drop_front(&mut self, len: usize) {
+        let cut = self.line_starts.iter().copied().find(|&start| start >= len).unwrap_or(self.text.len());
+        let style = self.styles.iter().rev().find(|&&(start, _)| start <= cut).map(|&(_, s)| s);
+        self.text.drain(..cut);
+        self.line_starts.retain(|&start| start > cut);
+        self.line_starts.iter_mut().for_each(|start| *start -= cut);
+        self.styles.retain(|&(start, _)| start > cut);
+        self.styles.iter_mut().for_each(|(start, _)| *start -= cut);
+        if let Some(style) = style {
+            self.styles.insert(0, (0, style));
+        }
+    }
+}
+
+/// Length of the escape sequence or carriage return starting `text`, or
+/// `None` when it is cut off
+fn escape_len(text: &str) -> Option<usize> {
+    let bytes = text.as_bytes();
+    if bytes[0] == b'\r' {
+        return (bytes.len() > 1).then_some(1);
+    }
+    match bytes.get(1)? {
+        // Control sequence: parameters, then a final byte
+        b'[' => bytes[2..].iter().position(|b| (0x40..=0x7e).contains(b)).map(|i| i + 3),
+        // Operating system command, such as a window title
+        b']' => {
+            let bell = text.find('\x07').map(|i| i + 1);
+            let terminator = text.find("\x1b\\").map(|i| i + 2);
+            bell.into_iter().chain(terminator).min()
+        }
+        _ => Some(1 + text[1..].chars().next().map_or(0, char::len_utf8)),
+    }
+}
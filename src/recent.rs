@@ -0,0 +1,116 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use crate::settings;
+
+/// Most files kept in the recent list besides pinned ones
+pub const MAX_RECENT: usize = 10;
+
+/// A file in the Open Recent menu
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RecentFile {
+    pub path: PathBuf,
+    /// Pinned files stay at the top and are never dropped or cleared
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+/// Recently opened and saved files, most recent first, stored in
+/// `recent.toml` in the data directory
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RecentFiles {
+    pub files: Vec<RecentFile>,
+    /// Files closed in this session, most recently closed last
+    #[serde(skip)]
+    pub closed: Vec<PathBuf>,
+}
+
+impl RecentFiles {
+    /// Location of the recent files list
+    pub fn path() -> Option<PathBuf> {
+        settings::data_dir().map(|dir| dir.join("recent.toml"))
+    }
+
+    /// Read the list from a file; a missing file gives an empty list
+    pub fn load_from(path: &Path) -> Result<Self, String> {
+        match fs::read_to_string(path) {
+            Ok(source) => toml::from_str(&source)
+                .map_err(|e| format!("Invalid recent files list in {}: {}", path.display(), e.to_string().trim())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+        }
+    }
+
+    /// Write the list to a file, creating its directory if needed
+    pub fn save_to(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, toml::to_string_pretty(self).unwrap_or_default())
+    }
+
+    /// Pinned files, in the order they were used
+    pub fn pinned(&self) -> impl Iterator<Item = &RecentFile> {
+        self.files.iter().filter(|file| file.pinned)
+    }
+
+    /// Files that are not pinned, most recent first
+    pub fn unpinned(&self) -> impl Iterator<Item = &RecentFile> {
+        self.files.iter().filter(|file| !file.pinned)
+    }
+
+    /// Move a file to the top of the list, dropping the oldest unpinned
+    /// files beyond `MAX_RECENT`
+    pub fn add(&mut self, path: &Path) {
+        let pinned = self.remove(path).is_some_and(|file| file.pinned);
+        self.files.insert(0, RecentFile { path: path.to_path_buf(), pinned });
+        let mut unpinned = 0;
+        self.files.retain(|file| {
+            unpinned += usize::from(!file.pinned);
+            file.pinned || unpinned <= MAX_RECENT
+        });
+    }
+
+    /// Take a file off the list
+    pub fn remove(&mut self, path: &Path) -> Option<RecentFile> {
+        let index = self.files.iter().position(|file| file.path == path)?;
+        Some(self.files.remove(index))
+    }
+
+    /// Pin a file, or unpin it if it is pinned
+    pub fn toggle_pin(&mut self, path: &Path) {
+        if let Some(file) = self.files.iter_mut().find(|file| file.path == path) {
+            file.pinned = !file.pinned;
+        }
+    }
+
+    /// Forget every file that is not pinned
+    pub fn clear(&mut self) {
+        self.files.retain(|file| file.pinned);
+    }
+
+    /// Drop files that no longer exist; returns whether any were dropped
+    pub fn prune_missing(&mut self) -> bool {
+        let count = self.files.len();
+        self.files.retain(|file| file.path.is_file());
+        self.files.len() != count
+    }
+
+    /// Remember that a file was closed
+    pub fn push_closed(&mut self, path: &Path) {
+        self.closed.retain(|closed| closed != path);
+        self.closed.push(path.to_path_buf());
+    }
+
+    /// The most recently closed file still on disk other than `current`,
+    /// taking it and anything closed after it off the stack
+    pub fn pop_closed(&mut self, current: Option<&Path>) -> Option<PathBuf> {
+        while let Some(path) = self.closed.pop() {
+            if Some(path.as_path()) != current && path.is_file() {
+                return Some(path);
+            }
+        }
+        None
+    }
+}
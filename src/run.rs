@@ -0,0 +1,193 @@
+use std::io::{self, Read};
+use std::path::Path;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+use std::time::Instant;
+use crate::format;
+use crate::languages;
+use crate::process;
+use crate::settings::CommandSettings;
+
+/// Command running a file: the configured runner for its language, or
+/// else the interpreter of its `#!` line or its language's usual one
+///
+/// `{file}` in the arguments is replaced by the file's path, which is
+/// added at the end when no argument names it.
+///
+/// A configured runner with an empty command turns running off.
+pub fn run_command(
+    configured: Option<&CommandSettings>,
+    text: &str,
+    language: &str,
+    file: &Path,
+) -> Option<CommandSettings> {
+    if configured.is_some_and(|runner| runner.command.is_empty()) {
+        return None;
+    }
+    let mut runner = configured
+        .cloned()
+        .or_else(|| languages::shebang_interpreter(text).map(|program| CommandSettings::new(program, &[])))
+        .or_else(|| CommandSettings::default_runner(language))?;
+    if runner.args.iter().any(|arg| arg.contains("{file}")) {
+        runner.args = format::expand_args(&runner.args, Some(file));
+    } else {
+        runner.args.push(file.to_string_lossy().into_owned());
+    }
+    Some(runner)
+}
+
+/// A piece of a running program's output
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RunOutput {
+    Stdout(String),
+    Stderr(String),
+}
+
+/// A program running in the background with its output streamed back
+pub struct RunJob {
+    /// The command line, for display
+    pub command: String,
+    pub started: Instant,
+    child: Child,
+    output: Receiver<RunOutput>,
+    status: Option<ExitStatus>,
+}
+
+impl RunJob {
+    /// Start a program in `dir`
+    ///
+    /// `on_output` is called from background threads whenever output
+    /// arrives or the program closes its output.
+    pub fn start(
+        runner: &CommandSettings,
+        dir: Option<&Path>,
+        on_output: impl Fn() + Send + Sync + 'static,
+    ) -> Result<Self, String> {
+        let mut command = Command::new(&runner.command);
+        command
+            .args(&runner.args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            // Interpreters that buffer output sent to a pipe stream it
+            // line by line instead
+            .env("PYTHONUNBUFFERED", "1");
+        if let Some(dir) = dir {
+            command.current_dir(dir);
+        }
+        #[cfg(unix)]
+        {
+            // Its own process group, so stopping it also stops the
+            // programs it started
+            use std::os::unix::process::CommandExt;
+            command.process_group(0);
+        }
+        let mut child = command.spawn().map_err(|e| format!("Failed to run {}: {}", runner.command, e))?;
+        let (sender, output) = mpsc::channel();
+        let on_output = Arc::new(on_output);
+        if let Some(stdout) = child.stdout.take() {
+            let (sender, on_output) = (sender.clone(), on_output.clone());
+            thread::spawn(move || stream(stdout, RunOutput::Stdout, sender, move || on_output()));
+        }
+        if let Some(stderr) = child.stderr.take() {
+            thread::spawn(move || stream(stderr, RunOutput::Stderr, sender, move || on_output()));
+        }
+        let command = std::iter::once(&runner.command)
+            .chain(&runner.args)
+            .map(|word| if word.contains(' ') { process::shell_quote(word) } else { word.clone() })
+            .collect::<Vec<_>>()
+            .join(" ");
+        Ok(Self {
+            command,
+            started: Instant::now(),
+            child,
+            output,
+            status: None,
+        })
+    }
+
+    /// Stop the program and the processes it started
+    pub fn kill(&mut self) {
+        if self.status.is_some() {
+            return;
+        }
+        #[cfg(unix)]
+        {
+            let group = format!("-{}", self.child.id());
+            let killed = Command::new("kill").args(["-KILL", "--", &group]).stderr(Stdio::null()).status();
+            if killed.is_ok_and(|status| status.success()) {
+                return;
+            }
+        }
+        let _ = self.child.kill();
+    }
+
+    /// Output since the last call, and the exit status once the program
+    /// finished and all its output was read
+    ///
+    /// A program that closes its output and keeps running is only seen
+    /// exiting by polling again.
+    pub fn poll(&mut self) -> (Vec<RunOutput>, Option<ExitStatus>) {
+        let mut chunks = Vec::new();
+        loop {
+            match self.output.try_recv() {
+                Ok(chunk) => chunks.push(chunk),
+                Err(TryRecvError::Empty) => return (chunks, None),
+                Err(TryRecvError::Disconnected) => break,
+            }
+        }
+        if self.status.is_none() {
+            // Output is closed, so the program is exiting or has closed
+            // it and keeps running
+            self.status = self.child.try_wait().ok().flatten();
+        }
+        (chunks, self.status)
+    }
+}
+
+impl Drop for RunJob {
+    fn drop(&mut self) {
+        self.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Send what a pipe produces as it arrives, keeping UTF-8 sequences that
+/// are split across reads together
+fn stream(
+    mut pipe: impl Read,
+    wrap: fn(String) -> RunOutput,
+    sender: mpsc::Sender<RunOutput>,
+    on_output: impl Fn(),
+) {
+    let mut buffer = [0; 8192];
+    let mut carry = Vec::new();
+    loop {
+        let read = match pipe.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(_) => break,
+        };
+        carry.extend_from_slice(&buffer[..read]);
+        let valid = match std::str::from_utf8(&carry) {
+            Ok(_) => carry.len(),
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            Err(_) => carry.len(),
+        };
+        let text = String::from_utf8_lossy(&carry[..valid]).into_owned();
+        carry.drain(..valid);
+        if sender.send(wrap(text)).is_err() {
+            return;
+        }
+        on_output();
+    }
+    if !carry.is_empty() {
+        let _ = sender.send(wrap(String::from_utf8_lossy(&carry).into_owned()));
+    }
+    // Closed before waking the app, so it sees the output end
+    drop(sender);
+    on_output();
+}
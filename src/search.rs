@@ -59,18 +59,18 @@ impl SearchState {
 
     /// Replace the current match
     pub fn replace_current(&mut self, text: &mut String) -> bool {
-        if let Some(current_idx) = self.current_match_index {
-            if let Some(&match_pos) = self.results.get(current_idx) {
-                // Verify the text still matches (safety check)
-                if text[match_pos..].starts_with(&self.query) {
-                    text.replace_range(match_pos..match_pos + self.query.len(), &self.replace_query);
-                    self.update_results(text);
-                    // Try to keep selection near where we were
-                    if !self.results.is_empty() {
-                        self.current_match_index = Some(current_idx % self.results.len());
-                    }
-                    return true;
+        if let Some(current_idx) = self.current_match_index
+            && let Some(&match_pos) = self.results.get(current_idx)
+        {
+            // Verify the text still matches (safety check)
+            if text[match_pos..].starts_with(&self.query) {
+                text.replace_range(match_pos..match_pos + self.query.len(), &self.replace_query);
+                self.update_results(text);
+                // Try to keep selection near where we were
+                if !self.results.is_empty() {
+                    self.current_match_index = Some(current_idx % self.results.len());
                 }
+                return true;
             }
         }
         false
@@ -89,8 +89,9 @@ impl SearchState {
         false
     }
 
-    /// Render the Find bar UI
-    pub fn render_bar(&mut self, ui: &mut egui::Ui, text: &mut String) -> bool {
+    /// Render the Find bar UI; a read-only document can be searched but not
+    /// replaced in
+    pub fn render_bar(&mut self, ui: &mut egui::Ui, text: &mut String, read_only: bool) -> bool {
         let mut modified = false;
         
         ui.vertical(|ui| {
@@ -136,16 +137,23 @@ impl SearchState {
                     ui.label("Replace with:");
                     ui.text_edit_singleline(&mut self.replace_query);
                     
-                    if ui.add_enabled(self.current_match_index.is_some(), egui::Button::new("Replace")).clicked() {
-                        if self.replace_current(text) {
-                            modified = true;
-                        }
+                    if ui
+                        .add_enabled(
+                            !read_only && self.current_match_index.is_some(),
+                            egui::Button::new("Replace"),
+                        )
+                        .clicked()
+                        && self.replace_current(text)
+                    {
+                        modified = true;
                     }
                     
-                    if ui.add_enabled(!self.results.is_empty(), egui::Button::new("Replace All")).clicked() {
-                        if self.replace_all(text) {
-                            modified = true;
-                        }
+                    if ui
+                        .add_enabled(!read_only && !self.results.is_empty(), egui::Button::new("Replace All"))
+                        .clicked()
+                        && self.replace_all(text)
+                    {
+                        modified = true;
                     }
                 });
             }
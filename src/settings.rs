@@ -0,0 +1,422 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use crate::indent::IndentStyle;
+use crate::keybindings::KeyBindings;
+use crate::wrap::WrapMode;
+
+/// Smallest and largest zoom factor reachable with Ctrl+- / Ctrl+=
+pub const MIN_ZOOM: f32 = 0.5;
+pub const MAX_ZOOM: f32 = 3.0;
+pub const ZOOM_STEP: f32 = 0.1;
+
+/// Directory holding Writer's configuration: `$XDG_CONFIG_HOME/writer`,
+/// falling back to `~/.config/writer`
+pub fn config_dir() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(base.join("writer"))
+}
+
+/// Directory holding Writer's data, such as file history:
+/// `$XDG_DATA_HOME/writer`, falling back to `~/.local/share/writer`
+pub fn data_dir() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))?;
+    Some(base.join("writer"))
+}
+
+/// Editor behavior
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EditorSettings {
+    pub show_line_numbers: bool,
+    pub syntax_highlighting: bool,
+    pub auto_indent: bool,
+    pub auto_close_brackets: bool,
+    /// Indent width for files whose indentation is not detected
+    pub tab_size: usize,
+    pub insert_spaces: bool,
+    pub word_wrap: bool,
+    /// Column to wrap at, or 0 to wrap at the window edge
+    pub wrap_column: usize,
+}
+
+impl Default for EditorSettings {
+    fn default() -> Self {
+        Self {
+            show_line_numbers: false,
+            syntax_highlighting: false,
+            auto_indent: true,
+            auto_close_brackets: true,
+            tab_size: 4,
+            insert_spaces: true,
+            word_wrap: false,
+            wrap_column: 0,
+        }
+    }
+}
+
+impl EditorSettings {
+    /// Indentation for new files and files without indented lines
+    pub fn indent_style(&self) -> IndentStyle {
+        if self.insert_spaces {
+            IndentStyle::Spaces(self.tab_size)
+        } else {
+            IndentStyle::Tabs
+        }
+    }
+
+    pub fn wrap_mode(&self) -> WrapMode {
+        match (self.word_wrap, self.wrap_column) {
+            (false, _) => WrapMode::Off,
+            (true, 0) => WrapMode::WindowWidth,
+            (true, column) => WrapMode::Column(column),
+        }
+    }
+
+    pub fn set_wrap_mode(&mut self, mode: WrapMode) {
+        self.word_wrap = mode != WrapMode::Off;
+        match mode {
+            WrapMode::Off => {}
+            WrapMode::WindowWidth => self.wrap_column = 0,
+            WrapMode::Column(column) => self.wrap_column = column,
+        }
+    }
+}
+
+/// What happens to files when they are opened and saved
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FileSettings {
+    /// Guess the indentation of opened files from their contents
+    pub detect_indentation: bool,
+    pub trim_trailing_whitespace: bool,
+    pub insert_final_newline: bool,
+    /// Run the language's formatter before saving
+    pub format_on_save: bool,
+    /// Keep a copy of every saved version in the local history
+    pub keep_history: bool,
+    /// Versions kept per file
+    pub history_versions: usize,
+    /// Days after which versions are dropped, or 0 to keep them
+    pub history_days: u64,
+    /// Size in megabytes from which files open in large-file mode, without
+    /// highlighting or language features; 0 never uses it
+    pub large_file_mb: u64,
+}
+
+impl Default for FileSettings {
+    fn default() -> Self {
+        Self {
+            detect_indentation: true,
+            trim_trailing_whitespace: false,
+            insert_final_newline: false,
+            format_on_save: false,
+            keep_history: true,
+            history_versions: 50,
+            history_days: 30,
+            large_file_mb: 16,
+        }
+    }
+}
+
+impl FileSettings {
+    /// Whether a file of `size` bytes opens in large-file mode
+    pub fn is_large(&self, size: u64) -> bool {
+        self.large_file_mb > 0 && size >= self.large_file_mb * 1024 * 1024
+    }
+
+    /// The text as it should be written to disk
+    pub fn prepare_for_save(&self, text: &str) -> String {
+        let mut result = if self.trim_trailing_whitespace {
+            text.split('\n')
+                .map(|line| line.trim_end_matches([' ', '\t']))
+                .collect::<Vec<_>>()
+                .join("\n")
+        } else {
+            text.to_string()
+        };
+        if self.insert_final_newline && !result.is_empty() && !result.ends_with('\n') {
+            result.push('\n');
+        }
+        result
+    }
+}
+
+/// The embedded terminal
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TerminalSettings {
+    /// Shell command line, or empty for the user's login shell
+    pub shell: String,
+    /// Lines kept above the screen
+    pub scrollback: usize,
+    /// Start in the current file's project root rather than its folder
+    pub start_in_project_root: bool,
+}
+
+impl Default for TerminalSettings {
+    fn default() -> Self {
+        Self {
+            shell: String::new(),
+            scrollback: 10_000,
+            start_in_project_root: true,
+        }
+    }
+}
+
+/// Light or dark user interface
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Appearance {
+    /// Follow the operating system setting
+    #[default]
+    System,
+    Light,
+    Dark,
+}
+
+impl Appearance {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Appearance::System => "System",
+            Appearance::Light => "Light",
+            Appearance::Dark => "Dark",
+        }
+    }
+}
+
+/// Theme, font and zoom preferences
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppearanceSettings {
+    pub appearance: Appearance,
+    /// Editor color theme used when the interface is dark
+    pub dark_theme: String,
+    /// Editor color theme used when the interface is light
+    pub light_theme: String,
+    pub font_size: f32,
+    pub zoom: f32,
+    /// TrueType/OpenType font used for the editor instead of the built-in one
+    pub monospace_font: Option<PathBuf>,
+    /// TrueType/OpenType font used for menus and dialogs
+    pub proportional_font: Option<PathBuf>,
+}
+
+impl Default for AppearanceSettings {
+    fn default() -> Self {
+        Self {
+            appearance: Appearance::System,
+            dark_theme: "Github Dark".to_string(),
+            light_theme: "Github Light".to_string(),
+            font_size: 14.0,
+            zoom: 1.0,
+            monospace_font: None,
+            proportional_font: None,
+        }
+    }
+}
+
+/// Command line of an external tool, such as a language server or a
+/// formatter
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CommandSettings {
+    pub command: String,
+    pub args: Vec<String>,
+}
+
+impl CommandSettings {
+    pub fn new(command: &str, args: &[&str]) -> Self {
+        Self {
+            command: command.to_string(),
+            args: args.iter().map(|a| a.to_string()).collect(),
+        }
+    }
+
+    /// Language server used for a language that has none in the settings
+    pub fn default_language_server(language: &str) -> Option<Self> {
+        match language {
+            "Rust" => Some(Self::new("rust-analyzer", &[])),
+            "Python" => Some(Self::new("pyright-langserver", &["--stdio"])),
+            _ => None,
+        }
+    }
+
+    /// Formatter used for a language that has none in the settings; it
+    /// reads the text on stdin and writes the result to stdout
+    pub fn default_formatter(language: &str) -> Option<Self> {
+        match language {
+            "Rust" => Some(Self::new("rustfmt", &["--emit", "stdout", "--edition", "{edition}"])),
+            "Python" => Some(Self::new("black", &["--quiet", "-"])),
+            "SQL" => Some(Self::new("sqlformat", &["--reindent", "-"])),
+            "Shell" => Some(Self::new("shfmt", &[])),
+            _ => None,
+        }
+    }
+
+    /// Program running a file of a language that has none in the
+    /// settings and no `#!` line
+    pub fn default_runner(language: &str) -> Option<Self> {
+        match language {
+            "Python" => Some(Self::new("python3", &[])),
+            "Lua" => Some(Self::new("lua", &[])),
+            "Shell" => Some(Self::new("sh", &[])),
+            "JavaScript" => Some(Self::new("node", &[])),
+            "TypeScript" => Some(Self::new("ts-node", &[])),
+            "Go" => Some(Self::new("go", &["run"])),
+            "Makefile" => Some(Self::new("make", &["-f"])),
+            _ => None,
+        }
+    }
+}
+
+/// The configured command for a language, falling back to a default; an
+/// empty command turns it off
+fn command_for(
+    configured: &BTreeMap<String, CommandSettings>,
+    language: &str,
+    default: fn(&str) -> Option<CommandSettings>,
+) -> Option<CommandSettings> {
+    match configured.get(language) {
+        Some(command) if command.command.is_empty() => None,
+        Some(command) => Some(command.clone()),
+        None => default(language),
+    }
+}
+
+/// User preferences stored in `settings.toml` in the config directory
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub editor: EditorSettings,
+    pub appearance: AppearanceSettings,
+    pub files: FileSettings,
+    pub terminal: TerminalSettings,
+    /// Shortcuts replacing the defaults, by command id; alternatives are
+    /// separated by spaces and an empty string unbinds the command
+    pub keybindings: BTreeMap<String, String>,
+    /// Language servers by language name, replacing the defaults; an empty
+    /// command turns the language's server off
+    pub language_servers: BTreeMap<String, CommandSettings>,
+    /// Formatters by language name, replacing the defaults; an empty
+    /// command turns the language's formatter off. `{file}` in the
+    /// arguments is replaced by the file's path and `{edition}` by the Rust
+    /// edition of its package
+    pub formatters: BTreeMap<String, CommandSettings>,
+    /// Programs running files by language name, replacing the defaults and
+    /// `#!` lines; an empty command turns running off. `{file}` in the
+    /// arguments is replaced by the file's path, which is otherwise added
+    /// at the end
+    pub runners: BTreeMap<String, CommandSettings>,
+}
+
+impl Settings {
+    /// Location of the settings file
+    pub fn path() -> Option<PathBuf> {
+        config_dir().map(|dir| dir.join("settings.toml"))
+    }
+
+    /// Parse and validate settings from TOML; missing keys keep their
+    /// defaults
+    pub fn from_toml(source: &str) -> Result<Self, String> {
+        let settings: Self = toml::from_str(source).map_err(|e| e.to_string().trim().to_string())?;
+        let errors = settings.validate();
+        if errors.is_empty() {
+            Ok(settings)
+        } else {
+            Err(errors.join("\n"))
+        }
+    }
+
+    /// Every value out of its allowed range
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        let editor = &self.editor;
+        if !(1..=16).contains(&editor.tab_size) {
+            errors.push(format!("editor.tab_size must be between 1 and 16, not {}", editor.tab_size));
+        }
+        if editor.wrap_column != 0 && !(20..=1000).contains(&editor.wrap_column) {
+            errors.push(format!(
+                "editor.wrap_column must be 0 or between 20 and 1000, not {}",
+                editor.wrap_column
+            ));
+        }
+        if !(1..=1000).contains(&self.files.history_versions) {
+            errors.push(format!(
+                "files.history_versions must be between 1 and 1000, not {}",
+                self.files.history_versions
+            ));
+        }
+        if self.terminal.scrollback > 100_000 {
+            errors.push(format!("terminal.scrollback must be at most 100000, not {}", self.terminal.scrollback));
+        }
+        let appearance = &self.appearance;
+        if !(6.0..=72.0).contains(&appearance.font_size) {
+            errors.push(format!(
+                "appearance.font_size must be between 6 and 72, not {}",
+                appearance.font_size
+            ));
+        }
+        if !(MIN_ZOOM..=MAX_ZOOM).contains(&appearance.zoom) {
+            errors.push(format!(
+                "appearance.zoom must be between {} and {}, not {}",
+                MIN_ZOOM, MAX_ZOOM, appearance.zoom
+            ));
+        }
+        for path in [&appearance.monospace_font, &appearance.proportional_font].into_iter().flatten() {
+            if !path.is_file() {
+                errors.push(format!("font file {} does not exist", path.display()));
+            }
+        }
+        if let Err(e) = KeyBindings::new(&self.keybindings) {
+            errors.extend(e);
+        }
+        errors
+    }
+
+    /// Server to start for a language, if any
+    pub fn language_server(&self, language: &str) -> Option<CommandSettings> {
+        command_for(&self.language_servers, language, CommandSettings::default_language_server)
+    }
+
+    /// Formatter to run for a language, if any
+    pub fn formatter(&self, language: &str) -> Option<CommandSettings> {
+        command_for(&self.formatters, language, CommandSettings::default_formatter)
+    }
+
+    pub fn to_toml(&self) -> String {
+        toml::to_string_pretty(self).unwrap_or_default()
+    }
+
+    /// Read settings from a file; a missing file gives the defaults
+    pub fn load_from(path: &Path) -> Result<Self, String> {
+        match fs::read_to_string(path) {
+            Ok(source) => Self::from_toml(&source)
+                .map_err(|e| format!("Invalid settings in {}: {}", path.display(), e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+        }
+    }
+
+    /// Write settings to a file, creating its directory if needed
+    pub fn save_to(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.to_toml())
+    }
+
+    /// Zoom in or out by `steps` increments, within the allowed range
+    pub fn zoom_by(&mut self, steps: i32) {
+        let zoom = self.appearance.zoom + steps as f32 * ZOOM_STEP;
+        // Round to avoid drifting away from whole steps
+        self.appearance.zoom = ((zoom * 10.0).round() / 10.0).clamp(MIN_ZOOM, MAX_ZOOM);
+    }
+}
@@ -1,45 +1,206 @@
+use std::collections::BTreeSet;
+use std::path::Path;
 use egui_code_editor::Syntax;
+use crate::languages;
 
-/// Determines the appropriate syntax highlighting based on file extension
+/// Comment marker for languages without line comments
+///
+/// The highlighter treats an empty marker as matching everything, so a
+/// character that never appears in text is used instead.
+pub const NO_COMMENT: &str = "\u{0}";
+
+/// Determines the appropriate syntax highlighting for a file
 pub fn get_syntax_for_file(filename: Option<&String>, highlighting_enabled: bool) -> Syntax {
     if !highlighting_enabled {
-        return Syntax::default();
+        return plain_text();
     }
-    
-    let Some(filename) = filename else {
-        return Syntax::default();
-    };
-    
-    match get_file_extension(filename) {
-        Some("rs") => Syntax::rust(),
-        Some("py") | Some("py3") | Some("pyw") => Syntax::python(),
-        Some("lua") => Syntax::lua(),
-        Some("sh") | Some("bash") | Some("zsh") => Syntax::shell(),
-        Some("sql") => Syntax::sql(),
-        Some("asm") | Some("s") => Syntax::asm(),
-        Some("txt") | _ => Syntax::default(),
+    languages::registry().detect(filename).syntax.clone()
+}
+
+/// Extracts the file extension from a filename or path
+///
+/// Names without a dot, and dotfiles such as `.bashrc`, have no extension.
+pub(crate) fn get_file_extension(filename: &str) -> Option<&str> {
+    let name = Path::new(filename).file_name()?.to_str()?;
+    match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => Some(extension),
+        _ => None,
     }
 }
 
-/// Extracts the file extension from a filename
-fn get_file_extension(filename: &str) -> Option<&str> {
-    filename.rsplit('.').next()
+/// Highlighting for a language from the registry
+pub fn get_syntax_for_language(language: &str, highlighting_enabled: bool) -> Syntax {
+    match languages::registry().by_name(language) {
+        Some(language) if highlighting_enabled => language.syntax.clone(),
+        _ => plain_text(),
+    }
 }
 
 /// Returns a human-readable name for the detected language
 pub fn get_language_name(filename: Option<&String>) -> &'static str {
-    let Some(filename) = filename else {
-        return "Plain Text";
-    };
-    
-    match get_file_extension(filename) {
-        Some("rs") => "Rust",
-        Some("py") | Some("py3") | Some("pyw") => "Python",
-        Some("lua") => "Lua",
-        Some("sh") | Some("bash") | Some("zsh") => "Shell",
-        Some("sql") => "SQL",
-        Some("asm") | Some("s") => "Assembly",
-        _ => "Plain Text",
+    languages::registry().detect(filename).name
+}
+
+/// Syntax with the given comment markers and no keywords
+fn base(language: &'static str, comment: &'static str, multiline: [&'static str; 2]) -> Syntax {
+    Syntax {
+        language,
+        case_sensitive: true,
+        comment,
+        comment_multiline: multiline,
+        hyperlinks: BTreeSet::from(["http"]),
+        keywords: BTreeSet::new(),
+        types: BTreeSet::new(),
+        special: BTreeSet::new(),
     }
 }
 
+pub fn plain_text() -> Syntax {
+    base("Plain Text", NO_COMMENT, [NO_COMMENT; 2])
+}
+
+pub fn json() -> Syntax {
+    base("JSON", "//", ["/*", "*/"]).with_special(["true", "false", "null"])
+}
+
+pub fn toml() -> Syntax {
+    base("TOML", "#", ["#", "#"])
+        .with_special(["true", "false", "inf", "nan"])
+}
+
+pub fn yaml() -> Syntax {
+    base("YAML", "#", ["#", "#"])
+        .with_special(["true", "false", "null", "yes", "no", "on", "off"])
+}
+
+pub fn markdown() -> Syntax {
+    base("Markdown", NO_COMMENT, ["<!--", "-->"])
+}
+
+const C_KEYWORDS: [&str; 28] = [
+    "break", "case", "const", "continue", "default", "do", "else", "enum", "extern", "for",
+    "goto", "if", "inline", "register", "restrict", "return", "sizeof", "static", "struct",
+    "switch", "typedef", "union", "volatile", "while", "#include", "#define", "#ifdef", "#endif",
+];
+
+const C_TYPES: [&str; 16] = [
+    "char", "double", "float", "int", "long", "short", "signed", "unsigned", "void", "bool",
+    "size_t", "int8_t", "int16_t", "int32_t", "int64_t", "uint8_t",
+];
+
+pub fn c() -> Syntax {
+    base("C", "//", ["/*", "*/"])
+        .with_keywords(C_KEYWORDS)
+        .with_types(C_TYPES)
+        .with_special(["NULL", "true", "false"])
+}
+
+pub fn cpp() -> Syntax {
+    let mut keywords = BTreeSet::from(C_KEYWORDS);
+    keywords.extend([
+        "auto", "catch", "class", "constexpr", "delete", "explicit", "friend", "namespace", "new",
+        "noexcept", "operator", "override", "private", "protected", "public", "template", "this",
+        "throw", "try", "typename", "using", "virtual",
+    ]);
+    let mut types = BTreeSet::from(C_TYPES);
+    types.extend(["string", "vector", "map", "unique_ptr", "shared_ptr"]);
+    base("C++", "//", ["/*", "*/"])
+        .with_keywords(keywords)
+        .with_types(types)
+        .with_special(["nullptr", "true", "false", "std"])
+}
+
+pub fn go() -> Syntax {
+    base("Go", "//", ["/*", "*/"])
+        .with_keywords([
+            "break", "case", "chan", "const", "continue", "default", "defer", "else",
+            "fallthrough", "for", "func", "go", "goto", "if", "import", "interface", "map",
+            "package", "range", "return", "select", "struct", "switch", "type", "var",
+        ])
+        .with_types([
+            "bool", "byte", "complex128", "error", "float32", "float64", "int", "int8", "int16",
+            "int32", "int64", "rune", "string", "uint", "uint8", "uint16", "uint32", "uint64",
+            "uintptr", "any",
+        ])
+        .with_special(["nil", "true", "false", "iota"])
+}
+
+const JS_KEYWORDS: [&str; 35] = [
+    "async", "await", "break", "case", "catch", "class", "const", "continue", "debugger",
+    "default", "delete", "do", "else", "export", "extends", "finally", "for", "from", "function",
+    "if", "import", "in", "instanceof", "let", "new", "of", "return", "static", "super", "switch",
+    "throw", "try", "typeof", "var", "while",
+];
+
+pub fn javascript() -> Syntax {
+    base("JavaScript", "//", ["/*", "*/"])
+        .with_keywords(JS_KEYWORDS)
+        .with_types(["Array", "Boolean", "Map", "Number", "Object", "Promise", "Set", "String"])
+        .with_special(["null", "undefined", "true", "false", "this", "NaN"])
+}
+
+pub fn typescript() -> Syntax {
+    let mut keywords = BTreeSet::from(JS_KEYWORDS);
+    keywords.extend([
+        "abstract", "as", "declare", "enum", "implements", "interface", "keyof", "namespace",
+        "private", "protected", "public", "readonly", "type",
+    ]);
+    base("TypeScript", "//", ["/*", "*/"])
+        .with_keywords(keywords)
+        .with_types([
+            "any", "boolean", "never", "number", "object", "string", "symbol", "unknown", "void",
+            "Array", "Map", "Promise", "Record", "Set",
+        ])
+        .with_special(["null", "undefined", "true", "false", "this"])
+}
+
+pub fn html() -> Syntax {
+    base("HTML", NO_COMMENT, ["<!--", "-->"])
+        .with_keywords([
+            "a", "body", "button", "div", "form", "head", "html", "img", "input", "li", "link",
+            "meta", "p", "script", "span", "style", "table", "td", "title", "tr", "ul",
+        ])
+        .with_types(["class", "href", "id", "name", "src", "type", "value"])
+        .with_special(["DOCTYPE"])
+}
+
+pub fn css() -> Syntax {
+    base("CSS", NO_COMMENT, ["/*", "*/"])
+        .with_keywords(["@import", "@media", "@font-face", "@keyframes", "important"])
+        .with_types(["px", "em", "rem", "vh", "vw"])
+        .with_special(["none", "auto", "inherit", "initial"])
+}
+
+pub fn java() -> Syntax {
+    base("Java", "//", ["/*", "*/"])
+        .with_keywords([
+            "abstract", "break", "case", "catch", "class", "continue", "default", "do", "else",
+            "enum", "extends", "final", "finally", "for", "if", "implements", "import",
+            "instanceof", "interface", "new", "package", "private", "protected", "public",
+            "return", "static", "super", "switch", "synchronized", "throw", "throws", "try",
+            "var", "while",
+        ])
+        .with_types([
+            "boolean", "byte", "char", "double", "float", "int", "long", "short", "void",
+            "String", "Object", "List", "Map",
+        ])
+        .with_special(["null", "true", "false", "this"])
+}
+
+pub fn makefile() -> Syntax {
+    base("Makefile", "#", ["#", "#"])
+        .with_keywords([
+            "define", "endef", "ifdef", "ifndef", "ifeq", "ifneq", "else", "endif", "include",
+            "export", "override",
+        ])
+        .with_special([".PHONY"])
+}
+
+pub fn dockerfile() -> Syntax {
+    base("Dockerfile", "#", ["#", "#"])
+        .with_keywords([
+            "FROM", "RUN", "CMD", "LABEL", "EXPOSE", "ENV", "ADD", "COPY", "ENTRYPOINT",
+            "VOLUME", "USER", "WORKDIR", "ARG", "ONBUILD", "STOPSIGNAL", "HEALTHCHECK", "SHELL",
+            "AS",
+        ])
+}
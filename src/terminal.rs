@@ -0,0 +1,237 @@
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::thread;
+use eframe::egui;
+use portable_pty::{native_pty_system, Child, CommandBuilder, MasterPty, PtySize};
+
+/// A cell of the terminal grid, as row and column of the visible screen
+pub type CellPosition = (u16, u16);
+
+/// A shell running in a pseudo-terminal, with its screen kept up to date
+/// by a background thread
+pub struct Terminal {
+    /// Directory the shell started in
+    pub directory: PathBuf,
+    parser: Arc<Mutex<vt100::Parser>>,
+    master: Box<dyn MasterPty + Send>,
+    writer: Box<dyn Write + Send>,
+    child: Box<dyn Child + Send + Sync>,
+    exit_code: Option<u32>,
+}
+
+impl Terminal {
+    /// Start `shell`, or the user's login shell when it is empty, in `dir`
+    ///
+    /// `on_output` is called from a background thread whenever the screen
+    /// changed.
+    pub fn start(
+        shell: &str,
+        dir: &Path,
+        (rows, cols): (u16, u16),
+        scrollback: usize,
+        on_output: impl Fn() + Send + 'static,
+    ) -> Result<Self, String> {
+        let size = PtySize { rows, cols, pixel_width: 0, pixel_height: 0 };
+        let pair = native_pty_system()
+            .openpty(size)
+            .map_err(|e| format!("Failed to open a terminal: {}", e))?;
+        let mut command = if shell.trim().is_empty() {
+            CommandBuilder::new_default_prog()
+        } else {
+            let mut words = shell.split_whitespace();
+            let mut command = CommandBuilder::new(words.next().unwrap_or_default());
+            command.args(words);
+            command
+        };
+        command.cwd(dir);
+        command.env("TERM", "xterm-256color");
+        command.env("COLORTERM", "truecolor");
+        let child = pair
+            .slave
+            .spawn_command(command)
+            .map_err(|e| format!("Failed to start {}: {}", if shell.is_empty() { "the shell" } else { shell }, e))?;
+        let mut reader = pair.master.try_clone_reader().map_err(|e| e.to_string())?;
+        let writer = pair.master.take_writer().map_err(|e| e.to_string())?;
+        let parser = Arc::new(Mutex::new(vt100::Parser::new(rows, cols, scrollback)));
+        let screen = parser.clone();
+        thread::spawn(move || {
+            let mut buffer = [0; 8192];
+            while let Ok(read) = reader.read(&mut buffer) {
+                if read == 0 {
+                    break;
+                }
+                screen.lock().unwrap_or_else(PoisonError::into_inner).process(&buffer[..read]);
+                on_output();
+            }
+            on_output();
+        });
+        Ok(Self {
+            directory: dir.to_path_buf(),
+            parser,
+            master: pair.master,
+            writer,
+            child,
+            exit_code: None,
+        })
+    }
+
+    /// The terminal's screen and scrollback
+    pub fn parser(&self) -> MutexGuard<'_, vt100::Parser> {
+        self.parser.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Send keyboard input to the shell, scrolling back to the bottom
+    pub fn write(&mut self, bytes: &[u8]) {
+        self.parser().screen_mut().set_scrollback(0);
+        let _ = self.writer.write_all(bytes);
+        let _ = self.writer.flush();
+    }
+
+    /// Send pasted text, marked as a paste when the program asked for it
+    pub fn paste(&mut self, text: &str) {
+        let bracketed = self.parser().screen().bracketed_paste();
+        let text = text.replace("\r\n", "\r").replace('\n', "\r");
+        if bracketed {
+            self.write(format!("\x1b[200~{}\x1b[201~", text).as_bytes());
+        } else {
+            self.write(text.as_bytes());
+        }
+    }
+
+    /// Rows and columns of the screen
+    pub fn size(&self) -> (u16, u16) {
+        self.parser().screen().size()
+    }
+
+    pub fn resize(&mut self, rows: u16, cols: u16) {
+        if self.size() == (rows, cols) || rows == 0 || cols == 0 {
+            return;
+        }
+        self.parser().screen_mut().set_size(rows, cols);
+        let _ = self.master.resize(PtySize { rows, cols, pixel_width: 0, pixel_height: 0 });
+    }
+
+    /// Scroll the view into the scrollback by `lines`, negative towards the
+    /// bottom
+    pub fn scroll(&mut self, lines: i32) {
+        let mut parser = self.parser();
+        let offset = parser.screen().scrollback() as i64 + i64::from(lines);
+        parser.screen_mut().set_scrollback(offset.max(0) as usize);
+    }
+
+    /// Visible text between two cells, in reading order
+    pub fn text_between(&self, start: CellPosition, end: CellPosition) -> String {
+        let (start, end) = if start <= end { (start, end) } else { (end, start) };
+        let text = self.parser().screen().contents_between(start.0, start.1, end.0, end.1 + 1);
+        text.lines().map(str::trim_end).collect::<Vec<_>>().join("\n")
+    }
+
+    /// Exit code of the shell once it exited
+    pub fn exit_code(&mut self) -> Option<u32> {
+        if self.exit_code.is_none() {
+            self.exit_code = self.child.try_wait().ok().flatten().map(|status| status.exit_code());
+        }
+        self.exit_code
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        if self.exit_code().is_none() {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+    }
+}
+
+/// Bytes a terminal expects for a key that does not produce text, or for a
+/// Ctrl combination
+///
+/// `application_cursor` is the mode in which full-screen programs read the
+/// arrow keys.
+pub fn key_bytes(key: egui::Key, modifiers: egui::Modifiers, application_cursor: bool) -> Option<Vec<u8>> {
+    use egui::Key;
+    if modifiers.ctrl && !modifiers.alt {
+        let name = key.name();
+        if name.len() == 1 && name.as_bytes()[0].is_ascii_alphabetic() {
+            return Some(vec![name.as_bytes()[0].to_ascii_uppercase() - b'@']);
+        }
+        match key {
+            Key::OpenBracket => return Some(vec![0x1b]),
+            Key::Backslash => return Some(vec![0x1c]),
+            Key::CloseBracket => return Some(vec![0x1d]),
+            Key::Space => return Some(vec![0]),
+            _ => {}
+        }
+    }
+    let arrow = |letter: char| {
+        let sequence = if modifiers.ctrl || modifiers.alt || modifiers.shift {
+            // xterm's modifier parameter: 1 + Shift + 2×Alt + 4×Ctrl
+            let parameter = 1 + u8::from(modifiers.shift) + 2 * u8::from(modifiers.alt) + 4 * u8::from(modifiers.ctrl);
+            format!("\x1b[1;{}{}", parameter, letter)
+        } else if application_cursor {
+            format!("\x1bO{}", letter)
+        } else {
+            format!("\x1b[{}", letter)
+        };
+        Some(sequence.into_bytes())
+    };
+    let bytes: &[u8] = match key {
+        Key::ArrowUp => return arrow('A'),
+        Key::ArrowDown => return arrow('B'),
+        Key::ArrowRight => return arrow('C'),
+        Key::ArrowLeft => return arrow('D'),
+        Key::Home => return arrow('H'),
+        Key::End => return arrow('F'),
+        Key::Enter => b"\r",
+        Key::Tab if modifiers.shift => b"\x1b[Z",
+        Key::Tab => b"\t",
+        Key::Backspace if modifiers.alt => b"\x1b\x7f",
+        Key::Backspace => b"\x7f",
+        Key::Escape => b"\x1b",
+        Key::Insert => b"\x1b[2~",
+        Key::Delete => b"\x1b[3~",
+        Key::PageUp => b"\x1b[5~",
+        Key::PageDown => b"\x1b[6~",
+        Key::F1 => b"\x1bOP",
+        Key::F2 => b"\x1bOQ",
+        Key::F3 => b"\x1bOR",
+        Key::F4 => b"\x1bOS",
+        Key::F5 => b"\x1b[15~",
+        Key::F6 => b"\x1b[17~",
+        Key::F7 => b"\x1b[18~",
+        Key::F8 => b"\x1b[19~",
+        Key::F9 => b"\x1b[20~",
+        Key::F10 => b"\x1b[21~",
+        Key::F11 => b"\x1b[23~",
+        Key::F12 => b"\x1b[24~",
+        _ => return None,
+    };
+    Some(bytes.to_vec())
+}
+
+/// The terminal panel and the shell running in it
+#[derive(Default)]
+pub struct TerminalState {
+    pub show_panel: bool,
+    pub session: Option<Terminal>,
+    /// Cells the user dragged across, from where the drag started
+    pub selection: Option<(CellPosition, CellPosition)>,
+    /// Give the terminal keyboard focus the next time it is shown
+    pub focus_requested: bool,
+    /// The terminal had keyboard focus last frame, so shortcuts go to
+    /// the shell
+    pub focused: bool,
+    /// Scrolled distance not yet amounting to a whole line
+    pub scroll_remainder: f32,
+}
+
+impl TerminalState {
+    /// Selected text, if any
+    pub fn selected_text(&self) -> Option<String> {
+        let (start, end) = self.selection?;
+        let text = self.session.as_ref()?.text_between(start, end);
+        (!text.is_empty()).then_some(text)
+    }
+}
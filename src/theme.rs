@@ -0,0 +1,149 @@
+use std::fs;
+use std::path::Path;
+use egui_code_editor::{ColorTheme, DEFAULT_THEMES};
+use serde::Deserialize;
+
+/// A theme file as written by the user
+///
+/// Colors that are left out are taken from the `base` theme, or from
+/// Github Dark/Light depending on `dark`.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ThemeFile {
+    name: String,
+    #[serde(default = "default_dark")]
+    dark: bool,
+    base: Option<String>,
+    bg: Option<String>,
+    cursor: Option<String>,
+    selection: Option<String>,
+    comments: Option<String>,
+    functions: Option<String>,
+    keywords: Option<String>,
+    literals: Option<String>,
+    numerics: Option<String>,
+    punctuation: Option<String>,
+    strs: Option<String>,
+    types: Option<String>,
+    special: Option<String>,
+}
+
+fn default_dark() -> bool {
+    true
+}
+
+/// Built-in theme by name
+pub fn builtin(name: &str) -> Option<ColorTheme> {
+    DEFAULT_THEMES.iter().copied().find(|t| t.name == name)
+}
+
+fn is_hex_color(color: &str) -> bool {
+    color.len() == 7
+        && color.starts_with('#')
+        && color[1..].chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Parse a user theme from TOML
+///
+/// `ColorTheme` only holds `&'static str`, so the strings of a parsed
+/// theme are leaked. Themes are only loaded on startup and on request.
+pub fn parse_theme(source: &str) -> Result<ColorTheme, String> {
+    let file: ThemeFile = toml::from_str(source).map_err(|e| e.message().trim().to_string())?;
+    let base = match &file.base {
+        Some(name) => builtin(name).ok_or_else(|| format!("unknown base theme \"{}\"", name))?,
+        None if file.dark => ColorTheme::GITHUB_DARK,
+        None => ColorTheme::GITHUB_LIGHT,
+    };
+
+    let leak = |s: String| -> &'static str { Box::leak(s.into_boxed_str()) };
+    let mut errors = Vec::new();
+    let mut color = |key: &str, value: Option<String>, fallback: &'static str| match value {
+        Some(value) if is_hex_color(&value) => leak(value),
+        Some(value) => {
+            errors.push(format!("{} = \"{}\" is not a #rrggbb color", key, value));
+            fallback
+        }
+        None => fallback,
+    };
+
+    let theme = ColorTheme {
+        name: leak(file.name),
+        dark: file.dark,
+        bg: color("bg", file.bg, base.bg),
+        cursor: color("cursor", file.cursor, base.cursor),
+        selection: color("selection", file.selection, base.selection),
+        comments: color("comments", file.comments, base.comments),
+        functions: color("functions", file.functions, base.functions),
+        keywords: color("keywords", file.keywords, base.keywords),
+        literals: color("literals", file.literals, base.literals),
+        numerics: color("numerics", file.numerics, base.numerics),
+        punctuation: color("punctuation", file.punctuation, base.punctuation),
+        strs: color("strs", file.strs, base.strs),
+        types: color("types", file.types, base.types),
+        special: color("special", file.special, base.special),
+    };
+    if errors.is_empty() {
+        Ok(theme)
+    } else {
+        Err(errors.join(", "))
+    }
+}
+
+/// Built-in editor themes plus the user's themes
+pub struct ThemeRegistry {
+    themes: Vec<ColorTheme>,
+    /// Problems found while loading user themes
+    pub errors: Vec<String>,
+}
+
+impl Default for ThemeRegistry {
+    fn default() -> Self {
+        Self {
+            themes: DEFAULT_THEMES.to_vec(),
+            errors: Vec::new(),
+        }
+    }
+}
+
+impl ThemeRegistry {
+    /// Load the built-in themes and every `*.toml` theme in `dir`
+    ///
+    /// A user theme with the same name as another theme replaces it.
+    pub fn load(dir: Option<&Path>) -> Self {
+        let mut registry = Self::default();
+        let Some(entries) = dir.and_then(|dir| fs::read_dir(dir).ok()) else {
+            return registry;
+        };
+
+        let mut paths: Vec<_> = entries
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+            .collect();
+        paths.sort();
+
+        for path in paths {
+            let result = fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|source| parse_theme(&source));
+            match result {
+                Ok(theme) => registry.add(theme),
+                Err(e) => registry.errors.push(format!("{}: {}", path.display(), e)),
+            }
+        }
+        registry
+    }
+
+    /// Add a theme, replacing any theme with the same name
+    pub fn add(&mut self, theme: ColorTheme) {
+        self.themes.retain(|t| t.name != theme.name);
+        self.themes.push(theme);
+    }
+
+    pub fn themes(&self) -> &[ColorTheme] {
+        &self.themes
+    }
+
+    pub fn find(&self, name: &str) -> Option<ColorTheme> {
+        self.themes.iter().copied().find(|t| t.name == name)
+    }
+}
@@ -0,0 +1,251 @@
+use std::ops::Range;
+use eframe::egui::text::LayoutJob;
+use egui_code_editor::{format_token, ColorTheme, TokenType};
+
+/// A run of text highlighted as one kind of token
+#[derive(Clone, Debug, PartialEq)]
+pub struct HighlightSpan {
+    /// Byte range in the highlighted text
+    pub range: Range<usize>,
+    pub token: TokenType,
+}
+
+/// Token kind for a capture name from a tree-sitter highlights query, such
+/// as `function.method` or `punctuation.bracket`
+pub fn token_for_capture(name: &str) -> Option<TokenType> {
+    let base = name.split('.').next().unwrap_or(name);
+    match (base, name) {
+        ("comment", _) => Some(TokenType::Comment(false)),
+        ("keyword" | "include" | "repeat" | "conditional", _) => Some(TokenType::Keyword),
+        ("function" | "method" | "constructor", _) => Some(TokenType::Function),
+        ("type", _) => Some(TokenType::Type),
+        ("string" | "escape" | "character", _) => Some(TokenType::Str('"')),
+        ("number" | "float", _) | (_, "constant.numeric") => Some(TokenType::Numeric(false)),
+        ("constant" | "boolean" | "attribute" | "label" | "embedded", _) => Some(TokenType::Special),
+        ("punctuation" | "operator", _) => Some(TokenType::Punctuation(' ')),
+        ("variable" | "property" | "field", _) => Some(TokenType::Literal),
+        _ => None,
+    }
+}
+
+/// Layout job for `text` with the given spans; text outside the spans is
+/// shown in the literal color
+pub fn layout_job(text: &str, spans: &[HighlightSpan], theme: &ColorTheme, fontsize: f32) -> LayoutJob {
+    let mut job = LayoutJob::default();
+    let mut position = 0;
+    let append = |job: &mut LayoutJob, range: Range<usize>, token: TokenType| {
+        if let Some(section) = text.get(range).filter(|s| !s.is_empty()) {
+            job.append(section, 0.0, format_token(theme, fontsize, token));
+        }
+    };
+    for span in spans {
+        if span.range.start < position {
+            continue;
+        }
+        append(&mut job, position..span.range.start, TokenType::Literal);
+        append(&mut job, span.range.clone(), span.token);
+        position = span.range.end;
+    }
+    append(&mut job, position..text.len(), TokenType::Literal);
+    job
+}
+
+/// Spans from overlapping captures, given as byte range, query pattern
+/// index and token
+///
+/// Smaller captures win over the captures containing them (an escape inside
+/// a string), and among equal ranges the earliest pattern wins, as in
+/// tree-sitter's own highlighter.
+pub fn resolve_captures(len: usize, mut captures: Vec<(Range<usize>, usize, TokenType)>) -> Vec<HighlightSpan> {
+    captures.sort_by_key(|(range, pattern, _)| (std::cmp::Reverse(range.len()), std::cmp::Reverse(*pattern)));
+    let mut tokens: Vec<Option<TokenType>> = vec![None; len];
+    for (range, _, token) in captures {
+        let end = range.end.min(len);
+        for slot in &mut tokens[range.start.min(end)..end] {
+            *slot = Some(token);
+        }
+    }
+
+    let mut spans: Vec<HighlightSpan> = Vec::new();
+    for (index, token) in tokens.into_iter().enumerate() {
+        let Some(token) = token else {
+            continue;
+        };
+        match spans.last_mut() {
+            Some(last) if last.range.end == index && last.token == token => last.range.end += 1,
+            _ => spans.push(HighlightSpan { range: index..index + 1, token }),
+        }
+    }
+    spans
+}
+
+/// Byte range that changed between two versions of a text, as the range
+/// replaced in `old` and the length of its replacement in `new`
+pub fn changed_range(old: &str, new: &str) -> (Range<usize>, usize) {
+    let mut prefix = old
+        .bytes()
+        .zip(new.bytes())
+        .take_while(|(a, b)| a == b)
+        .count();
+    while !old.is_char_boundary(prefix) || !new.is_char_boundary(prefix) {
+        prefix -= 1;
+    }
+    let max_suffix = old.len().min(new.len()) - prefix;
+    let mut suffix = old
+        .bytes()
+        .rev()
+        .zip(new.bytes().rev())
+        .take(max_suffix)
+        .take_while(|(a, b)| a == b)
+        .count();
+    while !old.is_char_boundary(old.len() - suffix) || !new.is_char_boundary(new.len() - suffix) {
+        suffix -= 1;
+    }
+    (prefix..old.len() - suffix, new.len() - suffix - prefix)
+}
+
+/// Whether a tree-sitter grammar for the language is compiled in
+pub fn supports(language: &str) -> bool {
+    #[cfg(feature = "tree-sitter")]
+    {
+        backend::grammar(language).is_some()
+    }
+    #[cfg(not(feature = "tree-sitter"))]
+    {
+        let _ = language;
+        false
+    }
+}
+
+/// Highlighting from a syntax tree that is updated incrementally as the
+/// text is edited
+///
+/// Only available when Writer is built with the `tree-sitter` feature and
+/// the language has a grammar; otherwise the keyword-based `Syntax`
+/// highlighting is used.
+pub struct TreeHighlighter {
+    language: &'static str,
+    /// Text last parsed, or `None` before the first parse
+    text: Option<String>,
+    spans: Vec<HighlightSpan>,
+    #[cfg(feature = "tree-sitter")]
+    backend: backend::Backend,
+}
+
+impl TreeHighlighter {
+    /// Highlighter for a language name from the registry, if it has a grammar
+    pub fn new(language: &'static str) -> Option<Self> {
+        #[cfg(feature = "tree-sitter")]
+        {
+            let backend = backend::Backend::new(language)?;
+            Some(Self {
+                language,
+                text: None,
+                spans: Vec::new(),
+                backend,
+            })
+        }
+        #[cfg(not(feature = "tree-sitter"))]
+        {
+            let _ = language;
+            None
+        }
+    }
+
+    pub fn language(&self) -> &'static str {
+        self.language
+    }
+
+    /// Reparse after the text changed, reusing the unchanged parts of the tree
+    pub fn update(&mut self, text: &str) {
+        if self.text.as_deref() == Some(text) {
+            return;
+        }
+        #[cfg(feature = "tree-sitter")]
+        {
+            self.spans = self.backend.update(self.text.as_deref().unwrap_or_default(), text);
+        }
+        self.text = Some(text.to_string());
+    }
+
+    pub fn spans(&self) -> &[HighlightSpan] {
+        &self.spans
+    }
+
+    /// Layout job for the text last passed to `update`
+    pub fn layout_job(&self, theme: &ColorTheme, fontsize: f32) -> LayoutJob {
+        layout_job(self.text.as_deref().unwrap_or_default(), &self.spans, theme, fontsize)
+    }
+}
+
+#[cfg(feature = "tree-sitter")]
+mod backend {
+    use tree_sitter::{InputEdit, Language, Parser, Point, Query, QueryCursor, StreamingIterator, Tree};
+    use super::{changed_range, resolve_captures, token_for_capture, HighlightSpan};
+
+    /// Grammar and highlights query compiled in for a language
+    pub fn grammar(language: &str) -> Option<(Language, &'static str)> {
+        match language {
+            "Rust" => Some((tree_sitter_rust::LANGUAGE.into(), tree_sitter_rust::HIGHLIGHTS_QUERY)),
+            "Python" => Some((tree_sitter_python::LANGUAGE.into(), tree_sitter_python::HIGHLIGHTS_QUERY)),
+            "JSON" => Some((tree_sitter_json::LANGUAGE.into(), tree_sitter_json::HIGHLIGHTS_QUERY)),
+            "Shell" => Some((tree_sitter_bash::LANGUAGE.into(), tree_sitter_bash::HIGHLIGHT_QUERY)),
+            _ => None,
+        }
+    }
+
+    fn point(text: &str, byte: usize) -> Point {
+        let before = &text[..byte];
+        let row = before.matches('\n').count();
+        let column = byte - before.rfind('\n').map_or(0, |i| i + 1);
+        Point { row, column }
+    }
+
+    pub struct Backend {
+        parser: Parser,
+        query: Query,
+        tree: Option<Tree>,
+    }
+
+    impl Backend {
+        pub fn new(language: &str) -> Option<Self> {
+            let (grammar, highlights) = grammar(language)?;
+            let mut parser = Parser::new();
+            parser.set_language(&grammar).ok()?;
+            let query = Query::new(&grammar, highlights).ok()?;
+            Some(Self { parser, query, tree: None })
+        }
+
+        /// Parse `new`, editing the tree of `old` so unchanged subtrees are
+        /// reused, and return its highlight spans
+        pub fn update(&mut self, old: &str, new: &str) -> Vec<HighlightSpan> {
+            if let Some(tree) = &mut self.tree {
+                let (range, inserted) = changed_range(old, new);
+                tree.edit(&InputEdit {
+                    start_byte: range.start,
+                    old_end_byte: range.end,
+                    new_end_byte: range.start + inserted,
+                    start_position: point(old, range.start),
+                    old_end_position: point(old, range.end),
+                    new_end_position: point(new, range.start + inserted),
+                });
+            }
+            self.tree = self.parser.parse(new, self.tree.as_ref());
+            let Some(tree) = &self.tree else {
+                return Vec::new();
+            };
+
+            let names = self.query.capture_names();
+            let mut captures = Vec::new();
+            let mut cursor = QueryCursor::new();
+            let mut matches = cursor.captures(&self.query, tree.root_node(), new.as_bytes());
+            while let Some((found, index)) = matches.next() {
+                let capture = found.captures[*index];
+                if let Some(token) = token_for_capture(names[capture.index as usize]) {
+                    captures.push((capture.node.byte_range(), found.pattern_index, token));
+                }
+            }
+            resolve_captures(new.len(), captures)
+        }
+    }
+}
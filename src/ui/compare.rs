@@ -0,0 +1,213 @@
+use std::ops::Range;
+use eframe::egui::{self, Color32};
+use eframe::egui::text::{LayoutJob, TextFormat};
+use crate::compare::{CompareView, Side};
+use crate::diff::{self, DiffRow};
+
+/// What the user asked for in the compare window
+pub enum CompareAction {
+    Close,
+    /// Copy a hunk onto the given side
+    Copy(usize, Side),
+    /// Write a side back to its file
+    Save(Side),
+}
+
+const REMOVED: Color32 = Color32::from_rgba_premultiplied(60, 20, 20, 60);
+const REMOVED_STRONG: Color32 = Color32::from_rgba_premultiplied(130, 35, 35, 120);
+const ADDED: Color32 = Color32::from_rgba_premultiplied(20, 55, 25, 60);
+const ADDED_STRONG: Color32 = Color32::from_rgba_premultiplied(35, 115, 45, 120);
+
+/// Render the diff of two texts in a window
+pub fn render_compare_window(ctx: &egui::Context, view: &mut CompareView) -> Option<CompareAction> {
+    let mut action = None;
+    let mut open = true;
+    egui::Window::new("Compare")
+        .open(&mut open)
+        .default_size([900.0, 600.0])
+        .resizable(true)
+        .collapsible(false)
+        .show(ctx, |ui| {
+            render_toolbar(ui, view, &mut action);
+            ui.separator();
+            render_rows(ui, view);
+        });
+    if !open {
+        action = Some(CompareAction::Close);
+    }
+    action
+}
+
+fn render_toolbar(ui: &mut egui::Ui, view: &mut CompareView, action: &mut Option<CompareAction>) {
+    let title = |side: Side| {
+        let side = view.side(side);
+        format!("{}{}", side.title, if side.modified { " *" } else { "" })
+    };
+    ui.horizontal(|ui| {
+        ui.strong(title(Side::Left));
+        ui.weak("↔");
+        ui.strong(title(Side::Right));
+    });
+    ui.horizontal(|ui| {
+        let mut unified = view.unified;
+        ui.selectable_value(&mut unified, false, "Side by Side");
+        ui.selectable_value(&mut unified, true, "Unified");
+        if unified != view.unified {
+            view.set_unified(unified);
+        }
+        ui.separator();
+        let has_hunks = !view.hunks.is_empty();
+        if ui.add_enabled(has_hunks, egui::Button::new("⏶ Previous")).clicked() {
+            view.step(false);
+        }
+        if ui.add_enabled(has_hunks, egui::Button::new("⏷ Next")).clicked() {
+            view.step(true);
+        }
+        match view.current {
+            _ if !has_hunks => ui.weak("No differences"),
+            Some(index) => ui.weak(format!("Change {} of {}", index + 1, view.hunks.len())),
+            None => ui.weak(format!("{} changes", view.hunks.len())),
+        };
+        ui.separator();
+        if let Some(index) = view.current {
+            if ui.button("Copy to Right →").on_hover_text("Replace the change on the right with the left side").clicked() {
+                *action = Some(CompareAction::Copy(index, Side::Right));
+            }
+            if ui.button("← Copy to Left").on_hover_text("Replace the change on the left with the right side").clicked() {
+                *action = Some(CompareAction::Copy(index, Side::Left));
+            }
+        }
+        for side in [Side::Left, Side::Right] {
+            let compared = view.side(side);
+            if compared.modified && compared.source.can_save() {
+                let label = if side == Side::Left { "Save Left" } else { "Save Right" };
+                if ui.button(label).clicked() {
+                    *action = Some(CompareAction::Save(side));
+                }
+            }
+        }
+    });
+}
+
+fn render_rows(ui: &mut egui::Ui, view: &mut CompareView) {
+    let font = egui::TextStyle::Monospace.resolve(ui.style());
+    let (row_height, char_width) = ui.fonts_mut(|fonts| (fonts.row_height(&font), fonts.glyph_width(&font, '0')));
+    let old_lines = diff::split_lines(&view.left.text);
+    let new_lines = diff::split_lines(&view.right.text);
+    let digits = old_lines.len().max(new_lines.len()).to_string().len();
+    let number_width = (digits + 1) as f32 * char_width;
+
+    let mut scroll = egui::ScrollArea::vertical().auto_shrink([false, false]);
+    if std::mem::take(&mut view.scroll_to_current)
+        && let Some(row) = view.current_row()
+    {
+        // Leave a few rows of context above the change
+        scroll = scroll.vertical_scroll_offset((row.saturating_sub(3)) as f32 * row_height);
+    }
+    let mut clicked_hunk = None;
+    scroll.show_rows(ui, row_height, view.rows.len(), |ui, range| {
+        ui.spacing_mut().item_spacing.y = 0.0;
+        for row in &view.rows[range] {
+            let width = ui.available_width();
+            let (rect, response) = ui.allocate_exact_size(egui::vec2(width, row_height), egui::Sense::click());
+            if response.clicked() && row.hunk.is_some() {
+                clicked_hunk = row.hunk;
+            }
+            let painter = ui.painter_at(rect);
+            let (old_changes, new_changes) = inline_ranges(view, row, &old_lines, &new_lines);
+            let old = row.old.map(|line| (line, old_lines[line], old_changes));
+            let new = row.new.map(|line| (line, new_lines[line], new_changes));
+            let changed = row.hunk.is_some();
+            if view.unified {
+                let (text, changes, background, strong, prefix) = match (old, new) {
+                    (Some((_, text, changes)), None) => (text, changes, REMOVED, REMOVED_STRONG, "-"),
+                    (None, Some((_, text, changes))) => (text, changes, ADDED, ADDED_STRONG, "+"),
+                    (Some((_, text, _)), Some(_)) => (text, Vec::new(), Color32::TRANSPARENT, Color32::TRANSPARENT, " "),
+                    (None, None) => continue,
+                };
+                painter.rect_filled(rect, 0.0, background);
+                let numbers = |line: Option<usize>| line.map(|line| (line + 1).to_string()).unwrap_or_default();
+                let numbers_rect = egui::Rect::from_min_size(rect.min, egui::vec2(2.0 * number_width, row_height));
+                paint_number(ui, &painter, numbers_rect.left_top() + egui::vec2(number_width, 0.0), &numbers(row.old));
+                paint_number(ui, &painter, numbers_rect.right_top(), &numbers(row.new));
+                let text_pos = numbers_rect.right_top() + egui::vec2(char_width, 0.0);
+                painter.text(text_pos, egui::Align2::LEFT_TOP, prefix, font.clone(), ui.visuals().weak_text_color());
+                paint_text(ui, &painter, text_pos + egui::vec2(2.0 * char_width, 0.0), text, &changes, strong);
+            } else {
+                let half = (rect.width() - 4.0) / 2.0;
+                let left = egui::Rect::from_min_size(rect.min, egui::vec2(half, row_height));
+                let right = egui::Rect::from_min_size(rect.min + egui::vec2(half + 4.0, 0.0), egui::vec2(half, row_height));
+                for (cell, content, background, strong) in
+                    [(left, old, REMOVED, REMOVED_STRONG), (right, new, ADDED, ADDED_STRONG)]
+                {
+                    let painter = ui.painter_at(cell);
+                    match content {
+                        Some((line, text, changes)) => {
+                            if changed {
+                                painter.rect_filled(cell, 0.0, background);
+                            }
+                            paint_number(ui, &painter, cell.left_top() + egui::vec2(number_width, 0.0), &(line + 1).to_string());
+                            let text_pos = cell.left_top() + egui::vec2(number_width + char_width, 0.0);
+                            paint_text(ui, &painter, text_pos, text, &changes, strong);
+                        }
+                        // Padding opposite lines the other side does not have
+                        None => {
+                            painter.rect_filled(cell, 0.0, ui.visuals().faint_bg_color);
+                        }
+                    }
+                }
+            }
+            if changed && row.hunk == view.current {
+                painter.rect_filled(
+                    egui::Rect::from_min_size(rect.min, egui::vec2(3.0, row_height)),
+                    0.0,
+                    ui.visuals().selection.stroke.color,
+                );
+            }
+        }
+    });
+    if clicked_hunk.is_some() {
+        view.current = clicked_hunk;
+    }
+}
+
+/// Changed characters of a modified line against the line it replaced
+fn inline_ranges(view: &CompareView, row: &DiffRow, old_lines: &[&str], new_lines: &[&str]) -> (Vec<Range<usize>>, Vec<Range<usize>>) {
+    let Some(hunk) = row.hunk.and_then(|index| view.hunks.get(index)) else {
+        return (Vec::new(), Vec::new());
+    };
+    // Lines of a hunk are paired up in order
+    let offset = match (row.old, row.new) {
+        (Some(old), _) => old - hunk.old.start,
+        (None, Some(new)) => new - hunk.new.start,
+        (None, None) => return (Vec::new(), Vec::new()),
+    };
+    let (old, new) = (hunk.old.start + offset, hunk.new.start + offset);
+    if !hunk.old.contains(&old) || !hunk.new.contains(&new) {
+        return (Vec::new(), Vec::new());
+    }
+    diff::inline_changes(old_lines[old], new_lines[new])
+}
+
+fn paint_number(ui: &egui::Ui, painter: &egui::Painter, right_top: egui::Pos2, number: &str) {
+    let font = egui::TextStyle::Monospace.resolve(ui.style());
+    let pos = right_top - egui::vec2(0.5 * font.size, 0.0);
+    painter.text(pos, egui::Align2::RIGHT_TOP, number, font, ui.visuals().weak_text_color());
+}
+
+/// A line with its changed characters on a stronger background
+fn paint_text(ui: &egui::Ui, painter: &egui::Painter, pos: egui::Pos2, text: &str, changes: &[Range<usize>], strong: Color32) {
+    let font = egui::TextStyle::Monospace.resolve(ui.style());
+    let plain = TextFormat::simple(font.clone(), ui.visuals().text_color());
+    let highlighted = TextFormat { background: strong, ..plain.clone() };
+    let mut job = LayoutJob::default();
+    let mut end = 0;
+    for range in changes {
+        job.append(&text[end..range.start], 0.0, plain.clone());
+        job.append(&text[range.clone()], 0.0, highlighted.clone());
+        end = range.end;
+    }
+    job.append(&text[end..], 0.0, plain);
+    let galley = ui.fonts_mut(|fonts| fonts.layout_job(job));
+    painter.galley(pos, galley, ui.visuals().text_color());
+}
@@ -0,0 +1,205 @@
+use eframe::egui::{self, Color32};
+use eframe::egui::text_edit::TextEditOutput;
+use crate::conflicts::{self, Conflict, ConflictState, Resolution};
+use crate::folding::FoldedView;
+use crate::ui::editor::char_rect;
+
+/// What was clicked next to a conflict in the editor
+pub enum ConflictLens {
+    Accept(Resolution),
+    /// Open the conflict in the merge view
+    Merge,
+}
+
+/// What the user asked for in the merge view
+pub enum MergeAction {
+    Resolve(usize, Resolution),
+    /// Replace a conflict with the hand-merged result
+    UseResult(usize),
+}
+
+const OURS: Color32 = Color32::from_rgba_premultiplied(20, 55, 25, 50);
+const BASE: Color32 = Color32::from_rgba_premultiplied(45, 45, 45, 50);
+const THEIRS: Color32 = Color32::from_rgba_premultiplied(20, 40, 70, 50);
+const MARKER: Color32 = Color32::from_rgba_premultiplied(70, 70, 70, 80);
+
+/// Render the bar telling that the file has merge conflicts; true when
+/// the merge view was asked for
+pub fn render_conflict_bar(ctx: &egui::Context, count: usize) -> bool {
+    let mut open = false;
+    egui::TopBottomPanel::top("conflict_bar").show(ctx, |ui| {
+        ui.horizontal(|ui| {
+            ui.colored_label(Color32::from_rgb(220, 180, 50), "⚠");
+            ui.label(if count == 1 {
+                "This file has a merge conflict".to_string()
+            } else {
+                format!("This file has {} merge conflicts", count)
+            });
+            open = ui.button("Open Merge View").clicked();
+        });
+    });
+    open
+}
+
+/// Tint the sides of every conflict in the editor and offer to resolve it
+/// after its opening marker; returns the conflict and what was clicked
+pub fn paint_conflicts(
+    ui: &egui::Ui,
+    output: &TextEditOutput,
+    text: &str,
+    view: &FoldedView,
+    conflicts: &[Conflict],
+) -> Option<(usize, ConflictLens)> {
+    let last = conflicts.last()?;
+    // Buffer char index of every line start up to the last conflict
+    let mut line_starts = vec![0];
+    for (index, c) in text.chars().enumerate() {
+        if line_starts.len() > last.end + 1 {
+            break;
+        }
+        if c == '\n' {
+            line_starts.push(index + 1);
+        }
+    }
+    let painter = ui.painter().with_clip_rect(output.text_clip_rect);
+    let left = output.text_clip_rect.left();
+    let right = output.text_clip_rect.right();
+    let mut clicked = None;
+    for (index, conflict) in conflicts.iter().enumerate() {
+        for line in conflict.start..=conflict.end {
+            let Some(&start) = line_starts.get(line) else {
+                break;
+            };
+            if !view.is_visible(start) {
+                continue;
+            }
+            let row = char_rect(output, view.to_display(start));
+            let color = if conflict.ours().contains(&line) {
+                OURS
+            } else if conflict.theirs().contains(&line) {
+                THEIRS
+            } else if conflict.base_lines().is_some_and(|lines| lines.contains(&line)) {
+                BASE
+            } else {
+                MARKER
+            };
+            painter.rect_filled(egui::Rect::from_x_y_ranges(left..=right, row.y_range()), 0.0, color);
+        }
+
+        let Some(&start) = line_starts.get(conflict.start) else {
+            continue;
+        };
+        if !view.is_visible(start) {
+            continue;
+        }
+        // Actions follow the opening marker on its line
+        let line_end = line_starts.get(conflict.start + 1).map_or(start, |next| next - 1);
+        let end_rect = char_rect(output, view.to_display(line_end));
+        let font = egui::TextStyle::Small.resolve(ui.style());
+        let mut x = end_rect.left() + 2.0 * end_rect.height();
+        let mut lenses = vec![
+            ("Accept Ours", ConflictLens::Accept(Resolution::Ours)),
+            ("Accept Theirs", ConflictLens::Accept(Resolution::Theirs)),
+            ("Accept Both", ConflictLens::Accept(Resolution::Both)),
+        ];
+        if conflict.base.is_some() {
+            lenses.push(("Accept Base", ConflictLens::Accept(Resolution::Base)));
+        }
+        lenses.push(("Merge…", ConflictLens::Merge));
+        for (lens_index, (label, lens)) in lenses.into_iter().enumerate() {
+            let galley = painter.layout_no_wrap(label.to_string(), font.clone(), ui.visuals().hyperlink_color);
+            let rect = egui::Rect::from_min_size(
+                egui::pos2(x, end_rect.center().y - galley.size().y / 2.0),
+                galley.size(),
+            );
+            let response = ui.interact(rect, ui.id().with(("conflict_lens", index, lens_index)), egui::Sense::click());
+            let color = if response.hovered() { ui.visuals().strong_text_color() } else { ui.visuals().hyperlink_color };
+            painter.galley_with_override_text_color(rect.min, galley, color);
+            if response.clicked() {
+                clicked = Some((index, lens));
+            }
+            x = rect.right() + font.size;
+        }
+    }
+    clicked
+}
+
+/// Render the three-way merge view for the current conflict
+pub fn render_merge_view(ctx: &egui::Context, state: &mut ConflictState, text: &str) -> Option<MergeAction> {
+    let mut action = None;
+    let mut open = state.show_merge_view;
+    let count = state.conflicts.len();
+    let Some(conflict) = state.conflicts.get(state.current).cloned() else {
+        state.show_merge_view = false;
+        return None;
+    };
+    // Start the result from our side whenever another conflict is shown
+    if state.result_for.as_ref() != Some(&conflict) {
+        state.result = conflicts::resolved_text(text, &conflict, Resolution::Ours);
+        state.result_for = Some(conflict.clone());
+    }
+    egui::Window::new("Merge Conflicts")
+        .open(&mut open)
+        .default_size([900.0, 550.0])
+        .resizable(true)
+        .collapsible(false)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                if ui.add_enabled(count > 1, egui::Button::new("⏶ Previous")).clicked() {
+                    state.current = (state.current + count - 1) % count;
+                }
+                if ui.add_enabled(count > 1, egui::Button::new("⏷ Next")).clicked() {
+                    state.current = (state.current + 1) % count;
+                }
+                ui.weak(format!("Conflict {} of {}, line {}", state.current + 1, count, conflict.start + 1));
+            });
+            ui.separator();
+            let mut sides = vec![(
+                format!("Ours ({})", conflict.ours_label),
+                conflict.ours(),
+                Resolution::Ours,
+            )];
+            if let Some(base) = conflict.base_lines() {
+                sides.push(("Base".to_string(), base, Resolution::Base));
+            }
+            sides.push((format!("Theirs ({})", conflict.theirs_label), conflict.theirs(), Resolution::Theirs));
+            let pane_height = (ui.available_height() * 0.5).max(120.0);
+            ui.columns(sides.len(), |columns| {
+                for (column, (title, lines, resolution)) in columns.iter_mut().zip(sides) {
+                    column.horizontal(|ui| {
+                        ui.strong(title);
+                        if ui.small_button("Accept").clicked() {
+                            action = Some(MergeAction::Resolve(state.current, resolution));
+                        }
+                    });
+                    egui::ScrollArea::both()
+                        .id_salt(("merge_side", resolution as u8))
+                        .max_height(pane_height)
+                        .auto_shrink([false, true])
+                        .show(column, |ui| {
+                            ui.label(egui::RichText::new(conflicts::lines_text(text, lines)).monospace());
+                        });
+                }
+            });
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.strong("Result");
+                if ui.button("Accept Both").clicked() {
+                    action = Some(MergeAction::Resolve(state.current, Resolution::Both));
+                }
+                if ui.button("Use Result").on_hover_text("Replace the conflict with the text below").clicked() {
+                    action = Some(MergeAction::UseResult(state.current));
+                }
+            });
+            egui::ScrollArea::vertical().id_salt("merge_result").show(ui, |ui| {
+                ui.add(
+                    egui::TextEdit::multiline(&mut state.result)
+                        .code_editor()
+                        .desired_width(f32::INFINITY)
+                        .desired_rows(8),
+                );
+            });
+        });
+    state.show_merge_view &= open;
+    action
+}
@@ -1,5 +1,12 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
 use eframe::egui;
+use egui_code_editor::ColorTheme;
 use crate::actions::ConfirmationAction;
+use crate::keybindings::Command;
+use crate::outline::{filter_symbols, Symbol};
+use crate::filter::ShellCommandKind;
+use crate::settings::{Appearance, Settings, MAX_ZOOM, MIN_ZOOM};
 
 /// Render the About dialog window
 pub fn render_about_dialog(
@@ -114,6 +121,40 @@ pub fn render_open_dialog(
     )
 }
 
+/// Render the warning shown before saving a file that still has merge
+/// conflicts; true when the user chose to save anyway
+pub fn render_conflict_save_dialog(ctx: &egui::Context, show: &mut bool, count: usize) -> bool {
+    let mut save = false;
+    let mut close = false;
+    if *show {
+        egui::Window::new("Unresolved Conflicts")
+            .open(show)
+            .resizable(false)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "This file still contains {} unresolved merge conflict{}. Save it with the conflict markers?",
+                    count,
+                    if count == 1 { "" } else { "s" }
+                ));
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.button("Save Anyway").clicked() {
+                        save = true;
+                        close = true;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        close = true;
+                    }
+                });
+            });
+        if close {
+            *show = false;
+        }
+    }
+    save
+}
+
 /// Render the error dialog
 pub fn render_error_dialog(
     ctx: &egui::Context,
@@ -161,22 +202,23 @@ pub fn render_goto_line_dialog(
                     // Focus the input field when dialog opens
                     response.request_focus();
                     
-                    if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
-                        if let Ok(line) = line_input.trim().parse::<usize>() {
-                            target_line = Some(line);
-                            close_requested = true;
-                        }
+                    if response.lost_focus()
+                        && ui.input(|i| i.key_pressed(egui::Key::Enter))
+                        && let Ok(line) = line_input.trim().parse::<usize>()
+                    {
+                        target_line = Some(line);
+                        close_requested = true;
                     }
                 });
                 
                 ui.separator();
                 
                 ui.horizontal(|ui| {
-                    if ui.button("Go").clicked() {
-                        if let Ok(line) = line_input.trim().parse::<usize>() {
-                            target_line = Some(line);
-                            close_requested = true;
-                        }
+                    if ui.button("Go").clicked()
+                        && let Ok(line) = line_input.trim().parse::<usize>()
+                    {
+                        target_line = Some(line);
+                        close_requested = true;
                     }
                     
                     if ui.button("Cancel").clicked() {
@@ -192,3 +234,445 @@ pub fn render_goto_line_dialog(
 
     target_line
 }
+
+/// Render the Rename Symbol dialog; returns the new name when confirmed
+pub fn render_rename_dialog(ctx: &egui::Context, show_dialog: &mut bool, name_input: &mut String) -> Option<String> {
+    let mut new_name = None;
+    let mut close_requested = false;
+
+    if *show_dialog {
+        egui::Window::new("Rename Symbol")
+            .open(show_dialog)
+            .resizable(false)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("New name:");
+                    let response = ui.text_edit_singleline(name_input);
+                    response.request_focus();
+                    if response.lost_focus()
+                        && ui.input(|i| i.key_pressed(egui::Key::Enter))
+                        && !name_input.trim().is_empty()
+                    {
+                        new_name = Some(name_input.trim().to_string());
+                        close_requested = true;
+                    }
+                });
+
+                ui.separator();
+
+                ui.horizontal(|ui| {
+                    if ui.button("Rename").clicked() && !name_input.trim().is_empty() {
+                        new_name = Some(name_input.trim().to_string());
+                        close_requested = true;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        close_requested = true;
+                    }
+                });
+            });
+
+        if close_requested {
+            *show_dialog = false;
+        }
+    }
+
+    new_name
+}
+
+/// Render the dialog asking for a shell command; returns the command when
+/// confirmed
+pub fn render_shell_command_dialog(
+    ctx: &egui::Context,
+    show_dialog: &mut bool,
+    kind: ShellCommandKind,
+    command_input: &mut String,
+) -> Option<String> {
+    let mut command = None;
+    let mut close_requested = false;
+
+    if *show_dialog {
+        egui::Window::new(kind.label())
+            .open(show_dialog)
+            .resizable(false)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.label(kind.description());
+                ui.horizontal(|ui| {
+                    ui.label("Command:");
+                    let response = ui.add(egui::TextEdit::singleline(command_input).desired_width(300.0).code_editor());
+                    response.request_focus();
+                    if response.lost_focus()
+                        && ui.input(|i| i.key_pressed(egui::Key::Enter))
+                        && !command_input.trim().is_empty()
+                    {
+                        command = Some(command_input.trim().to_string());
+                        close_requested = true;
+                    }
+                });
+
+                ui.separator();
+
+                ui.horizontal(|ui| {
+                    if ui.button("Run").clicked() && !command_input.trim().is_empty() {
+                        command = Some(command_input.trim().to_string());
+                        close_requested = true;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        close_requested = true;
+                    }
+                });
+            });
+
+        if close_requested {
+            *show_dialog = false;
+        }
+    }
+
+    command
+}
+
+/// Render the window shown while a shell command runs; returns true when
+/// the user cancels it
+pub fn render_shell_job_window(ctx: &egui::Context, command: &str, elapsed: std::time::Duration) -> bool {
+    let mut cancel = false;
+    egui::Window::new("Running Command")
+        .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-16.0, -40.0))
+        .resizable(false)
+        .collapsible(false)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.monospace(command);
+            });
+            ui.label(format!("{} s", elapsed.as_secs()));
+            if ui.button("Cancel").clicked() {
+                cancel = true;
+            }
+        });
+    cancel
+}
+
+/// Render the Go to Symbol dialog; returns the line of the chosen symbol
+pub fn render_goto_symbol_dialog(
+    ctx: &egui::Context,
+    show_dialog: &mut bool,
+    query: &mut String,
+    selected: &mut usize,
+    symbols: &[Symbol],
+) -> Option<usize> {
+    let mut target_line = None;
+    let mut close_requested = false;
+
+    if *show_dialog {
+        let matches = filter_symbols(symbols, query);
+        *selected = (*selected).min(matches.len().saturating_sub(1));
+        let (up, down, enter, escape) = ctx.input(|i| {
+            (
+                i.key_pressed(egui::Key::ArrowUp),
+                i.key_pressed(egui::Key::ArrowDown),
+                i.key_pressed(egui::Key::Enter),
+                i.key_pressed(egui::Key::Escape),
+            )
+        });
+        if up {
+            *selected = selected.saturating_sub(1);
+        }
+        if down && *selected + 1 < matches.len() {
+            *selected += 1;
+        }
+        if enter && let Some(symbol) = matches.get(*selected) {
+            target_line = Some(symbol.line);
+            close_requested = true;
+        }
+        if escape {
+            close_requested = true;
+        }
+
+        egui::Window::new("Go to Symbol")
+            .open(show_dialog)
+            .resizable(false)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                let response = ui.add(egui::TextEdit::singleline(query).hint_text("Symbol name"));
+                response.request_focus();
+                if response.changed() {
+                    *selected = 0;
+                }
+                ui.separator();
+                if matches.is_empty() {
+                    ui.weak("No matching symbols");
+                }
+                egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                    for (index, symbol) in matches.iter().enumerate() {
+                        let label = format!("{} {}", symbol.kind.label(), symbol.name);
+                        let item = ui.selectable_label(index == *selected, label);
+                        if index == *selected && (up || down) {
+                            item.scroll_to_me(None);
+                        }
+                        if item.on_hover_text(format!("Line {}", symbol.line + 1)).clicked() {
+                            target_line = Some(symbol.line);
+                            close_requested = true;
+                        }
+                    }
+                });
+            });
+
+        if close_requested {
+            *show_dialog = false;
+        }
+    }
+
+    target_line
+}
+
+/// Pages of the Preferences window
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PreferencesPage {
+    Editor,
+    Appearance,
+    Files,
+    Terminal,
+    Keybindings,
+}
+
+/// Settings being edited in the Preferences window
+pub struct PreferencesState {
+    pub draft: Settings,
+    pub page: PreferencesPage,
+    /// Shortcuts as typed, by command
+    pub keys: BTreeMap<Command, String>,
+    /// Font paths as typed; empty for the built-in font
+    pub monospace_font: String,
+    pub proportional_font: String,
+    /// Problems found when the draft was last applied
+    pub errors: Vec<String>,
+}
+
+impl PreferencesState {
+    pub fn new(settings: &Settings) -> Self {
+        let keys = Command::ALL
+            .into_iter()
+            .map(|command| {
+                let keys = settings
+                    .keybindings
+                    .get(command.id())
+                    .map_or(command.default_keys(), String::as_str);
+                (command, keys.to_string())
+            })
+            .collect();
+        let path_text = |path: &Option<PathBuf>| {
+            path.as_ref().map(|p| p.display().to_string()).unwrap_or_default()
+        };
+        Self {
+            draft: settings.clone(),
+            page: PreferencesPage::Editor,
+            keys,
+            monospace_font: path_text(&settings.appearance.monospace_font),
+            proportional_font: path_text(&settings.appearance.proportional_font),
+            errors: Vec::new(),
+        }
+    }
+
+    /// The edited settings; only shortcuts that differ from the defaults
+    /// are kept as overrides
+    pub fn settings(&self) -> Settings {
+        let mut settings = self.draft.clone();
+        settings.keybindings = self
+            .keys
+            .iter()
+            .filter(|(command, keys)| keys.split_whitespace().ne(command.default_keys().split_whitespace()))
+            .map(|(command, keys)| (command.id().to_string(), keys.trim().to_string()))
+            .collect();
+        let path = |text: &str| (!text.trim().is_empty()).then(|| PathBuf::from(text.trim()));
+        settings.appearance.monospace_font = path(&self.monospace_font);
+        settings.appearance.proportional_font = path(&self.proportional_font);
+        settings
+    }
+}
+
+/// What the user did in the Preferences window
+pub enum PreferencesAction {
+    None,
+    /// Use these settings and keep the window open
+    Apply(Settings),
+    /// Use these settings and close the window
+    ApplyAndClose(Settings),
+    Close,
+}
+
+/// Render the Preferences window
+///
+/// Settings are validated before they are handed back; problems are listed
+/// in the window instead.
+pub fn render_preferences_dialog(
+    ctx: &egui::Context,
+    state: &mut PreferencesState,
+    themes: &[ColorTheme],
+) -> PreferencesAction {
+    let mut action = PreferencesAction::None;
+    let mut open = true;
+
+    egui::Window::new("Preferences")
+        .open(&mut open)
+        .collapsible(false)
+        .default_width(420.0)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                for (page, label) in [
+                    (PreferencesPage::Editor, "Editor"),
+                    (PreferencesPage::Appearance, "Appearance"),
+                    (PreferencesPage::Files, "Files"),
+                    (PreferencesPage::Terminal, "Terminal"),
+                    (PreferencesPage::Keybindings, "Keybindings"),
+                ] {
+                    ui.selectable_value(&mut state.page, page, label);
+                }
+            });
+            ui.separator();
+
+            egui::ScrollArea::vertical().max_height(360.0).show(ui, |ui| match state.page {
+                PreferencesPage::Editor => render_editor_page(ui, &mut state.draft),
+                PreferencesPage::Appearance => render_appearance_page(ui, state, themes),
+                PreferencesPage::Files => {
+                    let files = &mut state.draft.files;
+                    ui.checkbox(&mut files.detect_indentation, "Detect indentation of opened files");
+                    ui.checkbox(&mut files.trim_trailing_whitespace, "Trim trailing whitespace on save");
+                    ui.checkbox(&mut files.insert_final_newline, "Insert final newline on save");
+                    ui.checkbox(&mut files.format_on_save, "Format on save");
+                    ui.separator();
+                    ui.checkbox(&mut files.keep_history, "Keep local history of saved versions");
+                    ui.add_enabled_ui(files.keep_history, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label("Versions per file:");
+                            ui.add(egui::DragValue::new(&mut files.history_versions).range(1..=1000));
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Drop versions after days:");
+                            ui.add(egui::DragValue::new(&mut files.history_days).range(0..=3650));
+                        })
+                        .response
+                        .on_hover_text("0 keeps versions regardless of age");
+                    });
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        ui.label("Large-file mode from MB:");
+                        ui.add(egui::DragValue::new(&mut files.large_file_mb).range(0..=4096));
+                    })
+                    .response
+                    .on_hover_text("Larger files open without highlighting or language features; 0 turns this off");
+                }
+                PreferencesPage::Terminal => {
+                    let terminal = &mut state.draft.terminal;
+                    ui.horizontal(|ui| {
+                        ui.label("Shell:");
+                        ui.add(egui::TextEdit::singleline(&mut terminal.shell).hint_text("login shell"));
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Scrollback lines:");
+                        ui.add(egui::DragValue::new(&mut terminal.scrollback).range(0..=100_000));
+                    });
+                    ui.checkbox(&mut terminal.start_in_project_root, "Start in the project root");
+                    ui.weak("Changes apply to the next shell started.");
+                }
+                PreferencesPage::Keybindings => {
+                    ui.label("Separate alternative shortcuts with spaces; leave empty to unbind.");
+                    egui::Grid::new("keybindings_grid").num_columns(2).show(ui, |ui| {
+                        for (command, keys) in state.keys.iter_mut() {
+                            ui.label(command.label());
+                            ui.add(egui::TextEdit::singleline(keys).hint_text(command.default_keys()));
+                            ui.end_row();
+                        }
+                    });
+                }
+            });
+
+            if !state.errors.is_empty() {
+                ui.separator();
+                for error in &state.errors {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
+            }
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                let ok = ui.button("OK").clicked();
+                let apply = ui.button("Apply").clicked();
+                if ui.button("Cancel").clicked() {
+                    action = PreferencesAction::Close;
+                }
+                if ok || apply {
+                    let settings = state.settings();
+                    state.errors = settings.validate();
+                    if state.errors.is_empty() {
+                        action = if ok {
+                            PreferencesAction::ApplyAndClose(settings)
+                        } else {
+                            PreferencesAction::Apply(settings)
+                        };
+                    }
+                }
+            });
+        });
+
+    if !open {
+        action = PreferencesAction::Close;
+    }
+    action
+}
+
+fn render_editor_page(ui: &mut egui::Ui, settings: &mut Settings) {
+    let editor = &mut settings.editor;
+    ui.checkbox(&mut editor.show_line_numbers, "Line numbers");
+    ui.checkbox(&mut editor.syntax_highlighting, "Syntax highlighting");
+    ui.checkbox(&mut editor.auto_indent, "Auto indent");
+    ui.checkbox(&mut editor.auto_close_brackets, "Auto-close brackets");
+    ui.horizontal(|ui| {
+        ui.label("Tab size:");
+        ui.add(egui::DragValue::new(&mut editor.tab_size).range(1..=16));
+        ui.checkbox(&mut editor.insert_spaces, "Insert spaces");
+    });
+    ui.horizontal(|ui| {
+        ui.checkbox(&mut editor.word_wrap, "Word wrap at column");
+        ui.add(egui::DragValue::new(&mut editor.wrap_column).range(0..=1000));
+        ui.label("(0 = window width)");
+    });
+}
+
+fn render_appearance_page(ui: &mut egui::Ui, state: &mut PreferencesState, themes: &[ColorTheme]) {
+    let appearance = &mut state.draft.appearance;
+    ui.horizontal(|ui| {
+        ui.label("Interface:");
+        for option in [Appearance::System, Appearance::Light, Appearance::Dark] {
+            ui.radio_value(&mut appearance.appearance, option, option.label());
+        }
+    });
+    egui::Grid::new("appearance_grid").num_columns(2).show(ui, |ui| {
+        for (label, selected, dark) in [
+            ("Dark theme:", &mut appearance.dark_theme, true),
+            ("Light theme:", &mut appearance.light_theme, false),
+        ] {
+            ui.label(label);
+            egui::ComboBox::from_id_salt(label)
+                .selected_text(selected.as_str())
+                .show_ui(ui, |ui| {
+                    for theme in themes.iter().filter(|t| t.dark == dark) {
+                        ui.selectable_value(selected, theme.name.to_string(), theme.name);
+                    }
+                });
+            ui.end_row();
+        }
+        ui.label("Font size:");
+        ui.add(egui::DragValue::new(&mut appearance.font_size).range(6.0..=72.0));
+        ui.end_row();
+        ui.label("Zoom:");
+        ui.add(egui::Slider::new(&mut appearance.zoom, MIN_ZOOM..=MAX_ZOOM).step_by(0.1));
+        ui.end_row();
+        ui.label("Editor font file:");
+        ui.add(egui::TextEdit::singleline(&mut state.monospace_font).hint_text("built-in"));
+        ui.end_row();
+        ui.label("Interface font file:");
+        ui.add(egui::TextEdit::singleline(&mut state.proportional_font).hint_text("built-in"));
+        ui.end_row();
+    });
+}
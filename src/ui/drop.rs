@@ -0,0 +1,25 @@
+use eframe::egui::{self, Color32};
+
+/// Dim the window and tell what dropping the hovered files would do
+pub fn render_drop_overlay(ctx: &egui::Context, hovered: &[egui::HoveredFile]) {
+    let Some(first) = hovered.first() else {
+        return;
+    };
+    let message = match &first.path {
+        Some(path) => {
+            let name = path.file_name().map_or_else(|| path.display().to_string(), |name| name.to_string_lossy().into_owned());
+            format!("Drop to open {}", name)
+        }
+        None => "Drop to insert text".to_string(),
+    };
+    let painter = ctx.layer_painter(egui::LayerId::new(egui::Order::Foreground, egui::Id::new("drop_overlay")));
+    let rect = ctx.content_rect();
+    painter.rect_filled(rect, 0.0, Color32::from_black_alpha(160));
+    painter.text(
+        rect.center(),
+        egui::Align2::CENTER_CENTER,
+        message,
+        egui::TextStyle::Heading.resolve(&ctx.style()),
+        Color32::WHITE,
+    );
+}
@@ -0,0 +1,422 @@
+use std::sync::Arc;
+use eframe::egui;
+use eframe::egui::text::CCursor;
+use eframe::egui::text_edit::TextEditOutput;
+use eframe::epaint::Galley;
+use egui_code_editor::highlighting::highlight;
+use egui_code_editor::{CodeEditor, ColorTheme, Syntax, TokenType};
+use crate::diff::{self, ChangeKind, Hunk};
+use crate::folding::FoldState;
+use crate::treesitter::TreeHighlighter;
+use crate::wrap::{self, WrapMode};
+
+/// Code editor with a gutter showing line numbers and fold markers
+///
+/// Highlighting is the same as `egui_code_editor::CodeEditor` unless a
+/// tree-sitter highlighter is given, but the gutter is painted from the laid-out rows so that line numbers follow
+/// buffer lines even when regions are folded.
+pub struct EditorView<'a> {
+    pub id: &'a str,
+    pub theme: ColorTheme,
+    pub syntax: Syntax,
+    /// Highlighter parsing the shown text, used instead of `syntax`
+    pub tree: Option<&'a mut TreeHighlighter>,
+    pub fontsize: f32,
+    pub rows: usize,
+    pub show_line_numbers: bool,
+    pub folds: &'a FoldState,
+    pub wrap: WrapMode,
+    /// Displayed char index to scroll into view
+    pub scroll_to: Option<usize>,
+    /// Changed lines to mark in the gutter, with the text they replaced;
+    /// `None` when the file is not tracked by git
+    pub changes: Option<(&'a [Hunk], &'a str)>,
+    /// Text can be selected and copied but not edited
+    pub read_only: bool,
+}
+
+/// What happened in the editor this frame
+pub struct EditorOutput {
+    pub text_edit: TextEditOutput,
+    /// Header line of a fold marker or placeholder that was clicked
+    pub toggled_fold: Option<usize>,
+    /// Index of the change marker that was clicked, and where
+    pub clicked_change: Option<(usize, egui::Pos2)>,
+}
+
+impl EditorView<'_> {
+    /// Show the editor for the displayed (folded) text
+    pub fn show(mut self, ui: &mut egui::Ui, text: &mut String) -> EditorOutput {
+        let font_id = egui::FontId::monospace(self.fontsize);
+        let char_width = ui.fonts_mut(|f| f.glyph_width(&font_id, '0'));
+        let digits = self.folds.view.line_count.max(self.rows).to_string().len();
+        let numbers_width = if self.show_line_numbers {
+            (digits + 1) as f32 * char_width
+        } else {
+            0.0
+        };
+        let marker_width = if self.folds.regions.is_empty() { 0.0 } else { self.fontsize };
+        let change_width = if self.changes.is_some() { CHANGE_WIDTH + 2.0 } else { 0.0 };
+
+        let highlighter = CodeEditor::default()
+            .with_theme(self.theme)
+            .with_syntax(self.syntax.clone())
+            .with_fontsize(self.fontsize);
+
+        let inner = ui.horizontal_top(|ui| {
+            self.theme.modify_style(ui, self.fontsize);
+            let (gutter, _) = ui.allocate_exact_size(
+                egui::vec2(change_width + numbers_width + marker_width, 0.0),
+                egui::Sense::hover(),
+            );
+
+            let wrap = self.wrap;
+            let mut tree = self.tree.take();
+            let mut layouter = |ui: &egui::Ui, buffer: &dyn egui::TextBuffer, wrap_width: f32| {
+                let mut job = match tree.as_deref_mut() {
+                    Some(tree) => {
+                        tree.update(buffer.as_str());
+                        tree.layout_job(&self.theme, self.fontsize)
+                    }
+                    None => highlight(ui.ctx(), &highlighter, buffer.as_str()),
+                };
+                let available = (wrap_width / char_width).floor() as usize;
+                let Some(column) = wrap.wrap_column(available) else {
+                    return ui.fonts_mut(|f| f.layout_job(job));
+                };
+                // Leave room for the continuation indent added after layout
+                let reserved = wrap::reserved_indent(buffer.as_str(), column);
+                job.wrap.max_width = (column - reserved) as f32 * char_width + 0.5;
+                let mut galley = ui.fonts_mut(|f| f.layout_job(job));
+                indent_continuation_rows(Arc::make_mut(&mut galley), buffer.as_str(), column, char_width);
+                galley
+            };
+            let read_only = self.read_only;
+            let mut text_edit = |ui: &mut egui::Ui| {
+                // A `&str` buffer rejects edits while keeping the cursor
+                let mut shown = text.as_str();
+                let buffer: &mut dyn egui::TextBuffer = if read_only { &mut shown } else { text };
+                egui::TextEdit::multiline(buffer)
+                    .id_source(self.id)
+                    .lock_focus(true)
+                    .desired_rows(self.rows)
+                    .frame(true)
+                    .desired_width(f32::INFINITY)
+                    .layouter(&mut layouter)
+                    .show(ui)
+            };
+
+            // Wrapped text fits the window, so it needs no horizontal scrolling
+            let output = if wrap == WrapMode::Off {
+                egui::ScrollArea::horizontal()
+                    .id_salt(format!("{}_inner_scroll", self.id))
+                    .show(ui, text_edit)
+                    .inner
+            } else {
+                text_edit(ui)
+            };
+
+            let toggled = self.paint_gutter(ui, &output, gutter.left() + change_width, numbers_width, marker_width);
+            let clicked_change = self.paint_changes(ui, &output, gutter.left());
+            (output, toggled, clicked_change)
+        });
+
+        let (text_edit, toggled_fold, clicked_change) = inner.inner;
+        let toggled_fold = toggled_fold.or_else(|| self.paint_placeholders(ui, &text_edit));
+        if let Some(index) = self.scroll_to {
+            let rect = text_edit
+                .galley
+                .pos_from_cursor(CCursor::new(index))
+                .translate(text_edit.galley_pos.to_vec2());
+            ui.scroll_to_rect(rect, Some(egui::Align::Center));
+        }
+        EditorOutput { text_edit, toggled_fold, clicked_change }
+    }
+
+    /// Paint line numbers and fold markers next to the first row of every
+    /// displayed line; returns the header line of a clicked marker
+    fn paint_gutter(
+        &self,
+        ui: &egui::Ui,
+        output: &TextEditOutput,
+        left: f32,
+        numbers_width: f32,
+        marker_width: f32,
+    ) -> Option<usize> {
+        let font_id = egui::FontId::monospace(self.fontsize);
+        let color = self.theme.type_color(TokenType::Comment(true));
+        let painter = ui.painter();
+        let mut toggled = None;
+        let mut display_line = 0;
+        let mut starts_line = true;
+
+        for row in &output.galley.rows {
+            if starts_line {
+                let line = self.folds.view.original_line(display_line);
+                let top = output.galley_pos.y + row.pos.y;
+                if self.show_line_numbers {
+                    painter.text(
+                        egui::pos2(left + numbers_width - 0.5 * font_id.size, top),
+                        egui::Align2::RIGHT_TOP,
+                        (line + 1).to_string(),
+                        font_id.clone(),
+                        color,
+                    );
+                }
+                if self.folds.region_at(line).is_some() {
+                    let rect = egui::Rect::from_min_size(
+                        egui::pos2(left + numbers_width, top),
+                        egui::vec2(marker_width, row.size.y),
+                    );
+                    let response = ui.interact(
+                        rect,
+                        ui.id().with(("fold_marker", line)),
+                        egui::Sense::click(),
+                    );
+                    let folded = self.folds.is_folded(line);
+                    let visible = folded || response.hovered() || ui.rect_contains_pointer(ui.min_rect());
+                    if visible {
+                        let marker_color = if response.hovered() { self.theme.cursor() } else { color };
+                        painter.add(fold_marker(rect, folded, marker_color));
+                    }
+                    if response.clicked() {
+                        toggled = Some(line);
+                    }
+                }
+            }
+            starts_line = row.ends_with_newline;
+            if row.ends_with_newline {
+                display_line += 1;
+            }
+        }
+
+        toggled
+    }
+
+    /// Paint a bar next to added and modified lines and a wedge where lines
+    /// were deleted; returns the index of a clicked marker
+    fn paint_changes(&self, ui: &egui::Ui, output: &TextEditOutput, left: f32) -> Option<(usize, egui::Pos2)> {
+        let (hunks, base) = self.changes?;
+        let mut clicked = None;
+        let mut display_line = 0;
+        let mut starts_line = true;
+        let mut marked: Option<(usize, egui::Rect)> = None;
+        // Deletions at the end of the buffer belong after the last row
+        let end = output.galley.rows.last().map(|row| row.pos.y + row.size.y);
+
+        let show = |index: usize, rect: egui::Rect, clicked: &mut Option<(usize, egui::Pos2)>| {
+            let hunk = &hunks[index];
+            let color = change_color(hunk.kind);
+            let painter = ui.painter();
+            if hunk.kind == ChangeKind::Deleted {
+                let (top, size) = (rect.top(), CHANGE_WIDTH * 1.5);
+                painter.add(egui::Shape::convex_polygon(
+                    vec![
+                        egui::pos2(rect.left(), top - size),
+                        egui::pos2(rect.left() + size, top),
+                        egui::pos2(rect.left(), top + size),
+                    ],
+                    color,
+                    egui::Stroke::NONE,
+                ));
+            } else {
+                painter.rect_filled(rect, 0.0, color);
+            }
+            let area = rect.expand2(egui::vec2(1.0, if hunk.kind == ChangeKind::Deleted { CHANGE_WIDTH } else { 0.0 }));
+            let response = ui.interact(area, ui.id().with(("change_marker", index)), egui::Sense::click());
+            let response = if hunk.old.is_empty() {
+                response.on_hover_text("Added lines")
+            } else {
+                response.on_hover_ui(|ui| {
+                    ui.label(egui::RichText::new(diff::old_text(base, hunk)).monospace());
+                })
+            };
+            if response.clicked() {
+                *clicked = Some((index, egui::pos2(rect.right(), rect.bottom())));
+            }
+        };
+
+        for row in &output.galley.rows {
+            if starts_line {
+                let line = self.folds.view.original_line(display_line);
+                let top = output.galley_pos.y + row.pos.y;
+                if let Some(index) = diff::hunk_at(hunks, line) {
+                    let height = if hunks[index].kind == ChangeKind::Deleted { 0.0 } else { row.size.y };
+                    let rect = egui::Rect::from_min_size(egui::pos2(left, top), egui::vec2(CHANGE_WIDTH, height));
+                    // Join the rows of one hunk into a single marker
+                    match &mut marked {
+                        Some((marked_index, marked_rect)) if *marked_index == index => {
+                            *marked_rect = marked_rect.union(rect);
+                        }
+                        _ => {
+                            if let Some((index, rect)) = marked.replace((index, rect)) {
+                                show(index, rect, &mut clicked);
+                            }
+                        }
+                    }
+                }
+            } else if let Some((index, rect)) = &mut marked
+                && hunks[*index].kind != ChangeKind::Deleted
+            {
+                rect.max.y = output.galley_pos.y + row.pos.y + row.size.y;
+            }
+            starts_line = row.ends_with_newline;
+            if row.ends_with_newline {
+                display_line += 1;
+            }
+        }
+        if let Some((index, rect)) = marked {
+            show(index, rect, &mut clicked);
+        }
+        let last_line = self.folds.view.original_line(display_line);
+        if let Some(hunk) = hunks.last()
+            && hunk.kind == ChangeKind::Deleted
+            && hunk.new.start > last_line
+            && let Some(bottom) = end
+        {
+            let top = output.galley_pos.y + bottom;
+            let rect = egui::Rect::from_min_size(egui::pos2(left, top), egui::vec2(CHANGE_WIDTH, 0.0));
+            show(hunks.len() - 1, rect, &mut clicked);
+        }
+        clicked
+    }
+
+    /// Draw a box around every fold placeholder; returns the header line of
+    /// a clicked placeholder
+    fn paint_placeholders(&self, ui: &egui::Ui, output: &TextEditOutput) -> Option<usize> {
+        let mut toggled = None;
+        let stroke = egui::Stroke::new(1.0, self.theme.type_color(TokenType::Comment(true)));
+        for (index, line) in self.folds.view.placeholders() {
+            let rect = char_rect(output, index).expand(1.0);
+            ui.painter()
+                .with_clip_rect(output.text_clip_rect)
+                .rect_stroke(rect, 3.0, stroke, egui::StrokeKind::Inside);
+            let response = ui
+                .interact(rect, ui.id().with(("fold_placeholder", line)), egui::Sense::click())
+                .on_hover_text("Unfold");
+            if response.clicked() {
+                toggled = Some(line);
+            }
+        }
+        toggled
+    }
+}
+
+/// Shift the continuation rows of every wrapped line right by the
+/// line's own indentation
+fn indent_continuation_rows(galley: &mut Galley, text: &str, column: usize, char_width: f32) {
+    let mut lines = text.split('\n');
+    let mut indent = 0.0;
+    let mut starts_line = true;
+    let mut right = galley.rect.right();
+
+    for placed in &mut galley.rows {
+        if starts_line {
+            let columns = lines.next().map_or(0, |line| wrap::continuation_indent(line, column));
+            indent = columns as f32 * char_width;
+        } else if indent > 0.0 {
+            let row = Arc::make_mut(&mut placed.row);
+            for glyph in &mut row.glyphs {
+                glyph.pos.x += indent;
+            }
+            row.visuals.mesh.translate(egui::vec2(indent, 0.0));
+            row.visuals.mesh_bounds = row.visuals.mesh_bounds.translate(egui::vec2(indent, 0.0));
+            row.size.x += indent;
+            right = right.max(placed.pos.x + row.size.x);
+        }
+        starts_line = placed.row.ends_with_newline;
+    }
+
+    galley.rect.max.x = right;
+    galley.mesh_bounds.max.x = galley.mesh_bounds.max.x.max(right);
+}
+
+/// Width of the change markers in the gutter
+const CHANGE_WIDTH: f32 = 3.0;
+
+fn change_color(kind: ChangeKind) -> egui::Color32 {
+    match kind {
+        ChangeKind::Added => egui::Color32::from_rgb(80, 180, 90),
+        ChangeKind::Modified => egui::Color32::from_rgb(80, 140, 220),
+        ChangeKind::Deleted => egui::Color32::from_rgb(220, 80, 80),
+    }
+}
+
+/// Triangle pointing right for a folded region, down for an open one
+fn fold_marker(rect: egui::Rect, folded: bool, color: egui::Color32) -> egui::Shape {
+    let c = rect.center();
+    let r = rect.width().min(rect.height()) * 0.25;
+    let points = if folded {
+        vec![c + egui::vec2(-r * 0.6, -r), c + egui::vec2(r * 0.8, 0.0), c + egui::vec2(-r * 0.6, r)]
+    } else {
+        vec![c + egui::vec2(-r, -r * 0.6), c + egui::vec2(r, -r * 0.6), c + egui::vec2(0.0, r * 0.8)]
+    };
+    egui::Shape::convex_polygon(points, color, egui::Stroke::NONE)
+}
+
+/// Outline the two characters of a matching bracket pair in the editor
+pub fn paint_bracket_pair(ui: &egui::Ui, output: &TextEditOutput, pair: [Option<usize>; 2]) {
+    let stroke = egui::Stroke::new(1.0, egui::Color32::from_gray(150));
+    let fill = egui::Color32::from_white_alpha(24);
+    for index in pair.into_iter().flatten() {
+        let rect = char_rect(output, index);
+        ui.painter()
+            .with_clip_rect(output.text_clip_rect)
+            .rect(rect, 2.0, fill, stroke, egui::StrokeKind::Inside);
+    }
+}
+
+/// Underline ranges of displayed char indices with a wavy line, showing
+/// the message when the pointer is over one
+pub fn paint_diagnostics(
+    ui: &egui::Ui,
+    output: &TextEditOutput,
+    diagnostics: &[(std::ops::Range<usize>, egui::Color32, &str)],
+) {
+    let painter = ui.painter().with_clip_rect(output.text_clip_rect);
+    for (index, (range, color, message)) in diagnostics.iter().enumerate() {
+        // One underline per row the range covers; an empty range still
+        // marks the character it starts at
+        let mut rows: Vec<egui::Rect> = Vec::new();
+        for i in range.start..range.end.max(range.start + 1) {
+            let rect = char_rect(output, i);
+            match rows.last_mut() {
+                Some(row) if row.min.y == rect.min.y => *row = row.union(rect),
+                _ => rows.push(rect),
+            }
+        }
+        for (row_index, rect) in rows.into_iter().enumerate() {
+            painter.add(squiggle(rect, *color));
+            ui.interact(rect, ui.id().with(("diagnostic", index, row_index)), egui::Sense::hover())
+                .on_hover_text(*message);
+        }
+    }
+}
+
+/// Zigzag line along the bottom of a rectangle
+fn squiggle(rect: egui::Rect, color: egui::Color32) -> egui::Shape {
+    let height = 2.0;
+    let step = 2.0;
+    let bottom = rect.bottom() - 1.0;
+    let mut points = Vec::new();
+    let mut x = rect.left();
+    let mut up = false;
+    while x <= rect.right() {
+        points.push(egui::pos2(x, if up { bottom - height } else { bottom }));
+        up = !up;
+        x += step;
+    }
+    if points.len() < 2 {
+        points.push(egui::pos2(rect.right(), bottom - height));
+    }
+    egui::Shape::line(points, egui::Stroke::new(1.0, color))
+}
+
+/// Screen rectangle of the character at a char index
+pub fn char_rect(output: &TextEditOutput, index: usize) -> egui::Rect {
+    let start = output.galley.pos_from_cursor(CCursor::new(index));
+    let end = output.galley.pos_from_cursor(CCursor::new(index + 1));
+    let right = if end.min.y == start.min.y { end.min.x } else { start.min.x + start.height() * 0.5 };
+    egui::Rect::from_min_max(start.min, egui::pos2(right, start.max.y))
+        .translate(output.galley_pos.to_vec2())
+}
@@ -0,0 +1,45 @@
+use eframe::egui;
+use crate::diff::{ChangeKind, Hunk};
+
+/// What the user asked for in the change popup
+pub enum ChangePopupAction {
+    Revert,
+    Close,
+}
+
+/// Render the committed text of a changed run of lines next to its gutter
+/// marker
+pub fn render_change_popup(ctx: &egui::Context, position: egui::Pos2, hunk: &Hunk, old_text: &str) -> Option<ChangePopupAction> {
+    let mut action = None;
+    egui::Area::new(egui::Id::new("change_popup"))
+        .order(egui::Order::Foreground)
+        .fixed_pos(position)
+        .show(ctx, |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                ui.set_max_width(600.0);
+                let lines = hunk.old.len().max(hunk.new.len());
+                ui.weak(match hunk.kind {
+                    ChangeKind::Added => format!("{} added lines", lines),
+                    ChangeKind::Modified => format!("{} modified lines; committed text:", lines),
+                    ChangeKind::Deleted => format!("{} deleted lines:", lines),
+                });
+                if hunk.kind != ChangeKind::Added {
+                    egui::ScrollArea::both().max_height(300.0).show(ui, |ui| {
+                        ui.label(egui::RichText::new(old_text).monospace());
+                    });
+                }
+                ui.horizontal(|ui| {
+                    if ui.button("Revert").clicked() {
+                        action = Some(ChangePopupAction::Revert);
+                    }
+                    if ui.button("Close").clicked() {
+                        action = Some(ChangePopupAction::Close);
+                    }
+                });
+            });
+        });
+    if ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+        action = Some(ChangePopupAction::Close);
+    }
+    action
+}
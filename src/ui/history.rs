@@ -0,0 +1,58 @@
+use std::time::SystemTime;
+use eframe::egui;
+use crate::history::{self, HistoryState};
+
+/// What the user asked for in the File History panel
+pub enum HistoryAction {
+    /// Diff a version against the buffer
+    Compare(usize),
+    /// Replace the buffer with a version
+    Restore(usize),
+}
+
+/// Render the File History panel listing the saved versions of the
+/// current file, newest first
+pub fn render_history_panel(ctx: &egui::Context, state: &mut HistoryState) -> Option<HistoryAction> {
+    let mut action = None;
+    egui::SidePanel::right("history_panel")
+        .resizable(true)
+        .default_width(240.0)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.heading("File History");
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if ui.button("Close").clicked() {
+                        state.show_panel = false;
+                    }
+                });
+            });
+            ui.separator();
+            if state.file.is_none() {
+                ui.weak("Save the file to keep its history");
+                return;
+            }
+            if state.versions.is_empty() {
+                ui.weak("No saved versions yet");
+                return;
+            }
+            let now = SystemTime::now();
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                for (index, version) in state.versions.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.label(history::format_age(version.time, now))
+                            .on_hover_text(format!("{} UTC", history::format_time(version.time)));
+                        ui.weak(history::format_size(version.size));
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            if ui.small_button("Restore").on_hover_text("Replace the editor text with this version").clicked() {
+                                action = Some(HistoryAction::Restore(index));
+                            }
+                            if ui.small_button("Compare").on_hover_text("Compare with the editor text").clicked() {
+                                action = Some(HistoryAction::Compare(index));
+                            }
+                        });
+                    });
+                }
+            });
+        });
+    action
+}
@@ -0,0 +1,171 @@
+use eframe::egui;
+use egui_code_editor::{ColorTheme, TokenType};
+use crate::large_file::{self, LargeFileView};
+use crate::search::SearchState;
+
+/// Id of the large-file editor, which takes keyboard focus
+pub fn editor_id() -> egui::Id {
+    egui::Id::new("large_file_editor")
+}
+
+/// What the user did in the large-file Find bar
+pub enum FindAction {
+    Next,
+    Previous,
+    /// The query was edited, so the last result no longer applies
+    Edited,
+}
+
+/// Render the Find bar for large-file mode, which searches the buffer one
+/// match at a time instead of listing every match
+pub fn render_find_bar(ui: &mut egui::Ui, search: &mut SearchState, not_found: bool) -> Option<FindAction> {
+    let mut action = None;
+    ui.horizontal(|ui| {
+        ui.label("Find:");
+        let response = ui.text_edit_singleline(&mut search.query);
+        if response.changed() {
+            action = Some(FindAction::Edited);
+        }
+        if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+            action = Some(FindAction::Next);
+            response.request_focus();
+        }
+        if ui.button("Next").clicked() {
+            action = Some(FindAction::Next);
+        }
+        if ui.button("Previous").clicked() {
+            action = Some(FindAction::Previous);
+        }
+        if not_found && !search.query.is_empty() {
+            ui.label("No matches found");
+        }
+        if ui.button("Close").clicked() {
+            search.show_bar = false;
+            search.query.clear();
+        }
+    });
+    action
+}
+
+/// Render a file in large-file mode, laying out only the lines in view
+pub fn render_large_file(
+    ui: &mut egui::Ui,
+    view: &mut LargeFileView,
+    theme: &ColorTheme,
+    fontsize: f32,
+    show_line_numbers: bool,
+) {
+    let font = egui::FontId::monospace(fontsize);
+    let row_height = ui.fonts_mut(|fonts| fonts.row_height(&font));
+    let char_width = ui.fonts_mut(|fonts| fonts.glyph_width(&font, 'M'));
+    let line_count = view.buffer.len_lines();
+    let gutter = if show_line_numbers {
+        (line_count.to_string().len() + 2) as f32 * char_width
+    } else {
+        char_width / 2.0
+    };
+    let text_color = theme.type_color(TokenType::Literal);
+    let number_color = theme.type_color(TokenType::Comment(true));
+    let (cursor_line, cursor_column) = view.cursor_position();
+
+    let mut scroll = egui::ScrollArea::both().id_salt("large_file_scroll").auto_shrink([false, false]);
+    if view.scroll_to_cursor && !view.visible_lines.contains(&cursor_line) {
+        // Keep a few lines of context above the cursor
+        let top = cursor_line.saturating_sub(3);
+        scroll = scroll.vertical_scroll_offset(top as f32 * row_height);
+    }
+    view.scroll_to_cursor = false;
+
+    ui.painter().rect_filled(ui.max_rect(), 0.0, theme.bg());
+    ui.spacing_mut().item_spacing.y = 0.0;
+    scroll.show_rows(ui, row_height, line_count, |ui, rows| {
+        view.visible_lines = rows.clone();
+        let lines: Vec<String> = rows.clone().map(|line| view.buffer.shown_line(line)).collect();
+        let widest = lines.iter().map(|line| large_file::expand_tabs(line).chars().count()).max().unwrap_or(0);
+        let width = (gutter + (widest + 1) as f32 * char_width).max(ui.available_width());
+        let (rect, _) = ui.allocate_exact_size(egui::vec2(width, rows.len() as f32 * row_height), egui::Sense::hover());
+        let response = ui.interact(rect, editor_id(), egui::Sense::click_and_drag());
+        let text_left = rect.left() + gutter;
+
+        // Line and char column under a point
+        let position_at = |pos: egui::Pos2| {
+            let row = (((pos.y - rect.top()) / row_height).floor().max(0.0) as usize).min(lines.len().saturating_sub(1));
+            let display = ((pos.x - text_left) / char_width).round().max(0.0) as usize;
+            let column = lines.get(row).map_or(0, |line| large_file::char_column(line, display));
+            (rows.start + row, column)
+        };
+        if response.drag_started()
+            && let Some(origin) = ui.input(|i| i.pointer.press_origin())
+        {
+            // Drags select from where the button went down
+            let (line, column) = position_at(origin);
+            view.set_cursor(line, column);
+        }
+        if response.clicked() || response.dragged() {
+            response.request_focus();
+            if let Some(pos) = response.interact_pointer_pos() {
+                let (line, column) = position_at(pos);
+                if response.dragged() || ui.input(|i| i.modifiers.shift) {
+                    view.select_to(line, column);
+                } else {
+                    view.set_cursor(line, column);
+                }
+                view.scroll_to_cursor = false;
+            }
+        }
+        if response.has_focus() {
+            ui.memory_mut(|memory| {
+                memory.set_focus_lock_filter(
+                    editor_id(),
+                    egui::EventFilter { tab: true, horizontal_arrows: true, vertical_arrows: true, escape: false },
+                );
+            });
+        }
+
+        let painter = ui.painter_at(rect);
+        let selection = view.selection();
+        for (row, line) in lines.iter().enumerate() {
+            let top = rect.top() + row as f32 * row_height;
+            let number = rows.start + row;
+            let line_start = view.buffer.line_to_char(number);
+            let line_end = line_start + view.buffer.line_len(number);
+            if !selection.is_empty() && selection.start <= line_end && selection.end > line_start {
+                let from = large_file::display_column(line, selection.start.saturating_sub(line_start));
+                // A selected line break shows as one more column
+                let to = if selection.end > line_end {
+                    large_file::display_column(line, usize::MAX) + 1
+                } else {
+                    large_file::display_column(line, selection.end - line_start)
+                };
+                let selected = egui::Rect::from_min_max(
+                    egui::pos2(text_left + from as f32 * char_width, top),
+                    egui::pos2(text_left + to as f32 * char_width, top + row_height),
+                );
+                painter.rect_filled(selected, 0.0, theme.selection());
+            }
+            if show_line_numbers {
+                painter.text(
+                    egui::pos2(text_left - char_width, top),
+                    egui::Align2::RIGHT_TOP,
+                    (number + 1).to_string(),
+                    font.clone(),
+                    number_color,
+                );
+            }
+            painter.text(
+                egui::pos2(text_left, top),
+                egui::Align2::LEFT_TOP,
+                large_file::expand_tabs(line),
+                font.clone(),
+                text_color,
+            );
+            if number == cursor_line && response.has_focus() {
+                let x = text_left + large_file::display_column(line, cursor_column) as f32 * char_width;
+                painter.line_segment(
+                    [egui::pos2(x, top), egui::pos2(x, top + row_height)],
+                    egui::Stroke::new(2.0, theme.cursor()),
+                );
+            }
+        }
+    });
+}
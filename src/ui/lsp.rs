@@ -0,0 +1,127 @@
+use eframe::egui;
+use crate::lsp::{CompletionItem, Diagnostic, Position, Severity};
+
+/// Color of the squiggle and icon for a diagnostic
+pub fn severity_color(severity: Severity) -> egui::Color32 {
+    match severity {
+        Severity::Error => egui::Color32::from_rgb(230, 80, 80),
+        Severity::Warning => egui::Color32::from_rgb(220, 180, 50),
+        Severity::Information => egui::Color32::from_rgb(80, 150, 230),
+        Severity::Hint => egui::Color32::from_gray(150),
+    }
+}
+
+fn severity_icon(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Error => "✖",
+        Severity::Warning => "⚠",
+        Severity::Information | Severity::Hint => "ℹ",
+    }
+}
+
+/// Render the Problems panel listing the diagnostics of the current file,
+/// and why its language server is not running if it failed; returns the
+/// position of a clicked problem
+pub fn render_problems_panel(
+    ctx: &egui::Context,
+    diagnostics: &[&Diagnostic],
+    server_error: Option<&str>,
+) -> Option<Position> {
+    let mut target = None;
+    egui::TopBottomPanel::bottom("problems_panel")
+        .resizable(true)
+        .default_height(120.0)
+        .show(ctx, |ui| {
+            let count = |severity| diagnostics.iter().filter(|d| d.severity == severity).count();
+            ui.horizontal(|ui| {
+                ui.strong("Problems");
+                ui.weak(format!(
+                    "{} errors, {} warnings",
+                    count(Severity::Error),
+                    count(Severity::Warning)
+                ));
+            });
+            ui.separator();
+            if let Some(error) = server_error {
+                ui.weak(error);
+            }
+            if diagnostics.is_empty() {
+                ui.weak("No problems");
+                return;
+            }
+            egui::ScrollArea::vertical().auto_shrink([false, true]).show(ui, |ui| {
+                for diagnostic in diagnostics {
+                    ui.horizontal(|ui| {
+                        ui.colored_label(severity_color(diagnostic.severity), severity_icon(diagnostic.severity))
+                            .on_hover_text(diagnostic.severity.label());
+                        let location = format!("{}:{}", diagnostic.start.line + 1, diagnostic.start.character + 1);
+                        let mut message = diagnostic.message.lines().next().unwrap_or_default().to_string();
+                        if let Some(source) = &diagnostic.source {
+                            message = format!("{} ({})", message, source);
+                        }
+                        let response = ui
+                            .selectable_label(false, format!("{}  {}", location, message))
+                            .on_hover_text(&diagnostic.message);
+                        if response.clicked() {
+                            target = Some(diagnostic.start);
+                        }
+                    });
+                }
+            });
+        });
+    target
+}
+
+/// Render the completion list below the cursor; returns the index of a
+/// clicked item
+pub fn render_completion_popup(
+    ctx: &egui::Context,
+    position: egui::Pos2,
+    items: &[&CompletionItem],
+    selected: usize,
+) -> Option<usize> {
+    let mut clicked = None;
+    egui::Area::new(egui::Id::new("completion_popup"))
+        .order(egui::Order::Foreground)
+        .fixed_pos(position)
+        .show(ctx, |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                if items.is_empty() {
+                    ui.weak("No suggestions");
+                    return;
+                }
+                egui::ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                    for (index, item) in items.iter().enumerate() {
+                        ui.horizontal(|ui| {
+                            let response = ui.selectable_label(index == selected, &item.label);
+                            if index == selected {
+                                response.scroll_to_me(None);
+                            }
+                            if let Some(detail) = &item.detail {
+                                ui.weak(detail);
+                            }
+                            if response.clicked() {
+                                clicked = Some(index);
+                            }
+                        });
+                    }
+                });
+            });
+        });
+    clicked
+}
+
+/// Render hover information below the cursor
+pub fn render_hover(ctx: &egui::Context, position: egui::Pos2, text: &str) {
+    egui::Area::new(egui::Id::new("hover_popup"))
+        .order(egui::Order::Foreground)
+        .fixed_pos(position)
+        .show(ctx, |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                ui.set_max_width(500.0);
+                egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                    ui.label(egui::RichText::new(text).monospace());
+                });
+            });
+        });
+}
@@ -1,68 +1,328 @@
 use eframe::egui;
+use crate::indent::{IndentStyle, TAB_WIDTH};
+use crate::wrap::WrapMode;
+use crate::filter::ShellCommandKind;
+use crate::keybindings::{Command, KeyBindings};
+use crate::settings::Appearance;
+use crate::recent::{RecentFile, RecentFiles};
+use egui_code_editor::ColorTheme;
 
-/// Render the top menu bar with File, Edit, View, Search, and Help menus
+/// Editor state the menus need to show enabled items and checkmarks
+pub struct MenuState<'a> {
+    pub can_undo: bool,
+    pub can_redo: bool,
+    pub show_line_numbers: bool,
+    pub syntax_highlighting: bool,
+    pub auto_indent: bool,
+    pub auto_close_brackets: bool,
+    pub indent_style: IndentStyle,
+    pub wrap_mode: WrapMode,
+    pub show_outline: bool,
+    pub show_history: bool,
+    pub recent: &'a RecentFiles,
+    pub read_only: bool,
+    pub show_problems: bool,
+    pub show_output: bool,
+    pub show_terminal: bool,
+    /// Whether a program started with Run File is still running
+    pub running: bool,
+    /// Whether there are changes to step through, in the buffer against
+    /// git or in the compare window
+    pub has_changes: bool,
+    /// Whether the buffer has merge conflict markers
+    pub has_conflicts: bool,
+    pub appearance: Appearance,
+    pub themes: &'a [ColorTheme],
+    pub editor_theme: &'a str,
+    pub keybindings: &'a KeyBindings,
+}
+
+/// Render the top menu bar with File, Edit, View, Search, Run, and Help
+/// menus
 pub fn render_menu(
     ui: &mut egui::Ui,
     show_about_window: &mut bool,
-    can_undo: bool,
-    can_redo: bool,
-    show_line_numbers: bool,
-    syntax_highlighting: bool,
+    state: &MenuState<'_>,
 ) -> MenuAction {
     let mut action = MenuAction::None;
+    let keys = state.keybindings;
     
     // Adds a menu button named "File"
     ui.menu_button("File", |ui| {
-        if ui.button("New").on_hover_text("Ctrl+N").clicked() {
+        if ui.button("New").on_hover_text(keys.shortcut_text(Command::NewFile)).clicked() {
             action = MenuAction::New;
         }
-        if ui.button("Open").on_hover_text("Cmd+O").clicked() {
+        if ui.button("Open").on_hover_text(keys.shortcut_text(Command::OpenFile)).clicked() {
             action = MenuAction::Open;
         }
-        if ui.button("Save").on_hover_text("Cmd+S").clicked() {
-            action = MenuAction::Save;
-        }
+        ui.menu_button("Open Recent", |ui| {
+            render_recent_menu(ui, state.recent, keys, &mut action);
+        });
+        ui.add_enabled_ui(!state.read_only, |ui| {
+            if ui.button("Save").on_hover_text(keys.shortcut_text(Command::SaveFile)).clicked() {
+                action = MenuAction::Save;
+            }
+        });
         if ui.button("Save As").clicked() {
             action = MenuAction::SaveAs;
         }
+        if ui
+            .checkbox(&mut state.read_only.clone(), "Read-Only")
+            .on_hover_text(keys.shortcut_text(Command::ToggleReadOnly))
+            .clicked()
+        {
+            action = MenuAction::ToggleReadOnly;
+        }
+        ui.separator();
+        if ui.button("Compare with Saved").on_hover_text(keys.shortcut_text(Command::CompareWithSaved)).clicked() {
+            action = MenuAction::CompareWithSaved;
+        }
+        if ui.button("Compare With File...").on_hover_text(keys.shortcut_text(Command::CompareWithFile)).clicked() {
+            action = MenuAction::CompareWithFile;
+        }
         ui.separator();
-        if ui.button("Quit").on_hover_text("Cmd+Q").clicked() {
+        if ui.button("Preferences...").on_hover_text(keys.shortcut_text(Command::Preferences)).clicked() {
+            action = MenuAction::Preferences;
+        }
+        if ui.button("Quit").on_hover_text(keys.shortcut_text(Command::Quit)).clicked() {
             action = MenuAction::Quit;
         }
     });
     
     // Add menu button named "Edit"
     ui.menu_button("Edit", |ui| {
-        ui.add_enabled_ui(can_undo, |ui| {
-            if ui.button("Undo").on_hover_text("Ctrl+Z").clicked() {
+        ui.add_enabled_ui(state.can_undo, |ui| {
+            if ui.button("Undo").on_hover_text(keys.shortcut_text(Command::Undo)).clicked() {
                 action = MenuAction::Undo;
             }
         });
-        ui.add_enabled_ui(can_redo, |ui| {
-            if ui.button("Redo").on_hover_text("Ctrl+Y").clicked() {
+        ui.add_enabled_ui(state.can_redo, |ui| {
+            if ui.button("Redo").on_hover_text(keys.shortcut_text(Command::Redo)).clicked() {
                 action = MenuAction::Redo;
             }
         });
+        ui.separator();
+        if ui.button("Trigger Completion").on_hover_text(keys.shortcut_text(Command::TriggerCompletion)).clicked() {
+            action = MenuAction::TriggerCompletion;
+        }
+        if ui.button("Rename Symbol...").on_hover_text(keys.shortcut_text(Command::RenameSymbol)).clicked() {
+            action = MenuAction::RenameSymbol;
+        }
+        if ui.button("Format Document").on_hover_text(keys.shortcut_text(Command::FormatDocument)).clicked() {
+            action = MenuAction::FormatDocument;
+        }
+        ui.add_enabled_ui(state.has_changes, |ui| {
+            if ui.button("Revert Change").on_hover_text(keys.shortcut_text(Command::RevertChange)).clicked() {
+                action = MenuAction::RevertChange;
+            }
+        });
+        ui.add_enabled_ui(state.has_conflicts, |ui| {
+            if ui.button("Merge Conflicts...").on_hover_text(keys.shortcut_text(Command::MergeConflicts)).clicked() {
+                action = MenuAction::MergeConflicts;
+            }
+        });
+        ui.separator();
+        for (kind, command) in [
+            (ShellCommandKind::Filter, Command::FilterThroughCommand),
+            (ShellCommandKind::Insert, Command::InsertCommandOutput),
+            (ShellCommandKind::RunOnFile, Command::RunCommandOnFile),
+        ] {
+            if ui.button(format!("{}...", kind.label())).on_hover_text(keys.shortcut_text(command)).clicked() {
+                action = MenuAction::ShellCommand(kind);
+            }
+        }
+        ui.separator();
+        ui.menu_button("Convert Indentation", |ui| {
+            let spaces = match state.indent_style {
+                IndentStyle::Spaces(n) => n,
+                IndentStyle::Tabs => TAB_WIDTH,
+            };
+            if ui.button(format!("To Spaces ({})", spaces)).clicked() {
+                action = MenuAction::ConvertIndentation(IndentStyle::Spaces(spaces));
+            }
+            if ui.button("To Tabs").clicked() {
+                action = MenuAction::ConvertIndentation(IndentStyle::Tabs);
+            }
+        });
     });
     
     // Add menu button named "View"
     ui.menu_button("View", |ui| {
-        if ui.checkbox(&mut show_line_numbers.clone(), "Line Numbers").clicked() {
+        ui.menu_button("Theme", |ui| {
+            for appearance in [Appearance::System, Appearance::Light, Appearance::Dark] {
+                if ui.radio(state.appearance == appearance, appearance.label()).clicked() {
+                    action = MenuAction::SetAppearance(appearance);
+                }
+            }
+            ui.separator();
+            for theme in state.themes {
+                if ui.radio(state.editor_theme == theme.name, theme.name).clicked() {
+                    action = MenuAction::SetEditorTheme(*theme);
+                }
+            }
+            ui.separator();
+            if ui.button("Reload Themes").clicked() {
+                action = MenuAction::ReloadThemes;
+            }
+        });
+        ui.menu_button("Font", |ui| {
+            if ui.button("Choose Editor Font...").clicked() {
+                action = MenuAction::ChooseFont(egui::FontFamily::Monospace);
+            }
+            if ui.button("Choose Interface Font...").clicked() {
+                action = MenuAction::ChooseFont(egui::FontFamily::Proportional);
+            }
+            if ui.button("Use Default Fonts").clicked() {
+                action = MenuAction::ResetFonts;
+            }
+        });
+        if ui.button("Zoom In").on_hover_text(keys.shortcut_text(Command::ZoomIn)).clicked() {
+            action = MenuAction::ZoomIn;
+        }
+        if ui.button("Zoom Out").on_hover_text(keys.shortcut_text(Command::ZoomOut)).clicked() {
+            action = MenuAction::ZoomOut;
+        }
+        if ui.button("Reset Zoom").on_hover_text(keys.shortcut_text(Command::ResetZoom)).clicked() {
+            action = MenuAction::ResetZoom;
+        }
+        ui.separator();
+        if ui.checkbox(&mut state.show_line_numbers.clone(), "Line Numbers").clicked() {
             action = MenuAction::ToggleLineNumbers;
         }
-        if ui.checkbox(&mut syntax_highlighting.clone(), "Syntax Highlighting").clicked() {
+        if ui.checkbox(&mut state.syntax_highlighting.clone(), "Syntax Highlighting").clicked() {
             action = MenuAction::ToggleSyntaxHighlighting;
         }
+        if ui.button("Reload Syntax Definitions").clicked() {
+            action = MenuAction::ReloadLanguages;
+        }
+        if ui
+            .checkbox(&mut state.show_outline.clone(), "Outline")
+            .on_hover_text(keys.shortcut_text(Command::ToggleOutline))
+            .clicked()
+        {
+            action = MenuAction::ToggleOutline;
+        }
+        if ui
+            .checkbox(&mut state.show_history.clone(), "File History")
+            .on_hover_text(keys.shortcut_text(Command::ToggleFileHistory))
+            .clicked()
+        {
+            action = MenuAction::ToggleFileHistory;
+        }
+        if ui
+            .checkbox(&mut state.show_problems.clone(), "Problems")
+            .on_hover_text(keys.shortcut_text(Command::ToggleProblems))
+            .clicked()
+        {
+            action = MenuAction::ToggleProblems;
+        }
+        if ui.checkbox(&mut state.show_output.clone(), "Output").clicked() {
+            action = MenuAction::ToggleOutput;
+        }
+        if ui
+            .checkbox(&mut state.show_terminal.clone(), "Terminal")
+            .on_hover_text(keys.shortcut_text(Command::ToggleTerminal))
+            .clicked()
+        {
+            action = MenuAction::ToggleTerminal;
+        }
+        ui.menu_button("Word Wrap", |ui| {
+            for mode in [
+                WrapMode::Off,
+                WrapMode::WindowWidth,
+                WrapMode::Column(80),
+                WrapMode::Column(100),
+                WrapMode::Column(120),
+            ] {
+                if ui.radio(state.wrap_mode == mode, mode.label()).clicked() {
+                    action = MenuAction::SetWrapMode(mode);
+                }
+            }
+            ui.separator();
+            ui.label("Alt+Z toggles wrapping");
+        });
+        if ui.checkbox(&mut state.auto_close_brackets.clone(), "Auto-Close Brackets").clicked() {
+            action = MenuAction::ToggleAutoCloseBrackets;
+        }
+        ui.menu_button("Indentation", |ui| {
+            if ui.checkbox(&mut state.auto_indent.clone(), "Auto Indent").clicked() {
+                action = MenuAction::ToggleAutoIndent;
+            }
+            ui.separator();
+            for style in [
+                IndentStyle::Spaces(2),
+                IndentStyle::Spaces(4),
+                IndentStyle::Spaces(8),
+                IndentStyle::Tabs,
+            ] {
+                if ui.radio(state.indent_style == style, style.label()).clicked() {
+                    action = MenuAction::SetIndentStyle(style);
+                }
+            }
+        });
+        ui.menu_button("Folding", |ui| {
+            if ui.button("Fold").on_hover_text(keys.shortcut_text(Command::Fold)).clicked() {
+                action = MenuAction::Fold;
+            }
+            if ui.button("Unfold").on_hover_text(keys.shortcut_text(Command::Unfold)).clicked() {
+                action = MenuAction::Unfold;
+            }
+            if ui.button("Fold All").clicked() {
+                action = MenuAction::FoldAll;
+            }
+            if ui.button("Unfold All").clicked() {
+                action = MenuAction::UnfoldAll;
+            }
+            ui.separator();
+            for level in 1..=5 {
+                if ui.button(format!("Fold Level {}", level)).clicked() {
+                    action = MenuAction::FoldLevel(level);
+                }
+            }
+        });
     });
     
     // Add menu button named "Search"
     ui.menu_button("Search", |ui| {
-        if ui.button("Find").on_hover_text("Cmd+F").clicked() {
+        if ui.button("Find").on_hover_text(keys.shortcut_text(Command::Find)).clicked() {
             action = MenuAction::Find;
         }
-        if ui.button("Go to Line").on_hover_text("Cmd+G").clicked() {
+        if ui.button("Go to Line").on_hover_text(keys.shortcut_text(Command::GoToLine)).clicked() {
             action = MenuAction::GoToLine;
         }
+        if ui.button("Go to Symbol...").on_hover_text(keys.shortcut_text(Command::GoToSymbol)).clicked() {
+            action = MenuAction::GoToSymbol;
+        }
+        if ui.button("Go to Definition").on_hover_text(keys.shortcut_text(Command::GoToDefinition)).clicked() {
+            action = MenuAction::GoToDefinition;
+        }
+        if ui.button("Show Hover").on_hover_text(keys.shortcut_text(Command::ShowHover)).clicked() {
+            action = MenuAction::ShowHover;
+        }
+        if ui.button("Go to Matching Bracket").on_hover_text(keys.shortcut_text(Command::GoToMatchingBracket)).clicked() {
+            action = MenuAction::GoToMatchingBracket;
+        }
+        ui.separator();
+        ui.add_enabled_ui(state.has_changes, |ui| {
+            if ui.button("Next Change").on_hover_text(keys.shortcut_text(Command::NextChange)).clicked() {
+                action = MenuAction::NextChange;
+            }
+            if ui.button("Previous Change").on_hover_text(keys.shortcut_text(Command::PreviousChange)).clicked() {
+                action = MenuAction::PreviousChange;
+            }
+        });
+    });
+    
+    // Add menu button named "Run"
+    ui.menu_button("Run", |ui| {
+        if ui.button("Run File").on_hover_text(keys.shortcut_text(Command::RunFile)).clicked() {
+            action = MenuAction::RunFile;
+        }
+        ui.add_enabled_ui(state.running, |ui| {
+            if ui.button("Stop").on_hover_text(keys.shortcut_text(Command::StopRun)).clicked() {
+                action = MenuAction::StopRun;
+            }
+        });
     });
     
     // Add menu button named "Help"
@@ -75,6 +335,89 @@ pub fn render_menu(
     action
 }
 
+/// Render the Open Recent submenu: pinned files, then the others most
+/// recent first, each with a pin toggle
+fn render_recent_menu(ui: &mut egui::Ui, recent: &RecentFiles, keys: &KeyBindings, action: &mut MenuAction) {
+    let mut entry = |ui: &mut egui::Ui, file: &RecentFile| {
+        ui.horizontal(|ui| {
+            let pin = if file.pinned { "Unpin" } else { "Pin" };
+            if ui.selectable_label(file.pinned, "📌").on_hover_text(pin).clicked() {
+                *action = MenuAction::TogglePinRecent(file.path.clone());
+            }
+            let name = file.path.file_name().map_or_else(|| file.path.display().to_string(), |name| name.to_string_lossy().into_owned());
+            if ui.button(name).on_hover_text(file.path.display().to_string()).clicked() {
+                *action = MenuAction::OpenRecent(file.path.clone());
+            }
+        });
+    };
+    let mut any = false;
+    for file in recent.pinned() {
+        entry(ui, file);
+        any = true;
+    }
+    if any && recent.unpinned().next().is_some() {
+        ui.separator();
+    }
+    for file in recent.unpinned() {
+        entry(ui, file);
+        any = true;
+    }
+    if !any {
+        ui.weak("No recent files");
+    }
+    ui.separator();
+    if ui.button("Reopen Closed File").on_hover_text(keys.shortcut_text(Command::ReopenClosedFile)).clicked() {
+        *action = MenuAction::ReopenClosedFile;
+    }
+    ui.add_enabled_ui(recent.unpinned().next().is_some(), |ui| {
+        if ui.button("Clear Recent").on_hover_text("Forget every file that is not pinned").clicked() {
+            *action = MenuAction::ClearRecent;
+        }
+    });
+}
+
+impl MenuAction {
+    /// Whether the action works in large-file mode, where the file is not
+    /// held as one string for the language features
+    pub fn works_on_large_files(&self) -> bool {
+        matches!(
+            self,
+            MenuAction::None
+                | MenuAction::New
+                | MenuAction::Open
+                | MenuAction::OpenRecent(_)
+                | MenuAction::TogglePinRecent(_)
+                | MenuAction::ClearRecent
+                | MenuAction::ReopenClosedFile
+                | MenuAction::ToggleReadOnly
+                | MenuAction::Save
+                | MenuAction::SaveAs
+                | MenuAction::Quit
+                | MenuAction::Preferences
+                | MenuAction::Undo
+                | MenuAction::Redo
+                | MenuAction::Find
+                | MenuAction::GoToLine
+                | MenuAction::ToggleLineNumbers
+                | MenuAction::ToggleSyntaxHighlighting
+                | MenuAction::ToggleProblems
+                | MenuAction::ToggleOutput
+                | MenuAction::ToggleTerminal
+                | MenuAction::RunFile
+                | MenuAction::StopRun
+                | MenuAction::SetAppearance(_)
+                | MenuAction::SetEditorTheme(_)
+                | MenuAction::ReloadThemes
+                | MenuAction::ReloadLanguages
+                | MenuAction::ChooseFont(_)
+                | MenuAction::ResetFonts
+                | MenuAction::ZoomIn
+                | MenuAction::ZoomOut
+                | MenuAction::ResetZoom
+        )
+    }
+}
+
 /// Actions that can be triggered from the menu
 pub enum MenuAction {
     None,
@@ -83,10 +426,56 @@ pub enum MenuAction {
     Save,
     SaveAs,
     Quit,
+    Preferences,
     Find,
     Undo,
     Redo,
     ToggleLineNumbers,
     ToggleSyntaxHighlighting,
     GoToLine,
+    GoToSymbol,
+    ToggleOutline,
+    ToggleFileHistory,
+    OpenRecent(std::path::PathBuf),
+    TogglePinRecent(std::path::PathBuf),
+    ClearRecent,
+    ReopenClosedFile,
+    ToggleReadOnly,
+    TriggerCompletion,
+    ShowHover,
+    GoToDefinition,
+    RenameSymbol,
+    FormatDocument,
+    ToggleProblems,
+    ToggleOutput,
+    ToggleTerminal,
+    ShellCommand(ShellCommandKind),
+    RunFile,
+    StopRun,
+    NextChange,
+    PreviousChange,
+    RevertChange,
+    CompareWithSaved,
+    CompareWithFile,
+    MergeConflicts,
+    ToggleAutoIndent,
+    SetIndentStyle(IndentStyle),
+    ConvertIndentation(IndentStyle),
+    ToggleAutoCloseBrackets,
+    GoToMatchingBracket,
+    Fold,
+    Unfold,
+    FoldAll,
+    UnfoldAll,
+    FoldLevel(usize),
+    SetWrapMode(WrapMode),
+    SetAppearance(Appearance),
+    SetEditorTheme(ColorTheme),
+    ReloadThemes,
+    ReloadLanguages,
+    ChooseFont(egui::FontFamily),
+    ResetFonts,
+    ZoomIn,
+    ZoomOut,
+    ResetZoom,
 }
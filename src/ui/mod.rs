@@ -1,3 +1,14 @@
+pub mod compare;
+pub mod conflicts;
+pub mod drop;
 pub mod dialogs;
+pub mod editor;
+pub mod git;
+pub mod history;
+pub mod large_file;
+pub mod lsp;
 pub mod menu;
+pub mod outline;
+pub mod output;
 pub mod status_bar;
+pub mod terminal;
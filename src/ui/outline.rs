@@ -0,0 +1,38 @@
+use eframe::egui;
+use crate::outline::{filter_symbols, OutlineState};
+
+/// Render the outline side panel; returns the line of a clicked symbol
+pub fn render_outline_panel(ctx: &egui::Context, state: &mut OutlineState) -> Option<usize> {
+    let mut target = None;
+    egui::SidePanel::left("outline_panel")
+        .resizable(true)
+        .default_width(200.0)
+        .show(ctx, |ui| {
+            ui.heading("Outline");
+            ui.add(egui::TextEdit::singleline(&mut state.filter).hint_text("Filter symbols"));
+            ui.separator();
+            if state.symbols.is_empty() {
+                ui.weak("No symbols");
+                return;
+            }
+            let filtering = !state.filter.trim().is_empty();
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                for symbol in filter_symbols(&state.symbols, &state.filter) {
+                    // Nesting is only meaningful in document order
+                    let depth = if filtering { 0 } else { symbol.depth };
+                    ui.horizontal(|ui| {
+                        ui.add_space(depth as f32 * 12.0);
+                        ui.weak(symbol.kind.label());
+                        if ui
+                            .selectable_label(false, &symbol.name)
+                            .on_hover_text(format!("Line {}", symbol.line + 1))
+                            .clicked()
+                        {
+                            target = Some(symbol.line);
+                        }
+                    });
+                }
+            });
+        });
+    target
+}
@@ -0,0 +1,154 @@
+use eframe::egui::{self, Color32};
+use eframe::egui::text::{LayoutJob, TextFormat};
+use crate::output::{find_locations, AnsiColor, AnsiStyle, OutputLocation, OutputLog};
+
+/// What the user asked for in the output panel
+pub enum OutputPanelAction {
+    /// Stop the running program
+    Stop,
+    /// Open a location printed in the output
+    Open(OutputLocation),
+}
+
+/// Color of an ANSI palette entry, with the basic colors adjusted to
+/// stay readable on the panel's background
+pub fn ansi_color(color: AnsiColor, dark_mode: bool) -> Color32 {
+    const DARK: [(u8, u8, u8); 16] = [
+        (0x3b, 0x3b, 0x3b), (0xf1, 0x4c, 0x4c), (0x23, 0xd1, 0x8b), (0xf5, 0xf5, 0x43),
+        (0x3b, 0x8e, 0xea), (0xd6, 0x70, 0xd6), (0x29, 0xb8, 0xdb), (0xcc, 0xcc, 0xcc),
+        (0x66, 0x66, 0x66), (0xf1, 0x4c, 0x4c), (0x23, 0xd1, 0x8b), (0xf5, 0xf5, 0x43),
+        (0x3b, 0x8e, 0xea), (0xd6, 0x70, 0xd6), (0x29, 0xb8, 0xdb), (0xff, 0xff, 0xff),
+    ];
+    const LIGHT: [(u8, u8, u8); 16] = [
+        (0x00, 0x00, 0x00), (0xcd, 0x31, 0x31), (0x00, 0xbc, 0x00), (0x94, 0x98, 0x00),
+        (0x04, 0x51, 0xa5), (0xbc, 0x05, 0xbc), (0x05, 0x98, 0xbc), (0x55, 0x55, 0x55),
+        (0x66, 0x66, 0x66), (0xcd, 0x31, 0x31), (0x14, 0xce, 0x14), (0xb5, 0xba, 0x00),
+        (0x04, 0x51, 0xa5), (0xbc, 0x05, 0xbc), (0x05, 0x98, 0xbc), (0xa5, 0xa5, 0xa5),
+    ];
+    match color {
+        AnsiColor::Rgb(r, g, b) => Color32::from_rgb(r, g, b),
+        AnsiColor::Indexed(index @ 0..=15) => {
+            let (r, g, b) = if dark_mode { DARK } else { LIGHT }[index as usize];
+            Color32::from_rgb(r, g, b)
+        }
+        AnsiColor::Indexed(index @ 16..=231) => {
+            // 6×6×6 color cube
+            let level = |n: u8| if n == 0 { 0 } else { 55 + n * 40 };
+            let n = index - 16;
+            Color32::from_rgb(level(n / 36), level(n / 6 % 6), level(n % 6))
+        }
+        AnsiColor::Indexed(index) => {
+            let gray = 8 + (index - 232) * 10;
+            Color32::from_rgb(gray, gray, gray)
+        }
+    }
+}
+
+/// Render the output panel at the bottom of the window, with a Stop
+/// button while `running`
+pub fn render_output_panel(ctx: &egui::Context, output: &mut OutputLog, running: bool) -> Option<OutputPanelAction> {
+    let mut action = None;
+    egui::TopBottomPanel::bottom("output_panel")
+        .resizable(true)
+        .default_height(150.0)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.strong("Output");
+                ui.weak(&output.title);
+                if running {
+                    ui.spinner();
+                }
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if ui.button("Close").clicked() {
+                        output.show_panel = false;
+                    }
+                    ui.add_enabled_ui(!running, |ui| {
+                        if ui.button("Clear").clicked() {
+                            output.clear();
+                        }
+                    });
+                    if ui.button("Copy").clicked() {
+                        ui.ctx().copy_text(output.text().to_string());
+                    }
+                    if running && ui.button("Stop").clicked() {
+                        action = Some(OutputPanelAction::Stop);
+                    }
+                });
+            });
+            ui.separator();
+            let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+            egui::ScrollArea::both()
+                .auto_shrink([false, false])
+                .stick_to_bottom(true)
+                .show_rows(ui, row_height, output.line_count(), |ui, rows| {
+                    for line in rows {
+                        if let Some(location) = render_line(ui, output, line) {
+                            action = Some(OutputPanelAction::Open(location));
+                        }
+                    }
+                });
+        });
+    action
+}
+
+/// Show one line of output; returns the location the user clicked
+fn render_line(ui: &mut egui::Ui, output: &OutputLog, line: usize) -> Option<OutputLocation> {
+    let text = &output.text()[output.line_range(line)];
+    let links = find_locations(text);
+    let mut job = LayoutJob::default();
+    job.wrap.max_width = f32::INFINITY;
+    for (range, style) in output.styled_line(line) {
+        // Split the run where links start and end
+        let mut cuts = vec![range.start, range.end];
+        for link in &links {
+            cuts.extend([link.range.start, link.range.end].into_iter().filter(|cut| range.contains(cut)));
+        }
+        cuts.sort_unstable();
+        cuts.dedup();
+        for piece in cuts.windows(2) {
+            let piece = piece[0]..piece[1];
+            let linked = links.iter().any(|link| link.range.start <= piece.start && piece.end <= link.range.end);
+            job.append(&text[piece], 0.0, text_format(ui, style, linked));
+        }
+    }
+    let galley = ui.fonts_mut(|fonts| fonts.layout_job(job));
+    let (rect, response) = ui.allocate_exact_size(galley.size(), egui::Sense::click());
+    let hovered = response
+        .hover_pos()
+        .map(|pos| galley.cursor_from_pos(pos - rect.min).index)
+        .and_then(|index| {
+            let offset = text.char_indices().nth(index).map_or(text.len(), |(i, _)| i);
+            links.iter().find(|link| link.range.contains(&offset))
+        });
+    ui.painter().galley(rect.min, galley, ui.visuals().text_color());
+    let location = hovered?;
+    let response = response.on_hover_cursor(egui::CursorIcon::PointingHand);
+    response.clicked().then(|| location.clone())
+}
+
+fn text_format(ui: &egui::Ui, style: AnsiStyle, linked: bool) -> TextFormat {
+    let visuals = ui.visuals();
+    let mut color = match style.foreground {
+        Some(color) => ansi_color(color, visuals.dark_mode),
+        None if style.bold => visuals.strong_text_color(),
+        None => visuals.text_color(),
+    };
+    if linked && style.foreground.is_none() {
+        color = visuals.hyperlink_color;
+    }
+    if style.dim {
+        color = color.gamma_multiply(0.6);
+    }
+    TextFormat {
+        font_id: egui::TextStyle::Monospace.resolve(ui.style()),
+        color,
+        background: style.background.map_or(Color32::TRANSPARENT, |c| ansi_color(c, visuals.dark_mode)),
+        italics: style.italic,
+        underline: if style.underline || linked {
+            egui::Stroke::new(1.0, color)
+        } else {
+            egui::Stroke::NONE
+        },
+        ..Default::default()
+    }
+}
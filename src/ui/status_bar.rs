@@ -1,19 +1,65 @@
 use eframe::egui;
 use eframe::egui::Align;
+use crate::indent::IndentStyle;
+use crate::languages::Language;
+
+/// What the status bar shows about the current file
+pub struct StatusBarState<'a> {
+    pub filename: &'a Option<String>,
+    pub is_dirty: bool,
+    pub language: &'a str,
+    pub language_override: bool,
+    pub languages: &'a [Language],
+    pub indent_style: IndentStyle,
+    /// Git branch of the file's repository
+    pub branch: Option<&'a str>,
+    /// The file is open in large-file mode
+    pub large_file: bool,
+    pub read_only: bool,
+}
 
 /// Render the bottom status bar showing filename and status
-pub fn render_status_bar(
-    ui: &mut egui::Ui,
-    filename: &Option<String>,
-    is_dirty: bool,
-    language: &str,
-) {
+///
+/// Returns the language picked from the language menu: `Some(None)` goes
+/// back to detecting the language from the file.
+pub fn render_status_bar(ui: &mut egui::Ui, state: &StatusBarState<'_>) -> Option<Option<&'static str>> {
+    let StatusBarState { filename, is_dirty, language, language_override, languages, indent_style, branch, large_file, read_only } = *state;
+    let mut choice = None;
     ui.horizontal(|ui| {
         let display_name = filename.as_deref().unwrap_or("untitled");
         let dirty_indicator = if is_dirty { "*" } else { "" };
         ui.label(format!("{}{}", display_name, dirty_indicator));
+        if read_only {
+            ui.label("🔒 Read-Only").on_hover_text("Edits are rejected and Save is off; File > Read-Only turns this off");
+        }
         ui.with_layout(egui::Layout::right_to_left(Align::LEFT), |ui| {
-            ui.label(language);
+            ui.menu_button(language, |ui| {
+                if ui.radio(!language_override, "Auto Detect").clicked() {
+                    choice = Some(None);
+                }
+                ui.separator();
+                egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                    for candidate in languages {
+                        let selected = language_override && candidate.name == language;
+                        if ui.radio(selected, candidate.name).clicked() {
+                            choice = Some(Some(candidate.name));
+                        }
+                    }
+                });
+            })
+            .response
+            .on_hover_text("Select Language Mode");
+            ui.separator();
+            ui.label(indent_style.label());
+            if let Some(branch) = branch {
+                ui.separator();
+                ui.label(branch).on_hover_text("Git branch");
+            }
+            if large_file {
+                ui.separator();
+                ui.label("Large File").on_hover_text("Highlighting and language features are off for this file");
+            }
         });
     });
+    choice
 }
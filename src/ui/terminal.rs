@@ -0,0 +1,245 @@
+use eframe::egui::{self, Color32};
+use eframe::egui::text::{LayoutJob, TextFormat};
+use crate::output::AnsiColor;
+use crate::terminal::{self, CellPosition, TerminalState};
+use crate::ui::output::ansi_color;
+
+/// What the user asked for in the terminal panel
+pub enum TerminalPanelAction {
+    /// Start a new shell in place of the current one
+    Restart,
+    /// End the shell and hide the panel
+    Kill,
+}
+
+/// Render the terminal panel at the bottom of the window
+pub fn render_terminal_panel(ctx: &egui::Context, state: &mut TerminalState) -> Option<TerminalPanelAction> {
+    let mut action = None;
+    egui::TopBottomPanel::bottom("terminal_panel")
+        .resizable(true)
+        .default_height(220.0)
+        .show(ctx, |ui| {
+            let exit_code = state.session.as_mut().and_then(|session| session.exit_code());
+            ui.horizontal(|ui| {
+                ui.strong("Terminal");
+                if let Some(session) = &state.session {
+                    ui.weak(session.directory.display().to_string());
+                }
+                if let Some(code) = exit_code {
+                    ui.weak(format!("[exited with code {}]", code));
+                }
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if ui.button("Close").on_hover_text("Hide the panel; the shell keeps running").clicked() {
+                        state.show_panel = false;
+                    }
+                    if ui.button("Kill").clicked() {
+                        action = Some(TerminalPanelAction::Kill);
+                    }
+                    if ui.button("Restart").clicked() {
+                        action = Some(TerminalPanelAction::Restart);
+                    }
+                });
+            });
+            ui.separator();
+            render_screen(ui, state);
+        });
+    action
+}
+
+/// The terminal grid, filling the rest of the panel
+fn render_screen(ui: &mut egui::Ui, state: &mut TerminalState) {
+    let font = egui::TextStyle::Monospace.resolve(ui.style());
+    let (cell_width, cell_height) = ui.fonts_mut(|fonts| (fonts.glyph_width(&font, 'M'), fonts.row_height(&font)));
+    let size = ui.available_size();
+    let rows = (size.y / cell_height).floor().clamp(1.0, 500.0) as u16;
+    let cols = (size.x / cell_width).floor().clamp(2.0, 1000.0) as u16;
+    let (rect, response) = ui.allocate_exact_size(size, egui::Sense::click_and_drag());
+    let Some(session) = &mut state.session else {
+        return;
+    };
+    session.resize(rows, cols);
+
+    if response.clicked() || response.drag_started() || std::mem::take(&mut state.focus_requested) {
+        response.request_focus();
+    }
+    state.focused = response.has_focus();
+    if state.focused {
+        ui.memory_mut(|m| {
+            m.set_focus_lock_filter(
+                response.id,
+                egui::EventFilter { tab: true, horizontal_arrows: true, vertical_arrows: true, escape: true },
+            )
+        });
+        handle_input(ui, state);
+    }
+
+    let cell_at = |pos: egui::Pos2| -> CellPosition {
+        let row = ((pos.y - rect.top()) / cell_height).clamp(0.0, f32::from(rows - 1)) as u16;
+        let col = ((pos.x - rect.left()) / cell_width).clamp(0.0, f32::from(cols - 1)) as u16;
+        (row, col)
+    };
+    if let Some(pos) = response.interact_pointer_pos() {
+        if response.drag_started() {
+            state.selection = Some((cell_at(pos), cell_at(pos)));
+        } else if response.dragged()
+            && let Some((_, end)) = &mut state.selection
+        {
+            *end = cell_at(pos);
+        }
+    }
+    if response.clicked() {
+        state.selection = None;
+    }
+    if response.hovered() {
+        state.scroll_remainder += ui.input(|i| i.smooth_scroll_delta.y) / cell_height;
+        let lines = state.scroll_remainder.trunc();
+        if lines != 0.0 {
+            state.scroll_remainder -= lines;
+            if let Some(session) = &mut state.session {
+                session.scroll(lines as i32);
+            }
+        }
+    }
+    response.context_menu(|ui| {
+        if ui.add_enabled(state.selection.is_some(), egui::Button::new("Copy")).clicked() {
+            if let Some(text) = state.selected_text() {
+                ui.ctx().copy_text(text);
+            }
+            ui.close();
+        }
+        if ui.button("Scroll to Bottom").clicked() {
+            if let Some(session) = &mut state.session {
+                session.scroll(i32::MIN / 2);
+            }
+            ui.close();
+        }
+    });
+
+    if let Some(session) = &state.session {
+        let parser = session.parser();
+        paint_screen(ui, rect, parser.screen(), egui::vec2(cell_width, cell_height), state.focused, state.selection);
+    }
+}
+
+/// Send typed keys, pastes and copies to the shell
+fn handle_input(ui: &egui::Ui, state: &mut TerminalState) {
+    let events = ui.input(|i| i.events.clone());
+    for event in events {
+        if let egui::Event::Copy = event
+            && let Some(text) = state.selected_text()
+        {
+            ui.ctx().copy_text(text);
+            state.selection = None;
+            continue;
+        }
+        let Some(session) = &mut state.session else {
+            return;
+        };
+        let application_cursor = session.parser().screen().application_cursor();
+        match event {
+            egui::Event::Text(text) => session.write(text.as_bytes()),
+            egui::Event::Key { key, pressed: true, modifiers, .. } => {
+                if let Some(bytes) = terminal::key_bytes(key, modifiers, application_cursor) {
+                    session.write(&bytes);
+                }
+            }
+            egui::Event::Paste(text) => session.paste(&text),
+            // Without a selection Ctrl+C interrupts, and Ctrl+X is
+            // passed on, as in other terminals
+            egui::Event::Copy => session.write(b"\x03"),
+            egui::Event::Cut => session.write(b"\x18"),
+            _ => {}
+        }
+    }
+}
+
+fn cell_color(color: vt100::Color, default: Color32, dark_mode: bool) -> Color32 {
+    match color {
+        vt100::Color::Default => default,
+        vt100::Color::Idx(index) => ansi_color(AnsiColor::Indexed(index), dark_mode),
+        vt100::Color::Rgb(r, g, b) => Color32::from_rgb(r, g, b),
+    }
+}
+
+fn is_selected(selection: Option<(CellPosition, CellPosition)>, cell: CellPosition) -> bool {
+    let Some((start, end)) = selection else {
+        return false;
+    };
+    let (start, end) = if start <= end { (start, end) } else { (end, start) };
+    start <= cell && cell <= end
+}
+
+fn paint_screen(
+    ui: &egui::Ui,
+    rect: egui::Rect,
+    screen: &vt100::Screen,
+    cell: egui::Vec2,
+    focused: bool,
+    selection: Option<(CellPosition, CellPosition)>,
+) {
+    let visuals = ui.visuals();
+    let painter = ui.painter_at(rect);
+    let font = egui::TextStyle::Monospace.resolve(ui.style());
+    let (foreground, background) = (visuals.text_color(), visuals.extreme_bg_color);
+    painter.rect_filled(rect, 0.0, background);
+    let (rows, cols) = screen.size();
+    for row in 0..rows {
+        let mut job = LayoutJob::default();
+        let mut run = String::new();
+        let mut run_format: Option<TextFormat> = None;
+        for col in 0..cols {
+            let Some(content) = screen.cell(row, col) else {
+                continue;
+            };
+            if content.is_wide_continuation() {
+                continue;
+            }
+            let mut fg = cell_color(content.fgcolor(), foreground, visuals.dark_mode);
+            let mut bg = cell_color(content.bgcolor(), Color32::TRANSPARENT, visuals.dark_mode);
+            if content.bold() && content.fgcolor() == vt100::Color::Default {
+                fg = visuals.strong_text_color();
+            }
+            if content.inverse() {
+                (fg, bg) = (if bg == Color32::TRANSPARENT { background } else { bg }, fg);
+            }
+            if content.dim() {
+                fg = fg.gamma_multiply(0.6);
+            }
+            if is_selected(selection, (row, col)) {
+                bg = visuals.selection.bg_fill;
+            }
+            let format = TextFormat {
+                font_id: font.clone(),
+                color: fg,
+                background: bg,
+                italics: content.italic(),
+                underline: if content.underline() { egui::Stroke::new(1.0, fg) } else { egui::Stroke::NONE },
+                ..Default::default()
+            };
+            if run_format.as_ref() != Some(&format) {
+                if let Some(format) = run_format.take() {
+                    job.append(&std::mem::take(&mut run), 0.0, format);
+                }
+                run_format = Some(format);
+            }
+            run.push_str(if content.has_contents() { content.contents() } else { " " });
+        }
+        if let Some(format) = run_format {
+            job.append(&run, 0.0, format);
+        }
+        let galley = ui.fonts_mut(|fonts| fonts.layout_job(job));
+        painter.galley(rect.left_top() + egui::vec2(0.0, f32::from(row) * cell.y), galley, foreground);
+    }
+    if screen.scrollback() == 0 && !screen.hide_cursor() {
+        let (row, col) = screen.cursor_position();
+        let cursor = egui::Rect::from_min_size(
+            rect.left_top() + egui::vec2(f32::from(col) * cell.x, f32::from(row) * cell.y),
+            cell,
+        );
+        if focused {
+            painter.rect_filled(cursor, 0.0, foreground.gamma_multiply(0.5));
+        } else {
+            painter.rect_stroke(cursor, 0.0, egui::Stroke::new(1.0, foreground), egui::StrokeKind::Inside);
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use crate::indent::{leading_whitespace, TAB_WIDTH};
+
+/// Where long lines are soft-wrapped in the editor
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum WrapMode {
+    #[default]
+    Off,
+    /// Wrap at the right edge of the editor
+    WindowWidth,
+    /// Wrap at a fixed column, or the window edge if that comes first
+    Column(usize),
+}
+
+impl WrapMode {
+    pub fn label(&self) -> String {
+        match self {
+            WrapMode::Off => "Off".to_string(),
+            WrapMode::WindowWidth => "Window Width".to_string(),
+            WrapMode::Column(n) => format!("Column {}", n),
+        }
+    }
+
+    /// Column to wrap at when `available` columns fit in the editor
+    pub fn wrap_column(&self, available: usize) -> Option<usize> {
+        match self {
+            WrapMode::Off => None,
+            WrapMode::WindowWidth => Some(available.max(1)),
+            WrapMode::Column(n) => Some((*n).min(available).max(1)),
+        }
+    }
+}
+
+/// Width of `text` in columns, with tabs expanded to the next tab stop
+pub fn display_width(text: &str) -> usize {
+    text.chars().fold(0, |width, c| {
+        if c == '\t' {
+            width + TAB_WIDTH - width % TAB_WIDTH
+        } else {
+            width + 1
+        }
+    })
+}
+
+/// Indent (in columns) of the continuation rows of `line` when wrapped at
+/// `columns`: the line's own indentation, but at most half the wrap width
+pub fn continuation_indent(line: &str, columns: usize) -> usize {
+    display_width(leading_whitespace(line)).min(columns / 2)
+}
+
+/// Columns to keep free at the wrap edge so that no indented continuation
+/// row of `text` runs past it
+pub fn reserved_indent(text: &str, columns: usize) -> usize {
+    text.split('\n')
+        .filter(|line| display_width(line) > columns)
+        .map(|line| continuation_indent(line, columns))
+        .max()
+        .unwrap_or(0)
+}
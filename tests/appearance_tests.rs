@@ -0,0 +1,93 @@
+mod support;
+
+use std::fs;
+use egui_code_editor::ColorTheme;
+use support::TempDir;
+use writer::fonts::looks_like_font;
+use writer::settings::{Appearance, Settings, MAX_ZOOM};
+use writer::theme::{parse_theme, ThemeRegistry};
+
+#[test]
+fn test_parse_user_theme() {
+    let theme = parse_theme("name = \"Night\"\nbg = \"#101010\"\nkeywords = \"#ff8800\"\n").unwrap();
+    assert_eq!(theme.name, "Night");
+    assert!(theme.dark);
+    assert_eq!(theme.bg, "#101010");
+    assert_eq!(theme.keywords, "#ff8800");
+    // Unset colors come from the default dark theme
+    assert_eq!(theme.strs, ColorTheme::GITHUB_DARK.strs);
+}
+
+#[test]
+fn test_parse_theme_with_base() {
+    let theme = parse_theme("name = \"Warm\"\ndark = false\nbase = \"Gruvbox Light\"\n").unwrap();
+    assert!(!theme.dark);
+    assert_eq!(theme.comments, ColorTheme::GRUVBOX_LIGHT.comments);
+}
+
+#[test]
+fn test_parse_theme_errors() {
+    assert!(parse_theme("bg = \"#000000\"\n").is_err());
+    let error = parse_theme("name = \"Bad\"\nbg = \"black\"\n").unwrap_err();
+    assert!(error.contains("bg"));
+    assert!(parse_theme("name = \"X\"\nbase = \"Nope\"\n").is_err());
+    assert!(parse_theme("name = \"X\"\ncolour = \"#000000\"\n").is_err());
+}
+
+#[test]
+fn test_registry_loads_theme_directory() {
+    let dir = TempDir::new("themes");
+    fs::write(dir.join("mine.toml"), "name = \"Mine\"\n").unwrap();
+    fs::write(dir.join("broken.toml"), "name = \n").unwrap();
+    fs::write(dir.join("notes.txt"), "ignored").unwrap();
+
+    let registry = ThemeRegistry::load(Some(dir.path()));
+
+    assert!(registry.find("Mine").is_some());
+    assert!(registry.find("Sonokai").is_some());
+    assert_eq!(registry.errors.len(), 1);
+    assert!(registry.errors[0].contains("broken.toml"));
+}
+
+#[test]
+fn test_user_theme_replaces_same_name() {
+    let mut registry = ThemeRegistry::default();
+    let count = registry.themes().len();
+    registry.add(parse_theme("name = \"Sonokai\"\nbg = \"#000000\"\n").unwrap());
+    assert_eq!(registry.themes().len(), count);
+    assert_eq!(registry.find("Sonokai").unwrap().bg, "#000000");
+}
+
+#[test]
+fn test_font_file_detection() {
+    assert!(looks_like_font(&[0x00, 0x01, 0x00, 0x00, 0x00]));
+    assert!(looks_like_font(b"OTTO...."));
+    assert!(!looks_like_font(b"<html>"));
+    assert!(!looks_like_font(b""));
+}
+
+#[test]
+fn test_appearance_settings_round_trip() {
+    let mut settings = Settings::default();
+    settings.appearance.appearance = Appearance::Dark;
+    settings.appearance.dark_theme = "Sonokai".to_string();
+    settings.zoom_by(2);
+    let parsed = Settings::from_toml(&settings.to_toml()).unwrap();
+    assert_eq!(parsed, settings);
+    assert_eq!(parsed.appearance.zoom, 1.2);
+}
+
+#[test]
+fn test_zoom_is_clamped() {
+    let mut settings = Settings::default();
+    settings.zoom_by(100);
+    assert_eq!(settings.appearance.zoom, MAX_ZOOM);
+}
+
+#[test]
+fn test_partial_settings_keep_defaults() {
+    let settings = Settings::from_toml("[appearance]\nappearance = \"light\"\n").unwrap();
+    assert_eq!(settings.appearance.appearance, Appearance::Light);
+    assert_eq!(settings.appearance.font_size, 14.0);
+    assert!(Settings::from_toml("[appearance]\nappearance = \"sepia\"\n").is_err());
+}
@@ -0,0 +1,112 @@
+use writer::brackets::{
+    bracket_pair_at, classify, delete_pair, matching_bracket_target, type_char, BracketState,
+    CharKind, PairRules,
+};
+
+#[test]
+fn test_pair_at_cursor() {
+    let text = "fn main() { call(a[0]); }";
+    // Cursor right after `{`
+    assert_eq!(bracket_pair_at(text, 11, "Rust"), Some((10, 24)));
+    // Cursor before `(` of call
+    assert_eq!(bracket_pair_at(text, 16, "Rust"), Some((16, 21)));
+    assert_eq!(bracket_pair_at(text, 3, "Rust"), None);
+}
+
+#[test]
+fn test_state_classifies_each_revision_once() {
+    let mut state = BracketState::default();
+    state.refresh("(a)", 1, "Rust");
+    assert_eq!(state.pair_at(0), Some((0, 2)));
+    // The same revision keeps the classification it has
+    state.refresh("\"(a)\"", 1, "Rust");
+    assert_eq!(state.pair_at(0), Some((0, 2)));
+    state.refresh("\"(a)\"", 2, "Rust");
+    assert_eq!(state.pair_at(1), None);
+}
+
+#[test]
+fn test_brackets_in_strings_and_comments_ignored() {
+    let text = "f(\")\", // )\n x)";
+    assert_eq!(bracket_pair_at(text, 1, "Rust"), Some((1, 14)));
+}
+
+#[test]
+fn test_rust_lifetime_is_not_a_string() {
+    let kinds = classify("fn f<'a>(x: &'a str) {}", &PairRules::for_language("Rust"));
+    assert!(kinds.iter().all(|&k| k == CharKind::Code));
+
+    let kinds = classify("let c = '(';", &PairRules::for_language("Rust"));
+    assert_eq!(kinds[9], CharKind::String);
+}
+
+#[test]
+fn test_python_triple_quoted_string() {
+    let text = "x = \"\"\"a ( \"b\" \"\"\"\ny = (1)";
+    let kinds = classify(text, &PairRules::for_language("Python"));
+    assert_eq!(kinds[9], CharKind::String);
+    assert_eq!(bracket_pair_at(text, 24, "Python"), Some((23, 25)));
+}
+
+#[test]
+fn test_string_prefixes_are_not_apostrophes() {
+    let text = "print(f'{x}')\nit's (1)";
+    let kinds = classify(text, &PairRules::for_language("Python"));
+    assert_eq!(kinds[8], CharKind::String);
+    assert_eq!(bracket_pair_at(text, 5, "Python"), Some((5, 12)));
+    assert_eq!(bracket_pair_at(text, 19, "Python"), Some((19, 21)));
+
+    let kinds = classify("let b = b'(';", &PairRules::for_language("Rust"));
+    assert_eq!(kinds[10], CharKind::String);
+}
+
+#[test]
+fn test_go_to_matching_bracket() {
+    let text = "{ a ( b ) }";
+    assert_eq!(matching_bracket_target(text, 0, "Rust"), Some(10));
+    assert_eq!(matching_bracket_target(text, 10, "Rust"), Some(0));
+    // Inside the parentheses but not next to one: jump to enclosing opener
+    assert_eq!(matching_bracket_target(text, 6, "Rust"), Some(4));
+}
+
+#[test]
+fn test_auto_close_and_overtype() {
+    let mut text = String::new();
+    assert_eq!(type_char(&mut text, 0..0, '(', "Rust"), Some(1..1));
+    assert_eq!(text, "()");
+    assert_eq!(type_char(&mut text, 1..1, ')', "Rust"), Some(2..2));
+    assert_eq!(text, "()");
+
+    let mut text = "x".to_string();
+    assert_eq!(type_char(&mut text, 1..1, '"', "Rust"), None);
+    let mut text = String::new();
+    assert_eq!(type_char(&mut text, 0..0, '"', "Rust"), Some(1..1));
+    assert_eq!(type_char(&mut text, 1..1, '"', "Rust"), Some(2..2));
+    assert_eq!(text, "\"\"");
+}
+
+#[test]
+fn test_no_pairing_for_lifetimes_and_in_comments() {
+    let mut text = "fn f<".to_string();
+    assert_eq!(type_char(&mut text, 5..5, '\'', "Rust"), None);
+
+    let mut text = "# it".to_string();
+    assert_eq!(type_char(&mut text, 4..4, '(', "Python"), None);
+}
+
+#[test]
+fn test_wrap_selection() {
+    let mut text = "word".to_string();
+    assert_eq!(type_char(&mut text, 0..4, '[', "Rust"), Some(1..5));
+    assert_eq!(text, "[word]");
+}
+
+#[test]
+fn test_delete_empty_pair() {
+    let mut text = "f()".to_string();
+    assert_eq!(delete_pair(&mut text, 2, "Rust"), Some(1));
+    assert_eq!(text, "f");
+
+    let mut text = "f(x)".to_string();
+    assert_eq!(delete_pair(&mut text, 3, "Rust"), None);
+}
@@ -0,0 +1,104 @@
+use writer::compare::{CompareSide, CompareSource, CompareView, Side};
+use writer::diff::{inline_changes, line_hunks, side_by_side_rows, unified_rows, ChangeKind, DiffRow};
+
+fn row(old: Option<usize>, new: Option<usize>, hunk: Option<usize>) -> DiffRow {
+    DiffRow { old, new, hunk }
+}
+
+fn view(left: &str, right: &str) -> CompareView {
+    CompareView::new(
+        CompareSide::new(CompareSource::Saved, "saved", left.to_string()),
+        CompareSide::new(CompareSource::Buffer, "editor", right.to_string()),
+    )
+}
+
+#[test]
+fn test_side_by_side_rows_pad_shorter_side() {
+    let hunks = line_hunks("a\nb\nc", "a\nB1\nB2\nc");
+    assert_eq!(
+        side_by_side_rows(&hunks, 3, 4),
+        vec![
+            row(Some(0), Some(0), None),
+            row(Some(1), Some(1), Some(0)),
+            row(None, Some(2), Some(0)),
+            row(Some(2), Some(3), None),
+        ]
+    );
+}
+
+#[test]
+fn test_unified_rows_put_old_lines_first() {
+    let hunks = line_hunks("a\nb\nc", "a\nB\nc");
+    assert_eq!(
+        unified_rows(&hunks, 3, 3),
+        vec![
+            row(Some(0), Some(0), None),
+            row(Some(1), None, Some(0)),
+            row(None, Some(1), Some(0)),
+            row(Some(2), Some(2), None),
+        ]
+    );
+}
+
+/// The changed parts of two lines, as text
+fn changed<'a>(old: &'a str, new: &'a str) -> (Vec<&'a str>, Vec<&'a str>) {
+    let (old_ranges, new_ranges) = inline_changes(old, new);
+    (
+        old_ranges.into_iter().map(|range| &old[range]).collect(),
+        new_ranges.into_iter().map(|range| &new[range]).collect(),
+    )
+}
+
+#[test]
+fn test_inline_changes_are_byte_ranges() {
+    assert_eq!(changed("let x = 1;", "let y = 1;"), (vec!["x"], vec!["y"]));
+    // "ï" and "é" are two bytes
+    assert_eq!(changed("naïve cat", "naïve dog"), (vec!["cat"], vec!["dog"]));
+    assert_eq!(changed("é", "éé"), (vec![], vec!["é"]));
+}
+
+#[test]
+fn test_reversed_hunk() {
+    let hunk = &line_hunks("a\nb", "a")[0];
+    assert_eq!(hunk.kind, ChangeKind::Deleted);
+    let reversed = hunk.reversed();
+    assert_eq!(reversed.kind, ChangeKind::Added);
+    assert_eq!((reversed.old, reversed.new), (hunk.new.clone(), hunk.old.clone()));
+}
+
+#[test]
+fn test_copy_hunk_both_ways() {
+    let view = view("a\nb\nc\nd", "a\nB\nc\nX\nd");
+    assert_eq!(view.hunks.len(), 2);
+    assert_eq!(view.copied_hunk(0, Side::Right).as_deref(), Some("a\nb\nc\nX\nd"));
+    assert_eq!(view.copied_hunk(1, Side::Right).as_deref(), Some("a\nB\nc\nd"));
+    assert_eq!(view.copied_hunk(0, Side::Left).as_deref(), Some("a\nB\nc\nd"));
+    assert_eq!(view.copied_hunk(1, Side::Left).as_deref(), Some("a\nb\nc\nX\nd"));
+    assert_eq!(view.copied_hunk(2, Side::Left), None);
+}
+
+#[test]
+fn test_navigation_wraps_around() {
+    let mut view = view("a\nb\nc\nd", "A\nb\nc\nD");
+    assert_eq!(view.current, Some(0));
+    view.step(true);
+    assert_eq!(view.current, Some(1));
+    view.step(true);
+    assert_eq!(view.current, Some(0));
+    view.step(false);
+    assert_eq!(view.current, Some(1));
+    assert_eq!(view.current_row(), Some(3));
+    view.set_unified(true);
+    assert_eq!(view.current_row(), Some(4));
+}
+
+#[test]
+fn test_set_text_recomputes() {
+    let mut view = view("a\nb", "a\nB");
+    assert_eq!(view.hunks.len(), 1);
+    view.set_text(Side::Right, "a\nb".to_string());
+    assert!(view.hunks.is_empty());
+    assert_eq!(view.current, None);
+    view.step(true);
+    assert_eq!(view.current, None);
+}
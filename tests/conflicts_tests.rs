@@ -0,0 +1,72 @@
+use writer::conflicts::{find_conflicts, resolve, replace_conflict, resolved_text, Conflict, ConflictState, Resolution};
+
+const MERGED: &str = "start\n<<<<<<< HEAD\nours\n=======\ntheirs 1\ntheirs 2\n>>>>>>> feature\nend\n";
+
+const DIFF3: &str = "<<<<<<< ours\na\n||||||| base\nb\n=======\nc\n>>>>>>> theirs";
+
+#[test]
+fn test_find_conflicts() {
+    assert_eq!(
+        find_conflicts(MERGED),
+        vec![Conflict {
+            start: 1,
+            base: None,
+            separator: 3,
+            end: 6,
+            ours_label: "HEAD".to_string(),
+            theirs_label: "feature".to_string(),
+        }]
+    );
+    let conflict = &find_conflicts(DIFF3)[0];
+    assert_eq!((conflict.ours(), conflict.base_lines(), conflict.theirs()), (1..2, Some(3..4), 5..6));
+}
+
+#[test]
+fn test_incomplete_markers_are_not_conflicts() {
+    assert!(find_conflicts("<<<<<<< HEAD\nours\n=======\ntheirs\n").is_empty());
+    assert!(find_conflicts("<<<<<<< HEAD\nours\n>>>>>>> feature\n").is_empty());
+    // Longer runs are not markers
+    assert!(find_conflicts("<<<<<<<< a\nx\n=======\ny\n>>>>>>> b").is_empty());
+    assert_eq!(find_conflicts("<<<<<<< a\r\nx\r\n=======\r\ny\r\n>>>>>>> b\r\n").len(), 1);
+}
+
+#[test]
+fn test_resolve_each_way() {
+    let conflict = &find_conflicts(MERGED)[0];
+    assert_eq!(resolve(MERGED, conflict, Resolution::Ours), "start\nours\nend\n");
+    assert_eq!(resolve(MERGED, conflict, Resolution::Theirs), "start\ntheirs 1\ntheirs 2\nend\n");
+    assert_eq!(resolve(MERGED, conflict, Resolution::Both), "start\nours\ntheirs 1\ntheirs 2\nend\n");
+    let conflict = &find_conflicts(DIFF3)[0];
+    assert_eq!(resolve(DIFF3, conflict, Resolution::Base), "b");
+}
+
+#[test]
+fn test_empty_side_removes_lines() {
+    let text = "a\n<<<<<<< HEAD\n=======\nb\n>>>>>>> other\nc";
+    let conflict = &find_conflicts(text)[0];
+    assert_eq!(resolved_text(text, conflict, Resolution::Ours), "");
+    assert_eq!(resolve(text, conflict, Resolution::Ours), "a\nc");
+    assert_eq!(resolve(text, conflict, Resolution::Both), "a\nb\nc");
+}
+
+#[test]
+fn test_replace_with_merged_text() {
+    let conflict = &find_conflicts(MERGED)[0];
+    assert_eq!(replace_conflict(MERGED, conflict, "both\nmerged"), "start\nboth\nmerged\nend\n");
+}
+
+#[test]
+fn test_state_follows_buffer() {
+    let mut state = ConflictState::default();
+    let two = format!("{}{}", MERGED, MERGED);
+    state.refresh(&two, 1);
+    (state.show_merge_view, state.current) = (true, 1);
+    assert_eq!(state.conflicts.len(), 2);
+    assert_eq!(state.conflicts[1].start, 9);
+    let resolved = resolve(&two, &state.conflicts[1], Resolution::Ours);
+    state.refresh(&resolved, 2);
+    assert_eq!(state.current, 0);
+    state.refresh("clean", 3);
+    assert!(state.conflicts.is_empty());
+    assert!(!state.show_merge_view);
+}
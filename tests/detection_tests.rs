@@ -0,0 +1,74 @@
+use writer::languages::{builtin, guess_from_content, modeline_language, shebang_interpreter};
+use writer::syntax::get_language_name;
+
+fn detect(filename: Option<&str>, text: &str) -> &'static str {
+    builtin().detect_document(filename.map(str::to_string).as_ref(), text).name
+}
+
+#[test]
+fn test_dotfiles_and_extensionless_names() {
+    assert_eq!(get_language_name(Some(&".bashrc".to_string())), "Shell");
+    assert_eq!(get_language_name(Some(&".hidden".to_string())), "Plain Text");
+    assert_eq!(get_language_name(Some(&"deploy".to_string())), "Plain Text");
+    assert_eq!(get_language_name(Some(&"/home/a.b/rs".to_string())), "Plain Text");
+    assert_eq!(get_language_name(Some(&"/home/a.b/main.rs".to_string())), "Rust");
+}
+
+#[test]
+fn test_shebang_interpreter() {
+    assert_eq!(shebang_interpreter("#!/bin/bash\necho hi"), Some("bash"));
+    assert_eq!(shebang_interpreter("#!/usr/bin/env python3\n"), Some("python3"));
+    assert_eq!(shebang_interpreter("#!/usr/bin/env -S node --harmony\n"), Some("node"));
+    assert_eq!(shebang_interpreter("# comment\n#!/bin/sh"), None);
+}
+
+#[test]
+fn test_shebang_detects_extensionless_script() {
+    assert_eq!(detect(Some("deploy"), "#!/usr/bin/env python3.12\nprint(1)\n"), "Python");
+    assert_eq!(detect(Some("run"), "#!/bin/sh\nexit 0\n"), "Shell");
+    assert_eq!(detect(Some("tool"), "#!/usr/bin/perl\n"), "Plain Text");
+}
+
+#[test]
+fn test_vim_and_emacs_modelines() {
+    assert_eq!(modeline_language("# vim: set ft=python :\n"), Some("python"));
+    assert_eq!(modeline_language("x\n// vi: filetype=cpp\n"), Some("cpp"));
+    assert_eq!(modeline_language("# -*- mode: Shell-script; tab-width: 2 -*-\n"), Some("Shell-script"));
+    assert_eq!(modeline_language(";; -*- lua -*-\n"), Some("lua"));
+    assert_eq!(modeline_language("novim: ft=python\n"), None);
+}
+
+#[test]
+fn test_modeline_only_near_start_or_end() {
+    let mut text = "line\n".repeat(20);
+    text.insert_str(50, "vim: ft=rust\n");
+    assert_eq!(modeline_language(&text), None);
+    text.push_str("# vim: ft=yaml\n");
+    assert_eq!(modeline_language(&text), Some("yaml"));
+}
+
+#[test]
+fn test_modeline_overrides_filename() {
+    assert_eq!(detect(Some("notes.txt"), "# vim: ft=markdown\n"), "Markdown");
+    assert_eq!(detect(Some("build.conf"), "# -*- mode: toml -*-\n"), "TOML");
+    assert_eq!(detect(Some("main.rs"), "// vim: ft=unknown\n"), "Rust");
+}
+
+#[test]
+fn test_content_heuristics() {
+    assert_eq!(guess_from_content("{\n  \"a\": 1\n}"), Some("JSON"));
+    assert_eq!(guess_from_content("<!DOCTYPE html>\n<html>"), Some("HTML"));
+    assert_eq!(guess_from_content("fn main() {\n}\n"), Some("Rust"));
+    assert_eq!(guess_from_content("def main():\n    pass\n"), Some("Python"));
+    assert_eq!(guess_from_content("#include <stdio.h>\n"), Some("C"));
+    assert_eq!(guess_from_content("package main\n\nfunc main() {}\n"), Some("Go"));
+    assert_eq!(guess_from_content("[package]\nname = \"writer\"\n"), Some("TOML"));
+    assert_eq!(guess_from_content("Dear diary,\ntoday was fine.\n"), None);
+}
+
+#[test]
+fn test_content_only_used_for_unsaved_documents() {
+    assert_eq!(detect(None, "fn main() {}\n"), "Rust");
+    assert_eq!(detect(Some("notes.txt"), "fn main() {}\n"), "Plain Text");
+    assert_eq!(detect(None, ""), "Plain Text");
+}
@@ -0,0 +1,48 @@
+mod support;
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use eframe::egui::DroppedFile;
+use support::TempDir;
+use writer::file_ops::{dropped_items, is_read_only, Dropped};
+
+fn file(path: Option<&str>, bytes: Option<&[u8]>) -> DroppedFile {
+    DroppedFile {
+        path: path.map(PathBuf::from),
+        bytes: bytes.map(Arc::from),
+        ..DroppedFile::default()
+    }
+}
+
+#[test]
+fn test_dropped_files_by_path() {
+    let dropped = dropped_items(&[file(Some("/tmp/a.txt"), None), file(Some("/tmp/b.rs"), Some(b"ignored"))]);
+    assert_eq!(
+        dropped,
+        vec![Dropped::File(PathBuf::from("/tmp/a.txt")), Dropped::File(PathBuf::from("/tmp/b.rs"))]
+    );
+}
+
+#[test]
+fn test_dropped_text() {
+    assert_eq!(dropped_items(&[file(None, Some("héllo".as_bytes()))]), vec![Dropped::Text("héllo".to_string())]);
+}
+
+#[test]
+fn test_dropped_items_without_contents_are_skipped() {
+    assert!(dropped_items(&[file(None, None), file(None, Some(&[0xff, 0xfe]))]).is_empty());
+    assert!(dropped_items(&[]).is_empty());
+}
+
+#[test]
+fn test_read_only_files() {
+    let dir = TempDir::new("read-only");
+    let path = dir.join("file.txt");
+    std::fs::write(&path, "text").unwrap();
+    assert!(!is_read_only(&path));
+    let mut permissions = std::fs::metadata(&path).unwrap().permissions();
+    permissions.set_readonly(true);
+    std::fs::set_permissions(&path, permissions).unwrap();
+    assert!(is_read_only(&path));
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "text");
+}
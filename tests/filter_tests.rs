@@ -0,0 +1,82 @@
+use std::path::Path;
+use std::time::{Duration, Instant};
+use writer::filter::{apply_output, command_on_file, ShellAction, ShellJob};
+use writer::process::{shell_quote, ProcessOutput};
+
+fn run_job(command: &str, input: Option<&str>) -> Result<ProcessOutput, String> {
+    let job = ShellJob::start(command, input.map(str::to_string), None, ShellAction::Show, String::new(), || {});
+    wait(&job, Duration::from_secs(10))
+}
+
+fn wait(job: &ShellJob, timeout: Duration) -> Result<ProcessOutput, String> {
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline {
+        if let Some(result) = job.poll() {
+            return result;
+        }
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    panic!("command did not finish");
+}
+
+#[test]
+fn test_filter_part_of_a_line() {
+    let (text, range) = apply_output("say hello!", &ShellAction::Replace(4..9), "HELLO\n").unwrap();
+    assert_eq!(text, "say HELLO!");
+    assert_eq!(range, 4..9);
+}
+
+#[test]
+fn test_filter_whole_lines_keeps_newline() {
+    let text = "é\nc\nb\na\n";
+    let (filtered, range) = apply_output(text, &ShellAction::Replace(2..8), "a\nb\nc\n").unwrap();
+    assert_eq!(filtered, "é\na\nb\nc\n");
+    assert_eq!(range, 2..8);
+}
+
+#[test]
+fn test_insert_output_at_cursor() {
+    let (text, range) = apply_output("date: ", &ShellAction::Insert(6), "Monday\r\n").unwrap();
+    assert_eq!(text, "date: Monday");
+    assert_eq!(range, 6..12);
+    assert_eq!(apply_output("x", &ShellAction::Show, "out"), None);
+}
+
+#[test]
+fn test_command_on_file() {
+    let file = Path::new("/tmp/it's here.txt");
+    assert_eq!(command_on_file("wc -l", file), "wc -l '/tmp/it'\\''s here.txt'");
+    assert_eq!(command_on_file("cp {file} {file}.bak", Path::new("/a")), "cp '/a' '/a'.bak");
+}
+
+#[test]
+fn test_quoted_text_reaches_command_unchanged() {
+    let text = "it's a \"test\" $HOME `x`";
+    let output = run_job(&format!("printf %s {}", shell_quote(text)), None).unwrap();
+    assert_eq!(output.stdout, text);
+}
+
+#[test]
+fn test_job_filters_input() {
+    let output = run_job("sort", Some("b\nc\na\n")).unwrap();
+    assert!(output.status.success());
+    assert_eq!(output.stdout, "a\nb\nc\n");
+}
+
+#[test]
+fn test_job_failure_keeps_stderr() {
+    let output = run_job("echo partial; echo oops >&2; exit 2", None).unwrap();
+    assert!(!output.status.success());
+    assert_eq!(output.stdout, "partial\n");
+    assert_eq!(output.stderr, "oops\n");
+}
+
+#[test]
+fn test_cancel_kills_command() {
+    let job = ShellJob::start("sleep 30", None, None, ShellAction::Show, String::new(), || {});
+    let started = Instant::now();
+    job.cancel();
+    let error = wait(&job, Duration::from_secs(5)).err().unwrap();
+    assert!(error.contains("cancelled"), "{}", error);
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
@@ -0,0 +1,95 @@
+use writer::folding::{compute_regions, FoldEdit, FoldRegion, FoldState};
+
+const RUST: &str = "fn a() {\n    if x {\n        y();\n    }\n}\nfn b() {}\n";
+
+fn folded(text: &str, language: &str, line: usize) -> FoldState {
+    let mut folds = FoldState::default();
+    folds.refresh(text, 1, language);
+    folds.toggle(line, text);
+    folds
+}
+
+#[test]
+fn test_bracket_regions() {
+    assert_eq!(
+        compute_regions(RUST, "Rust"),
+        vec![
+            FoldRegion { start_line: 0, end_line: 3, level: 1 },
+            FoldRegion { start_line: 1, end_line: 2, level: 2 },
+        ]
+    );
+}
+
+#[test]
+fn test_lua_keyword_regions() {
+    let text = "local function f()\n  if x then\n    y()\n  end\nend\n";
+    let regions = compute_regions(text, "Lua");
+    assert_eq!(regions[0], FoldRegion { start_line: 0, end_line: 3, level: 1 });
+    assert_eq!(regions[1], FoldRegion { start_line: 1, end_line: 2, level: 2 });
+}
+
+#[test]
+fn test_indentation_regions() {
+    let text = "def f():\n    if x:\n        y\n    z\nw\n";
+    let regions = compute_regions(text, "Python");
+    assert_eq!(regions.len(), 2);
+    assert_eq!(regions[0], FoldRegion { start_line: 0, end_line: 3, level: 1 });
+}
+
+#[test]
+fn test_folded_view_text_and_mapping() {
+    let folds = folded(RUST, "Rust", 0);
+    assert_eq!(folds.view.text, "fn a() {…\n}\nfn b() {}\n");
+    assert_eq!(folds.view.original_line(1), 4);
+    assert_eq!(folds.view.display_line(2), 0);
+    // Inside the hidden lines maps to the placeholder
+    assert_eq!(folds.view.to_display(15), 8);
+    assert!(!folds.view.is_visible(15));
+    // The closing brace after the fold
+    assert_eq!(folds.view.to_original(10), RUST.find("}\nfn b").unwrap());
+}
+
+#[test]
+fn test_edit_outside_fold_is_applied() {
+    let folds = folded(RUST, "Rust", 0);
+    let edited = folds.view.text.replace("fn b", "fn c");
+    assert_eq!(
+        folds.view.apply_edit(RUST, &edited),
+        FoldEdit::Applied(RUST.replace("fn b", "fn c"))
+    );
+}
+
+#[test]
+fn test_deleting_placeholder_unfolds() {
+    let folds = folded(RUST, "Rust", 0);
+    let edited = folds.view.text.replacen('…', "", 1);
+    assert_eq!(folds.view.apply_edit(RUST, &edited), FoldEdit::Unfold(0));
+}
+
+#[test]
+fn test_fold_level_and_unfold_all() {
+    let mut folds = FoldState::default();
+    folds.refresh(RUST, 1, "Rust");
+    folds.fold_level(2, RUST);
+    assert!(folds.is_folded(1));
+    assert!(!folds.is_folded(0));
+    folds.unfold_all(RUST);
+    assert!(folds.view.is_identity());
+}
+
+#[test]
+fn test_reveal_line_unfolds_enclosing_region() {
+    let mut folds = folded(RUST, "Rust", 0);
+    folds.reveal_line(2, RUST);
+    assert!(!folds.is_folded(0));
+    assert!(folds.view.is_identity());
+}
+
+#[test]
+fn test_folds_shift_with_inserted_lines() {
+    let mut folds = folded(RUST, "Rust", 0);
+    let text = format!("// header\n{}", RUST);
+    folds.refresh(&text, 2, "Rust");
+    assert!(folds.is_folded(1));
+    assert!(!folds.is_folded(0));
+}
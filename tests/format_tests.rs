@@ -0,0 +1,116 @@
+mod support;
+
+use std::path::Path;
+use std::time::Duration;
+use support::TempDir;
+use writer::format::{expand_args, map_cursor, run_formatter, rust_edition, FormatJob};
+use writer::settings::{CommandSettings, Settings};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+fn shell(script: &str) -> CommandSettings {
+    CommandSettings::new("sh", &["-c", script])
+}
+
+#[test]
+fn test_cursor_keeps_its_place_after_reindent() {
+    let old = "fn main(){\nlet x=1;\n}";
+    let new = "fn main() {\n    let x = 1;\n}\n";
+    // Before `x`
+    let index = old.find('x').unwrap();
+    assert_eq!(map_cursor(old, new, index), new.find('x').unwrap());
+    // At the start of an indented line, before `let`
+    let index = old.find("let").unwrap();
+    assert_eq!(map_cursor(old, new, index), new.find("let").unwrap());
+    // At the end of the text
+    assert_eq!(map_cursor(old, new, old.len()), new.len() - 1);
+    assert_eq!(map_cursor(old, new, 0), 0);
+}
+
+#[test]
+fn test_cursor_in_whitespace_stays_after_previous_character() {
+    let old = "a  \n\n\nb";
+    let new = "a\n\nb\n";
+    assert_eq!(map_cursor(old, new, 2), 1);
+    // Counts chars, not bytes
+    assert_eq!(map_cursor("é =1", "é = 1", 3), 4);
+}
+
+#[test]
+fn test_formatter_output_replaces_text() {
+    let formatted = run_formatter(&CommandSettings::new("tr", &["a-z", "A-Z"]), "let x;\n", None, TIMEOUT);
+    assert_eq!(formatted.unwrap(), "LET X;\n");
+}
+
+#[test]
+fn test_failing_formatter_reports_stderr() {
+    let error = run_formatter(&shell("cat >/dev/null; echo 'line 1: bad input' >&2; exit 3"), "x", None, TIMEOUT)
+        .unwrap_err();
+    assert!(error.message.starts_with("sh failed"), "{}", error.message);
+    assert_eq!(error.stderr.trim(), "line 1: bad input");
+}
+
+#[test]
+fn test_empty_output_is_an_error() {
+    let error = run_formatter(&shell("cat >/dev/null"), "text", None, TIMEOUT).unwrap_err();
+    assert!(error.message.contains("no output"), "{}", error.message);
+    assert_eq!(run_formatter(&shell("cat"), "", None, TIMEOUT).unwrap(), "");
+}
+
+#[test]
+fn test_missing_formatter_and_timeout() {
+    let missing = CommandSettings::new("writer-no-such-formatter", &[]);
+    let error = run_formatter(&missing, "x", None, TIMEOUT).unwrap_err();
+    assert!(error.message.starts_with("Failed to run writer-no-such-formatter"), "{}", error.message);
+
+    let error = run_formatter(&shell("sleep 5"), "x", None, Duration::from_millis(100)).unwrap_err();
+    assert!(error.message.contains("did not finish"), "{}", error.message);
+}
+
+#[test]
+fn test_file_placeholder() {
+    let args = vec!["--stdin-filepath".to_string(), "{file}".to_string()];
+    assert_eq!(expand_args(&args, Some(Path::new("/src/a b.py"))), ["--stdin-filepath", "/src/a b.py"]);
+    let printer = CommandSettings::new("sh", &["-c", "cat >/dev/null; printf %s \"$0\"", "{file}"]);
+    assert_eq!(run_formatter(&printer, "x", Some(Path::new("/tmp/x.sh")), TIMEOUT).unwrap(), "/tmp/x.sh");
+}
+
+#[test]
+fn test_format_job_runs_in_background() {
+    let (done, finished) = std::sync::mpsc::channel();
+    let job = FormatJob::start(&shell("tr a-z A-Z"), "abc".to_string(), None, 7, true, move || {
+        let _ = done.send(());
+    });
+    assert_eq!((job.revision, job.save), (7, true));
+    finished.recv_timeout(TIMEOUT).unwrap();
+    assert_eq!(job.poll().unwrap().unwrap(), "ABC");
+}
+
+#[test]
+fn test_edition_placeholder() {
+    let dir = TempDir::new("format-edition");
+    let file = dir.join("crate/src/main.rs");
+    std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+    // Outside any package
+    assert_eq!(rust_edition(Some(&file)), "2024");
+    std::fs::write(dir.join("crate/Cargo.toml"), "[package]\nname = \"x\"\nedition = \"2024\"\n").unwrap();
+    let args = vec!["--edition".to_string(), "{edition}".to_string()];
+    assert_eq!(expand_args(&args, Some(&file)), ["--edition", "2024"]);
+    // Cargo's own default
+    std::fs::write(dir.join("crate/Cargo.toml"), "[package]\nname = \"x\"\n").unwrap();
+    assert_eq!(rust_edition(Some(&file)), "2015");
+}
+
+#[test]
+fn test_formatter_settings() {
+    let mut settings = Settings::default();
+    assert_eq!(settings.formatter("Rust").unwrap().command, "rustfmt");
+    assert_eq!(settings.formatter("Shell").unwrap().command, "shfmt");
+    assert!(settings.formatter("Markdown").is_none());
+
+    let source = "[formatters.Rust]\ncommand = \"\"\n\n[formatters.Markdown]\ncommand = \"prettier\"\nargs = [\"--stdin-filepath\", \"{file}\"]\n";
+    settings = Settings::from_toml(source).unwrap();
+    assert!(settings.formatter("Rust").is_none());
+    assert_eq!(settings.formatter("Markdown").unwrap().args[1], "{file}");
+    assert_eq!(Settings::from_toml(&settings.to_toml()).unwrap(), settings);
+}
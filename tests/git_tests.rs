@@ -0,0 +1,142 @@
+mod support;
+
+use std::path::Path;
+use std::process::Command;
+use std::time::{Duration, Instant};
+use writer::diff::{hunk_at, line_hunks, next_hunk, old_text, previous_hunk, revert_hunk, ChangeKind, Hunk};
+use support::TempDir;
+use writer::git::{GitState, Repository};
+
+fn git(dir: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(status.status.success(), "git {:?}: {}", args, String::from_utf8_lossy(&status.stderr));
+}
+
+/// A repository with one committed file, `notes.txt`
+fn repository(name: &str) -> TempDir {
+    let dir = TempDir::new(&format!("git-{}", name));
+    git(dir.path(), &["init", "-q", "-b", "main"]);
+    std::fs::write(dir.join("notes.txt"), "one\ntwo\nthree\n").unwrap();
+    git(dir.path(), &["add", "notes.txt"]);
+    git(dir.path(), &["commit", "-q", "-m", "first"]);
+    dir
+}
+
+/// Refresh until the background diff of `revision` is done
+fn settle(state: &mut GitState, text: &str, revision: u64) {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while !state.is_current(revision) {
+        assert!(Instant::now() < deadline, "the diff did not finish");
+        state.refresh(text, revision, || {});
+        std::thread::sleep(Duration::from_millis(5));
+    }
+}
+
+#[test]
+fn test_line_hunk_kinds() {
+    let hunks = line_hunks("a\nb\nc\nd\n", "a\nB\nc\nnew\nd\n");
+    assert_eq!(
+        hunks,
+        vec![
+            Hunk { kind: ChangeKind::Modified, old: 1..2, new: 1..2 },
+            Hunk { kind: ChangeKind::Added, old: 3..3, new: 3..4 },
+        ]
+    );
+    assert_eq!(line_hunks("a\nb\nc", "a\nc"), vec![Hunk { kind: ChangeKind::Deleted, old: 1..2, new: 1..1 }]);
+}
+
+#[test]
+fn test_line_endings_are_not_changes() {
+    assert!(line_hunks("a\r\nb\r\n", "a\nb\n").is_empty());
+}
+
+#[test]
+fn test_hunk_lookup_and_navigation() {
+    let hunks = line_hunks("a\nb\nc\nd\ne", "a\nB\nc\ne");
+    assert_eq!(hunk_at(&hunks, 1), Some(0));
+    // The deleted "d" belongs to the line after it
+    assert_eq!(hunk_at(&hunks, 3), Some(1));
+    assert_eq!(hunk_at(&hunks, 2), None);
+    assert_eq!(next_hunk(&hunks, 1), Some(1));
+    assert_eq!(next_hunk(&hunks, 3), Some(0));
+    assert_eq!(previous_hunk(&hunks, 3), Some(0));
+    assert_eq!(previous_hunk(&hunks, 0), Some(1));
+    assert_eq!(next_hunk(&[], 0), None);
+}
+
+#[test]
+fn test_revert_hunk() {
+    let old = "a\nb\nc\nd";
+    let new = "a\nB\nc\nX\nY";
+    let hunks = line_hunks(old, new);
+    assert_eq!(old_text(old, &hunks[1]), "d");
+    assert_eq!(revert_hunk(old, new, &hunks[1]), "a\nB\nc\nd");
+    assert_eq!(revert_hunk(old, new, &hunks[0]), "a\nb\nc\nX\nY");
+    let deleted = line_hunks(old, "a\nd");
+    assert_eq!(revert_hunk(old, "a\nd", &deleted[0]), old);
+}
+
+#[test]
+fn test_revert_hunk_keeps_line_endings() {
+    let old = "a\r\nb\r\nc\r\n";
+    let new = "a\r\nB\r\nc\r\n";
+    let hunks = line_hunks(old, new);
+    assert_eq!(revert_hunk(old, new, &hunks[0]), old);
+    // Files mixing endings keep each line's own
+    let old = "a\nb\r\nc\n";
+    let new = "a\nB\r\nc\r\nd";
+    let hunks = line_hunks(old, new);
+    assert_eq!(revert_hunk(old, new, &hunks[0]), "a\nb\r\nc\r\nd");
+}
+
+#[test]
+fn test_repository_discovery_and_branch() {
+    let dir = repository("branch");
+    std::fs::create_dir_all(dir.join("src")).unwrap();
+    let repository = Repository::discover(&dir.join("src")).unwrap();
+    assert_eq!(repository.root, dir.path().canonicalize().unwrap());
+    assert_eq!(repository.branch().as_deref(), Some("main"));
+    assert_eq!(repository.head_commit().map(|id| id.len()), Some(40));
+    git(dir.path(), &["checkout", "-q", "-b", "feature"]);
+    assert_eq!(repository.branch().as_deref(), Some("feature"));
+}
+
+#[test]
+fn test_committed_text() {
+    let dir = repository("committed");
+    let repository = Repository::discover(dir.path()).unwrap();
+    assert_eq!(repository.committed_text(&dir.join("notes.txt")).as_deref(), Some("one\ntwo\nthree\n"));
+    std::fs::write(dir.join("new.txt"), "x").unwrap();
+    assert_eq!(repository.committed_text(&dir.join("new.txt")), None);
+}
+
+#[test]
+fn test_git_state_tracks_buffer() {
+    let dir = repository("state");
+    let mut state = GitState::default();
+    state.open(Some(&dir.join("notes.txt")));
+    assert_eq!(state.branch.as_deref(), Some("main"));
+    settle(&mut state, "one\ntwo\nthree\n", 1);
+    assert!(state.hunks.is_empty());
+    settle(&mut state, "one\n2\nthree\n", 2);
+    assert_eq!(state.hunks, vec![Hunk { kind: ChangeKind::Modified, old: 1..2, new: 1..2 }]);
+}
+
+#[test]
+fn test_outside_repository() {
+    let dir = TempDir::new("no-git");
+    let mut state = GitState::default();
+    state.open(Some(&dir.join("loose.txt")));
+    state.refresh("text", 1, || {});
+    // The temporary directory may itself sit inside a repository
+    if state.repository.is_none() {
+        assert_eq!(state.branch, None);
+        assert!(state.hunks.is_empty());
+    }
+}
@@ -0,0 +1,124 @@
+mod support;
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use support::TempDir;
+use writer::history::{format_age, format_size, format_time, read_version, HistoryState, HistoryStore};
+use writer::settings::FileSettings;
+
+/// A fresh history root for one test, in a directory removed with it
+fn store(name: &str, max_versions: usize, max_age: Option<Duration>) -> (TempDir, HistoryStore) {
+    let dir = TempDir::new(&format!("history-{}", name));
+    let root = dir.join("history");
+    (dir, HistoryStore { root, max_versions, max_age })
+}
+
+fn contents(store: &HistoryStore, file: &Path) -> Vec<String> {
+    store.versions(file).iter().map(|version| read_version(version).unwrap()).collect()
+}
+
+#[test]
+fn test_record_lists_newest_first() {
+    let (_dir, store) = store("record", 10, None);
+    let file = PathBuf::from("/tmp/notes.txt");
+    assert!(store.record(&file, "one").unwrap());
+    assert!(store.record(&file, "two").unwrap());
+    assert!(store.record(&file, "three").unwrap());
+    assert_eq!(contents(&store, &file), vec!["three", "two", "one"]);
+    let versions = store.versions(&file);
+    assert_eq!(versions[0].size, 5);
+    assert!(versions[0].time > versions[1].time);
+}
+
+#[test]
+fn test_identical_save_is_not_recorded() {
+    let (_dir, store) = store("dedup", 10, None);
+    let file = PathBuf::from("/tmp/same.txt");
+    assert!(store.record(&file, "text").unwrap());
+    assert!(!store.record(&file, "text").unwrap());
+    assert!(store.record(&file, "changed").unwrap());
+    // Only the newest version is compared against
+    assert!(store.record(&file, "text").unwrap());
+    assert_eq!(contents(&store, &file), vec!["text", "changed", "text"]);
+}
+
+#[test]
+fn test_files_have_separate_histories() {
+    let (_dir, store) = store("separate", 10, None);
+    let first = PathBuf::from("/tmp/first.txt");
+    let second = PathBuf::from("/tmp/second.txt");
+    store.record(&first, "a").unwrap();
+    store.record(&second, "b").unwrap();
+    assert_eq!(contents(&store, &first), vec!["a"]);
+    assert_eq!(contents(&store, &second), vec!["b"]);
+    assert!(store.versions(Path::new("/tmp/never-saved.txt")).is_empty());
+}
+
+#[test]
+fn test_directory_names_are_stable() {
+    // A file keeps its directory from one release to the next
+    let (_dir, store) = store("stable", 10, None);
+    let file = PathBuf::from("/no-such-dir/notes.txt");
+    store.record(&file, "a").unwrap();
+    assert_eq!(store.versions(&file)[0].path.parent().unwrap(), store.root.join("d85297d1275b015d"));
+}
+
+#[test]
+fn test_prune_to_max_versions() {
+    let (_dir, store) = store("count", 3, None);
+    let file = PathBuf::from("/tmp/many.txt");
+    for index in 0..6 {
+        store.record(&file, &index.to_string()).unwrap();
+    }
+    assert_eq!(contents(&store, &file), vec!["5", "4", "3"]);
+}
+
+#[test]
+fn test_prune_old_versions_but_keep_newest() {
+    let (_dir, store) = store("age", 10, Some(Duration::from_secs(60)));
+    let file = PathBuf::from("/tmp/old.txt");
+    store.record(&file, "old").unwrap();
+    // Age the stored version by renaming it two hours into the past
+    let version = store.versions(&file).remove(0);
+    let millis = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() - 2 * 60 * 60 * 1000;
+    std::fs::rename(&version.path, version.path.with_file_name(format!("{}.txt", millis))).unwrap();
+    assert_eq!(contents(&store, &file), vec!["old"]);
+    store.record(&file, "new").unwrap();
+    assert_eq!(contents(&store, &file), vec!["new"]);
+}
+
+#[test]
+fn test_settings_limits() {
+    let files = FileSettings { history_versions: 0, history_days: 0, ..FileSettings::default() };
+    let store = HistoryStore::new(PathBuf::from("/tmp"), &files);
+    assert_eq!((store.max_versions, store.max_age), (1, None));
+    let files = FileSettings { history_days: 2, ..FileSettings::default() };
+    let store = HistoryStore::new(PathBuf::from("/tmp"), &files);
+    assert_eq!(store.max_age, Some(Duration::from_secs(2 * 24 * 60 * 60)));
+}
+
+#[test]
+fn test_state_reload() {
+    let (_dir, store) = store("state", 10, None);
+    let file = PathBuf::from("/tmp/state.txt");
+    store.record(&file, "a").unwrap();
+    let mut state = HistoryState::default();
+    state.reload(Some(&store), Some(&file));
+    assert_eq!((state.file.as_deref(), state.versions.len()), (Some(file.as_path()), 1));
+    state.reload(Some(&store), None);
+    assert!(state.versions.is_empty());
+}
+
+#[test]
+fn test_formatting() {
+    let time = UNIX_EPOCH + Duration::from_secs(1_709_210_096);
+    assert_eq!(format_time(time), "2024-02-29 12:34:56");
+    assert_eq!(format_time(UNIX_EPOCH), "1970-01-01 00:00:00");
+    assert_eq!(format_age(time, time + Duration::from_secs(30)), "just now");
+    assert_eq!(format_age(time, time + Duration::from_secs(60)), "1 minute ago");
+    assert_eq!(format_age(time, time + Duration::from_secs(3 * 3600)), "3 hours ago");
+    assert_eq!(format_age(time, time + Duration::from_secs(2 * 86_400)), "2 days ago");
+    assert_eq!(format_size(512), "512 B");
+    assert_eq!(format_size(1536), "1.5 KB");
+    assert_eq!(format_size(3 * 1_048_576), "3.0 MB");
+}
@@ -0,0 +1,89 @@
+use writer::indent::{
+    convert_indentation, dedent_before_closing, detect_indent, indent_lines, insert_newline,
+    outdent_lines, IndentStyle,
+};
+
+#[test]
+fn test_detect_spaces() {
+    let text = "fn main() {\n  let x = 1;\n  if x {\n    go();\n  }\n}\n";
+    assert_eq!(detect_indent(text), Some(IndentStyle::Spaces(2)));
+}
+
+#[test]
+fn test_detect_tabs() {
+    let text = "fn main() {\n\tlet x = 1;\n\tgo();\n}\n";
+    assert_eq!(detect_indent(text), Some(IndentStyle::Tabs));
+}
+
+#[test]
+fn test_detect_no_indentation() {
+    assert_eq!(detect_indent("one\ntwo\nthree\n"), None);
+}
+
+#[test]
+fn test_newline_keeps_indentation() {
+    let mut text = "    let x = 1;".to_string();
+    let cursor = insert_newline(&mut text, 14..14, IndentStyle::Spaces(4), "Rust");
+    assert_eq!(text, "    let x = 1;\n    ");
+    assert_eq!(cursor, 19);
+}
+
+#[test]
+fn test_newline_after_opener() {
+    let mut text = "if x:".to_string();
+    let cursor = insert_newline(&mut text, 5..5, IndentStyle::Spaces(4), "Python");
+    assert_eq!(text, "if x:\n    ");
+    assert_eq!(cursor, 10);
+
+    let mut text = "if x then".to_string();
+    insert_newline(&mut text, 9..9, IndentStyle::Spaces(2), "Lua");
+    assert_eq!(text, "if x then\n  ");
+}
+
+#[test]
+fn test_newline_between_brackets() {
+    let mut text = "fn main() {}".to_string();
+    let cursor = insert_newline(&mut text, 11..11, IndentStyle::Spaces(4), "Rust");
+    assert_eq!(text, "fn main() {\n    \n}");
+    assert_eq!(cursor, 16);
+}
+
+#[test]
+fn test_dedent_closing_bracket() {
+    let mut text = "{\n        ".to_string();
+    let cursor = dedent_before_closing(&mut text, 10, '}', IndentStyle::Spaces(4));
+    assert_eq!(text, "{\n    }");
+    assert_eq!(cursor, Some(7));
+
+    let mut text = "x = (a ".to_string();
+    assert_eq!(dedent_before_closing(&mut text, 7, ')', IndentStyle::Spaces(4)), None);
+}
+
+#[test]
+fn test_indent_and_outdent_lines() {
+    let mut text = "a\nb\n\nc".to_string();
+    let selection = indent_lines(&mut text, 0..6, IndentStyle::Spaces(2));
+    assert_eq!(text, "  a\n  b\n\n  c");
+    assert_eq!(selection, 0..12);
+
+    let selection = outdent_lines(&mut text, selection, IndentStyle::Spaces(2));
+    assert_eq!(text, "a\nb\n\nc");
+    assert_eq!(selection, 0..6);
+}
+
+#[test]
+fn test_tab_with_empty_selection_pads_to_stop() {
+    let mut text = "ab".to_string();
+    let selection = indent_lines(&mut text, 1..1, IndentStyle::Spaces(4));
+    assert_eq!(text, "a   b");
+    assert_eq!(selection, 4..4);
+}
+
+#[test]
+fn test_convert_indentation() {
+    let text = "a\n\tb\n\t\tc\n";
+    let spaces = convert_indentation(text, IndentStyle::Tabs, IndentStyle::Spaces(2));
+    assert_eq!(spaces, "a\n  b\n    c\n");
+    let tabs = convert_indentation(&spaces, IndentStyle::Spaces(2), IndentStyle::Tabs);
+    assert_eq!(tabs, text);
+}
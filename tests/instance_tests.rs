@@ -0,0 +1,124 @@
+mod support;
+
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+use support::TempDir;
+use writer::instance::{forward, parse_args, InstanceServer, OpenRequest};
+
+fn args(words: &[&str]) -> Result<writer::instance::Args, String> {
+    parse_args(words.iter().map(|word| word.to_string()), Path::new("/work"))
+}
+
+/// A socket path of its own for one test, in a directory removed with it
+fn socket(name: &str) -> (TempDir, PathBuf) {
+    use std::os::unix::fs::PermissionsExt;
+    let dir = TempDir::new(&format!("instance-{}", name));
+    // The server only listens in a directory private to the user
+    std::fs::set_permissions(dir.path(), std::fs::Permissions::from_mode(0o700)).unwrap();
+    let socket = dir.join("writer.sock");
+    (dir, socket)
+}
+
+#[test]
+fn test_parse_file_and_line() {
+    let cwd = Path::new("/work");
+    assert_eq!(
+        OpenRequest::parse("src/main.rs:42", cwd),
+        OpenRequest { path: PathBuf::from("/work/src/main.rs"), line: Some(42), read_only: false }
+    );
+    assert_eq!(
+        OpenRequest::parse("/etc/hosts", cwd),
+        OpenRequest { path: PathBuf::from("/etc/hosts"), line: None, read_only: false }
+    );
+    assert_eq!(OpenRequest::parse("notes:draft", cwd).line, None);
+    assert_eq!(OpenRequest::parse(":3", cwd).path, PathBuf::from("/work/:3"));
+}
+
+#[test]
+fn test_existing_file_with_colon_is_taken_as_is() {
+    let dir = TempDir::new("instance-colon");
+    std::fs::write(dir.join("log:1"), "").unwrap();
+    assert_eq!(OpenRequest::parse("log:1", dir.path()), OpenRequest { path: dir.join("log:1"), line: None, read_only: false });
+}
+
+#[test]
+fn test_parse_args() {
+    let parsed = args(&["--new-window", "a.txt", "--", "--b.txt"]).unwrap();
+    assert!(parsed.new_window);
+    assert!(!parsed.help);
+    let paths: Vec<_> = parsed.files.iter().map(|file| file.path.clone()).collect();
+    assert_eq!(paths, vec![PathBuf::from("/work/a.txt"), PathBuf::from("/work/--b.txt")]);
+    assert!(args(&["--help"]).unwrap().help);
+    assert!(args(&[]).unwrap().files.is_empty());
+    assert_eq!(args(&["--bogus"]), Err("unknown option --bogus".to_string()));
+}
+
+#[test]
+fn test_readonly_applies_to_every_file() {
+    let parsed = args(&["a.txt", "--readonly", "b.txt:3"]).unwrap();
+    assert!(parsed.read_only);
+    assert!(parsed.files.iter().all(|file| file.read_only));
+    assert!(args(&["a.txt"]).unwrap().files.iter().all(|file| !file.read_only));
+    // Requests from older instances leave the flag out
+    let request: OpenRequest = serde_json::from_str(r#"{"path":"/work/a.txt","line":null}"#).unwrap();
+    assert!(!request.read_only);
+}
+
+#[test]
+fn test_forward_to_running_instance() {
+    let (_dir, socket) = socket("forward");
+    let (notify, notified) = mpsc::channel();
+    let server = InstanceServer::start(&socket, move || {
+        let _ = notify.send(());
+    })
+    .unwrap();
+    let files = vec![
+        OpenRequest { path: PathBuf::from("/work/a.txt"), line: Some(3), read_only: true },
+        OpenRequest { path: PathBuf::from("/work/b.txt"), line: None, read_only: false },
+    ];
+    forward(&socket, &files).unwrap();
+    forward(&socket, &[]).unwrap();
+    for _ in 0..2 {
+        notified.recv_timeout(Duration::from_secs(5)).unwrap();
+    }
+    assert_eq!(server.poll(), vec![files, Vec::new()]);
+    assert!(server.poll().is_empty());
+    drop(server);
+    assert!(!socket.exists());
+}
+
+#[test]
+fn test_stale_socket_is_replaced() {
+    let (_dir, socket) = socket("stale");
+    assert!(forward(&socket, &[]).is_err());
+    // A socket file left behind by an instance that exited
+    drop(std::os::unix::net::UnixListener::bind(&socket).unwrap());
+    assert!(socket.exists());
+    assert!(forward(&socket, &[]).is_err());
+    let server = InstanceServer::start(&socket, || {}).unwrap();
+    forward(&socket, &[]).unwrap();
+    // A live socket is left alone
+    assert_eq!(
+        InstanceServer::start(&socket, || {}).err().map(|e| e.kind()),
+        Some(std::io::ErrorKind::AddrInUse)
+    );
+    forward(&socket, &[]).unwrap();
+    drop(server);
+}
+
+#[test]
+fn test_socket_directory_must_be_private() {
+    use std::os::unix::fs::PermissionsExt;
+    let (dir, socket) = socket("shared");
+    let dir = dir.path();
+    std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o777)).unwrap();
+    assert!(InstanceServer::start(&socket, || {}).is_err());
+    // A missing directory is created private
+    let nested = dir.join("run").join("writer.sock");
+    std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700)).unwrap();
+    let server = InstanceServer::start(&nested, || {}).unwrap();
+    let mode = std::fs::metadata(nested.parent().unwrap()).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o700);
+    drop(server);
+}
@@ -0,0 +1,52 @@
+use std::collections::BTreeMap;
+use eframe::egui::{Key, KeyboardShortcut, Modifiers};
+use writer::keybindings::{format_shortcut, parse_shortcut, Command, KeyBindings};
+
+#[test]
+fn test_parse_shortcut() {
+    assert_eq!(
+        parse_shortcut("Ctrl+Shift+Z"),
+        Ok(KeyboardShortcut::new(Modifiers::COMMAND | Modifiers::SHIFT, Key::Z))
+    );
+    assert_eq!(parse_shortcut("alt+z"), Ok(KeyboardShortcut::new(Modifiers::ALT, Key::Z)));
+    assert_eq!(parse_shortcut("Ctrl+Comma"), Ok(KeyboardShortcut::new(Modifiers::COMMAND, Key::Comma)));
+    assert_eq!(parse_shortcut("Ctrl+0"), Ok(KeyboardShortcut::new(Modifiers::COMMAND, Key::Num0)));
+    assert!(parse_shortcut("Hyper+Z").is_err());
+    assert!(parse_shortcut("Ctrl+Nope").is_err());
+}
+
+#[test]
+fn test_format_round_trips() {
+    for text in ["Ctrl+Shift+Z", "Alt+Z", "Ctrl+Minus", "F5"] {
+        let shortcut = parse_shortcut(text).unwrap();
+        assert_eq!(format_shortcut(&shortcut), text);
+    }
+}
+
+#[test]
+fn test_default_bindings_are_valid() {
+    let bindings = KeyBindings::new(&BTreeMap::new()).unwrap();
+    assert_eq!(bindings.shortcut_text(Command::SaveFile), "Ctrl+S");
+    assert_eq!(bindings.shortcut_text(Command::Redo), "Ctrl+Shift+Z");
+}
+
+#[test]
+fn test_override_and_unbind() {
+    let mut overrides = BTreeMap::new();
+    overrides.insert("find".to_string(), "Ctrl+Shift+F".to_string());
+    overrides.insert("quit".to_string(), String::new());
+    let bindings = KeyBindings::new(&overrides).unwrap();
+    assert_eq!(bindings.shortcut_text(Command::Find), "Ctrl+Shift+F");
+    assert_eq!(bindings.shortcut_text(Command::Quit), "");
+}
+
+#[test]
+fn test_conflicts_and_unknown_commands_are_errors() {
+    let mut overrides = BTreeMap::new();
+    overrides.insert("find".to_string(), "Ctrl+S".to_string());
+    overrides.insert("teleport".to_string(), "Ctrl+T".to_string());
+    let errors = KeyBindings::new(&overrides).unwrap_err();
+    assert_eq!(errors.len(), 2);
+    assert!(errors.iter().any(|e| e.contains("teleport")));
+    assert!(errors.iter().any(|e| e.contains("Ctrl+S")));
+}
@@ -0,0 +1,91 @@
+use writer::indent::IndentStyle;
+use writer::languages::{builtin, glob_matches, LanguageRegistry};
+use writer::syntax::get_language_name;
+
+fn language_of(filename: &str) -> &'static str {
+    get_language_name(Some(&filename.to_string()))
+}
+
+#[test]
+fn test_new_extensions() {
+    assert_eq!(language_of("package.json"), "JSON");
+    assert_eq!(language_of("config.toml"), "TOML");
+    assert_eq!(language_of("ci.yml"), "YAML");
+    assert_eq!(language_of("README.md"), "Markdown");
+    assert_eq!(language_of("main.c"), "C");
+    assert_eq!(language_of("main.cpp"), "C++");
+    assert_eq!(language_of("main.go"), "Go");
+    assert_eq!(language_of("app.js"), "JavaScript");
+    assert_eq!(language_of("app.tsx"), "TypeScript");
+    assert_eq!(language_of("index.html"), "HTML");
+    assert_eq!(language_of("style.css"), "CSS");
+    assert_eq!(language_of("Main.java"), "Java");
+}
+
+#[test]
+fn test_extensions_ignore_case() {
+    assert_eq!(language_of("NOTES.MD"), "Markdown");
+    assert_eq!(language_of("Build.RS"), "Rust");
+}
+
+#[test]
+fn test_exact_filenames() {
+    assert_eq!(language_of("Makefile"), "Makefile");
+    assert_eq!(language_of("/src/project/Dockerfile"), "Dockerfile");
+    assert_eq!(language_of("Cargo.lock"), "TOML");
+    assert_eq!(language_of("/home/user/.bashrc"), "Shell");
+}
+
+#[test]
+fn test_globs() {
+    assert!(glob_matches("Dockerfile.*", "Dockerfile.dev"));
+    assert!(glob_matches("*.dockerfile", "api.dockerfile"));
+    assert!(glob_matches("a?c*", "abcdef"));
+    assert!(!glob_matches("Makefile.*", "Makefile"));
+    assert!(!glob_matches("*.dockerfile", "api.dockerfile.bak"));
+    assert_eq!(language_of("Dockerfile.dev"), "Dockerfile");
+    assert_eq!(language_of("Makefile.am"), "Makefile");
+}
+
+#[test]
+fn test_name_and_syntax_agree() {
+    for language in builtin().languages() {
+        assert_eq!(language.syntax.language, language.name);
+        for extension in language.extensions {
+            assert_eq!(language_of(&format!("file.{}", extension)), language.name);
+        }
+    }
+}
+
+#[test]
+fn test_comment_tokens_and_indent() {
+    let registry = builtin();
+    let go = registry.by_name("Go").unwrap();
+    assert_eq!(go.line_comment, Some("//"));
+    assert_eq!(go.indent, Some(IndentStyle::Tabs));
+    let yaml = registry.by_name("YAML").unwrap();
+    assert_eq!(yaml.line_comment, Some("#"));
+    assert_eq!(yaml.indent, Some(IndentStyle::Spaces(2)));
+    let html = registry.by_name("HTML").unwrap();
+    assert_eq!(html.line_comment, None);
+    assert_eq!(html.block_comment, Some(("<!--", "-->")));
+    assert_eq!(registry.by_name("Makefile").unwrap().indent, Some(IndentStyle::Tabs));
+    assert!(registry.by_name("Cobol").is_none());
+}
+
+#[test]
+fn test_add_replaces_language() {
+    let mut registry = LanguageRegistry::default();
+    let count = registry.languages().len();
+    let mut json = registry.by_name("JSON").unwrap().clone();
+    json.extensions = &["json", "geojson"];
+    registry.add(json);
+    assert_eq!(registry.languages().len(), count);
+    assert_eq!(registry.for_filename("map.geojson").unwrap().name, "JSON");
+}
+
+#[test]
+fn test_unknown_falls_back_to_plain_text() {
+    assert_eq!(builtin().detect(None).name, "Plain Text");
+    assert_eq!(builtin().detect(Some(&"data.xyz".to_string())).name, "Plain Text");
+}
@@ -0,0 +1,191 @@
+mod support;
+
+use support::TempDir;
+use writer::large_file::{char_column, display_column, expand_tabs, LargeFileView, RopeBuffer, MAX_SHOWN_LINE};
+use writer::settings::FileSettings;
+
+fn view(text: &str) -> LargeFileView {
+    LargeFileView::new(RopeBuffer::from_text(text))
+}
+
+fn text(buffer: &RopeBuffer) -> String {
+    buffer.slice(0..buffer.len_chars())
+}
+
+#[test]
+fn test_lines() {
+    let buffer = RopeBuffer::from_text("one\r\ntwo\n\nfour");
+    assert_eq!(buffer.len_lines(), 4);
+    assert_eq!(buffer.line_len(0), 3);
+    assert_eq!(buffer.shown_line(0), "one");
+    assert_eq!(buffer.shown_line(2), "");
+    assert_eq!(buffer.line_to_char(3), 10);
+    assert_eq!(buffer.char_to_line(6), 1);
+    let long = RopeBuffer::from_text(&"x".repeat(MAX_SHOWN_LINE + 5));
+    assert_eq!(long.shown_line(0).len(), MAX_SHOWN_LINE);
+}
+
+#[test]
+fn test_typing_is_undone_together() {
+    let mut view = view("ab\n");
+    view.set_cursor(0, 1);
+    for c in ["x", "y", "z"] {
+        view.insert(c);
+    }
+    view.insert("\n");
+    assert_eq!(text(&view.buffer), "axyz\nb\n");
+    view.undo();
+    assert_eq!((text(&view.buffer), view.cursor), ("axyzb\n".to_string(), 4));
+    view.undo();
+    assert_eq!((text(&view.buffer), view.cursor), ("ab\n".to_string(), 1));
+    assert!(!view.buffer.can_undo());
+    view.redo();
+    view.redo();
+    assert_eq!(text(&view.buffer), "axyz\nb\n");
+}
+
+#[test]
+fn test_revisions_track_saved_state() {
+    let mut view = view("text");
+    let loaded = view.buffer.revision();
+    assert!(!view.buffer.is_dirty());
+    view.insert("more ");
+    assert_ne!(view.buffer.revision(), loaded);
+    assert!(view.buffer.is_dirty());
+    view.undo();
+    assert_eq!(view.buffer.revision(), loaded);
+    assert!(!view.buffer.is_dirty());
+
+    let dir = TempDir::new("large-file");
+    let path = dir.join("file.txt");
+    view.insert("saved ");
+    view.buffer.save(&path).unwrap();
+    assert!(!view.buffer.is_dirty());
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "saved text");
+    assert_eq!(text(&RopeBuffer::load(&path).unwrap()), "saved text");
+    view.undo();
+    assert!(view.buffer.is_dirty());
+}
+
+#[test]
+fn test_failed_save_keeps_the_file() {
+    let dir = TempDir::new("large-file-save");
+    std::fs::create_dir(dir.join("folder")).unwrap();
+    let mut view = view("ext");
+    view.insert("t");
+    // A file cannot replace a folder
+    assert!(view.buffer.save(&dir.join("folder")).is_err());
+    assert!(view.buffer.is_dirty());
+    assert!(dir.join("folder").is_dir());
+    std::fs::write(dir.join("notes.txt"), "old").unwrap();
+    view.buffer.save(&dir.join("notes.txt")).unwrap();
+    assert!(!view.buffer.is_dirty());
+    assert_eq!(std::fs::read_to_string(dir.join("notes.txt")).unwrap(), "text");
+    // Nothing is left behind next to the files
+    let mut names: Vec<_> = std::fs::read_dir(dir.path()).unwrap().map(|entry| entry.unwrap().file_name()).collect();
+    names.sort();
+    assert_eq!(names, ["folder", "notes.txt"]);
+}
+
+#[test]
+fn test_deleting_line_breaks() {
+    let mut view = view("a\r\nb");
+    view.set_cursor(1, 0);
+    view.backspace();
+    assert_eq!((text(&view.buffer), view.cursor), ("ab".to_string(), 1));
+    let mut view = self::view("a\r\nb");
+    view.set_cursor(0, 1);
+    view.delete();
+    assert_eq!(text(&view.buffer), "ab");
+}
+
+#[test]
+fn test_cursor_movement() {
+    let mut view = view("long line\nab\n\tx");
+    view.set_cursor(0, 7);
+    view.move_vertically(1);
+    assert_eq!(view.cursor_position(), (1, 2));
+    // The column is kept through a shorter line
+    view.move_vertically(1);
+    assert_eq!(view.cursor_position(), (2, 2));
+    view.move_vertically(-2);
+    assert_eq!(view.cursor_position(), (0, 7));
+    view.move_to_line_edge(true);
+    view.move_horizontally(true);
+    assert_eq!(view.cursor_position(), (1, 0));
+    view.move_horizontally(false);
+    assert_eq!(view.cursor_position(), (0, 9));
+    view.move_to_text_edge(true);
+    assert_eq!(view.cursor_position(), (2, 2));
+}
+
+#[test]
+fn test_moving_right_at_the_end_stays_put() {
+    let mut view = view("abc\ndef");
+    view.move_to_text_edge(true);
+    assert_eq!(view.cursor, 7);
+    view.move_horizontally(true);
+    assert_eq!(view.cursor, 7);
+}
+
+#[test]
+fn test_selection() {
+    let mut view = view("one\ntwo");
+    view.set_cursor(0, 1);
+    view.begin_move(true);
+    view.move_vertically(1);
+    assert_eq!(view.selected_text().as_deref(), Some("ne\nt"));
+    view.insert("X");
+    assert_eq!((text(&view.buffer), view.cursor), ("oXwo".to_string(), 2));
+    assert_eq!(view.selected_text(), None);
+    view.select_all();
+    view.backspace();
+    assert_eq!(text(&view.buffer), "");
+    // Moving without extending drops the selection
+    let mut view = self::view("abc");
+    view.select(0..2);
+    view.begin_move(false);
+    view.move_horizontally(true);
+    assert_eq!((view.selection(), view.cursor), (3..3, 3));
+}
+
+#[test]
+fn test_find_wraps_around() {
+    let buffer = RopeBuffer::from_text("ab\né ab\nab");
+    assert_eq!(buffer.find("ab", 1, true), Some(5));
+    assert_eq!(buffer.find("ab", 8, true), Some(8));
+    assert_eq!(buffer.find("ab", 9, true), Some(0));
+    assert_eq!(buffer.find("ab", 5, false), Some(0));
+    assert_eq!(buffer.find("ab", 0, false), Some(8));
+    assert_eq!(buffer.find("zz", 0, true), None);
+    assert_eq!(buffer.find("", 0, true), None);
+
+    let mut view = view("x ab ab");
+    assert!(view.find("ab", true));
+    assert!(view.find("ab", true));
+    assert_eq!(view.selection(), 5..7);
+    assert!(view.find("ab", false));
+    assert_eq!(view.selection(), 2..4);
+    assert!(!view.find("q", true));
+    assert!(view.not_found);
+}
+
+#[test]
+fn test_tab_columns() {
+    assert_eq!(expand_tabs("\tx\ty"), "    x   y");
+    assert_eq!(display_column("\tx\ty", 2), 5);
+    assert_eq!(display_column("\tx\ty", 3), 8);
+    assert_eq!(char_column("\tx\ty", 1), 0);
+    assert_eq!(char_column("\tx\ty", 3), 1);
+    assert_eq!(char_column("\tx\ty", 4), 1);
+    assert_eq!(char_column("\tx\ty", 20), 4);
+}
+
+#[test]
+fn test_threshold() {
+    let files = FileSettings { large_file_mb: 2, ..FileSettings::default() };
+    assert!(!files.is_large(2 * 1024 * 1024 - 1));
+    assert!(files.is_large(2 * 1024 * 1024));
+    let files = FileSettings { large_file_mb: 0, ..FileSettings::default() };
+    assert!(!files.is_large(u64::MAX));
+}
@@ -0,0 +1,197 @@
+mod support;
+
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use serde_json::json;
+use support::TempDir;
+use writer::lsp::{
+    apply_edits, encode_message, path_to_uri, project_root, read_message, uri_to_path, LspClient, LspEvent,
+    Position, Severity, TextEdit,
+};
+
+const URI: &str = "file:///tmp/main.rs";
+
+/// The fake server example, which `cargo test` builds next to this suite
+fn server_path() -> PathBuf {
+    let deps = std::env::current_exe().unwrap().parent().unwrap().to_path_buf();
+    let path = deps.with_file_name("examples").join(format!("fake-lsp-server{}", std::env::consts::EXE_SUFFIX));
+    assert!(path.exists(), "{} is missing, build it with `cargo build --example fake-lsp-server`", path.display());
+    path
+}
+
+fn start_server(args: &[&str]) -> LspClient {
+    let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+    let command = server_path().to_string_lossy().into_owned();
+    LspClient::start(&command, &args, &std::env::temp_dir(), || {}).unwrap()
+}
+
+/// Poll until the server sends an event `pick` accepts
+fn wait_for<T>(client: &mut LspClient, mut pick: impl FnMut(LspEvent) -> Option<T>) -> T {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while Instant::now() < deadline {
+        for event in client.poll() {
+            if let Some(found) = pick(event) {
+                return found;
+            }
+        }
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    panic!("no matching event from the language server");
+}
+
+fn diagnostics(client: &mut LspClient) -> Vec<(Severity, Position)> {
+    wait_for(client, |event| match event {
+        LspEvent::Diagnostics { diagnostics, .. } => {
+            Some(diagnostics.into_iter().map(|d| (d.severity, d.start)).collect())
+        }
+        _ => None,
+    })
+}
+
+#[test]
+fn test_utf16_positions() {
+    let text = "a\nh€llo 😀x\n";
+    let x = text.find('x').unwrap();
+    let position = Position::from_offset(text, x);
+    // The emoji takes two UTF-16 code units
+    assert_eq!(position, Position { line: 1, character: 8 });
+    assert_eq!(position.to_offset(text), x);
+    assert_eq!(Position { line: 0, character: 99 }.to_offset(text), 1);
+    assert_eq!(Position { line: 9, character: 0 }.to_offset(text), text.len());
+}
+
+#[test]
+fn test_apply_edits_uses_original_positions() {
+    let edit = |line, start, end, text: &str| TextEdit {
+        start: Position { line, character: start },
+        end: Position { line, character: end },
+        new_text: text.to_string(),
+    };
+    let edits = [edit(1, 0, 3, "four"), edit(0, 0, 0, "a"), edit(0, 0, 0, "b"), edit(0, 3, 3, "!")];
+    assert_eq!(apply_edits("one\ntwo\n", &edits), "abone!\nfour\n");
+}
+
+#[test]
+fn test_message_framing() {
+    let mut stream = encode_message(&json!({ "id": 1, "result": "é" }));
+    stream.extend(encode_message(&json!({ "method": "exit" })));
+    let mut reader = BufReader::new(stream.as_slice());
+    assert_eq!(read_message(&mut reader).unwrap(), Some(json!({ "id": 1, "result": "é" })));
+    assert_eq!(read_message(&mut reader).unwrap(), Some(json!({ "method": "exit" })));
+    assert_eq!(read_message(&mut reader).unwrap(), None);
+}
+
+#[test]
+fn test_file_uris() {
+    let path = Path::new("/home/me/My Notes/50%.md");
+    let uri = path_to_uri(path);
+    assert_eq!(uri, "file:///home/me/My%20Notes/50%25.md");
+    assert_eq!(uri_to_path(&uri).as_deref(), Some(path));
+    assert_eq!(uri_to_path("untitled:1"), None);
+}
+
+#[test]
+fn test_project_root() {
+    let dir = TempDir::new("lsp-root");
+    std::fs::create_dir_all(dir.join("src/bin")).unwrap();
+    std::fs::write(dir.join("Cargo.toml"), "").unwrap();
+    assert_eq!(project_root(&dir.join("src/bin/tool.rs")), dir.path());
+}
+
+#[test]
+fn test_diagnostics_follow_incremental_changes() {
+    let mut client = start_server(&[]);
+    let mut text = "fn main() {\n    // TODO\n}\n".to_string();
+    client.did_open(URI, "Rust", &text);
+    assert_eq!(diagnostics(&mut client), vec![(Severity::Warning, Position { line: 1, character: 7 })]);
+
+    // Only the changed range is sent, so the server's copy must line up
+    let old = text.clone();
+    text = text.replace("// TODO", "let é = error;");
+    client.did_change(URI, &old, &text);
+    assert_eq!(diagnostics(&mut client), vec![(Severity::Error, Position { line: 1, character: 12 })]);
+    let old = text.clone();
+    text.insert_str(0, "// TODO\n");
+    client.did_change(URI, &old, &text);
+    assert_eq!(diagnostics(&mut client).len(), 2);
+}
+
+#[test]
+fn test_full_text_sync() {
+    let mut client = start_server(&["--full-sync"]);
+    client.did_open(URI, "Rust", "a\n");
+    assert!(diagnostics(&mut client).is_empty());
+    client.did_change(URI, "a\n", "a TODO\n");
+    assert_eq!(diagnostics(&mut client), vec![(Severity::Warning, Position { line: 0, character: 2 })]);
+}
+
+#[test]
+fn test_dropping_does_not_wait_for_the_server() {
+    let mut client = start_server(&["--slow-shutdown"]);
+    client.did_open(URI, "Rust", "a\n");
+    assert!(diagnostics(&mut client).is_empty());
+    let started = Instant::now();
+    drop(client);
+    assert!(started.elapsed() < Duration::from_millis(200), "{:?}", started.elapsed());
+}
+
+#[test]
+fn test_completion_hover_and_definition() {
+    let mut client = start_server(&[]);
+    let text = "let value = 1;\nprint(value);\n";
+    client.did_open(URI, "Rust", text);
+    let use_site = Position::from_offset(text, text.rfind("value").unwrap() + 2);
+
+    client.completion(URI, use_site);
+    let items = wait_for(&mut client, |event| match event {
+        LspEvent::Completion(items) => Some(items),
+        _ => None,
+    });
+    assert_eq!(items.len(), 3);
+    assert_eq!(items[0].detail.as_deref(), Some("macro"));
+    assert_eq!(items[2].insert_text, "format!");
+
+    client.hover(URI, use_site);
+    let hover = wait_for(&mut client, |event| match event {
+        LspEvent::Hover(text) => Some(text),
+        _ => None,
+    });
+    assert_eq!(hover, "`value`");
+
+    client.definition(URI, use_site);
+    let definition = wait_for(&mut client, |event| match event {
+        LspEvent::Definition { uri, position } => Some((uri, position)),
+        _ => None,
+    });
+    assert_eq!(definition, (URI.to_string(), Position { line: 0, character: 4 }));
+}
+
+#[test]
+fn test_rename_and_formatting_edits() {
+    let mut client = start_server(&[]);
+    let text = "let old = 1;  \nold + old\n";
+    client.did_open(URI, "Rust", text);
+
+    client.rename(URI, Position { line: 1, character: 0 }, "new");
+    let changes = wait_for(&mut client, |event| match event {
+        LspEvent::Edits(changes) => Some(changes),
+        _ => None,
+    });
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].0, URI);
+    assert_eq!(apply_edits(text, &changes[0].1), "let new = 1;  \nnew + new\n");
+
+    client.formatting(URI, 4, true);
+    let changes = wait_for(&mut client, |event| match event {
+        LspEvent::Edits(changes) => Some(changes),
+        _ => None,
+    });
+    assert_eq!(apply_edits(text, &changes[0].1), "let old = 1;\nold + old\n");
+}
+
+#[test]
+fn test_missing_server_fails_to_start() {
+    let result = LspClient::start("writer-no-such-language-server", &[], &std::env::temp_dir(), || {});
+    assert!(result.is_err());
+}
@@ -0,0 +1,98 @@
+use writer::outline::{filter_symbols, fuzzy_score, symbols, OutlineState, SymbolKind};
+
+fn names(text: &str, language: &str) -> Vec<(SymbolKind, String, usize)> {
+    symbols(text, language)
+        .into_iter()
+        .map(|s| (s.kind, s.name, s.depth))
+        .collect()
+}
+
+#[test]
+fn test_rust_symbols() {
+    let text = "pub mod ui;\npub(crate) struct App {\n}\nimpl<T: Clone> Display for Wrapper<T> {\n    pub async fn render(&self) {}\n}\nconst MAX: usize = 3;\nlet fn_name = 1;\n";
+    assert_eq!(
+        names(text, "Rust"),
+        vec![
+            (SymbolKind::Module, "ui".to_string(), 0),
+            (SymbolKind::Struct, "App".to_string(), 0),
+            (SymbolKind::Impl, "Display for Wrapper<T>".to_string(), 0),
+            (SymbolKind::Function, "render".to_string(), 1),
+            (SymbolKind::Constant, "MAX".to_string(), 0),
+        ]
+    );
+}
+
+#[test]
+fn test_python_nesting() {
+    let text = "class Parser:\n    def parse(self):\n        pass\n\nasync def main():\n\tdef inner(): pass\n";
+    let found = symbols(text, "Python");
+    assert_eq!(found.len(), 4);
+    assert_eq!((found[0].kind, found[0].depth), (SymbolKind::Class, 0));
+    assert_eq!((found[1].name.as_str(), found[1].line, found[1].depth), ("parse", 1, 1));
+    assert_eq!(found[2].name, "main");
+    assert_eq!(found[3].depth, 1);
+}
+
+#[test]
+fn test_lua_functions() {
+    let text = "local function helper(x)\nend\nfunction M.setup(opts)\nend\nlocal on_exit = function() end\nlocal count = 1\n";
+    let found: Vec<String> = symbols(text, "Lua").into_iter().map(|s| s.name).collect();
+    assert_eq!(found, ["helper", "M.setup", "on_exit"]);
+}
+
+#[test]
+fn test_sql_create_statements() {
+    let text = "create table if not exists Users (id int);\nCREATE OR REPLACE VIEW active AS SELECT 1;\nCREATE UNIQUE INDEX users_email ON Users(email);\nSELECT * FROM Users;\n";
+    assert_eq!(
+        names(text, "SQL"),
+        vec![
+            (SymbolKind::Table, "Users".to_string(), 0),
+            (SymbolKind::View, "active".to_string(), 0),
+            (SymbolKind::Index, "users_email".to_string(), 0),
+        ]
+    );
+}
+
+#[test]
+fn test_markdown_headings_skip_code_fences() {
+    let text = "# Title\nText\n```sh\n# not a heading\n```\n## Usage ##\n#hashtag\n";
+    assert_eq!(
+        names(text, "Markdown"),
+        vec![
+            (SymbolKind::Heading, "Title".to_string(), 0),
+            (SymbolKind::Heading, "Usage".to_string(), 1),
+        ]
+    );
+}
+
+#[test]
+fn test_fuzzy_score() {
+    assert!(fuzzy_score("gts", "go_to_symbol").is_some());
+    assert!(fuzzy_score("GTS", "goToSymbol").is_some());
+    assert_eq!(fuzzy_score("sx", "symbol"), None);
+    assert_eq!(fuzzy_score("", "anything"), Some(0));
+    // Consecutive and word-start matches score higher
+    assert!(fuzzy_score("par", "parse").unwrap() > fuzzy_score("par", "compare").unwrap());
+}
+
+#[test]
+fn test_filter_orders_best_match_first() {
+    let found = symbols("fn compare() {}\nfn parse() {}\nfn render() {}\n", "Rust");
+    let names: Vec<&str> = filter_symbols(&found, "par").iter().map(|s| s.name.as_str()).collect();
+    assert_eq!(names, ["parse", "compare"]);
+    assert_eq!(filter_symbols(&found, "").len(), 3);
+}
+
+#[test]
+fn test_outline_refreshes_on_change() {
+    let mut outline = OutlineState::default();
+    outline.refresh("fn a() {}\n", 1, "Rust");
+    assert_eq!(outline.symbols.len(), 1);
+    outline.refresh("fn a() {}\nfn b() {}\n", 2, "Rust");
+    assert_eq!(outline.symbols.len(), 2);
+    // Unchanged text is only looked at again for another language
+    outline.refresh("", 2, "Rust");
+    assert_eq!(outline.symbols.len(), 2);
+    outline.refresh("fn a() {}\nfn b() {}\n", 2, "Plain Text");
+    assert!(outline.symbols.is_empty());
+}
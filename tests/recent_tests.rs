@@ -0,0 +1,98 @@
+mod support;
+
+use std::path::{Path, PathBuf};
+use support::TempDir;
+use writer::recent::{RecentFile, RecentFiles, MAX_RECENT};
+
+fn paths<'a>(files: impl Iterator<Item = &'a RecentFile>) -> Vec<PathBuf> {
+    files.map(|file| file.path.clone()).collect()
+}
+
+#[test]
+fn test_add_moves_to_top() {
+    let mut recent = RecentFiles::default();
+    recent.add(Path::new("/a"));
+    recent.add(Path::new("/b"));
+    recent.add(Path::new("/a"));
+    assert_eq!(paths(recent.files.iter()), vec![PathBuf::from("/a"), PathBuf::from("/b")]);
+}
+
+#[test]
+fn test_oldest_unpinned_are_dropped() {
+    let mut recent = RecentFiles::default();
+    recent.add(Path::new("/pinned"));
+    recent.toggle_pin(Path::new("/pinned"));
+    for index in 0..MAX_RECENT + 2 {
+        recent.add(&PathBuf::from(format!("/{}", index)));
+    }
+    assert_eq!(recent.unpinned().count(), MAX_RECENT);
+    assert_eq!(recent.unpinned().next().unwrap().path, PathBuf::from(format!("/{}", MAX_RECENT + 1)));
+    assert!(recent.files.iter().all(|file| file.path != Path::new("/0")));
+    assert_eq!(paths(recent.pinned()), vec![PathBuf::from("/pinned")]);
+}
+
+#[test]
+fn test_pinning_survives_reopening_and_clearing() {
+    let mut recent = RecentFiles::default();
+    recent.add(Path::new("/a"));
+    recent.add(Path::new("/b"));
+    recent.toggle_pin(Path::new("/a"));
+    recent.add(Path::new("/a"));
+    assert!(recent.files[0].pinned);
+    recent.clear();
+    assert_eq!(paths(recent.files.iter()), vec![PathBuf::from("/a")]);
+    recent.toggle_pin(Path::new("/a"));
+    recent.clear();
+    assert!(recent.files.is_empty());
+}
+
+#[test]
+fn test_prune_missing() {
+    let dir = TempDir::new("recent-prune");
+    let kept = dir.join("kept.txt");
+    std::fs::write(&kept, "").unwrap();
+    let mut recent = RecentFiles::default();
+    recent.add(&dir.join("gone.txt"));
+    recent.add(&kept);
+    assert!(recent.prune_missing());
+    assert_eq!(paths(recent.files.iter()), vec![kept]);
+    assert!(!recent.prune_missing());
+}
+
+#[test]
+fn test_save_and_load() {
+    let dir = TempDir::new("recent-save");
+    let path = dir.join("data").join("recent.toml");
+    assert_eq!(RecentFiles::load_from(&path).unwrap(), RecentFiles::default());
+    let mut recent = RecentFiles::default();
+    recent.add(Path::new("/a"));
+    recent.add(Path::new("/b"));
+    recent.toggle_pin(Path::new("/a"));
+    recent.push_closed(Path::new("/c"));
+    recent.save_to(&path).unwrap();
+    let loaded = RecentFiles::load_from(&path).unwrap();
+    assert_eq!(loaded.files, recent.files);
+    // Closed files only last for the session
+    assert!(loaded.closed.is_empty());
+    std::fs::write(&path, "files = 3").unwrap();
+    assert!(RecentFiles::load_from(&path).is_err());
+}
+
+#[test]
+fn test_reopen_closed() {
+    let dir = TempDir::new("recent-closed");
+    let [first, second, current] = ["first.txt", "second.txt", "current.txt"].map(|name| dir.join(name));
+    for path in [&first, &second, &current] {
+        std::fs::write(path, "").unwrap();
+    }
+    let mut recent = RecentFiles::default();
+    recent.push_closed(&first);
+    recent.push_closed(&dir.join("deleted.txt"));
+    recent.push_closed(&second);
+    recent.push_closed(&current);
+    // Closing a file again moves it to the top
+    recent.push_closed(&second);
+    assert_eq!(recent.pop_closed(Some(&current)), Some(second));
+    assert_eq!(recent.pop_closed(Some(&current)), Some(first));
+    assert_eq!(recent.pop_closed(None), None);
+}
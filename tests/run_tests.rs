@@ -0,0 +1,128 @@
+use std::path::Path;
+use std::time::{Duration, Instant};
+use writer::output::{find_locations, AnsiColor, AnsiStyle, OutputLocation, OutputLog};
+use writer::run::{run_command, RunJob, RunOutput};
+use writer::settings::CommandSettings;
+
+/// Output and exit status of a job, waiting for it to finish
+fn finish(job: &mut RunJob) -> (String, String, Option<i32>) {
+    let (mut stdout, mut stderr) = (String::new(), String::new());
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while Instant::now() < deadline {
+        let (chunks, status) = job.poll();
+        for chunk in chunks {
+            match chunk {
+                RunOutput::Stdout(text) => stdout.push_str(&text),
+                RunOutput::Stderr(text) => stderr.push_str(&text),
+            }
+        }
+        if let Some(status) = status {
+            return (stdout, stderr, status.code());
+        }
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    panic!("program did not finish");
+}
+
+#[test]
+fn test_ansi_colors_are_stripped_and_styled() {
+    let mut output = OutputLog::default();
+    output.append("\x1b[1;31merror\x1b[0m: bad\n\x1b[38;5;208mok\x1b[39m", false);
+    assert_eq!(output.text(), "error: bad\nok");
+    let red = AnsiStyle { foreground: Some(AnsiColor::Indexed(1)), bold: true, ..Default::default() };
+    assert_eq!(output.styled_line(0), vec![(0..5, red), (5..10, AnsiStyle::default())]);
+    assert_eq!(output.styled_line(1)[0].1.foreground, Some(AnsiColor::Indexed(208)));
+}
+
+#[test]
+fn test_escape_split_across_chunks() {
+    let mut output = OutputLog::default();
+    output.append("a\x1b[3", false);
+    output.append("2mb", false);
+    assert_eq!(output.text(), "ab");
+    assert_eq!(output.styled_line(0)[1], (1..2, AnsiStyle { foreground: Some(AnsiColor::Indexed(2)), ..Default::default() }));
+}
+
+#[test]
+fn test_carriage_return_rewrites_line() {
+    let mut output = OutputLog::default();
+    output.append("done\n10%\r", false);
+    output.append("50%\r100%\r\nnext", false);
+    assert_eq!(output.text(), "done\n100%\nnext");
+    assert_eq!(output.line_count(), 3);
+}
+
+#[test]
+fn test_error_output_is_red_by_default() {
+    let mut output = OutputLog::default();
+    output.append("out\n", false);
+    output.append("err\n", true);
+    assert_eq!(output.styled_line(1)[0].1.foreground, Some(AnsiColor::Indexed(1)));
+    assert_eq!(output.styled_line(0)[0].1.foreground, None);
+}
+
+#[test]
+fn test_find_locations() {
+    let found = find_locations("  --> src/main.rs:12:5 and lib.rs:3, not http://host:80 or 12:30");
+    assert_eq!(
+        found,
+        vec![
+            OutputLocation { range: 6..22, path: "src/main.rs".to_string(), line: 12, column: Some(5) },
+            OutputLocation { range: 27..35, path: "lib.rs".to_string(), line: 3, column: None },
+        ]
+    );
+    let python = find_locations("  File \"/tmp/app.py\", line 7, in <module>");
+    assert_eq!(python[0].path, "/tmp/app.py");
+    assert_eq!((python[0].line, python[0].range.clone()), (7, 2..28));
+}
+
+#[test]
+fn test_find_locations_after_wide_whitespace() {
+    let line = "erreur\u{a0}: src/main.rs:3:5";
+    let found = find_locations(line);
+    assert_eq!(&line[found[0].range.clone()], "src/main.rs:3:5");
+    let line = "\u{3000}é src/a.rs:1";
+    let found = find_locations(line);
+    assert_eq!(&line[found[0].range.clone()], "src/a.rs:1");
+}
+
+#[test]
+fn test_run_command_choice() {
+    let file = Path::new("/tmp/script");
+    let python = run_command(None, "print(1)\n", "Python", file).unwrap();
+    assert_eq!((python.command.as_str(), python.args.as_slice()), ("python3", ["/tmp/script".to_string()].as_slice()));
+    let shebang = run_command(None, "#!/usr/bin/env bash\necho\n", "Shell", file).unwrap();
+    assert_eq!(shebang.command, "bash");
+    let configured = CommandSettings::new("deno", &["run", "{file}", "--quiet"]);
+    let deno = run_command(Some(&configured), "#!/usr/bin/env node\n", "JavaScript", file).unwrap();
+    assert_eq!(deno.args, ["run", "/tmp/script", "--quiet"]);
+    assert!(run_command(Some(&CommandSettings::default()), "", "Python", file).is_none());
+    assert!(run_command(None, "text", "Plain Text", file).is_none());
+}
+
+#[test]
+fn test_run_streams_output_and_status() {
+    let script = CommandSettings::new("sh", &["-c", "echo out; echo err >&2; exit 3"]);
+    let mut job = RunJob::start(&script, None, || {}).unwrap();
+    assert_eq!(finish(&mut job), ("out\n".to_string(), "err\n".to_string(), Some(3)));
+}
+
+#[test]
+fn test_kill_stops_program() {
+    let script = CommandSettings::new("sh", &["-c", "sleep 30 & sleep 30"]);
+    let mut job = RunJob::start(&script, None, || {}).unwrap();
+    let started = Instant::now();
+    job.kill();
+    let (_, _, code) = finish(&mut job);
+    assert_eq!(code, None);
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn test_missing_program_is_an_error() {
+    let missing = CommandSettings::new("no-such-program-here", &[]);
+    let Err(message) = RunJob::start(&missing, None, || {}) else {
+        panic!("a missing program started");
+    };
+    assert!(message.starts_with("Failed to run no-such-program-here"));
+}
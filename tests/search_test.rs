@@ -1,3 +1,6 @@
+// The tests set up their state field by field
+#![allow(clippy::field_reassign_with_default)]
+
 use writer::search::SearchState;
 
 #[test]
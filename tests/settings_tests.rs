@@ -0,0 +1,82 @@
+mod support;
+
+use support::TempDir;
+use writer::indent::IndentStyle;
+use writer::settings::{FileSettings, Settings};
+use writer::wrap::WrapMode;
+
+#[test]
+fn test_defaults_match_previous_behavior() {
+    let settings = Settings::default();
+    assert!(!settings.editor.show_line_numbers);
+    assert!(!settings.editor.syntax_highlighting);
+    assert!(settings.editor.auto_indent);
+    assert_eq!(settings.editor.wrap_mode(), WrapMode::Off);
+    assert!(settings.validate().is_empty());
+}
+
+#[test]
+fn test_round_trip_through_file() {
+    let dir = TempDir::new("settings");
+    let path = dir.join("writer").join("settings.toml");
+    let mut settings = Settings::default();
+    settings.editor.show_line_numbers = true;
+    settings.files.insert_final_newline = true;
+    settings.keybindings.insert("find".to_string(), "Ctrl+Shift+F".to_string());
+    settings.save_to(&path).unwrap();
+
+    assert_eq!(Settings::load_from(&path), Ok(settings));
+}
+
+#[test]
+fn test_missing_file_gives_defaults() {
+    let dir = TempDir::new("settings-missing");
+    let path = dir.join("no-such-dir").join("settings.toml");
+    assert_eq!(Settings::load_from(&path), Ok(Settings::default()));
+}
+
+#[test]
+fn test_invalid_values_are_reported() {
+    let error = Settings::from_toml("[editor]\ntab_size = 0\n[appearance]\nfont_size = 200.0\n").unwrap_err();
+    assert!(error.contains("editor.tab_size"));
+    assert!(error.contains("appearance.font_size"));
+
+    let error = Settings::from_toml("[editor]\nshow_line_numbers = \"yes\"\n").unwrap_err();
+    assert!(error.contains("show_line_numbers"));
+
+    let error = Settings::from_toml("[keybindings]\nfly = \"Ctrl+K\"\n").unwrap_err();
+    assert!(error.contains("fly"));
+}
+
+#[test]
+fn test_wrap_mode_mapping() {
+    let mut settings = Settings::default();
+    settings.editor.set_wrap_mode(WrapMode::Column(100));
+    assert!(settings.editor.word_wrap);
+    assert_eq!(settings.editor.wrap_column, 100);
+    settings.editor.set_wrap_mode(WrapMode::Off);
+    // The column is kept for when wrapping is turned back on
+    settings.editor.word_wrap = true;
+    assert_eq!(settings.editor.wrap_mode(), WrapMode::Column(100));
+}
+
+#[test]
+fn test_indent_style_from_settings() {
+    let mut settings = Settings::default();
+    settings.editor.tab_size = 2;
+    assert_eq!(settings.editor.indent_style(), IndentStyle::Spaces(2));
+    settings.editor.insert_spaces = false;
+    assert_eq!(settings.editor.indent_style(), IndentStyle::Tabs);
+}
+
+#[test]
+fn test_prepare_for_save() {
+    let files = FileSettings {
+        trim_trailing_whitespace: true,
+        insert_final_newline: true,
+        ..Default::default()
+    };
+    assert_eq!(files.prepare_for_save("a  \nb\t\nc"), "a\nb\nc\n");
+    assert_eq!(files.prepare_for_save(""), "");
+    assert_eq!(FileSettings::default().prepare_for_save("a  "), "a  ");
+}
@@ -0,0 +1,209 @@
+// Minimal language server used by the LSP client tests
+//
+// It keeps one document in sync, reports every `TODO` as a warning and
+// every `error` as an error, and answers completion, hover, definition,
+// rename and formatting requests from the document text. Pass
+// `--full-sync` to make it ask for the whole text on every change, and
+// `--slow-shutdown` to make it take two seconds to answer `shutdown`.
+
+use std::io::{self, BufRead, Write};
+use serde_json::{json, Value};
+
+fn read_message(reader: &mut impl BufRead) -> Option<Value> {
+    let mut length = 0;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).ok()? == 0 {
+            return None;
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some(value) = line.strip_prefix("Content-Length:") {
+            length = value.trim().parse().ok()?;
+        }
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).ok()?;
+    serde_json::from_slice(&body).ok()
+}
+
+fn write_message(out: &mut impl Write, message: Value) {
+    let body = message.to_string();
+    write!(out, "Content-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+    out.flush().unwrap();
+}
+
+/// Byte offset of a UTF-16 line/character position
+fn offset(text: &str, position: &Value) -> usize {
+    let line = position["line"].as_u64().unwrap() as usize;
+    let character = position["character"].as_u64().unwrap() as usize;
+    let start: usize = text.split('\n').take(line).map(|l| l.len() + 1).sum();
+    let mut units = 0;
+    for (i, c) in text[start.min(text.len())..].char_indices() {
+        if units >= character || c == '\n' {
+            return start + i;
+        }
+        units += c.len_utf16();
+    }
+    text.len()
+}
+
+fn position(text: &str, offset: usize) -> Value {
+    let before = &text[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    json!({ "line": before.matches('\n').count(), "character": before[line_start..].encode_utf16().count() })
+}
+
+fn range(text: &str, start: usize, end: usize) -> Value {
+    json!({ "start": position(text, start), "end": position(text, end) })
+}
+
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Byte range of the word at an offset
+fn word_at(text: &str, offset: usize) -> (usize, usize) {
+    let start = text[..offset].rfind(|c| !is_word(c)).map_or(0, |i| i + 1);
+    let end = text[offset..].find(|c| !is_word(c)).map_or(text.len(), |i| offset + i);
+    (start, end)
+}
+
+/// Byte offsets of whole-word occurrences of `word`
+fn occurrences(text: &str, word: &str) -> Vec<usize> {
+    text.match_indices(word)
+        .map(|(i, _)| i)
+        .filter(|&i| {
+            let before = text[..i].chars().next_back().is_none_or(|c| !is_word(c));
+            let after = text[i + word.len()..].chars().next().is_none_or(|c| !is_word(c));
+            before && after
+        })
+        .collect()
+}
+
+fn diagnostics(uri: &str, text: &str) -> Value {
+    let mut diagnostics = Vec::new();
+    for (word, severity) in [("TODO", 2), ("error", 1)] {
+        for start in occurrences(text, word) {
+            diagnostics.push(json!({
+                "range": range(text, start, start + word.len()),
+                "severity": severity,
+                "source": "fake",
+                "message": format!("found {}", word),
+            }));
+        }
+    }
+    json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/publishDiagnostics",
+        "params": { "uri": uri, "diagnostics": diagnostics },
+    })
+}
+
+fn main() {
+    let full_sync = std::env::args().any(|a| a == "--full-sync");
+    let slow_shutdown = std::env::args().any(|a| a == "--slow-shutdown");
+    let stdin = io::stdin();
+    let mut reader = stdin.lock();
+    let mut out = io::stdout();
+    let mut uri = String::new();
+    let mut text = String::new();
+
+    while let Some(message) = read_message(&mut reader) {
+        let method = message["method"].as_str().unwrap_or_default();
+        let params = &message["params"];
+        let at = |text: &str| offset(text, &params["position"]);
+        let result = match method {
+            "initialize" => json!({
+                "capabilities": {
+                    "textDocumentSync": { "openClose": true, "change": if full_sync { 1 } else { 2 } },
+                    "completionProvider": {},
+                    "hoverProvider": true,
+                    "definitionProvider": true,
+                    "renameProvider": true,
+                    "documentFormattingProvider": true,
+                },
+            }),
+            "textDocument/didOpen" => {
+                uri = params["textDocument"]["uri"].as_str().unwrap().to_string();
+                text = params["textDocument"]["text"].as_str().unwrap().to_string();
+                write_message(&mut out, diagnostics(&uri, &text));
+                continue;
+            }
+            "textDocument/didChange" => {
+                for change in params["contentChanges"].as_array().unwrap() {
+                    let new_text = change["text"].as_str().unwrap();
+                    if change.get("range").is_some() {
+                        let start = offset(&text, &change["range"]["start"]);
+                        let end = offset(&text, &change["range"]["end"]);
+                        text.replace_range(start..end, new_text);
+                    } else {
+                        text = new_text.to_string();
+                    }
+                }
+                write_message(&mut out, diagnostics(&uri, &text));
+                continue;
+            }
+            "textDocument/completion" => json!({
+                "isIncomplete": false,
+                "items": [
+                    { "label": "println", "detail": "macro" },
+                    { "label": "print" },
+                    { "label": "format", "insertText": "format!" },
+                ],
+            }),
+            "textDocument/hover" => {
+                let (start, end) = word_at(&text, at(&text));
+                json!({ "contents": { "kind": "markdown", "value": format!("`{}`", &text[start..end]) } })
+            }
+            "textDocument/definition" => {
+                let (start, end) = word_at(&text, at(&text));
+                match occurrences(&text, &text[start..end]).first() {
+                    Some(&first) => json!({ "uri": uri, "range": range(&text, first, first + end - start) }),
+                    None => Value::Null,
+                }
+            }
+            "textDocument/rename" => {
+                let (start, end) = word_at(&text, at(&text));
+                let edits: Vec<Value> = occurrences(&text, &text[start..end])
+                    .into_iter()
+                    .map(|i| json!({ "range": range(&text, i, i + end - start), "newText": params["newName"] }))
+                    .collect();
+                json!({ "changes": { uri.clone(): edits } })
+            }
+            "textDocument/formatting" => {
+                // Remove trailing spaces
+                let mut edits = Vec::new();
+                let mut line_start = 0;
+                for line in text.split('\n') {
+                    let trimmed = line.trim_end_matches(' ').len();
+                    if trimmed < line.len() {
+                        edits.push(json!({
+                            "range": range(&text, line_start + trimmed, line_start + line.len()),
+                            "newText": "",
+                        }));
+                    }
+                    line_start += line.len() + 1;
+                }
+                Value::Array(edits)
+            }
+            "shutdown" => {
+                if slow_shutdown {
+                    std::thread::sleep(std::time::Duration::from_secs(2));
+                }
+                Value::Null
+            }
+            "exit" => return,
+            _ => {
+                if let Some(id) = message.get("id").filter(|_| message.get("method").is_some()) {
+                    let error = json!({ "code": -32601, "message": format!("unknown method {}", method) });
+                    write_message(&mut out, json!({ "jsonrpc": "2.0", "id": id, "error": error }));
+                }
+                continue;
+            }
+        };
+        write_message(&mut out, json!({ "jsonrpc": "2.0", "id": message["id"], "result": result }));
+    }
+}
@@ -0,0 +1,31 @@
+// Each suite uses only some of the helpers
+#![allow(dead_code)]
+
+use std::path::{Path, PathBuf};
+
+/// A directory of its own for one test, removed again when dropped
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    /// A fresh, empty directory named after `name` and this process
+    pub fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("writer-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+    pub fn join(&self, path: impl AsRef<Path>) -> PathBuf {
+        self.0.join(path)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
@@ -0,0 +1,94 @@
+mod support;
+
+use std::fs;
+use support::TempDir;
+use writer::indent::IndentStyle;
+use writer::languages::{parse_definition, DefinitionFormat, LanguageRegistry};
+
+const RULES: &str = r#"
+name = "Rules"
+extensions = ["rules"]
+filenames = ["Rulefile"]
+line_comment = "//"
+block_comment = ["(*", "*)"]
+quotes = ["\"", "`"]
+keywords = ["when", "then", "end"]
+types = ["Fact"]
+special = ["@"]
+tab_size = 2
+"#;
+
+#[test]
+fn test_parse_toml_definition() {
+    let language = parse_definition(RULES, DefinitionFormat::Toml).unwrap();
+    assert_eq!(language.name, "Rules");
+    assert_eq!(language.syntax.language, "Rules");
+    assert_eq!(language.syntax.comment, "//");
+    assert_eq!(language.syntax.comment_multiline, ["(*", "*)"]);
+    assert_eq!(language.block_comment, Some(("(*", "*)")));
+    assert_eq!(language.quotes, &['"', '`']);
+    assert!(language.syntax.is_keyword("when"));
+    assert!(language.syntax.is_type("Fact"));
+    assert_eq!(language.indent, Some(IndentStyle::Spaces(2)));
+}
+
+#[test]
+fn test_parse_json_definition() {
+    let source = r#"{"name": "Flow", "extensions": ["flow"], "keywords": ["step"], "insert_spaces": false}"#;
+    let language = parse_definition(source, DefinitionFormat::Json).unwrap();
+    assert_eq!(language.extensions, &["flow"]);
+    assert!(language.syntax.is_keyword("step"));
+    assert_eq!(language.indent, Some(IndentStyle::Tabs));
+    assert_eq!(language.line_comment, None);
+}
+
+#[test]
+fn test_case_insensitive_keywords() {
+    let source = "name = \"Query\"\nextensions = [\"q\"]\ncase_sensitive = false\nkeywords = [\"select\"]\n";
+    let language = parse_definition(source, DefinitionFormat::Toml).unwrap();
+    assert!(language.syntax.is_keyword("select"));
+    assert!(language.syntax.is_keyword("SeLeCt"));
+}
+
+#[test]
+fn test_syntax_errors_name_the_line() {
+    let source = "name = \"Broken\"\nextensions = [\"b\"]\ncolour = \"red\"\n";
+    let error = parse_definition(source, DefinitionFormat::Toml).unwrap_err();
+    assert!(error.starts_with("line 3:"), "{}", error);
+    assert!(error.contains("colour"), "{}", error);
+
+    let error = parse_definition("{\"name\": \"Broken\",\n}", DefinitionFormat::Json).unwrap_err();
+    assert!(error.contains("line 2"), "{}", error);
+}
+
+#[test]
+fn test_invalid_values_are_all_reported() {
+    let source = "name = \"\"\nquotes = [\"''\"]\nline_comment = \"\"\nkeywords = [\"two words\"]\ntab_size = 0\n";
+    let error = parse_definition(source, DefinitionFormat::Toml).unwrap_err();
+    for expected in ["name", "no extensions", "quote", "line_comment", "two words", "tab_size"] {
+        assert!(error.contains(expected), "{} missing from {}", expected, error);
+    }
+}
+
+#[test]
+fn test_user_language_takes_precedence() {
+    let mut registry = LanguageRegistry::default();
+    let source = "name = \"Config\"\nextensions = [\"json\"]\nline_comment = \"#\"\n";
+    registry.add(parse_definition(source, DefinitionFormat::Toml).unwrap());
+    assert_eq!(registry.for_filename("app.json").unwrap().name, "Config");
+    assert_eq!(registry.by_name("JSON").unwrap().name, "JSON");
+}
+
+#[test]
+fn test_load_directory() {
+    let dir = TempDir::new("syntaxes");
+    fs::write(dir.join("rules.toml"), RULES).unwrap();
+    fs::write(dir.join("broken.json"), "{\"name\": 1}").unwrap();
+    fs::write(dir.join("notes.txt"), "not a definition").unwrap();
+    let registry = LanguageRegistry::load(Some(dir.path()));
+
+    assert_eq!(registry.for_filename("Rulefile").unwrap().name, "Rules");
+    assert_eq!(registry.for_filename("main.rs").unwrap().name, "Rust");
+    assert_eq!(registry.errors.len(), 1);
+    assert!(registry.errors[0].contains("broken.json"));
+}
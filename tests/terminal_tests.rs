@@ -0,0 +1,77 @@
+mod support;
+
+use std::path::Path;
+use std::time::{Duration, Instant};
+use eframe::egui::{Key, Modifiers};
+use support::TempDir;
+use writer::terminal::{key_bytes, Terminal};
+
+fn start(dir: &Path) -> Terminal {
+    Terminal::start("sh", dir, (24, 80), 100, || {}).unwrap()
+}
+
+/// Wait until the screen shows `text`
+fn wait_for(terminal: &Terminal, text: &str) {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while Instant::now() < deadline {
+        if terminal.parser().screen().contents().contains(text) {
+            return;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    panic!("{:?} never appeared in {:?}", text, terminal.parser().screen().contents());
+}
+
+#[test]
+fn test_key_bytes() {
+    assert_eq!(key_bytes(Key::Enter, Modifiers::NONE, false), Some(b"\r".to_vec()));
+    assert_eq!(key_bytes(Key::C, Modifiers::CTRL, false), Some(vec![3]));
+    assert_eq!(key_bytes(Key::D, Modifiers::CTRL, false), Some(vec![4]));
+    assert_eq!(key_bytes(Key::A, Modifiers::NONE, false), None);
+    assert_eq!(key_bytes(Key::ArrowUp, Modifiers::NONE, false), Some(b"\x1b[A".to_vec()));
+    assert_eq!(key_bytes(Key::ArrowUp, Modifiers::NONE, true), Some(b"\x1bOA".to_vec()));
+    assert_eq!(key_bytes(Key::ArrowLeft, Modifiers::CTRL, false), Some(b"\x1b[1;5D".to_vec()));
+    assert_eq!(key_bytes(Key::Tab, Modifiers::SHIFT, false), Some(b"\x1b[Z".to_vec()));
+}
+
+#[test]
+fn test_shell_output_reaches_screen() {
+    let mut terminal = start(&std::env::temp_dir());
+    terminal.write(b"echo sum-$((40 + 2))\r");
+    wait_for(&terminal, "sum-42");
+}
+
+#[test]
+fn test_starts_in_directory() {
+    let dir = TempDir::new("terminal");
+    let mut terminal = start(dir.path());
+    terminal.write(b"pwd\r");
+    wait_for(&terminal, &format!("writer-terminal-{}", std::process::id()));
+}
+
+#[test]
+fn test_resize_reaches_programs() {
+    let mut terminal = start(&std::env::temp_dir());
+    terminal.resize(30, 100);
+    assert_eq!(terminal.size(), (30, 100));
+    terminal.write(b"stty size\r");
+    wait_for(&terminal, "30 100");
+}
+
+#[test]
+fn test_exit_code_and_selection() {
+    let mut terminal = start(&std::env::temp_dir());
+    // Quotes keep the echoed command from matching the output
+    terminal.write(b"printf '%s\\n' fir''st sec''ond; exit 4\r");
+    wait_for(&terminal, "\nsecond");
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while terminal.exit_code().is_none() && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(terminal.exit_code(), Some(4));
+    let contents = terminal.parser().screen().contents();
+    // The prompt may come after the echoed input, in front of the output
+    let (row, line) = contents.lines().enumerate().find(|(_, line)| line.ends_with("first")).expect(&contents);
+    let (row, column) = (row as u16, (line.len() - 5) as u16);
+    assert_eq!(terminal.text_between((row + 1, 5), (row, column)), "first\nsecond");
+}
@@ -0,0 +1,93 @@
+use egui_code_editor::{ColorTheme, TokenType};
+use writer::treesitter::{changed_range, layout_job, resolve_captures, token_for_capture, HighlightSpan, TreeHighlighter};
+
+#[test]
+fn test_capture_names() {
+    assert_eq!(token_for_capture("keyword"), Some(TokenType::Keyword));
+    assert_eq!(token_for_capture("function.method"), Some(TokenType::Function));
+    assert_eq!(token_for_capture("comment.documentation"), Some(TokenType::Comment(false)));
+    assert_eq!(token_for_capture("constant.builtin"), Some(TokenType::Special));
+    assert_eq!(token_for_capture("constant.numeric"), Some(TokenType::Numeric(false)));
+    assert_eq!(token_for_capture("punctuation.bracket"), Some(TokenType::Punctuation(' ')));
+    assert_eq!(token_for_capture("unknown"), None);
+}
+
+#[test]
+fn test_inner_captures_win() {
+    let string = TokenType::Str('"');
+    let special = TokenType::Special;
+    // "a\nb" with an escape capture inside the string capture
+    let spans = resolve_captures(6, vec![(0..6, 0, string), (2..4, 1, special)]);
+    assert_eq!(
+        spans,
+        vec![
+            HighlightSpan { range: 0..2, token: string },
+            HighlightSpan { range: 2..4, token: special },
+            HighlightSpan { range: 4..6, token: string },
+        ]
+    );
+}
+
+#[test]
+fn test_earliest_pattern_wins_for_same_range() {
+    let spans = resolve_captures(3, vec![(0..3, 5, TokenType::Literal), (0..3, 1, TokenType::Function)]);
+    assert_eq!(spans, vec![HighlightSpan { range: 0..3, token: TokenType::Function }]);
+}
+
+#[test]
+fn test_changed_range() {
+    assert_eq!(changed_range("let x = 1;", "let xy = 1;"), (5..5, 1));
+    assert_eq!(changed_range("abc", "abc"), (3..3, 0));
+    assert_eq!(changed_range("aaa", "aa"), (2..3, 0));
+    // Never splits a multi-byte character
+    assert_eq!(changed_range("é", "è"), (0..2, 2));
+}
+
+#[test]
+fn test_layout_job_covers_text() {
+    let text = "fn main";
+    let spans = [HighlightSpan { range: 0..2, token: TokenType::Keyword }];
+    let job = layout_job(text, &spans, &ColorTheme::GITHUB_DARK, 14.0);
+    assert_eq!(job.text, text);
+    assert_eq!(job.sections.len(), 2);
+    assert_eq!(job.sections[0].byte_range, 0..2);
+}
+
+#[test]
+fn test_no_highlighter_for_unknown_languages() {
+    assert!(TreeHighlighter::new("Plain Text").is_none());
+    #[cfg(not(feature = "tree-sitter"))]
+    assert!(TreeHighlighter::new("Rust").is_none());
+}
+
+#[cfg(feature = "tree-sitter")]
+#[test]
+fn test_rust_highlighting() {
+    let mut highlighter = TreeHighlighter::new("Rust").unwrap();
+    let text = "// hi\nfn main() { let s = \"a\\n\"; }\n";
+    highlighter.update(text);
+    let token_at = |byte: usize| {
+        highlighter
+            .spans()
+            .iter()
+            .find(|s| s.range.contains(&byte))
+            .map(|s| s.token)
+    };
+    assert_eq!(token_at(0), Some(TokenType::Comment(false)));
+    assert_eq!(token_at(6), Some(TokenType::Keyword));
+    assert_eq!(token_at(text.find("main").unwrap()), Some(TokenType::Function));
+    assert_eq!(token_at(text.find("\"a").unwrap()), Some(TokenType::Str('"')));
+}
+
+#[cfg(feature = "tree-sitter")]
+#[test]
+fn test_incremental_update_matches_full_parse() {
+    let mut highlighter = TreeHighlighter::new("Python").unwrap();
+    highlighter.update("def f():\n    return 1\n");
+    let edited = "def f(x):\n    # note\n    return x\n";
+    highlighter.update(edited);
+
+    let mut fresh = TreeHighlighter::new("Python").unwrap();
+    fresh.update(edited);
+    assert_eq!(highlighter.spans(), fresh.spans());
+}
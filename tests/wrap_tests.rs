@@ -0,0 +1,37 @@
+use writer::wrap::{continuation_indent, display_width, reserved_indent, WrapMode};
+
+#[test]
+fn test_wrap_column() {
+    assert_eq!(WrapMode::Off.wrap_column(100), None);
+    assert_eq!(WrapMode::WindowWidth.wrap_column(100), Some(100));
+    assert_eq!(WrapMode::Column(80).wrap_column(100), Some(80));
+    // A narrow window wraps before the column
+    assert_eq!(WrapMode::Column(80).wrap_column(60), Some(60));
+}
+
+#[test]
+fn test_display_width_expands_tabs() {
+    assert_eq!(display_width("abc"), 3);
+    assert_eq!(display_width("\tx"), 5);
+    assert_eq!(display_width("ab\tx"), 5);
+}
+
+#[test]
+fn test_continuation_indent_follows_line() {
+    assert_eq!(continuation_indent("    let x = 1;", 80), 4);
+    assert_eq!(continuation_indent("\t\tfoo", 80), 8);
+    assert_eq!(continuation_indent("no indent", 80), 0);
+}
+
+#[test]
+fn test_continuation_indent_is_capped() {
+    let line = format!("{}text", " ".repeat(30));
+    assert_eq!(continuation_indent(&line, 40), 20);
+}
+
+#[test]
+fn test_reserved_indent_only_counts_long_lines() {
+    let text = format!("        short\n  {}\n", "word ".repeat(20));
+    assert_eq!(reserved_indent(&text, 40), 2);
+    assert_eq!(reserved_indent(&text, 200), 0);
+}